@@ -0,0 +1,70 @@
+use std::env;
+use std::io;
+use std::io::{BufRead, Write};
+use std::process;
+
+use angelina::database::Database;
+use angelina::execution::output::WriteError;
+
+/// read sql from stdin line by line and print the output of each statement as a table,
+/// until `.exit` or the end of the input.
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: angelina <db path>");
+            process::exit(2);
+        }
+    };
+    let mut db = match Database::open(&path) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let stdout = io::stdout();
+    match repl(&mut db, io::stdin().lock(), &mut stdout.lock()) {
+        // the reader of the output is gone, e.g. `angelina db | head`
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Ok(()) => {}
+    }
+    if let Err(e) = db.flush() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn repl<R: BufRead, W: Write>(db: &mut Database, input: R, out: &mut W) -> io::Result<()> {
+    let mut lines = input.lines();
+    loop {
+        write!(out, "angelina> ")?;
+        out.flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return writeln!(out),
+        };
+        let line = line.trim();
+        if line == ".exit" {
+            return Ok(());
+        }
+        if line.is_empty() {
+            continue;
+        }
+        for result in db.execute_batch(line) {
+            match result {
+                Ok(output) => match output.write_table(out) {
+                    Ok(()) => {}
+                    Err(WriteError::Io(e)) => return Err(e),
+                    Err(e) => eprintln!("{}", e),
+                },
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,859 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::datamodel::buffer::{Buffer, DecodeError};
+use crate::datamodel::edge_label::EdgeLabel;
+use crate::datamodel::property_key::PropertyKey;
+use crate::datamodel::vertex_label::VertexLabel;
+use crate::execution::eval::{evaluate, Collation};
+use crate::execution::executor::{ExecutionError, QueryExecutor};
+use crate::execution::integrity::IntegrityIssue;
+use crate::execution::memory::ExecutionMemory;
+use crate::execution::output::{QueryOutput, QueryStats};
+use crate::handlers::chunked_scan::CancellationToken;
+use crate::handlers::schema_handler::SchemaHandler;
+use crate::handlers::sled_engine::{SledEngine, StorageError};
+use crate::handlers::write_observer::WriteObserver;
+use crate::parser::ast::{Statement, Value};
+use crate::parser::parser::{Parser, ParserError, ParserOptions};
+use crate::parser::tokenizer::{Token, Tokenizer};
+
+static META_TABLE_NAME: &str = "META";
+static ACTIVE_GRAPH_KEY: &str = "ACTIVE_GRAPH";
+static GRAPH_KEY_PREFIX: &str = "GRAPH/";
+pub static DEFAULT_GRAPH_NAME: &str = "default";
+static AUDIT_LOG_TABLE_NAME: &str = "AUDIT_LOG";
+static AUDIT_LOG_ID_KEY: &str = "AUDIT_LOG_ID";
+
+/// entry point of angelina. owns the storage engine and executes queries against
+/// the active graph. the active graph is persisted in the META tree, so reopening
+/// a database resumes the graph that was last used.
+pub struct Database {
+    engine: Rc<SledEngine>,
+    graph: String,
+    executor: QueryExecutor,
+    observer: Option<Rc<dyn WriteObserver>>,
+    cancellation: Option<CancellationToken>,
+    collation: Collation,
+    disable_pushdown: bool,
+    single_copy_edge_properties: bool,
+    parser_options: ParserOptions,
+    audit_log: bool,
+}
+
+impl Database {
+    /// fails if the db at `path` can not be opened, e.g. it is locked or not a directory.
+    pub fn open(path: &str) -> Result<Self, AngelinaError> {
+        Ok(Self::from_engine(SledEngine::new(path)?)?)
+    }
+
+    pub fn open_tmp() -> Self {
+        Self::from_engine(SledEngine::new_tmp()).unwrap()
+    }
+
+    pub(crate) fn from_engine(engine: SledEngine) -> Result<Self, StorageError> {
+        let engine = Rc::new(engine);
+        if engine
+            .get(META_TABLE_NAME, &Self::graph_key(DEFAULT_GRAPH_NAME))?
+            .is_none()
+        {
+            engine.insert(META_TABLE_NAME, &Self::graph_key(DEFAULT_GRAPH_NAME), &[])?;
+        }
+        let graph = match engine.get(META_TABLE_NAME, ACTIVE_GRAPH_KEY.as_bytes())? {
+            Some(name) => String::from_utf8(name).map_err(|e| DecodeError::new(e.to_string()))?,
+            None => DEFAULT_GRAPH_NAME.to_owned(),
+        };
+        let executor = Self::build_executor(&engine, &graph);
+        Ok(Database {
+            engine,
+            graph,
+            executor,
+            observer: None,
+            cancellation: None,
+            collation: Collation::default(),
+            disable_pushdown: false,
+            single_copy_edge_properties: false,
+            parser_options: ParserOptions::default(),
+            audit_log: false,
+        })
+    }
+
+    /// register an observer notified after every vertex/edge write of every graph.
+    pub fn set_write_observer(&mut self, observer: Option<Rc<dyn WriteObserver>>) {
+        self.executor.set_write_observer(observer.clone());
+        self.observer = observer;
+    }
+
+    /// cancel the queries of this db with `token`, e.g. from another thread or by a
+    /// timeout. a cancelled query fails at its next chunk of scanned vertices.
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.executor.set_cancellation_token(token.clone());
+        self.cancellation = token;
+    }
+
+    pub fn set_collation(&mut self, collation: Collation) {
+        self.executor.set_collation(collation);
+        self.collation = collation;
+    }
+
+    /// plan queries without pushing predicates down, to compare with the optimized plans
+    /// when debugging.
+    pub fn set_disable_pushdown(&mut self, disabled: bool) {
+        self.executor.set_disable_pushdown(disabled);
+        self.disable_pushdown = disabled;
+    }
+
+    /// store the properties of new and updated edges on their out copy only, halving the
+    /// writes of edge properties. reads of the in copy then fetch the out copy.
+    pub fn set_single_copy_edge_properties(&mut self, enabled: bool) {
+        self.executor.set_single_copy_edge_properties(enabled);
+        self.single_copy_edge_properties = enabled;
+    }
+
+    pub fn set_parser_options(&mut self, options: ParserOptions) {
+        self.parser_options = options;
+    }
+
+    /// append every executed sql to the AUDIT_LOG tree, see `replay`. off by default.
+    pub fn set_audit_log(&mut self, enabled: bool) {
+        self.audit_log = enabled;
+    }
+
+    pub fn execute(&mut self, sql: &str) -> Result<QueryOutput, AngelinaError> {
+        if self.audit_log {
+            self.append_audit_log(sql)?;
+        }
+        let stmts = Parser::parse_sql_with_options(sql, self.parser_options)?;
+        let mut output = QueryOutput::empty();
+        for stmt in &stmts {
+            output = self.execute_statement(stmt)?;
+        }
+        Ok(output)
+    }
+
+    /// execute every statement of `sql` even if some of them fail, e.g. to report which
+    /// statements of a script failed. a script that does not parse is a single error.
+    pub fn execute_batch(&mut self, sql: &str) -> Vec<Result<QueryOutput, AngelinaError>> {
+        if self.audit_log {
+            if let Err(e) = self.append_audit_log(sql) {
+                return vec![Err(e.into())];
+            }
+        }
+        match Parser::parse_sql_with_options(sql, self.parser_options) {
+            Ok(stmts) => stmts
+                .iter()
+                .map(|stmt| self.execute_statement(stmt))
+                .collect(),
+            Err(e) => vec![Err(e.into())],
+        }
+    }
+
+    /// graph statements switch or change the graphs of the db, the others run on the
+    /// active graph.
+    fn execute_statement(&mut self, stmt: &Statement) -> Result<QueryOutput, AngelinaError> {
+        let (name, status) = match stmt {
+            Statement::CreateGraph { name } => {
+                self.create_graph(name)?;
+                (name, "CREATED")
+            }
+            Statement::DropGraph { name } => {
+                if self.drop_graph(name)? {
+                    (name, "DROPPED")
+                } else {
+                    (name, "NOT_FOUND")
+                }
+            }
+            Statement::UseGraph { name } => {
+                self.use_graph(name)?;
+                (name, "ACTIVE")
+            }
+            _ => return Ok(self.executor.execute_statement(stmt)?),
+        };
+        Ok(QueryOutput {
+            columns: vec!["graph".to_owned(), "status".to_owned()],
+            items: Box::new(
+                vec![vec![name.to_owned(), status.to_owned()]]
+                    .into_iter()
+                    .map(Ok),
+            ),
+            stats: QueryStats::default(),
+        })
+    }
+
+    /// re-run the audit log of this db against `target`, each sql on the graph it was
+    /// executed on. statements that fail are skipped, as they failed when logged too.
+    /// returns the number of statements replayed successfully.
+    pub fn replay(&self, target: &mut Database) -> Result<usize, AngelinaError> {
+        let mut replayed = 0;
+        for (_, graph, sql) in self.audit_log_entries()? {
+            if target.current_graph() != graph {
+                if !target.has_graph(&graph)? {
+                    target.create_graph(&graph)?;
+                }
+                target.use_graph(&graph)?;
+            }
+            if target.execute(&sql).is_ok() {
+                replayed += 1;
+            }
+        }
+        Ok(replayed)
+    }
+
+    /// (timestamp in millis, graph, sql) of every logged statement, oldest first.
+    pub fn audit_log_entries(&self) -> Result<Vec<(u64, String, String)>, AngelinaError> {
+        Ok(self
+            .engine
+            .open_tree(AUDIT_LOG_TABLE_NAME)?
+            .iter()
+            .map(|res| -> Result<_, StorageError> {
+                let (_, value) = res?;
+                let mut value_buf = Buffer::from(&value);
+                let timestamp = value_buf.try_get_u64()?;
+                let graph = value_buf.try_get_string_utf8()?;
+                let sql = value_buf.try_get_string_utf8()?;
+                Ok((timestamp, graph, sql))
+            })
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn append_audit_log(&self, sql: &str) -> Result<(), StorageError> {
+        let id = self.engine.increment(META_TABLE_NAME, AUDIT_LOG_ID_KEY)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64);
+        let mut value_buf = Buffer::new();
+        value_buf.put_u64(timestamp);
+        value_buf.put_string(&self.graph);
+        value_buf.put_string(sql);
+        // big endian ids keep the log in execution order
+        self.engine
+            .insert(AUDIT_LOG_TABLE_NAME, &id.to_be_bytes(), &value_buf.to_vec())
+    }
+
+    /// inconsistencies between the elements of the active graph and its schema.
+    pub fn check_integrity(&self) -> Result<Vec<IntegrityIssue>, AngelinaError> {
+        Ok(self.executor.check_integrity()?)
+    }
+
+    /// write the active graph as graphviz dot, see `QueryExecutor::export_dot`.
+    pub fn export_dot<W: io::Write>(&self, w: &mut W) -> Result<(), AngelinaError> {
+        Ok(self.executor.export_dot(w)?)
+    }
+
+    /// check that the db opened cleanly: the META tree is consistent and the schema of every
+    /// graph can be read. `integrity` also runs `check_integrity` on the active graph, which
+    /// reads all its elements. trees that can't be read at all are errors.
+    pub fn check(&self, integrity: bool) -> Result<HealthReport, AngelinaError> {
+        let mut meta_issues = vec![];
+        let graphs = self.graphs()?;
+        if !graphs.iter().any(|graph| graph == DEFAULT_GRAPH_NAME) {
+            meta_issues.push(format!("graph {} is not registered", DEFAULT_GRAPH_NAME));
+        }
+        if let Some(name) = self
+            .engine
+            .get(META_TABLE_NAME, ACTIVE_GRAPH_KEY.as_bytes())?
+        {
+            match String::from_utf8(name) {
+                Ok(name) if !graphs.contains(&name) => {
+                    meta_issues.push(format!("active graph {} is not registered", name))
+                }
+                Ok(_) => {}
+                Err(_) => meta_issues.push("active graph name is not utf8".to_owned()),
+            }
+        }
+        if let Some(id) = self
+            .engine
+            .get(META_TABLE_NAME, AUDIT_LOG_ID_KEY.as_bytes())?
+        {
+            if id.len() != 8 {
+                meta_issues.push("audit log id is not a number".to_owned());
+            }
+        }
+        let mut schema_elements = 0;
+        for graph in &graphs {
+            let schema = SchemaHandler::new(Rc::new(self.engine.with_namespace(graph)));
+            schema_elements += schema.get_vertex_labels()?.len()
+                + schema.get_edge_labels()?.len()
+                + schema.get_property_keys()?.len();
+        }
+        let integrity_issues = match integrity {
+            true => Some(self.check_integrity()?),
+            false => None,
+        };
+        Ok(HealthReport {
+            graphs,
+            schema_elements,
+            meta_issues,
+            integrity_issues,
+        })
+    }
+
+    /// names of all the graphs of the db, sorted.
+    pub fn graphs(&self) -> Result<Vec<String>, AngelinaError> {
+        let mut graphs = vec![];
+        for entry in self
+            .engine
+            .open_tree(META_TABLE_NAME)?
+            .scan_prefix(GRAPH_KEY_PREFIX)
+        {
+            let (key, _) = entry.map_err(StorageError::from)?;
+            graphs.push(String::from_utf8_lossy(&key[GRAPH_KEY_PREFIX.len()..]).into_owned());
+        }
+        Ok(graphs)
+    }
+
+    /// vertex label of the active graph named `name`.
+    pub fn vertex_label(&self, name: &str) -> Result<Option<VertexLabel>, AngelinaError> {
+        Ok(self
+            .executor
+            .schema_handler()
+            .get_vertex_label_by_name(name)?)
+    }
+
+    /// edge label of the active graph named `name`.
+    pub fn edge_label(&self, name: &str) -> Result<Option<EdgeLabel>, AngelinaError> {
+        Ok(self
+            .executor
+            .schema_handler()
+            .get_edge_label_by_name(name)?)
+    }
+
+    /// property key of the active graph named `name`.
+    pub fn property_key(&self, name: &str) -> Result<Option<PropertyKey>, AngelinaError> {
+        Ok(self
+            .executor
+            .schema_handler()
+            .get_property_key_by_name(name)?)
+    }
+
+    /// make every write so far durable, see `SledEngine::flush`.
+    pub fn flush(&self) -> Result<(), AngelinaError> {
+        Ok(self.engine.flush()?)
+    }
+
+    pub fn current_graph(&self) -> &str {
+        &self.graph
+    }
+
+    /// `name` must be an identifier, so that statements like `USE name` can refer to it.
+    pub fn create_graph(&mut self, name: &str) -> Result<(), AngelinaError> {
+        let identifier = Tokenizer::new(name).tokenize();
+        if !matches!(
+            identifier.as_deref(),
+            Ok([Token::Identifier(_), Token::EOF])
+        ) {
+            return Err(AngelinaError::GraphError(format!(
+                "Invalid Graph name {}",
+                name
+            )));
+        }
+        if self.has_graph(name)? {
+            return Err(AngelinaError::GraphError(format!(
+                "Graph {} already exists",
+                name
+            )));
+        }
+        self.engine
+            .insert(META_TABLE_NAME, &Self::graph_key(name), &[])?;
+        Ok(())
+    }
+
+    pub fn use_graph(&mut self, name: &str) -> Result<(), AngelinaError> {
+        if !self.has_graph(name)? {
+            return Err(AngelinaError::GraphError(format!(
+                "No Graph named {}",
+                name
+            )));
+        }
+        self.engine.insert(
+            META_TABLE_NAME,
+            ACTIVE_GRAPH_KEY.as_bytes(),
+            name.as_bytes(),
+        )?;
+        self.executor = Self::build_executor(&self.engine, name);
+        self.executor.set_write_observer(self.observer.clone());
+        self.executor
+            .set_cancellation_token(self.cancellation.clone());
+        self.executor.set_collation(self.collation);
+        self.executor.set_disable_pushdown(self.disable_pushdown);
+        self.executor
+            .set_single_copy_edge_properties(self.single_copy_edge_properties);
+        self.graph = name.to_owned();
+        Ok(())
+    }
+
+    /// remove the graph `name` and all its data. returns whether it existed. the active
+    /// graph can not be dropped.
+    pub fn drop_graph(&mut self, name: &str) -> Result<bool, AngelinaError> {
+        if name == self.graph {
+            return Err(AngelinaError::GraphError(format!(
+                "Can not drop the active Graph {}",
+                name
+            )));
+        }
+        if !self.has_graph(name)? {
+            return Ok(false);
+        }
+        self.engine
+            .remove(META_TABLE_NAME, &Self::graph_key(name))?;
+        self.engine.with_namespace(name).drop_namespace()?;
+        Ok(true)
+    }
+
+    pub fn has_graph(&self, name: &str) -> Result<bool, AngelinaError> {
+        Ok(self
+            .engine
+            .get(META_TABLE_NAME, &Self::graph_key(name))?
+            .is_some())
+    }
+
+    /// the executor of the active graph, to run operators directly.
+    #[cfg(test)]
+    pub(crate) fn executor(&self) -> &QueryExecutor {
+        &self.executor
+    }
+
+    fn build_executor(engine: &SledEngine, graph: &str) -> QueryExecutor {
+        QueryExecutor::new(Rc::new(engine.with_namespace(graph)))
+    }
+
+    fn graph_key(name: &str) -> Vec<u8> {
+        format!("{}{}", GRAPH_KEY_PREFIX, name).into_bytes()
+    }
+}
+
+/// result of `Database::check`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    pub graphs: Vec<String>,
+    /// number of vertex labels, edge labels and property keys of all the graphs.
+    pub schema_elements: usize,
+    /// inconsistencies of the META tree, e.g. an active graph that doesn't exist.
+    pub meta_issues: Vec<String>,
+    /// issues of the active graph found by `check_integrity`, if it was run.
+    pub integrity_issues: Option<Vec<IntegrityIssue>>,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.meta_issues.is_empty()
+            && self
+                .integrity_issues
+                .as_ref()
+                .map_or(true, |issues| issues.is_empty())
+    }
+}
+
+/// evaluate a standalone expression against `bindings`, without any graph.
+/// identifiers, e.g. `age` or `a.age`, are resolved by their full name.
+pub fn evaluate_expr(
+    sql_expr: &str,
+    bindings: &HashMap<String, Value>,
+) -> Result<Value, AngelinaError> {
+    let expr = Parser::parse_expression(sql_expr)?;
+    let mut row = ExecutionMemory::new();
+    for (name, value) in bindings {
+        row.bind_value(name, value.clone());
+    }
+    Ok(evaluate(&expr, &row)?)
+}
+
+#[derive(Debug, Clone)]
+pub enum AngelinaError {
+    ParserError(ParserError),
+    ExecutionError(ExecutionError),
+    GraphError(String),
+    StorageError(StorageError),
+}
+
+impl Display for AngelinaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AngelinaError::ParserError(e) => write!(f, "{}", e),
+            AngelinaError::ExecutionError(e) => write!(f, "ExecutionError: {}", e),
+            AngelinaError::GraphError(msg) => write!(f, "GraphError: {}", msg),
+            AngelinaError::StorageError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ParserError> for AngelinaError {
+    fn from(e: ParserError) -> Self {
+        AngelinaError::ParserError(e)
+    }
+}
+
+impl From<ExecutionError> for AngelinaError {
+    fn from(e: ExecutionError) -> Self {
+        AngelinaError::ExecutionError(e)
+    }
+}
+
+impl From<StorageError> for AngelinaError {
+    fn from(e: StorageError) -> Self {
+        AngelinaError::StorageError(e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::env;
+    use std::fs;
+    use std::time::Duration;
+
+    use crate::datamodel::vertex::Vertex;
+
+    use super::*;
+
+    fn tmp_path(name: &str) -> String {
+        let path = env::temp_dir().join(format!("angelina_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path.to_str().unwrap().to_owned()
+    }
+
+    fn vertex_labels(db: &mut Database) -> Vec<String> {
+        db.execute("SHOW VERTEX LABEL")
+            .unwrap()
+            .into_rows()
+            .unwrap()
+            .into_iter()
+            .map(|row| row[1].to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_use_graph() {
+        let path = tmp_path("use_graph");
+        {
+            let mut db = Database::open(&path).unwrap();
+            assert_eq!(db.current_graph(), DEFAULT_GRAPH_NAME);
+            db.create_graph("g1").unwrap();
+            db.use_graph("g1").unwrap();
+            db.execute("CREATE VERTEX LABEL person").unwrap();
+            assert_eq!(vertex_labels(&mut db), vec!["person"]);
+
+            db.use_graph(DEFAULT_GRAPH_NAME).unwrap();
+            assert!(vertex_labels(&mut db).is_empty());
+            db.use_graph("g1").unwrap();
+        }
+        {
+            let mut db = Database::open(&path).unwrap();
+            assert_eq!(db.current_graph(), "g1");
+            assert_eq!(vertex_labels(&mut db), vec!["person"]);
+            assert!(db.use_graph("nonexistent").is_err());
+            assert_eq!(db.current_graph(), "g1");
+            assert!(db.create_graph("g1").is_err());
+            // names are identifiers
+            for name in &["a/b", "", "1g", "g 1", "select"] {
+                assert!(db.create_graph(name).is_err(), "{}", name);
+            }
+            assert_eq!(db.graphs().unwrap(), vec![DEFAULT_GRAPH_NAME, "g1"]);
+        }
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_graph_statements() {
+        let mut db = Database::open_tmp();
+        let run = |db: &mut Database, sql: &str| -> Vec<Vec<String>> {
+            db.execute(sql).unwrap().into_rows().unwrap()
+        };
+        assert_eq!(run(&mut db, "CREATE GRAPH g1"), vec![vec!["g1", "CREATED"]]);
+        db.execute("CREATE GRAPH g2").unwrap();
+        assert!(db.execute("CREATE GRAPH g1").is_err());
+
+        assert_eq!(run(&mut db, "USE g1"), vec![vec!["g1", "ACTIVE"]]);
+        db.execute("CREATE VERTEX LABEL person; CREATE PROPERTY KEY (name, single)")
+            .unwrap();
+        db.execute("INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')")
+            .unwrap();
+        db.execute("USE g2; CREATE VERTEX LABEL software").unwrap();
+        assert_eq!(db.current_graph(), "g2");
+        assert_eq!(vertex_labels(&mut db), vec!["software"]);
+        assert!(run(&mut db, "SELECT a.id FROM (a)").is_empty());
+        assert!(db
+            .execute("INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('jerry')")
+            .is_err());
+
+        db.execute("USE g1").unwrap();
+        assert_eq!(vertex_labels(&mut db), vec!["person"]);
+        assert_eq!(run(&mut db, "SELECT a.name FROM (a)"), vec![vec!["tom"]]);
+        assert!(db.execute("USE nonexistent").is_err());
+
+        // the active graph can't be dropped
+        assert!(db.execute("DROP GRAPH g1").is_err());
+        db.execute("USE g2").unwrap();
+        assert_eq!(run(&mut db, "DROP GRAPH g1"), vec![vec!["g1", "DROPPED"]]);
+        assert_eq!(run(&mut db, "DROP GRAPH g1"), vec![vec!["g1", "NOT_FOUND"]]);
+        assert_eq!(db.graphs().unwrap(), vec![DEFAULT_GRAPH_NAME, "g2"]);
+
+        // a new graph of the same name starts empty
+        db.execute("CREATE GRAPH g1; USE g1").unwrap();
+        assert!(vertex_labels(&mut db).is_empty());
+        assert!(run(&mut db, "SELECT a.id FROM (a)").is_empty());
+    }
+
+    #[test]
+    fn test_check() {
+        let mut db = Database::open_tmp();
+        let report = db.check(true).unwrap();
+        assert!(report.is_healthy(), "{:?}", report);
+        assert_eq!(report.graphs, vec![DEFAULT_GRAPH_NAME]);
+        assert_eq!(report.integrity_issues, Some(vec![]));
+
+        for sql in &[
+            "CREATE VERTEX LABEL person",
+            "CREATE EDGE LABEL (knows, many2manymulti)",
+            "CREATE PROPERTY KEY (name, single)",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('jerry')",
+            "INSERT EDGE knows BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 'v2')",
+        ] {
+            db.execute(sql).unwrap();
+        }
+        db.create_graph("g1").unwrap();
+        let report = db.check(true).unwrap();
+        assert!(report.is_healthy(), "{:?}", report);
+        assert_eq!(report.graphs, vec![DEFAULT_GRAPH_NAME, "g1"]);
+        assert_eq!(report.schema_elements, 3);
+
+        // remove a vertex but not its edge
+        db.engine
+            .with_namespace(DEFAULT_GRAPH_NAME)
+            .remove("VERTEX", &Vertex::build_key("v2"))
+            .unwrap();
+        let report = db.check(true).unwrap();
+        assert!(!report.is_healthy());
+        assert_eq!(
+            report.integrity_issues.unwrap()[0].to_string(),
+            "edge 0 v1->v2 refers to missing vertex v2"
+        );
+        // not found without the integrity check
+        assert!(db.check(false).unwrap().is_healthy());
+
+        db.engine
+            .insert(META_TABLE_NAME, ACTIVE_GRAPH_KEY.as_bytes(), b"gone")
+            .unwrap();
+        assert_eq!(
+            db.check(false).unwrap().meta_issues,
+            vec!["active graph gone is not registered"]
+        );
+    }
+
+    #[test]
+    fn test_open_invalid_path() {
+        let file = tmp_path("not_a_dir");
+        fs::write(&file, "").unwrap();
+        let result = Database::open(&format!("{}/db", file));
+        assert!(matches!(result, Err(AngelinaError::StorageError(_))));
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_open_corrupt_active_graph() {
+        let path = tmp_path("corrupt_active_graph");
+        {
+            let db = Database::open(&path).unwrap();
+            db.engine
+                .insert(META_TABLE_NAME, ACTIVE_GRAPH_KEY.as_bytes(), &[0xff])
+                .unwrap();
+            assert_eq!(
+                db.check(false).unwrap().meta_issues,
+                vec!["active graph name is not utf8"]
+            );
+        }
+        let result = Database::open(&path);
+        assert!(matches!(result, Err(AngelinaError::StorageError(_))));
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[derive(Default)]
+    struct CountingObserver {
+        vertex_writes: RefCell<Vec<(bool, bool)>>,
+    }
+
+    impl WriteObserver for CountingObserver {
+        fn on_vertex_write(&self, before: Option<&Vertex>, after: Option<&Vertex>) {
+            self.vertex_writes
+                .borrow_mut()
+                .push((before.is_some(), after.is_some()));
+        }
+    }
+
+    #[test]
+    fn test_write_observer() {
+        let mut db = Database::open_tmp();
+        let observer = Rc::new(CountingObserver::default());
+        db.set_write_observer(Some(observer.clone()));
+        db.execute("CREATE VERTEX LABEL person").unwrap();
+        db.execute("CREATE PROPERTY KEY (name, single)").unwrap();
+        db.execute("INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')")
+            .unwrap();
+        // create, then one update per property
+        assert_eq!(
+            *observer.vertex_writes.borrow(),
+            vec![(false, true), (true, true)]
+        );
+    }
+
+    #[test]
+    fn test_audit_log_replay() {
+        let mut db = Database::open_tmp();
+        db.execute("CREATE VERTEX LABEL software").unwrap();
+        db.set_audit_log(true);
+        db.execute("CREATE VERTEX LABEL person").unwrap();
+        db.create_graph("g1").unwrap();
+        db.use_graph("g1").unwrap();
+        db.execute("CREATE VERTEX LABEL city").unwrap();
+        db.set_audit_log(false);
+        db.execute("CREATE VERTEX LABEL country").unwrap();
+
+        let entries = db.audit_log_entries().unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|(_, graph, sql)| (graph.as_str(), sql.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (DEFAULT_GRAPH_NAME, "CREATE VERTEX LABEL person"),
+                ("g1", "CREATE VERTEX LABEL city")
+            ]
+        );
+        assert!(entries[0].0 <= entries[1].0);
+
+        let mut replica = Database::open_tmp();
+        assert_eq!(db.replay(&mut replica).unwrap(), 2);
+        assert_eq!(replica.current_graph(), "g1");
+        assert_eq!(vertex_labels(&mut replica), vec!["city"]);
+        replica.use_graph(DEFAULT_GRAPH_NAME).unwrap();
+        assert_eq!(vertex_labels(&mut replica), vec!["person"]);
+
+        // a truncated entry is an error, not a panic
+        db.engine
+            .insert(AUDIT_LOG_TABLE_NAME, &u64::MAX.to_be_bytes(), &[0, 1])
+            .unwrap();
+        assert!(matches!(
+            db.audit_log_entries(),
+            Err(AngelinaError::StorageError(_))
+        ));
+    }
+
+    #[test]
+    fn test_cancel_query() {
+        let mut db = Database::open_tmp();
+        db.execute("CREATE VERTEX LABEL person").unwrap();
+        db.execute("CREATE PROPERTY KEY (name, single)").unwrap();
+        for i in 0..600 {
+            db.execute(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('v{}'):('n')",
+                i
+            ))
+            .unwrap();
+        }
+        let token = CancellationToken::new();
+        db.set_cancellation_token(Some(token.clone()));
+        // a cancelled scan fails the rows it has not read yet
+        let select = |db: &mut Database, sql: &str| -> Result<Vec<Vec<String>>, AngelinaError> {
+            Ok(db.execute(sql)?.into_rows()?)
+        };
+        assert_eq!(select(&mut db, "SELECT a.id FROM (a)").unwrap().len(), 600);
+
+        token.cancel();
+        let err = select(&mut db, "SELECT a.id FROM (a)").err().unwrap();
+        assert_eq!(err.to_string(), "ExecutionError: scan cancelled");
+        // lookups don't scan
+        assert!(select(&mut db, "SELECT a.id FROM (a) WHERE a.id = 'v1'").is_ok());
+
+        db.set_cancellation_token(Some(CancellationToken::with_timeout(Duration::from_secs(
+            0,
+        ))));
+        assert!(select(&mut db, "SELECT count(*) FROM (a)").is_err());
+        db.set_cancellation_token(None);
+        assert!(select(&mut db, "SELECT count(*) FROM (a)").is_ok());
+    }
+
+    #[test]
+    fn test_execute_batch() {
+        let mut db = Database::open_tmp();
+        let results = db.execute_batch(
+            "CREATE VERTEX LABEL person; \
+             CREATE PROPERTY KEY (name, single); \
+             INSERT VERTEX dog PROPERTIES (name) VALUES ('v1'):('a'); \
+             INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('b')",
+        );
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok() && results[1].is_ok());
+        let err = results[2].as_ref().err().unwrap();
+        assert!(err.to_string().contains("dog"), "{}", err);
+        assert!(results[3].is_ok());
+        assert_eq!(
+            db.execute("SELECT a.id FROM (a)")
+                .unwrap()
+                .into_rows()
+                .unwrap(),
+            vec![vec!["v2"]]
+        );
+
+        let results = db.execute_batch("SELECT FROM");
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn test_structural_accessors() {
+        let mut db = Database::open_tmp();
+        db.execute("CREATE VERTEX LABEL person").unwrap();
+        db.set_parser_options(ParserOptions {
+            strict_structural_accessors: false,
+            ..ParserOptions::default()
+        });
+        db.execute("CREATE PROPERTY KEY (label, single)").unwrap();
+        db.execute("CREATE PROPERTY KEY (id, single)").unwrap();
+        db.execute("INSERT VERTEX person PROPERTIES (label, id) VALUES ('v1'):('admin', 7)")
+            .unwrap();
+        let select = |db: &mut Database, sql: &str| -> Vec<Vec<String>> {
+            db.execute(sql).unwrap().into_rows().unwrap()
+        };
+
+        let sql = "SELECT a.label, a.id, label(a), id(a) FROM (a) WHERE a.label = 'admin'";
+        assert_eq!(
+            select(&mut db, sql),
+            vec![vec!["admin", "7", "person", "v1"]]
+        );
+
+        // the default reads the label and the id of the vertex
+        db.set_parser_options(ParserOptions::default());
+        assert_eq!(
+            select(&mut db, "SELECT a.label, a.id FROM (a)"),
+            vec![vec!["person", "v1"]]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_expr() {
+        let mut bindings = HashMap::new();
+        bindings.insert("age".to_string(), Value::Number("30".to_string()));
+        bindings.insert("a.name".to_string(), Value::String("tom".to_string()));
+
+        assert_eq!(
+            evaluate_expr("age * 2 + 1", &bindings).unwrap(),
+            Value::Number("61".to_string())
+        );
+        assert_eq!(
+            evaluate_expr("age >= 18 AND a.name = 'tom'", &bindings).unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            evaluate_expr("NOT (age < 40)", &bindings).unwrap(),
+            Value::Boolean(false)
+        );
+        // unknown names and trailing tokens are errors
+        assert!(evaluate_expr("height > 1", &bindings).is_err());
+        assert!(evaluate_expr("age > 1 2", &bindings).is_err());
+    }
+}
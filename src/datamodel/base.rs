@@ -1,15 +1,77 @@
+use std::convert::TryFrom;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result;
+use std::str::FromStr;
 
-pub trait BaseSchemaModel {
+use crate::datamodel::buffer::DecodeError;
+use crate::execution::executor::ExecutionError;
+
+pub trait BaseSchemaModel: Sized {
     fn serialize(&self) -> (Vec<u8>, Vec<u8>);
-    fn deserialize(key: &[u8], value: &[u8]) -> Self;
-    fn deserialize_value(id: u64, value: &[u8]) -> Self;
+    fn deserialize(key: &[u8], value: &[u8]) -> std::result::Result<Self, DecodeError>;
+    fn deserialize_value(id: u64, value: &[u8]) -> std::result::Result<Self, DecodeError>;
     fn build_key(id: u64) -> Vec<u8>;
     fn get_prefix() -> Vec<u8>;
 }
 
+/// id of a vertex label. vertex label ids, edge label ids, property key ids and edge ids
+/// are distinct types so that one can't be passed for another:
+///
+/// ```
+/// # use angelina::datamodel::base::VertexLabelId;
+/// # use angelina::handlers::schema_handler::SchemaHandler;
+/// fn lookup(schema: &SchemaHandler) {
+///     schema.get_vertex_label(VertexLabelId(0));
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use angelina::datamodel::base::EdgeLabelId;
+/// # use angelina::handlers::schema_handler::SchemaHandler;
+/// fn lookup(schema: &SchemaHandler) {
+///     schema.get_vertex_label(EdgeLabelId(0));
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct VertexLabelId(pub u64);
+
+/// id of an edge label.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct EdgeLabelId(pub u64);
+
+/// id of a property key.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct PropertyKeyId(pub u64);
+
+/// id of an edge, unique among all edges of a graph.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct EdgeId(pub u64);
+
+impl Display for VertexLabelId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for EdgeLabelId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for PropertyKeyId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for EdgeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[repr(u8)]
 pub enum SchemaType {
     VertexLabel = 0x01,
@@ -47,28 +109,36 @@ impl Display for EdgeMultiplicity {
     }
 }
 
-impl From<u8> for EdgeMultiplicity {
-    fn from(i: u8) -> Self {
+impl TryFrom<u8> for EdgeMultiplicity {
+    type Error = DecodeError;
+
+    fn try_from(i: u8) -> std::result::Result<Self, DecodeError> {
         match i {
-            0x01 => EdgeMultiplicity::One2One,
-            0x02 => EdgeMultiplicity::One2Many,
-            0x03 => EdgeMultiplicity::Many2One,
-            0x04 => EdgeMultiplicity::Many2ManySimple,
-            0x05 => EdgeMultiplicity::Many2ManyMulti,
-            _ => panic!("No Such EdgeMultiplicity"),
+            0x01 => Ok(EdgeMultiplicity::One2One),
+            0x02 => Ok(EdgeMultiplicity::One2Many),
+            0x03 => Ok(EdgeMultiplicity::Many2One),
+            0x04 => Ok(EdgeMultiplicity::Many2ManySimple),
+            0x05 => Ok(EdgeMultiplicity::Many2ManyMulti),
+            _ => Err(DecodeError::new(format!("No Such EdgeMultiplicity {}", i))),
         }
     }
 }
 
-impl From<&str> for EdgeMultiplicity {
-    fn from(value: &str) -> EdgeMultiplicity {
+impl FromStr for EdgeMultiplicity {
+    type Err = ExecutionError;
+
+    /// case insensitive, e.g. `one2many`.
+    fn from_str(value: &str) -> std::result::Result<EdgeMultiplicity, ExecutionError> {
         match value.to_uppercase().as_str() {
-            "ONE2ONE" => EdgeMultiplicity::One2One,
-            "ONE2MANY" => EdgeMultiplicity::One2Many,
-            "MANY2ONE" => EdgeMultiplicity::Many2One,
-            "MANY2MANYSIMPLE" => EdgeMultiplicity::Many2ManySimple,
-            "MANY2MANYMULTI" => EdgeMultiplicity::Many2ManyMulti,
-            _ => panic!("No Such EdgeMultiplicity"),
+            "ONE2ONE" => Ok(EdgeMultiplicity::One2One),
+            "ONE2MANY" => Ok(EdgeMultiplicity::One2Many),
+            "MANY2ONE" => Ok(EdgeMultiplicity::Many2One),
+            "MANY2MANYSIMPLE" => Ok(EdgeMultiplicity::Many2ManySimple),
+            "MANY2MANYMULTI" => Ok(EdgeMultiplicity::Many2ManyMulti),
+            _ => Err(ExecutionError::new(format!(
+                "No Such EdgeMultiplicity {}",
+                value
+            ))),
         }
     }
 }
@@ -91,24 +161,32 @@ impl Display for Cardinality {
     }
 }
 
-impl From<u8> for Cardinality {
-    fn from(value: u8) -> Cardinality {
+impl TryFrom<u8> for Cardinality {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> std::result::Result<Cardinality, DecodeError> {
         match value {
-            0x01 => Cardinality::Single,
-            0x02 => Cardinality::List,
-            0x03 => Cardinality::Set,
-            _ => panic!("No Such Cardinality"),
+            0x01 => Ok(Cardinality::Single),
+            0x02 => Ok(Cardinality::List),
+            0x03 => Ok(Cardinality::Set),
+            _ => Err(DecodeError::new(format!("No Such Cardinality {}", value))),
         }
     }
 }
 
-impl From<&str> for Cardinality {
-    fn from(value: &str) -> Cardinality {
+impl FromStr for Cardinality {
+    type Err = ExecutionError;
+
+    /// case insensitive, e.g. `single`.
+    fn from_str(value: &str) -> std::result::Result<Cardinality, ExecutionError> {
         match value.to_uppercase().as_str() {
-            "SINGLE" => Cardinality::Single,
-            "LIST" => Cardinality::List,
-            "SET" => Cardinality::Set,
-            _ => panic!("No Such Cardinality"),
+            "SINGLE" => Ok(Cardinality::Single),
+            "LIST" => Ok(Cardinality::List),
+            "SET" => Ok(Cardinality::Set),
+            _ => Err(ExecutionError::new(format!(
+                "No Such Cardinality {}",
+                value
+            ))),
         }
     }
 }
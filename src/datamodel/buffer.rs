@@ -1,22 +1,50 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
 use bytes::{Buf, BufMut, BytesMut};
 
-use crate::datamodel::constants::STRING_TERM;
+use crate::datamodel::constants::{ESCAPE, STRING_TERM};
+
+/// stored bytes that can't be read back, e.g. a truncated key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    msg: String,
+}
+
+impl DecodeError {
+    pub fn new(msg: String) -> Self {
+        DecodeError { msg }
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "corrupt data: {}", self.msg)
+    }
+}
+
+const SIGN_BIT: u64 = 1 << 63;
 
 pub struct Buffer {
-    bytes: BytesMut
+    bytes: BytesMut,
 }
 
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Buffer {
     pub fn new() -> Buffer {
         Buffer {
-            bytes: BytesMut::new()
+            bytes: BytesMut::new(),
         }
     }
 
     pub fn from(arr: &[u8]) -> Buffer {
         Buffer {
-            bytes: BytesMut::from(arr)
+            bytes: BytesMut::from(arr),
         }
     }
 
@@ -32,19 +60,113 @@ impl Buffer {
         self.bytes.get_u64()
     }
 
+    pub fn get_i64(&mut self) -> i64 {
+        self.try_get_i64().unwrap()
+    }
+
+    pub fn get_f64(&mut self) -> f64 {
+        self.try_get_f64().unwrap()
+    }
+
     pub fn get_string_raw(&mut self) -> Vec<u8> {
+        self.try_get_string_raw().unwrap()
+    }
+
+    pub fn get_string_utf8(&mut self) -> String {
+        self.try_get_string_utf8().unwrap()
+    }
+
+    /// reads what `put_escaped_string` wrote.
+    pub fn get_escaped_string_utf8(&mut self) -> String {
+        self.try_get_escaped_string_utf8().unwrap()
+    }
+
+    /// like `get_u8`, but fails instead of panicking if the buffer is too short. so do the
+    /// other `try_get_` methods, which the deserializers of stored bytes use.
+    pub fn try_get_u8(&mut self) -> Result<u8, DecodeError> {
+        self.check_remaining(1)?;
+        Ok(self.bytes.get_u8())
+    }
+
+    pub fn try_get_u32(&mut self) -> Result<u32, DecodeError> {
+        self.check_remaining(4)?;
+        Ok(self.bytes.get_u32())
+    }
+
+    pub fn try_get_u64(&mut self) -> Result<u64, DecodeError> {
+        self.check_remaining(8)?;
+        Ok(self.bytes.get_u64())
+    }
+
+    pub fn try_get_i64(&mut self) -> Result<i64, DecodeError> {
+        Ok((self.try_get_u64()? ^ SIGN_BIT) as i64)
+    }
+
+    pub fn try_get_f64(&mut self) -> Result<f64, DecodeError> {
+        let bits = self.try_get_u64()?;
+        let bits = match bits & SIGN_BIT {
+            0 => !bits,
+            _ => bits ^ SIGN_BIT,
+        };
+        Ok(f64::from_bits(bits))
+    }
+
+    pub fn try_get_slice(&mut self, len: usize) -> Result<Vec<u8>, DecodeError> {
+        self.check_remaining(len)?;
+        Ok(self.bytes.split_to(len).to_vec())
+    }
+
+    pub fn try_get_string_raw(&mut self) -> Result<Vec<u8>, DecodeError> {
+        match self.bytes.iter().position(|byte| *byte == STRING_TERM) {
+            Some(len) => {
+                let string = self.bytes.split_to(len).to_vec();
+                self.bytes.advance(1);
+                Ok(string)
+            }
+            None => Err(DecodeError::new("unterminated string".to_owned())),
+        }
+    }
+
+    pub fn try_get_string_utf8(&mut self) -> Result<String, DecodeError> {
+        let string = self.try_get_string_raw()?;
+        Self::utf8(string)
+    }
+
+    pub fn try_get_escaped_string_utf8(&mut self) -> Result<String, DecodeError> {
         let mut string = Vec::new();
-        let mut next = self.bytes.get_u8();
+        let mut next = self.try_get_u8()?;
         while next != STRING_TERM {
+            if next == ESCAPE {
+                next = self
+                    .try_get_u8()?
+                    .checked_sub(1)
+                    .ok_or_else(|| DecodeError::new("invalid escape".to_owned()))?;
+            }
             string.push(next);
-            next = self.bytes.get_u8();
+            next = self.try_get_u8()?;
         }
-        string
+        Self::utf8(string)
     }
 
-    pub fn get_string_utf8(&mut self) -> String {
-        let string = self.get_string_raw();
-        String::from_utf8(string).unwrap()
+    /// the rest of the buffer as a string, e.g. the unterminated id at the end of a key.
+    pub fn try_get_remaining_utf8(&mut self) -> Result<String, DecodeError> {
+        let string = self.bytes.split_to(self.bytes.len()).to_vec();
+        Self::utf8(string)
+    }
+
+    fn check_remaining(&self, len: usize) -> Result<(), DecodeError> {
+        match self.bytes.remaining() >= len {
+            true => Ok(()),
+            false => Err(DecodeError::new(format!(
+                "expected {} more bytes, found {}",
+                len,
+                self.bytes.remaining()
+            ))),
+        }
+    }
+
+    fn utf8(string: Vec<u8>) -> Result<String, DecodeError> {
+        String::from_utf8(string).map_err(|e| DecodeError::new(e.to_string()))
     }
 
     pub fn put_u8(&mut self, n: u8) {
@@ -59,11 +181,43 @@ impl Buffer {
         self.bytes.put_u64(n)
     }
 
+    /// written with the sign bit flipped, so the bytes sort like the numbers.
+    pub fn put_i64(&mut self, n: i64) {
+        self.bytes.put_u64(n as u64 ^ SIGN_BIT)
+    }
+
+    /// written so the bytes sort like the numbers: the sign bit of positive numbers is
+    /// flipped and all the bits of negative numbers are. -0.0 sorts before 0.0 and NaN
+    /// after infinity.
+    pub fn put_f64(&mut self, n: f64) {
+        let bits = n.to_bits();
+        let bits = match bits & SIGN_BIT {
+            0 => bits ^ SIGN_BIT,
+            _ => !bits,
+        };
+        self.bytes.put_u64(bits)
+    }
+
     pub fn put_string(&mut self, data: &str) {
         self.bytes.put_slice(data.as_bytes());
         self.bytes.put_u8(STRING_TERM)
     }
 
+    /// like `put_string`, for strings that may contain `STRING_TERM`, e.g. ids in keys.
+    /// `STRING_TERM` is written as `ESCAPE 0x01` and `ESCAPE` as `ESCAPE 0x02`, so the
+    /// written strings sort like the strings and none is a prefix of another.
+    pub fn put_escaped_string(&mut self, data: &str) {
+        for byte in data.bytes() {
+            if byte == STRING_TERM || byte == ESCAPE {
+                self.bytes.put_u8(ESCAPE);
+                self.bytes.put_u8(byte + 1);
+            } else {
+                self.bytes.put_u8(byte);
+            }
+        }
+        self.bytes.put_u8(STRING_TERM)
+    }
+
     pub fn put_slice(&mut self, data: &[u8]) {
         self.bytes.put_slice(data);
     }
@@ -85,7 +239,6 @@ impl Buffer {
     }
 }
 
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -108,32 +261,131 @@ mod test {
         for i in 0..10 {
             buf.put_u8(i);
         }
-        for i in 10..1 {
+        for i in 0..10 {
             assert_eq!(i, buf.get_u8());
         }
 
         for i in 0..10 {
             buf.put_u32(i);
         }
-        for i in 10..1 {
+        for i in 0..10 {
             assert_eq!(i, buf.get_u32());
         }
 
         for i in 0..10 {
             buf.put_u64(i);
         }
-        for i in 10..1 {
+        for i in 0..10 {
             assert_eq!(i, buf.get_u64());
         }
 
         for i in 0..10 {
             buf.put_string(format!("{}", i).as_str());
         }
-        for i in 10..1 {
+        for i in 0..10 {
             assert_eq!(format!("{}", i), buf.get_string_utf8());
         }
     }
 
+    #[test]
+    fn test_escaped_string() {
+        let strings = ["", "a", "a\0", "a\0b", "a\u{1}", "a\u{1}\0", "a\u{2}", "ab"];
+        let mut buf = Buffer::new();
+        let mut encoded = vec![];
+        for s in &strings {
+            buf.put_escaped_string(s);
+            let mut one = Buffer::new();
+            one.put_escaped_string(s);
+            encoded.push(one.to_vec());
+        }
+        assert_eq!(encoded[3], vec![b'a', ESCAPE, 0x01, b'b', STRING_TERM]);
+        for s in &strings {
+            assert_eq!(*s, buf.get_escaped_string_utf8());
+        }
+        // same order as the strings, and no string is a prefix of another
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(sorted, encoded);
+        for (i, a) in encoded.iter().enumerate() {
+            assert!(encoded.iter().skip(i + 1).all(|b| !b.starts_with(a)));
+        }
+    }
+
+    #[test]
+    fn test_signed_and_float() {
+        let ints = [i64::MIN, -1000, -1, 0, 1, 255, 256, i64::MAX];
+        let floats = [
+            f64::NEG_INFINITY,
+            f64::MIN,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.0,
+            1.5,
+            1e300,
+            f64::INFINITY,
+        ];
+        let mut buf = Buffer::new();
+        let mut encoded_ints = vec![];
+        for n in &ints {
+            buf.put_i64(*n);
+            let mut one = Buffer::new();
+            one.put_i64(*n);
+            encoded_ints.push(one.to_vec());
+        }
+        let mut encoded_floats = vec![];
+        for n in &floats {
+            buf.put_f64(*n);
+            let mut one = Buffer::new();
+            one.put_f64(*n);
+            encoded_floats.push(one.to_vec());
+        }
+        for n in &ints {
+            assert_eq!(*n, buf.get_i64());
+        }
+        for n in &floats {
+            let decoded = buf.get_f64();
+            assert_eq!(n.to_bits(), decoded.to_bits());
+        }
+        assert!(!buf.has_remaining());
+        // same order as the numbers, so they can be range scanned
+        for encoded in &[encoded_ints, encoded_floats] {
+            let mut sorted = encoded.clone();
+            sorted.sort();
+            assert_eq!(&sorted, encoded);
+        }
+
+        let mut buf = Buffer::new();
+        buf.put_f64(f64::NAN);
+        assert!(buf.get_f64().is_nan());
+        assert!(Buffer::from(&[0x80, 0]).try_get_i64().is_err());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut buf = Buffer::from(&[0, 0, 1]);
+        assert_eq!(
+            buf.try_get_u64().unwrap_err().to_string(),
+            "corrupt data: expected 8 more bytes, found 3"
+        );
+        // nothing was read
+        assert_eq!(buf.try_get_u8(), Ok(0));
+        assert!(buf.try_get_u32().is_err());
+
+        assert!(Buffer::from(b"abc").try_get_string_utf8().is_err());
+        assert!(Buffer::from(&[b'a', ESCAPE])
+            .try_get_escaped_string_utf8()
+            .is_err());
+        assert!(Buffer::from(&[0xff, STRING_TERM])
+            .try_get_string_utf8()
+            .is_err());
+        let mut buf = Buffer::from(&[b'a', STRING_TERM, 1]);
+        assert_eq!(buf.try_get_string_utf8(), Ok("a".to_owned()));
+        assert_eq!(buf.try_get_u8(), Ok(1));
+    }
+
     #[test]
     fn test_advance() {
         let mut buf = Buffer::from(&[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3]);
@@ -142,4 +394,3 @@ mod test {
         assert_eq!(3, buf.get_u32());
     }
 }
-
@@ -1,2 +1,7 @@
 pub static STRING_TERM: u8 = 0x00;
+/// prefixes an escaped `STRING_TERM` or `ESCAPE` in key strings, see `Buffer::put_escaped_string`.
+pub static ESCAPE: u8 = 0x01;
 pub static MAX_ID_LENGTH: usize = 256;
+/// value of an in edge whose properties are only stored on its out edge, see
+/// `EdgeHandler::single_copy_properties`.
+pub static PROPERTIES_ON_OUT_EDGE: &[u8] = &[0xff];
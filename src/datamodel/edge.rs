@@ -1,74 +1,114 @@
-use crate::datamodel::base::{EdgeDirection, ElementType};
-use crate::datamodel::buffer::Buffer;
+use crate::datamodel::base::{EdgeDirection, EdgeId, EdgeLabelId, ElementType};
+use crate::datamodel::buffer::{Buffer, DecodeError};
+use crate::datamodel::constants::PROPERTIES_ON_OUT_EDGE;
 use crate::datamodel::property::Properties;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Edge {
     pub src_vertex_id: String,
     pub dst_vertex_id: String,
-    pub edge_id: u64,
-    pub label: u64,
+    pub edge_id: EdgeId,
+    pub label: EdgeLabelId,
     pub properties: Properties,
 }
 
-
 impl Edge {
     pub fn serialize(&self, direction: EdgeDirection) -> (Vec<u8>, Vec<u8>) {
         let key = self.generate_key(direction);
-        let mut value_buf = Buffer::from(&self.properties.data);
+        let value_buf = Buffer::from(&self.properties.data);
 
         (key, value_buf.to_vec())
     }
 
-    pub fn deserialize(key: &[u8], value: &[u8]) -> Edge {
+    pub fn deserialize(key: &[u8], value: &[u8]) -> Result<Edge, DecodeError> {
         let mut key_buf = Buffer::from(key);
-        let element_type = key_buf.get_u8();      // ElementType
-        let first_id = key_buf.get_string_utf8();
-        let edge_label = key_buf.get_u64();
-        let second_id = key_buf.get_string_utf8();
-        let edge_id = key_buf.get_u64();
+        let element_type = key_buf.try_get_u8()?; // ElementType
+        let first_id = key_buf.try_get_escaped_string_utf8()?;
+        let edge_label = EdgeLabelId(key_buf.try_get_u64()?);
+        let second_id = key_buf.try_get_escaped_string_utf8()?;
+        let edge_id = EdgeId(key_buf.try_get_u64()?);
 
-        match element_type {
-            _ if element_type == ElementType::OutEdge as u8 => {
-                Edge {
-                    src_vertex_id: first_id,
-                    dst_vertex_id: second_id,
-                    edge_id,
-                    label: edge_label,
-                    properties: Properties { data: value.to_owned() },
-                }
-            }
-            _ if element_type == ElementType::InEdge as u8 => {
-                Edge {
-                    src_vertex_id: second_id,
-                    dst_vertex_id: first_id,
-                    edge_id,
-                    label: edge_label,
-                    properties: Properties { data: value.to_owned() },
-                }
+        let (src_vertex_id, dst_vertex_id) = match element_type {
+            _ if element_type == ElementType::OutEdge as u8 => (first_id, second_id),
+            _ if element_type == ElementType::InEdge as u8 => (second_id, first_id),
+            _ => {
+                return Err(DecodeError::new(format!(
+                    "unknown edge element type {}",
+                    element_type
+                )))
             }
-            _ => panic!("ElementType Error!")
-        }
+        };
+        Ok(Edge {
+            src_vertex_id,
+            dst_vertex_id,
+            edge_id,
+            label: edge_label,
+            // the marker is replaced by the properties of the out copy when the edge is read
+            properties: match value == PROPERTIES_ON_OUT_EDGE {
+                true => Properties {
+                    data: value.to_vec(),
+                },
+                false => Properties::decode(value)?,
+            },
+        })
     }
 
     pub fn generate_key(&self, direction: EdgeDirection) -> Vec<u8> {
-        Self::build_key(&self.src_vertex_id, &self.dst_vertex_id, self.label,
-                        self.edge_id, direction)
+        Self::build_key(
+            &self.src_vertex_id,
+            &self.dst_vertex_id,
+            self.label,
+            self.edge_id,
+            direction,
+        )
     }
 
-    pub fn build_key(src_id: &str, dst_id: &str, label: u64,
-                     edge_id: u64, direction: EdgeDirection) -> Vec<u8> {
+    pub fn build_key(
+        src_id: &str,
+        dst_id: &str,
+        label: EdgeLabelId,
+        edge_id: EdgeId,
+        direction: EdgeDirection,
+    ) -> Vec<u8> {
         let (element_type, first_id, second_id) = match direction {
             EdgeDirection::Out => (ElementType::OutEdge, src_id, dst_id),
-            EdgeDirection::In => (ElementType::InEdge, dst_id, src_id)
+            EdgeDirection::In => (ElementType::InEdge, dst_id, src_id),
         };
 
         let mut key_buf = Buffer::new();
         key_buf.put_u8(element_type as u8);
-        key_buf.put_string(first_id);
-        key_buf.put_u64(label);
-        key_buf.put_string(second_id);
-        key_buf.put_u64(edge_id);
+        key_buf.put_escaped_string(first_id);
+        key_buf.put_u64(label.0);
+        key_buf.put_escaped_string(second_id);
+        key_buf.put_u64(edge_id.0);
         key_buf.to_vec()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_truncated_key() {
+        let edge = Edge {
+            src_vertex_id: "v1".to_owned(),
+            dst_vertex_id: "v2".to_owned(),
+            edge_id: EdgeId(3),
+            label: EdgeLabelId(1),
+            properties: Properties { data: vec![] },
+        };
+        let (key, value) = edge.serialize(EdgeDirection::In);
+        assert_eq!(Edge::deserialize(&key, &value), Ok(edge));
+        for len in 0..key.len() {
+            assert!(Edge::deserialize(&key[..len], &value).is_err(), "{}", len);
+        }
+
+        let mut key = key;
+        key[0] = ElementType::Vertex as u8;
+        assert_eq!(
+            Edge::deserialize(&key, &value).unwrap_err().to_string(),
+            "corrupt data: unknown edge element type 4"
+        );
+    }
+}
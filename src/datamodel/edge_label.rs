@@ -1,40 +1,61 @@
-use crate::datamodel::base::{BaseSchemaModel, EdgeMultiplicity, SchemaType};
-use crate::datamodel::buffer::Buffer;
+use std::convert::TryFrom;
+
+use crate::datamodel::base::{BaseSchemaModel, EdgeLabelId, EdgeMultiplicity, SchemaType};
+use crate::datamodel::buffer::{Buffer, DecodeError};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct EdgeLabel {
-    pub(crate) id: u64,
+    pub(crate) id: EdgeLabelId,
     pub(crate) name: String,
     pub(crate) multiplicity: EdgeMultiplicity,
 }
 
+impl EdgeLabel {
+    pub fn id(&self) -> EdgeLabelId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn multiplicity(&self) -> EdgeMultiplicity {
+        self.multiplicity
+    }
+
+    /// the statement that creates this label.
+    pub fn create_statement(&self) -> String {
+        format!("CREATE EDGE LABEL ({}, {})", self.name, self.multiplicity)
+    }
+}
+
 impl BaseSchemaModel for EdgeLabel {
     fn serialize(&self) -> (Vec<u8>, Vec<u8>) {
-        let key = Self::build_key(self.id);
+        let key = Self::build_key(self.id.0);
         let mut value_buf = Buffer::new();
         value_buf.put_string(&self.name);
         value_buf.put_u8(self.multiplicity as u8);
         (key, value_buf.to_vec())
     }
 
-    fn deserialize(key: &[u8], value: &[u8]) -> Self {
+    fn deserialize(key: &[u8], value: &[u8]) -> Result<Self, DecodeError> {
         let mut key_buf = Buffer::from(key);
-        key_buf.get_u8(); // SchemaType
-        let id = key_buf.get_u64();
+        key_buf.try_get_u8()?; // SchemaType
+        let id = key_buf.try_get_u64()?;
 
         Self::deserialize_value(id, value)
     }
 
-    fn deserialize_value(id: u64, value: &[u8]) -> Self {
+    fn deserialize_value(id: u64, value: &[u8]) -> Result<Self, DecodeError> {
         let mut value_buf = Buffer::from(value);
-        let name = value_buf.get_string_utf8();
-        let multiplicity = value_buf.get_u8();
+        let name = value_buf.try_get_string_utf8()?;
+        let multiplicity = value_buf.try_get_u8()?;
 
-        EdgeLabel {
-            id,
+        Ok(EdgeLabel {
+            id: EdgeLabelId(id),
             name,
-            multiplicity: EdgeMultiplicity::from(multiplicity),
-        }
+            multiplicity: EdgeMultiplicity::try_from(multiplicity)?,
+        })
     }
 
     fn build_key(id: u64) -> Vec<u8> {
@@ -58,12 +79,28 @@ mod test {
     #[test]
     fn test_serde_edge_label_model() {
         let elm = EdgeLabel {
-            id: 1,
+            id: EdgeLabelId(1),
             name: "mock".to_string(),
             multiplicity: EdgeMultiplicity::One2One,
         };
         let ser = elm.serialize();
-        let de = EdgeLabel::deserialize(&ser.0, &ser.1);
+        let de = EdgeLabel::deserialize(&ser.0, &ser.1).unwrap();
         assert_eq!(elm, de);
     }
+
+    #[test]
+    fn test_edge_label_golden_bytes() {
+        let elm = EdgeLabel {
+            id: EdgeLabelId(0x0102),
+            name: "knows".to_string(),
+            multiplicity: EdgeMultiplicity::Many2ManyMulti,
+        };
+        let (key, value) = elm.serialize();
+        assert_eq!(key, vec![0x02, 0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+        assert_eq!(value, b"knows\0\x05".to_vec());
+
+        // an unknown multiplicity is corrupt data
+        let err = EdgeLabel::deserialize_value(0x0102, b"knows\0\x06").unwrap_err();
+        assert_eq!(err.to_string(), "corrupt data: No Such EdgeMultiplicity 6");
+    }
 }
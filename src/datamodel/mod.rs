@@ -1,9 +1,9 @@
-pub(crate) mod base;
-pub mod vertex_label;
+pub mod base;
+pub mod buffer;
+pub mod constants;
+pub mod edge;
 pub mod edge_label;
+pub mod property;
 pub mod property_key;
 pub mod vertex;
-pub mod edge;
-pub mod property;
-pub(crate) mod constants;
-mod buffer;
+pub mod vertex_label;
@@ -1,73 +1,186 @@
-use crate::datamodel::buffer::Buffer;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::datamodel::base::{Cardinality, PropertyKeyId};
+use crate::datamodel::buffer::{Buffer, DecodeError};
 use crate::datamodel::constants::STRING_TERM;
+use crate::datamodel::property_key::PropertyKey;
+
+/// each property is `key | value_len | prop_id | tag | value | STRING_TERM`, where
+/// value_len is the byte length of the value text.
+///
+/// set on the value length of properties written with a value type tag.
+/// properties written before the tag existed don't have it and are strings.
+static TAGGED_VALUE: u64 = 1 << 63;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Properties {
-    pub(crate) data: Vec<u8>
+    pub(crate) data: Vec<u8>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Property {
-    key: u64,
-    id: u64,
-    pub(crate) value: String,
+    pub(crate) key: PropertyKeyId,
+    pub(crate) id: u64,
+    pub(crate) value: PropertyValue,
+}
+
+/// value of a property. stored as its text after a one byte type tag.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl PropertyValue {
+    fn tag(&self) -> u8 {
+        match self {
+            PropertyValue::Int(_) => 1,
+            PropertyValue::Float(_) => 2,
+            PropertyValue::Bool(_) => 3,
+            PropertyValue::String(_) => 4,
+        }
+    }
+
+    /// text that does not parse as the tagged type is kept as a string.
+    fn from_tagged(tag: u8, text: String) -> PropertyValue {
+        let value = match tag {
+            1 => text.parse().ok().map(PropertyValue::Int),
+            2 => text.parse().ok().map(PropertyValue::Float),
+            3 => text.parse().ok().map(PropertyValue::Bool),
+            _ => None,
+        };
+        value.unwrap_or(PropertyValue::String(text))
+    }
+
+    pub(crate) fn serialize(&self, buf: &mut Buffer) {
+        buf.put_string(&self.to_string());
+        buf.put_u8(self.tag());
+    }
+
+    /// reads what `serialize` wrote. a missing tag reads as a string.
+    pub(crate) fn deserialize(buf: &mut Buffer) -> Result<PropertyValue, DecodeError> {
+        let text = buf.try_get_string_utf8()?;
+        Ok(match buf.has_remaining() {
+            true => PropertyValue::from_tagged(buf.try_get_u8()?, text),
+            false => PropertyValue::String(text),
+        })
+    }
+}
+
+impl Display for PropertyValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::Int(i) => write!(f, "{}", i),
+            PropertyValue::Float(x) => write!(f, "{:?}", x),
+            PropertyValue::Bool(b) => write!(f, "{}", b),
+            PropertyValue::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl From<&str> for PropertyValue {
+    fn from(s: &str) -> Self {
+        PropertyValue::String(s.to_owned())
+    }
 }
 
 impl Properties {
+    /// checks that `data` is a well formed property section, so that iterating over the
+    /// returned properties can not fail.
+    pub fn decode(data: &[u8]) -> Result<Properties, DecodeError> {
+        let mut properties = Buffer::from(data);
+        while properties.has_remaining() {
+            read_property(&mut properties)?;
+        }
+        Ok(Properties {
+            data: data.to_vec(),
+        })
+    }
+
     pub fn get_properties(&self) -> Box<PropertyIterator> {
-        let mut properties = Buffer::from(&self.data);
+        let properties = Buffer::from(&self.data);
         Box::new(PropertyIterator {
             properties,
-            offset: 0,
-            predicate: Box::new(|_, _, _| { true }),
+            predicate: Box::new(|_, _, _| true),
         })
     }
 
-    pub fn get_property(&self, key_id: u64) -> Box<PropertyIterator> {
-        let mut properties = Buffer::from(&self.data);
+    pub fn get_property(&self, key_id: PropertyKeyId) -> Box<PropertyIterator> {
+        let properties = Buffer::from(&self.data);
         Box::new(PropertyIterator {
             properties,
-            offset: 0,
-            predicate: Box::new(move |key, _, _| { key == key_id }),
+            predicate: Box::new(move |key, _, _| key == key_id),
         })
     }
 
-    pub fn add_property(&mut self, key: u64, prop_id: u64, value: &str) {
+    /// whether there is a property of `key_id`. only the headers are read, the values are
+    /// skipped over by their length.
+    pub fn contains_key(&self, key_id: PropertyKeyId) -> bool {
+        let mut properties = Buffer::from(&self.data);
+        while properties.has_remaining() {
+            let key = properties.get_u64();
+            if key == key_id.0 {
+                return true;
+            }
+            let value_len = properties.get_u64();
+            let tag_len = (value_len & TAGGED_VALUE != 0) as usize;
+            let len = (value_len & !TAGGED_VALUE) as usize;
+            // prop_id, tag, value and STRING_TERM
+            properties.advance(8 + tag_len + len + 1);
+        }
+        false
+    }
+
+    pub fn add_property(&mut self, key: PropertyKeyId, prop_id: u64, value: &PropertyValue) {
+        let text = value.to_string();
         let mut property = Buffer::new();
-        property.put_u64(key);
-        property.put_u64(value.len() as u64);
+        property.put_u64(key.0);
+        property.put_u64(text.len() as u64 | TAGGED_VALUE);
         property.put_u64(prop_id);
-        property.put_string(value);
+        property.put_u8(value.tag());
+        property.put_string(&text);
 
         self.data.append(&mut property.bytes().to_vec());
     }
 
-    pub fn remove_property(&mut self, key: u64, prop_id: Vec<u64>) {
-        let mut data = Buffer::from(&self.data);
-        let mut new_data = Buffer::new();
-        while data.has_remaining() {
-            let key_id = data.get_u64();
-            let value_len = data.get_u64();
-            let pid = data.get_u64();
-            let value = data.get_string_raw();
-            if key == key_id && (prop_id.is_empty() || prop_id.contains(&pid)) {
-                continue;
+    /// apply the cardinality of `property_key` before adding `value`: a single value
+    /// replaces the current one, a set skips values it already has.
+    /// returns false if `value` must not be added.
+    pub fn prepare_add(&mut self, property_key: &PropertyKey, value: &PropertyValue) -> bool {
+        match property_key.cardinality {
+            Cardinality::Single => {
+                self.remove_property(property_key.id, vec![]);
+                true
             }
-            new_data.put_u64(key_id);
-            new_data.put_u64(value_len);
-            new_data.put_u64(pid);
-            new_data.put_slice(&value);
-            new_data.put_u8(STRING_TERM);
+            Cardinality::Set => !self
+                .get_property(property_key.id)
+                .any(|p| &p.value == value),
+            Cardinality::List => true,
+        }
+    }
+
+    /// the remaining properties are written again by `add_property`, so untagged
+    /// properties come out in the current layout.
+    pub fn remove_property(&mut self, key: PropertyKeyId, prop_id: Vec<u64>) {
+        let remaining = self
+            .get_properties()
+            .filter(|p| !(p.key == key && (prop_id.is_empty() || prop_id.contains(&p.id))))
+            .collect::<Vec<_>>();
+        self.data.clear();
+        for p in remaining {
+            self.add_property(p.key, p.id, &p.value);
         }
-        self.data = new_data.to_vec();
     }
 }
 
+type PropertyPredicate = Box<dyn Fn(PropertyKeyId, u64, &PropertyValue) -> bool>;
 
 pub struct PropertyIterator {
     properties: Buffer,
-    offset: u32,
-    predicate: Box<dyn Fn(u64, u64, &str) -> bool>,
+    predicate: PropertyPredicate,
 }
 
 impl Iterator for PropertyIterator {
@@ -75,23 +188,36 @@ impl Iterator for PropertyIterator {
 
     fn next(&mut self) -> Option<Self::Item> {
         while self.properties.has_remaining() {
-            let key_id = self.properties.get_u64();
-            let value_len = self.properties.get_u64() as usize;
-            let prop_id = self.properties.get_u64();
-            let value = self.properties.get_string_utf8();
-
-            if (self.predicate)(key_id, prop_id, &value) {
-                return Some(Property {
-                    key: key_id,
-                    id: prop_id,
-                    value,
-                });
+            let property = read_property(&mut self.properties)
+                .expect("property sections are checked by Properties::decode");
+            if (self.predicate)(property.key, property.id, &property.value) {
+                return Some(property);
             }
         }
         None
     }
 }
 
+fn read_property(properties: &mut Buffer) -> Result<Property, DecodeError> {
+    let key = PropertyKeyId(properties.try_get_u64()?);
+    let value_len = properties.try_get_u64()?;
+    let id = properties.try_get_u64()?;
+    let tag = match value_len & TAGGED_VALUE {
+        0 => None,
+        _ => Some(properties.try_get_u8()?),
+    };
+    let len = (value_len & !TAGGED_VALUE) as usize;
+    let text = String::from_utf8(properties.try_get_slice(len)?)
+        .map_err(|e| DecodeError::new(e.to_string()))?;
+    if properties.try_get_u8()? != STRING_TERM {
+        return Err(DecodeError::new("unterminated property value".to_owned()));
+    }
+    let value = match tag {
+        Some(tag) => PropertyValue::from_tagged(tag, text),
+        None => PropertyValue::String(text),
+    };
+    Ok(Property { key, id, value })
+}
 
 #[cfg(test)]
 mod test {
@@ -99,8 +225,8 @@ mod test {
 
     fn build_test_properties() -> Properties {
         let mut properties = Properties { data: Vec::new() };
-        properties.add_property(12, 99, "hello angelina");
-        properties.add_property(13, 100, "hello angelina2");
+        properties.add_property(PropertyKeyId(12), 99, &"hello angelina".into());
+        properties.add_property(PropertyKeyId(13), 100, &"hello angelina2".into());
         properties
     }
 
@@ -116,30 +242,168 @@ mod test {
     fn test_get_property() {
         let properties = build_test_properties();
 
-        for p in properties.get_property(12) {
-            assert_eq!(p, Property { key: 12, id: 99, value: "hello angelina".to_string() })
+        for p in properties.get_property(PropertyKeyId(12)) {
+            assert_eq!(
+                p,
+                Property {
+                    key: PropertyKeyId(12),
+                    id: 99,
+                    value: "hello angelina".into()
+                }
+            )
         }
-        for p in properties.get_property(13) {
-            assert_eq!(p, Property { key: 13, id: 100, value: "hello angelina2".to_string() })
+        for p in properties.get_property(PropertyKeyId(13)) {
+            assert_eq!(
+                p,
+                Property {
+                    key: PropertyKeyId(13),
+                    id: 100,
+                    value: "hello angelina2".into()
+                }
+            )
         }
     }
 
     #[test]
     fn test_write_property() {
         let mut properties = build_test_properties();
-        properties.add_property(14, 101, "hello angelina3");
-        for p in properties.get_property(14) {
-            assert_eq!(p, Property { key: 14, id: 101, value: "hello angelina3".to_string() })
+        properties.add_property(PropertyKeyId(14), 101, &"hello angelina3".into());
+        for p in properties.get_property(PropertyKeyId(14)) {
+            assert_eq!(
+                p,
+                Property {
+                    key: PropertyKeyId(14),
+                    id: 101,
+                    value: "hello angelina3".into()
+                }
+            )
         }
     }
 
     #[test]
     fn test_multi_property() {
         let mut properties = build_test_properties();
-        properties.add_property(12, 102, "hello angelina3");
-        properties.add_property(12, 104, "hello angelina3");
+        properties.add_property(PropertyKeyId(12), 102, &"hello angelina3".into());
+        properties.add_property(PropertyKeyId(12), 104, &"hello angelina3".into());
         assert_eq!(
-            properties.get_property(12).map(|p| { p.id }).collect::<Vec<u64>>(),
-            vec![99, 102, 104])
+            properties
+                .get_property(PropertyKeyId(12))
+                .map(|p| { p.id })
+                .collect::<Vec<u64>>(),
+            vec![99, 102, 104]
+        )
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let values = vec![
+            PropertyValue::Int(-42),
+            PropertyValue::Float(2.5),
+            PropertyValue::Float(3.0),
+            PropertyValue::Bool(true),
+            PropertyValue::String("42".to_string()),
+        ];
+        let mut properties = Properties { data: Vec::new() };
+        for (i, value) in values.iter().enumerate() {
+            properties.add_property(PropertyKeyId(1), i as u64, value);
+        }
+        assert_eq!(
+            properties
+                .get_properties()
+                .map(|p| p.value)
+                .collect::<Vec<_>>(),
+            values
+        );
+        properties.remove_property(PropertyKeyId(1), vec![0]);
+        assert_eq!(
+            properties
+                .get_properties()
+                .map(|p| p.value)
+                .collect::<Vec<_>>(),
+            values[1..].to_vec()
+        );
+    }
+
+    #[test]
+    fn test_untagged_values_are_strings() {
+        // layout of properties written before values were tagged
+        let mut buf = Buffer::new();
+        buf.put_u64(7);
+        buf.put_u64(2);
+        buf.put_u64(1);
+        buf.put_string("42");
+        let properties = Properties { data: buf.to_vec() };
+        assert_eq!(
+            properties
+                .get_property(PropertyKeyId(7))
+                .next()
+                .unwrap()
+                .value,
+            PropertyValue::String("42".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        let properties = build_test_properties();
+        assert_eq!(Properties::decode(&properties.data).unwrap(), properties);
+
+        let data = &properties.data;
+        for corrupt in &[&data[..data.len() - 1], &data[..12]] {
+            assert!(Properties::decode(corrupt).is_err());
+        }
+        let mut not_utf8 = data.clone();
+        not_utf8[25] = 0xff;
+        assert!(Properties::decode(&not_utf8).is_err());
+    }
+
+    #[test]
+    fn test_contains_key() {
+        let mut properties = build_test_properties();
+        assert!(properties.contains_key(PropertyKeyId(13)));
+        assert!(!properties.contains_key(PropertyKeyId(14)));
+
+        // a large value that is not utf8, so decoding it would fail
+        let mut large = Buffer::new();
+        large.put_u64(15);
+        large.put_u64(1 << 20);
+        large.put_u64(1);
+        large.put_slice(&vec![0xff; 1 << 20]);
+        large.put_u8(STRING_TERM);
+        properties.data.extend_from_slice(large.bytes());
+        properties.add_property(PropertyKeyId(16), 2, &PropertyValue::Int(1));
+        assert!(properties.contains_key(PropertyKeyId(15)));
+        assert!(properties.contains_key(PropertyKeyId(16)));
+        assert!(!properties.contains_key(PropertyKeyId(17)));
+    }
+
+    #[test]
+    fn test_remove_middle_property() {
+        let mut properties = Properties { data: Vec::new() };
+        properties.add_property(PropertyKeyId(1), 10, &"first".into());
+        properties.add_property(PropertyKeyId(2), 11, &PropertyValue::Int(2));
+        properties.add_property(PropertyKeyId(3), 12, &"third".into());
+        properties.remove_property(PropertyKeyId(2), vec![]);
+        assert_eq!(
+            properties.get_properties().collect::<Vec<_>>(),
+            vec![
+                Property {
+                    key: PropertyKeyId(1),
+                    id: 10,
+                    value: "first".into()
+                },
+                Property {
+                    key: PropertyKeyId(3),
+                    id: 12,
+                    value: "third".into()
+                },
+            ]
+        );
+
+        // same bytes as never having added the middle one
+        let mut expected = Properties { data: Vec::new() };
+        expected.add_property(PropertyKeyId(1), 10, &"first".into());
+        expected.add_property(PropertyKeyId(3), 12, &"third".into());
+        assert_eq!(properties, expected);
     }
 }
@@ -1,40 +1,97 @@
-use crate::datamodel::base::{BaseSchemaModel, Cardinality, SchemaType};
-use crate::datamodel::buffer::Buffer;
+use std::convert::TryFrom;
 
-#[derive(Debug, Eq, PartialEq)]
+use crate::datamodel::base::{BaseSchemaModel, Cardinality, PropertyKeyId, SchemaType};
+use crate::datamodel::buffer::{Buffer, DecodeError};
+use crate::datamodel::property::PropertyValue;
+
+/// fields after the cardinality are optional and written as `field | len u32 | payload`,
+/// so readers skip the fields they don't know. 0xf8..=0xff never appear in utf8, so a field
+/// can't be taken for the untagged default value text of keys written before fields were
+/// tagged.
+const DEFAULT_VALUE_FIELD: u8 = 0xff;
+
+#[derive(Debug, PartialEq)]
 pub struct PropertyKey {
-    pub(crate) id: u64,
+    pub(crate) id: PropertyKeyId,
     pub(crate) name: String,
     pub(crate) cardinality: Cardinality,
+    /// value filled in when a vertex is inserted without this property.
+    pub(crate) default_value: Option<PropertyValue>,
+}
+
+impl PropertyKey {
+    pub fn id(&self) -> PropertyKeyId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn cardinality(&self) -> Cardinality {
+        self.cardinality
+    }
+
+    pub fn default_value(&self) -> Option<&PropertyValue> {
+        self.default_value.as_ref()
+    }
 }
 
 impl BaseSchemaModel for PropertyKey {
     fn serialize(&self) -> (Vec<u8>, Vec<u8>) {
-        let key = Self::build_key(self.id);
+        let key = Self::build_key(self.id.0);
         let mut value_buf = Buffer::new();
         value_buf.put_string(&self.name);
         value_buf.put_u8(self.cardinality as u8);
+        if let Some(default_value) = &self.default_value {
+            let mut field = Buffer::new();
+            default_value.serialize(&mut field);
+            value_buf.put_u8(DEFAULT_VALUE_FIELD);
+            value_buf.put_u32(field.bytes().len() as u32);
+            value_buf.put_slice(field.bytes());
+        }
         (key, value_buf.to_vec())
     }
 
-    fn deserialize(key: &[u8], value: &[u8]) -> Self {
+    fn deserialize(key: &[u8], value: &[u8]) -> Result<Self, DecodeError> {
         let mut key_buf = Buffer::from(key);
-        key_buf.get_u8(); // SchemaType
-        let id = key_buf.get_u64();
+        key_buf.try_get_u8()?; // SchemaType
+        let id = key_buf.try_get_u64()?;
 
         Self::deserialize_value(id, value)
     }
 
-    fn deserialize_value(id: u64, value: &[u8]) -> Self {
+    fn deserialize_value(id: u64, value: &[u8]) -> Result<Self, DecodeError> {
         let mut value_buf = Buffer::from(value);
-        let name = value_buf.get_string_utf8();
-        let cardinality = value_buf.get_u8();
+        let name = value_buf.try_get_string_utf8()?;
+        let cardinality = value_buf.try_get_u8()?;
+        // keys stored before defaults existed end after the cardinality
+        let mut default_value = None;
+        while value_buf.has_remaining() {
+            let field = value_buf.bytes()[0];
+            if field < 0xf8 {
+                // untagged default value, the last thing in the value
+                default_value = Some(PropertyValue::deserialize(&mut value_buf)?);
+                break;
+            }
+            value_buf.advance(1);
+            let len = value_buf.try_get_u32()? as usize;
+            let field_bytes = value_buf.bytes().get(..len).ok_or_else(|| {
+                DecodeError::new(format!("field {} is shorter than {} bytes", field, len))
+            })?;
+            if field == DEFAULT_VALUE_FIELD {
+                let mut field_buf = Buffer::from(field_bytes);
+                default_value = Some(PropertyValue::deserialize(&mut field_buf)?);
+            }
+            value_buf.advance(len);
+        }
 
-        PropertyKey {
-            id,
+        Ok(PropertyKey {
+            id: PropertyKeyId(id),
             name,
-            cardinality: Cardinality::from(cardinality),
-        }
+            cardinality: Cardinality::try_from(cardinality)?,
+            default_value,
+        })
     }
 
     fn build_key(id: u64) -> Vec<u8> {
@@ -58,12 +115,61 @@ mod test {
     #[test]
     fn test_serde_edge_label_model() {
         let pkm = PropertyKey {
-            id: 1,
+            id: PropertyKeyId(1),
             name: "mock".to_string(),
             cardinality: Cardinality::Single,
+            default_value: None,
         };
         let ser = pkm.serialize();
-        let de = PropertyKey::deserialize(&ser.0, &ser.1);
+        let de = PropertyKey::deserialize(&ser.0, &ser.1).unwrap();
         assert_eq!(pkm, de);
+
+        let pkm = PropertyKey {
+            default_value: Some(PropertyValue::Int(1)),
+            ..pkm
+        };
+        let ser = pkm.serialize();
+        let de = PropertyKey::deserialize(&ser.0, &ser.1).unwrap();
+        assert_eq!(pkm, de);
+    }
+
+    #[test]
+    fn test_property_key_golden_bytes() {
+        let mut pkm = PropertyKey {
+            id: PropertyKeyId(0x0102),
+            name: "age".to_string(),
+            cardinality: Cardinality::Set,
+            default_value: None,
+        };
+        let (key, value) = pkm.serialize();
+        assert_eq!(key, vec![0x03, 0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+        assert_eq!(value, vec![b'a', b'g', b'e', 0x00, 0x03]);
+
+        pkm.default_value = Some(PropertyValue::Int(-7));
+        let (_, value) = pkm.serialize();
+        assert_eq!(
+            value,
+            vec![b'a', b'g', b'e', 0x00, 0x03, 0xff, 0, 0, 0, 4, b'-', b'7', 0x00, 0x01]
+        );
+        assert_eq!(PropertyKey::deserialize_value(0x0102, &value).unwrap(), pkm);
+
+        // untagged default value of keys written before fields were tagged
+        let legacy = vec![b'a', b'g', b'e', 0x00, 0x03, b'-', b'7', 0x00, 0x01];
+        assert_eq!(
+            PropertyKey::deserialize_value(0x0102, &legacy).unwrap(),
+            pkm
+        );
+
+        // unknown fields are skipped
+        let mut future = value.clone();
+        future.splice(5..5, vec![0xfe, 0, 0, 0, 2, 0xaa, 0xbb]);
+        assert_eq!(
+            PropertyKey::deserialize_value(0x0102, &future).unwrap(),
+            pkm
+        );
+
+        // an unknown cardinality is corrupt data
+        let err = PropertyKey::deserialize_value(0x0102, &[b'a', 0x00, 0x09]).unwrap_err();
+        assert_eq!(err.to_string(), "corrupt data: No Such Cardinality 9");
     }
 }
@@ -1,33 +1,51 @@
-use crate::datamodel::base::{BaseSchemaModel, SchemaType};
-use crate::datamodel::buffer::Buffer;
+use crate::datamodel::base::{BaseSchemaModel, SchemaType, VertexLabelId};
+use crate::datamodel::buffer::{Buffer, DecodeError};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct VertexLabel {
-    pub(crate) id: u64,
+    pub(crate) id: VertexLabelId,
     pub(crate) name: String,
 }
 
+impl VertexLabel {
+    pub fn id(&self) -> VertexLabelId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the statement that creates this label.
+    pub fn create_statement(&self) -> String {
+        format!("CREATE VERTEX LABEL {}", self.name)
+    }
+}
+
 impl BaseSchemaModel for VertexLabel {
     fn serialize(&self) -> (Vec<u8>, Vec<u8>) {
-        let key = Self::build_key(self.id);
+        let key = Self::build_key(self.id.0);
 
         let mut value_buf = Buffer::new();
         value_buf.put_string(&self.name);
         (key, value_buf.to_vec())
     }
 
-    fn deserialize(key: &[u8], value: &[u8]) -> Self {
+    fn deserialize(key: &[u8], value: &[u8]) -> Result<Self, DecodeError> {
         let mut key_buf = Buffer::from(key);
-        key_buf.get_u8(); // SchemaType
-        let id = key_buf.get_u64();
+        key_buf.try_get_u8()?; // SchemaType
+        let id = key_buf.try_get_u64()?;
 
         Self::deserialize_value(id, value)
     }
 
-    fn deserialize_value(id: u64, value: &[u8]) -> Self {
-        let name = Buffer::from(value).get_string_utf8();
+    fn deserialize_value(id: u64, value: &[u8]) -> Result<Self, DecodeError> {
+        let name = Buffer::from(value).try_get_string_utf8()?;
 
-        VertexLabel { id, name }
+        Ok(VertexLabel {
+            id: VertexLabelId(id),
+            name,
+        })
     }
 
     fn build_key(id: u64) -> Vec<u8> {
@@ -51,11 +69,22 @@ mod test {
     #[test]
     fn test_serde_vertex_model() {
         let vlm = VertexLabel {
-            id: 1,
+            id: VertexLabelId(1),
             name: "mock".to_string(),
         };
         let ser = vlm.serialize();
-        let de = VertexLabel::deserialize(&ser.0, &ser.1);
+        let de = VertexLabel::deserialize(&ser.0, &ser.1).unwrap();
         assert_eq!(vlm, de);
     }
+
+    #[test]
+    fn test_vertex_label_golden_bytes() {
+        let vlm = VertexLabel {
+            id: VertexLabelId(0x0102),
+            name: "person".to_string(),
+        };
+        let (key, value) = vlm.serialize();
+        assert_eq!(key, vec![0x01, 0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+        assert_eq!(value, b"person\0".to_vec());
+    }
 }
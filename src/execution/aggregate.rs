@@ -0,0 +1,136 @@
+use std::cmp::Ordering;
+
+use crate::execution::eval::{compare_values, evaluate, evaluate_binary_op};
+use crate::execution::executor::ExecutionError;
+use crate::execution::memory::ExecutionMemory;
+use crate::parser::ast::{Expr, Value};
+use crate::parser::operator::BinaryOperator;
+
+/// running state of one aggregate function call over the rows of a group.
+/// NULL arguments are skipped, and an aggregate other than count over no values is NULL.
+pub struct Accumulator {
+    func_name: String,
+    argument: Expr,
+    state: State,
+}
+
+enum State {
+    Count(u64),
+    Sum(Option<Value>),
+    /// sum and count
+    Avg(Option<Value>, u64),
+    Min(Option<Value>),
+    Max(Option<Value>),
+}
+
+impl Accumulator {
+    pub fn new(expr: &Expr) -> Result<Self, ExecutionError> {
+        let (func_name, arguments) = match expr {
+            Expr::Function {
+                func_name,
+                arguments,
+            } => (func_name, arguments),
+            _ => {
+                return Err(ExecutionError::new(format!(
+                    "{:?} is not an aggregate",
+                    expr
+                )))
+            }
+        };
+        let argument = match &arguments[..] {
+            [argument] => argument.clone(),
+            _ => {
+                return Err(ExecutionError::new(format!(
+                    "{} expects 1 argument but got {}",
+                    func_name,
+                    arguments.len()
+                )))
+            }
+        };
+        let state = match func_name.to_lowercase().as_str() {
+            "count" => State::Count(0),
+            "sum" => State::Sum(None),
+            "avg" => State::Avg(None, 0),
+            "min" => State::Min(None),
+            "max" => State::Max(None),
+            _ => {
+                return Err(ExecutionError::new(format!(
+                    "unknown aggregate {}",
+                    func_name
+                )))
+            }
+        };
+        Ok(Accumulator {
+            func_name: func_name.to_owned(),
+            argument,
+            state,
+        })
+    }
+
+    pub fn update(&mut self, row: &ExecutionMemory) -> Result<(), ExecutionError> {
+        // count(*) counts rows, the others skip NULL
+        let value = match &self.argument {
+            Expr::Wildcard => Value::Boolean(true),
+            argument => evaluate(argument, row)?,
+        };
+        if value == Value::Null {
+            return Ok(());
+        }
+        let collation = row.collation();
+        match &mut self.state {
+            State::Count(n) => *n += 1,
+            State::Sum(sum) => Self::add(&self.func_name, sum, value, row)?,
+            State::Avg(sum, n) => {
+                Self::add(&self.func_name, sum, value, row)?;
+                *n += 1;
+            }
+            State::Min(min) => {
+                if min.as_ref().map_or(true, |min| {
+                    compare_values(&value, min, collation) == Ordering::Less
+                }) {
+                    *min = Some(value);
+                }
+            }
+            State::Max(max) => {
+                if max.as_ref().map_or(true, |max| {
+                    compare_values(&value, max, collation) == Ordering::Greater
+                }) {
+                    *max = Some(value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add(
+        func_name: &str,
+        sum: &mut Option<Value>,
+        value: Value,
+        row: &ExecutionMemory,
+    ) -> Result<(), ExecutionError> {
+        if !matches!(value, Value::Number(_)) {
+            return Err(ExecutionError::new(format!(
+                "{} expects numbers but got {:?}",
+                func_name, value
+            )));
+        }
+        *sum = Some(match sum.take() {
+            Some(sum) => evaluate_binary_op(&BinaryOperator::Plus, &sum, &value, row.collation())?,
+            None => value,
+        });
+        Ok(())
+    }
+
+    pub fn finish(&self) -> Value {
+        match &self.state {
+            State::Count(n) => Value::Number(n.to_string()),
+            State::Avg(Some(Value::Number(sum)), n) => {
+                Value::Number((sum.parse::<f64>().unwrap() / *n as f64).to_string())
+            }
+            State::Sum(Some(value)) | State::Min(Some(value)) | State::Max(Some(value)) => {
+                value.clone()
+            }
+            _ => Value::Null,
+        }
+    }
+}
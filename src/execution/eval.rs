@@ -0,0 +1,1021 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::datamodel::property::PropertyValue;
+use crate::execution::executor::ExecutionError;
+use crate::execution::memory::ExecutionMemory;
+use crate::parser::ast::{Expr, OrderByExpr, Value};
+use crate::parser::operator::{BinaryOperator, UnaryOperator};
+
+/// evaluate an expression against the elements bound in `row`.
+/// a property evaluates to the value of its type, see `property_to_value`. a missing
+/// property evaluates to NULL.
+pub fn evaluate(expr: &Expr, row: &ExecutionMemory) -> Result<Value, ExecutionError> {
+    match expr {
+        Expr::Value(value) => Ok(value.clone()),
+        Expr::Nested(expr) => evaluate(expr, row),
+        Expr::Identifier(name) if row.get_value(name).is_some() => {
+            Ok(row.get_value(name).unwrap().clone())
+        }
+        Expr::CompoundIdentifier(idents) if row.get_value(&idents.join(".")).is_some() => {
+            Ok(row.get_value(&idents.join(".")).unwrap().clone())
+        }
+        Expr::Identifier(name) | Expr::IdExpr(name) => evaluate_element_id(name, row),
+        // computed by the Aggregate operator
+        Expr::Function { .. } if row.get_value(&column_name(expr)).is_some() => {
+            Ok(row.get_value(&column_name(expr)).unwrap().clone())
+        }
+        Expr::Function {
+            func_name,
+            arguments,
+        } => evaluate_function(func_name, arguments, row),
+        Expr::LabelExpr(name) => evaluate_label(name, row),
+        Expr::CompoundIdentifier(idents) => match &idents[..] {
+            [name, prop] => evaluate_property(name, prop, row),
+            _ => Err(ExecutionError::new(format!(
+                "can not resolve {}",
+                idents.join(".")
+            ))),
+        },
+        Expr::UnaryOp { op, expr } => evaluate_unary_op(op, &evaluate(expr, row)?),
+        Expr::InList { expr, list } => evaluate_in_list(expr, list, row),
+        Expr::IsNull { expr, negated } => {
+            Ok(Value::Boolean(evaluate_is_null(expr, row)? != *negated))
+        }
+        Expr::Exists(_) => Err(ExecutionError::new(
+            "EXISTS is only supported in the conditions of WHERE joined by AND".to_string(),
+        )),
+        Expr::BinaryOp {
+            op: BinaryOperator::And,
+            left,
+            right,
+        } => evaluate_and(&evaluate(left, row)?, &evaluate(right, row)?),
+        Expr::BinaryOp {
+            op: BinaryOperator::Or,
+            left,
+            right,
+        } => evaluate_or(&evaluate(left, row)?, &evaluate(right, row)?),
+        Expr::BinaryOp { op, left, right } => evaluate_binary_op(
+            op,
+            &evaluate(left, row)?,
+            &evaluate(right, row)?,
+            row.collation(),
+        ),
+        _ => Err(ExecutionError::new(format!("can not evaluate {:?}", expr))),
+    }
+}
+
+/// render a value as an output column.
+pub fn value_to_string(value: Value) -> String {
+    match value {
+        Value::Number(n) => n,
+        Value::String(s) => s,
+        Value::Boolean(b) => b.to_string(),
+        Value::Null => "NULL".to_string(),
+    }
+}
+
+/// name of the output column of a projected expression, e.g. `a.prop1`.
+pub fn column_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Value(value) => value_to_string(value.clone()),
+        Expr::Identifier(name) => name.to_owned(),
+        Expr::CompoundIdentifier(idents) => idents.join("."),
+        Expr::LabelExpr(name) => format!("{}.label", name),
+        Expr::IdExpr(name) => format!("{}.id", name),
+        Expr::Wildcard => "*".to_string(),
+        Expr::Nested(expr) => format!("({})", column_name(expr)),
+        Expr::IsNull { expr, negated } => format!(
+            "{} IS {}NULL",
+            column_name(expr),
+            if *negated { "NOT " } else { "" }
+        ),
+        Expr::Function {
+            func_name,
+            arguments,
+        } => format!(
+            "{}({})",
+            func_name,
+            arguments
+                .iter()
+                .map(column_name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        expr => expr.to_string(),
+    }
+}
+
+/// whether `func_name` aggregates over rows rather than computing over a single row.
+pub fn is_aggregate(func_name: &str) -> bool {
+    AGGREGATES.contains(&func_name.to_lowercase().as_str())
+}
+
+static AGGREGATES: [&str; 5] = ["count", "sum", "avg", "min", "max"];
+
+fn evaluate_element_id(name: &str, row: &ExecutionMemory) -> Result<Value, ExecutionError> {
+    if let Some(vertex) = row.get_vertex(name) {
+        Ok(Value::String(vertex.id.to_owned()))
+    } else if let Some(edge) = row.get_edge(name) {
+        Ok(Value::Number(edge.edge_id.to_string()))
+    } else {
+        Err(unbound_element(name))
+    }
+}
+
+/// the endpoint of `edge` that is not `vertex`, e.g. the far vertex of an undirected edge.
+/// `vertex` itself for a self loop.
+fn evaluate_other_end(
+    edge: &str,
+    vertex: &str,
+    row: &ExecutionMemory,
+) -> Result<Value, ExecutionError> {
+    let edge = row.get_edge(edge).ok_or_else(|| unbound_element(edge))?;
+    let vertex = row
+        .get_vertex(vertex)
+        .ok_or_else(|| unbound_element(vertex))?;
+    match edge.src_vertex_id == vertex.id {
+        true => Ok(Value::String(edge.dst_vertex_id.to_owned())),
+        false => Ok(Value::String(edge.src_vertex_id.to_owned())),
+    }
+}
+
+fn evaluate_label(name: &str, row: &ExecutionMemory) -> Result<Value, ExecutionError> {
+    let catalog = row.catalog();
+    let label = if let Some(vertex) = row.get_vertex(name) {
+        catalog.vertex_labels.get(&vertex.label)
+    } else if let Some(edge) = row.get_edge(name) {
+        catalog.edge_labels.get(&edge.label)
+    } else {
+        return Err(unbound_element(name));
+    };
+    Ok(label.map_or(Value::Null, |label| Value::String(label.to_owned())))
+}
+
+fn evaluate_function(
+    func_name: &str,
+    arguments: &[Expr],
+    row: &ExecutionMemory,
+) -> Result<Value, ExecutionError> {
+    match (func_name.to_lowercase().as_str(), arguments) {
+        ("label_id", [Expr::Identifier(name)]) => evaluate_label_id(name, row),
+        ("label", [Expr::Identifier(name)]) => evaluate_label(name, row),
+        ("id", [Expr::Identifier(name)]) => evaluate_element_id(name, row),
+        ("other_end", [Expr::Identifier(edge), Expr::Identifier(vertex)]) => {
+            evaluate_other_end(edge, vertex, row)
+        }
+        ("has_value", [Expr::Identifier(name), pattern]) => {
+            evaluate_has_value(name, &evaluate(pattern, row)?, row)
+        }
+        (name, _) => match SCALAR_FUNCTIONS.iter().find(|f| f.name == name) {
+            Some(function) => {
+                // innermost calls first
+                let arguments = arguments
+                    .iter()
+                    .map(|arg| evaluate(arg, row))
+                    .collect::<Result<Vec<_>, _>>()?;
+                function.call(&arguments)
+            }
+            None => Err(ExecutionError::new(format!(
+                "unknown function {}",
+                column_name(&Expr::Function {
+                    func_name: func_name.to_owned(),
+                    arguments: arguments.to_vec(),
+                })
+            ))),
+        },
+    }
+}
+
+/// a function computed from the values of its arguments.
+struct ScalarFunction {
+    name: &'static str,
+    min_args: usize,
+    /// None for any number of arguments.
+    max_args: Option<usize>,
+    eval: fn(&[Value]) -> Result<Value, String>,
+}
+
+impl ScalarFunction {
+    /// a NULL argument makes the result NULL.
+    fn call(&self, arguments: &[Value]) -> Result<Value, ExecutionError> {
+        let expected = match self.max_args {
+            Some(max) if max == self.min_args => max.to_string(),
+            Some(max) => format!("{} to {}", self.min_args, max),
+            None => format!("at least {}", self.min_args),
+        };
+        if arguments.len() < self.min_args || self.max_args.is_some_and(|max| arguments.len() > max)
+        {
+            return Err(ExecutionError::new(format!(
+                "{} expects {} arguments but got {}",
+                self.name,
+                expected,
+                arguments.len()
+            )));
+        }
+        if arguments.contains(&Value::Null) {
+            return Ok(Value::Null);
+        }
+        (self.eval)(arguments).map_err(|msg| ExecutionError::new(format!("{} {}", self.name, msg)))
+    }
+}
+
+static SCALAR_FUNCTIONS: [ScalarFunction; 4] = [
+    ScalarFunction {
+        name: "upper",
+        min_args: 1,
+        max_args: Some(1),
+        eval: |args| Ok(Value::String(string_argument(&args[0])?.to_uppercase())),
+    },
+    ScalarFunction {
+        name: "lower",
+        min_args: 1,
+        max_args: Some(1),
+        eval: |args| Ok(Value::String(string_argument(&args[0])?.to_lowercase())),
+    },
+    ScalarFunction {
+        name: "length",
+        min_args: 1,
+        max_args: Some(1),
+        eval: |args| {
+            Ok(Value::Number(
+                string_argument(&args[0])?.chars().count().to_string(),
+            ))
+        },
+    },
+    ScalarFunction {
+        name: "concat",
+        min_args: 1,
+        max_args: None,
+        eval: |args| {
+            Ok(Value::String(
+                args.iter().cloned().map(value_to_string).collect(),
+            ))
+        },
+    },
+];
+
+fn string_argument(value: &Value) -> Result<&str, String> {
+    match value {
+        Value::String(s) => Ok(s),
+        _ => Err(format!("expects strings but got {:?}", value)),
+    }
+}
+
+/// id of the label as stored on the element, without resolving its name.
+fn evaluate_label_id(name: &str, row: &ExecutionMemory) -> Result<Value, ExecutionError> {
+    if let Some(vertex) = row.get_vertex(name) {
+        Ok(Value::Number(vertex.label.to_string()))
+    } else if let Some(edge) = row.get_edge(name) {
+        Ok(Value::Number(edge.label.to_string()))
+    } else {
+        Err(unbound_element(name))
+    }
+}
+
+fn evaluate_property(
+    name: &str,
+    prop: &str,
+    row: &ExecutionMemory,
+) -> Result<Value, ExecutionError> {
+    let properties = if let Some(vertex) = row.get_vertex(name) {
+        &vertex.properties
+    } else if let Some(edge) = row.get_edge(name) {
+        match prop {
+            "src" => return Ok(Value::String(edge.src_vertex_id.to_owned())),
+            "dst" => return Ok(Value::String(edge.dst_vertex_id.to_owned())),
+            _ => &edge.properties,
+        }
+    } else {
+        return Err(unbound_element(name));
+    };
+    let property = row
+        .catalog()
+        .property_keys
+        .get(prop)
+        .and_then(|key_id| properties.get_property(*key_id).next());
+    Ok(property.map_or(Value::Null, |p| property_to_value(p.value)))
+}
+
+/// `has_value(a, pattern)`: whether any property value of the element `a` matches the
+/// LIKE `pattern`, e.g. `has_value(a, '%foo%')`. the src and dst of an edge are not
+/// properties.
+fn evaluate_has_value(
+    name: &str,
+    pattern: &Value,
+    row: &ExecutionMemory,
+) -> Result<Value, ExecutionError> {
+    let properties = match (row.get_vertex(name), row.get_edge(name)) {
+        (Some(vertex), _) => &vertex.properties,
+        (None, Some(edge)) => &edge.properties,
+        _ => return Err(unbound_element(name)),
+    };
+    if *pattern == Value::Null {
+        return Ok(Value::Null);
+    }
+    for property in properties.get_properties() {
+        let value = property_to_value(property.value);
+        if let Value::Boolean(_) = value {
+            continue;
+        }
+        if evaluate_like(&value, pattern, row.collation())? == Value::Boolean(true) {
+            return Ok(Value::Boolean(true));
+        }
+    }
+    Ok(Value::Boolean(false))
+}
+
+/// whether `expr` is NULL. a property of a bound element is only checked for presence,
+/// without decoding its value.
+fn evaluate_is_null(expr: &Expr, row: &ExecutionMemory) -> Result<bool, ExecutionError> {
+    if let Expr::CompoundIdentifier(idents) = expr {
+        if let ([name, prop], None) = (&idents[..], row.get_value(&idents.join("."))) {
+            let properties = match (row.get_vertex(name), row.get_edge(name)) {
+                (Some(vertex), _) => Some(&vertex.properties),
+                (None, Some(edge)) if prop != "src" && prop != "dst" => Some(&edge.properties),
+                _ => None,
+            };
+            if let Some(properties) = properties {
+                return Ok(row
+                    .catalog()
+                    .property_keys
+                    .get(prop)
+                    .map_or(true, |key_id| !properties.contains_key(*key_id)));
+            }
+        }
+    }
+    Ok(evaluate(expr, row)? == Value::Null)
+}
+
+pub fn property_to_value(value: PropertyValue) -> Value {
+    match value {
+        PropertyValue::Int(_) | PropertyValue::Float(_) => Value::Number(value.to_string()),
+        PropertyValue::Bool(b) => Value::Boolean(b),
+        PropertyValue::String(s) => Value::String(s),
+    }
+}
+
+fn unbound_element(name: &str) -> ExecutionError {
+    ExecutionError::new(format!("element {} is not bound", name))
+}
+
+fn evaluate_unary_op(op: &UnaryOperator, value: &Value) -> Result<Value, ExecutionError> {
+    match (op, value) {
+        (_, Value::Null) => Ok(Value::Null),
+        (UnaryOperator::Not, Value::Boolean(b)) => Ok(Value::Boolean(!b)),
+        (UnaryOperator::Plus, Value::Number(_)) => Ok(value.clone()),
+        (UnaryOperator::Minus, Value::Number(n)) => {
+            Ok(Value::Number(Number::parse(n)?.negate().to_string()))
+        }
+        _ => Err(ExecutionError::new(format!(
+            "can not apply {:?} to {:?}",
+            op, value
+        ))),
+    }
+}
+
+pub(crate) fn evaluate_binary_op(
+    op: &BinaryOperator,
+    left: &Value,
+    right: &Value,
+    collation: Collation,
+) -> Result<Value, ExecutionError> {
+    if let (Value::Null, _) | (_, Value::Null) = (left, right) {
+        return Ok(Value::Null);
+    }
+    if op == &BinaryOperator::Like {
+        return evaluate_like(left, right, collation);
+    }
+    let ordering = compare_operands(left, right, collation);
+    let result = match op {
+        BinaryOperator::Eq => ordering == Some(Ordering::Equal),
+        BinaryOperator::NotEq => ordering != Some(Ordering::Equal),
+        BinaryOperator::Gt | BinaryOperator::Lt | BinaryOperator::Gte | BinaryOperator::Lte => {
+            let ordering = match ordering {
+                Some(ordering) => ordering,
+                // incomparable types, e.g. 'abc' < 1
+                None => return Ok(Value::Null),
+            };
+            match op {
+                BinaryOperator::Gt => ordering == Ordering::Greater,
+                BinaryOperator::Lt => ordering == Ordering::Less,
+                BinaryOperator::Gte => ordering != Ordering::Less,
+                _ => ordering != Ordering::Greater,
+            }
+        }
+        _ => {
+            return match (left, right) {
+                (Value::Number(l), Value::Number(r)) => {
+                    let (l, r) = (Number::parse(l)?, Number::parse(r)?);
+                    Ok(Value::Number(l.arithmetic(op, &r)?.to_string()))
+                }
+                _ => Err(ExecutionError::new(format!(
+                    "can not apply {:?} to {:?} and {:?}",
+                    op, left, right
+                ))),
+            }
+        }
+    };
+    Ok(Value::Boolean(result))
+}
+
+/// TRUE if `expr` equals a value of `list`, otherwise NULL if `expr` or any value is NULL,
+/// as a chain of `=` joined by OR.
+fn evaluate_in_list(
+    expr: &Expr,
+    list: &[Expr],
+    row: &ExecutionMemory,
+) -> Result<Value, ExecutionError> {
+    let value = evaluate(expr, row)?;
+    let mut result = Value::Boolean(false);
+    for item in list {
+        let eq = evaluate_binary_op(
+            &BinaryOperator::Eq,
+            &value,
+            &evaluate(item, row)?,
+            row.collation(),
+        )?;
+        result = evaluate_or(&result, &eq)?;
+        if result == Value::Boolean(true) {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+/// `text LIKE pattern`, where `%` matches any sequence of characters and `_` a single one.
+/// there is no escape character. case sensitivity follows the collation, so LIKE is case
+/// sensitive under the default binary collation. numbers match as their text.
+fn evaluate_like(
+    text: &Value,
+    pattern: &Value,
+    collation: Collation,
+) -> Result<Value, ExecutionError> {
+    match (text, pattern) {
+        (Value::String(text) | Value::Number(text), Value::String(pattern)) => {
+            let text = collation.fold(text).chars().collect::<Vec<_>>();
+            let pattern = collation.fold(pattern).chars().collect::<Vec<_>>();
+            Ok(Value::Boolean(like_match(&text, &pattern)))
+        }
+        _ => Err(ExecutionError::new(format!(
+            "can not apply Like to {:?} and {:?}",
+            text, pattern
+        ))),
+    }
+}
+
+/// greedy matching that backtracks to the last `%` on a mismatch.
+fn like_match(text: &[char], pattern: &[char]) -> bool {
+    let (mut t, mut p) = (0, 0);
+    // positions in the pattern after the last `%` and in the text it resumes from
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(c) if *c == '_' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((after_percent, resume)) => {
+                    // let the `%` take one more character
+                    p = after_percent;
+                    t = resume + 1;
+                    backtrack = Some((after_percent, resume + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
+/// three-valued AND: FALSE if any side is FALSE, otherwise NULL if any side is NULL.
+fn evaluate_and(left: &Value, right: &Value) -> Result<Value, ExecutionError> {
+    match (left, right) {
+        (Value::Boolean(false), _) | (_, Value::Boolean(false)) => Ok(Value::Boolean(false)),
+        (Value::Boolean(true), Value::Boolean(true)) => Ok(Value::Boolean(true)),
+        (Value::Null, Value::Boolean(_)) | (Value::Boolean(_), Value::Null) => Ok(Value::Null),
+        (Value::Null, Value::Null) => Ok(Value::Null),
+        _ => Err(ExecutionError::new(format!(
+            "can not apply And to {:?} and {:?}",
+            left, right
+        ))),
+    }
+}
+
+/// three-valued OR: TRUE if any side is TRUE, otherwise NULL if any side is NULL.
+fn evaluate_or(left: &Value, right: &Value) -> Result<Value, ExecutionError> {
+    match (left, right) {
+        (Value::Boolean(true), _) | (_, Value::Boolean(true)) => Ok(Value::Boolean(true)),
+        (Value::Boolean(false), Value::Boolean(false)) => Ok(Value::Boolean(false)),
+        (Value::Null, Value::Boolean(_)) | (Value::Boolean(_), Value::Null) => Ok(Value::Null),
+        (Value::Null, Value::Null) => Ok(Value::Null),
+        _ => Err(ExecutionError::new(format!(
+            "can not apply Or to {:?} and {:?}",
+            left, right
+        ))),
+    }
+}
+
+/// order of two non-null operands of a comparison, None if they are incomparable.
+/// int and float properties are numbers and compare by value. a string still compares to a
+/// number as the number it parses to, e.g. an untagged property written before values were
+/// typed. other values only compare to values of the same type.
+fn compare_operands(left: &Value, right: &Value, collation: Collation) -> Option<Ordering> {
+    match (left, right) {
+        (Value::String(s), Value::Number(_)) => {
+            compare_operands(&Value::Number(s.to_owned()), right, collation)
+        }
+        (Value::Number(_), Value::String(s)) => {
+            compare_operands(left, &Value::Number(s.to_owned()), collation)
+        }
+        (Value::Number(l), Value::Number(r)) => match (Number::parse(l), Number::parse(r)) {
+            (Ok(l), Ok(r)) => Some(compare_numbers(&l, &r)),
+            _ => None,
+        },
+        _ if type_rank(left) == type_rank(right) => Some(compare_values(left, right, collation)),
+        _ => None,
+    }
+}
+
+fn compare_numbers(left: &Number, right: &Number) -> Ordering {
+    match (left, right) {
+        (Number::Int(l), Number::Int(r)) => l.cmp(r),
+        _ => left
+            .as_f64()
+            .partial_cmp(&right.as_f64())
+            .unwrap_or(Ordering::Equal),
+    }
+}
+
+/// sort comparator of ORDER BY. nulls are placed according to `nulls_first` regardless
+/// of the direction, the other values are compared with `compare_values`.
+pub fn compare_sort_keys(
+    order_by: &OrderByExpr,
+    left: &Value,
+    right: &Value,
+    collation: Collation,
+) -> Ordering {
+    match (left, right) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) if order_by.nulls_first() => Ordering::Less,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) if order_by.nulls_first() => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        _ if order_by.asc => compare_values(left, right, collation),
+        _ => compare_values(left, right, collation).reverse(),
+    }
+}
+
+/// compare the keys of two rows, one per `order_by` expression, by the first key that
+/// differs.
+pub fn compare_sort_rows(
+    order_by: &[OrderByExpr],
+    left: &[Value],
+    right: &[Value],
+    collation: Collation,
+) -> Ordering {
+    order_by
+        .iter()
+        .zip(left.iter().zip(right))
+        .map(|(order_by, (left, right))| compare_sort_keys(order_by, left, right, collation))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+/// total order over values: numbers compare numerically, strings and booleans in their
+/// natural order, strings under `collation`. values of different types are ordered
+/// Null < Boolean < Number < String.
+pub fn compare_values(left: &Value, right: &Value, collation: Collation) -> Ordering {
+    match (left, right) {
+        (Value::Boolean(l), Value::Boolean(r)) => l.cmp(r),
+        (Value::String(l), Value::String(r)) => collation.compare(l, r),
+        (Value::Number(l), Value::Number(r)) => match (Number::parse(l), Number::parse(r)) {
+            (Ok(l), Ok(r)) => compare_numbers(&l, &r),
+            _ => l.cmp(r),
+        },
+        _ => type_rank(left).cmp(&type_rank(right)),
+    }
+}
+
+/// hash key of a value such that values equal under `=` have equal keys, None for NULL.
+/// values with equal keys are not necessarily equal, e.g. `'1'` and `'1.0'`.
+pub fn join_key(value: &Value, collation: Collation) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Boolean(b) => Some(format!("b{}", b)),
+        // a string equals a number it parses to
+        Value::Number(s) | Value::String(s) if Number::parse(s).is_ok() => {
+            Some(format!("n{}", Number::parse(s).unwrap().as_f64()))
+        }
+        Value::Number(s) => Some(format!("n{}", s)),
+        Value::String(s) => Some(format!("s{}", collation.fold(s))),
+    }
+}
+
+/// how strings are compared in predicates and ORDER BY.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Collation {
+    /// byte order of the utf-8 encoding
+    #[default]
+    Binary,
+    /// byte order of the lowercased strings, so `'a' = 'A'`
+    CaseInsensitive,
+}
+
+impl Collation {
+    pub fn compare(&self, left: &str, right: &str) -> Ordering {
+        match self {
+            Collation::Binary => left.cmp(right),
+            Collation::CaseInsensitive => left.to_lowercase().cmp(&right.to_lowercase()),
+        }
+    }
+
+    /// the string all strings equal to `s` under this collation map to.
+    pub fn fold(&self, s: &str) -> String {
+        match self {
+            Collation::Binary => s.to_owned(),
+            Collation::CaseInsensitive => s.to_lowercase(),
+        }
+    }
+}
+
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Boolean(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+    }
+}
+
+/// numbers are stored as their literal text in `Value::Number`, and get parsed on demand.
+/// arithmetic promotion rules:
+///   - two integers use checked `i64` arithmetic and stay integers.
+///   - if the `i64` arithmetic overflows, it is redone in `f64`, so `i64::MAX + 1` is a float.
+///   - if any operand is fractional (contains `.` or an exponent), `f64` is used.
+///   - integer `/` and `%` truncate like SQL. dividing by an integer zero is an error,
+///     while float division follows IEEE 754.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn parse(s: &str) -> Result<Number, ExecutionError> {
+        if let Ok(i) = s.parse::<i64>() {
+            return Ok(Number::Int(i));
+        }
+        match s.parse::<f64>() {
+            Ok(f) => Ok(Number::Float(f)),
+            Err(_) => Err(ExecutionError::new(format!("invalid number {}", s))),
+        }
+    }
+
+    fn as_f64(&self) -> f64 {
+        match self {
+            Number::Int(i) => *i as f64,
+            Number::Float(f) => *f,
+        }
+    }
+
+    fn negate(&self) -> Number {
+        match self {
+            Number::Int(i) => match i.checked_neg() {
+                Some(i) => Number::Int(i),
+                None => Number::Float(-(*i as f64)),
+            },
+            Number::Float(f) => Number::Float(-f),
+        }
+    }
+
+    /// integer operands give an integer unless it overflows. `^` with a negative or
+    /// fractional exponent gives a float, e.g. `2 ^ -1 = 0.5`.
+    fn arithmetic(&self, op: &BinaryOperator, other: &Number) -> Result<Number, ExecutionError> {
+        if op == &BinaryOperator::Power && self.as_f64() == 0.0 && other.as_f64() < 0.0 {
+            return Err(ExecutionError::new("division by zero".to_string()));
+        }
+        if let (Number::Int(l), Number::Int(r)) = (self, other) {
+            let (l, r) = (*l, *r);
+            if r == 0 && (op == &BinaryOperator::Divide || op == &BinaryOperator::Modulus) {
+                return Err(ExecutionError::new("division by zero".to_string()));
+            }
+            let checked = match op {
+                BinaryOperator::Plus => l.checked_add(r),
+                BinaryOperator::Minus => l.checked_sub(r),
+                BinaryOperator::Multiply => l.checked_mul(r),
+                BinaryOperator::Divide => l.checked_div(r),
+                BinaryOperator::Modulus => l.checked_rem(r),
+                BinaryOperator::Power => u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+                _ => return Err(Self::unsupported(op)),
+            };
+            if let Some(i) = checked {
+                return Ok(Number::Int(i));
+            }
+        }
+        let (l, r) = (self.as_f64(), other.as_f64());
+        let f = match op {
+            BinaryOperator::Plus => l + r,
+            BinaryOperator::Minus => l - r,
+            BinaryOperator::Multiply => l * r,
+            BinaryOperator::Divide => l / r,
+            BinaryOperator::Modulus => l % r,
+            BinaryOperator::Power => l.powf(r),
+            _ => return Err(Self::unsupported(op)),
+        };
+        Ok(Number::Float(f))
+    }
+
+    fn unsupported(op: &BinaryOperator) -> ExecutionError {
+        ExecutionError::new(format!("{:?} is not an arithmetic operator", op))
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(i) => write!(f, "{}", i),
+            Number::Float(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::parser::Parser;
+
+    use super::*;
+
+    fn eval_select(sql: &str) -> Result<Value, ExecutionError> {
+        match &Parser::parse_sql(sql).unwrap()[0] {
+            crate::parser::ast::Statement::Select { items, .. } => {
+                evaluate(&items[0], &ExecutionMemory::new())
+            }
+            _ => panic!("not a select"),
+        }
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        assert_eq!(
+            eval_select("SELECT 1 + 2 * 3").unwrap(),
+            Value::Number("7".to_string())
+        );
+        assert_eq!(
+            eval_select("SELECT 7 / 2").unwrap(),
+            Value::Number("3".to_string())
+        );
+        assert_eq!(
+            eval_select("SELECT -7 % 3").unwrap(),
+            Value::Number("-1".to_string())
+        );
+        assert!(eval_select("SELECT 1 / 0").is_err());
+    }
+
+    #[test]
+    fn test_power() {
+        let power = |sql: &str| eval_select(&format!("SELECT {}", sql)).unwrap();
+        let number = |s: &str| Value::Number(s.to_string());
+        assert_eq!(power("2 ^ 10"), number("1024"));
+        assert_eq!(power("2 ^ 3 ^ 2"), number("512"));
+        assert_eq!(power("-2 ^ 2"), number("-4"));
+        assert_eq!(power("(-2) ^ 3"), number("-8"));
+        assert_eq!(power("2 ^ -1"), number("0.5"));
+        assert_eq!(power("4 ^ 0.5"), number("2"));
+        assert_eq!(power("1.5 ^ 2"), number("2.25"));
+        assert_eq!(power("2 ^ 64"), number(&2f64.powi(64).to_string()));
+        assert_eq!(power("2 ^ NULL"), Value::Null);
+        assert!(eval_select("SELECT 0 ^ -1").is_err());
+    }
+
+    #[test]
+    fn test_overflow_promotes_to_float() {
+        let sql = format!("SELECT {} + 1", i64::MAX);
+        assert_eq!(
+            eval_select(&sql).unwrap(),
+            Value::Number(((i64::MAX as f64) + 1.0).to_string())
+        );
+        let sql = format!("SELECT {} * 2", i64::MAX);
+        assert_eq!(
+            eval_select(&sql).unwrap(),
+            Value::Number((i64::MAX as f64 * 2.0).to_string())
+        );
+    }
+
+    #[test]
+    fn test_fractional_arithmetic() {
+        assert_eq!(
+            eval_select("SELECT 1.5 + 2").unwrap(),
+            Value::Number("3.5".to_string())
+        );
+        assert_eq!(
+            eval_select("SELECT 1 / 2.0").unwrap(),
+            Value::Number("0.5".to_string())
+        );
+        assert_eq!(eval_select("SELECT 1 + NULL").unwrap(), Value::Null);
+        assert!(eval_select("SELECT 1 + 'a'").is_err());
+    }
+
+    fn sort_with(order_by: &str) -> Vec<Value> {
+        let sql = format!("SELECT a FROM (a) ORDER BY a.prop {}", order_by);
+        let order_by = match Parser::parse_sql(&sql).unwrap().remove(0) {
+            crate::parser::ast::Statement::Select { mut order_by, .. } => order_by.remove(0),
+            _ => panic!("not a select"),
+        };
+        let mut values = vec![
+            Value::Number("2".to_string()),
+            Value::Null,
+            Value::Number("10".to_string()),
+            Value::Number("1".to_string()),
+        ];
+        values.sort_by(|l, r| compare_sort_keys(&order_by, l, r, Collation::Binary));
+        values
+    }
+
+    #[test]
+    fn test_nested_functions() {
+        assert_eq!(
+            eval_select("SELECT upper(concat('a', lower('B')))").unwrap(),
+            Value::String("AB".to_string())
+        );
+        assert_eq!(
+            eval_select("SELECT length(concat('ab', 1, 'c'))").unwrap(),
+            Value::Number("4".to_string())
+        );
+        assert_eq!(
+            eval_select("SELECT upper(concat('a', NULL))").unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            eval_select("SELECT upper(concat('a', lower(1)))")
+                .unwrap_err()
+                .to_string(),
+            "lower expects strings but got Number(\"1\")"
+        );
+        assert_eq!(
+            eval_select("SELECT concat(upper('a', 'b'))")
+                .unwrap_err()
+                .to_string(),
+            "upper expects 1 arguments but got 2"
+        );
+        assert!(eval_select("SELECT upper(nope('a'))").is_err());
+    }
+
+    #[test]
+    fn test_sort_nulls() {
+        let n = |s: &str| Value::Number(s.to_string());
+        assert_eq!(sort_with(""), vec![n("1"), n("2"), n("10"), Value::Null]);
+        assert_eq!(
+            sort_with("DESC"),
+            vec![Value::Null, n("10"), n("2"), n("1")]
+        );
+        assert_eq!(
+            sort_with("ASC NULLS FIRST"),
+            vec![Value::Null, n("1"), n("2"), n("10")]
+        );
+        assert_eq!(
+            sort_with("DESC NULLS LAST"),
+            vec![n("10"), n("2"), n("1"), Value::Null]
+        );
+    }
+
+    #[test]
+    fn test_collation() {
+        let order_by = OrderByExpr {
+            expr: Expr::Wildcard,
+            asc: true,
+            nulls_first: None,
+        };
+        let sorted = |collation: Collation| {
+            let mut values: Vec<Value> = ["B", "a", "C"]
+                .iter()
+                .map(|s| Value::String(s.to_string()))
+                .collect();
+            values.sort_by(|l, r| compare_sort_keys(&order_by, l, r, collation));
+            values
+                .into_iter()
+                .map(value_to_string)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(sorted(Collation::Binary), vec!["B", "C", "a"]);
+        assert_eq!(sorted(Collation::CaseInsensitive), vec!["a", "B", "C"]);
+
+        let mut row = ExecutionMemory::new();
+        let eq = Expr::BinaryOp {
+            op: BinaryOperator::Eq,
+            left: Box::new(Expr::Value(Value::String("a".to_string()))),
+            right: Box::new(Expr::Value(Value::String("A".to_string()))),
+        };
+        assert_eq!(evaluate(&eq, &row).unwrap(), Value::Boolean(false));
+        row.set_collation(Collation::CaseInsensitive);
+        assert_eq!(evaluate(&eq, &row).unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparison_and_logic() {
+        let t = Value::Boolean(true);
+        let f = Value::Boolean(false);
+        assert_eq!(eval_select("SELECT 2 > 1").unwrap(), t);
+        assert_eq!(eval_select("SELECT '10' >= 9").unwrap(), t);
+        assert_eq!(eval_select("SELECT '10' >= '9'").unwrap(), f);
+        assert_eq!(eval_select("SELECT 'abc' < 1").unwrap(), Value::Null);
+        assert_eq!(eval_select("SELECT 1 != 1.0").unwrap(), f);
+        assert_eq!(eval_select("SELECT NOT 1 = 2").unwrap(), t);
+        assert_eq!(eval_select("SELECT 1 = 2 OR 2 = 2").unwrap(), t);
+        assert_eq!(eval_select("SELECT NULL OR 1 = 1").unwrap(), t);
+        assert_eq!(eval_select("SELECT NULL OR 1 = 2").unwrap(), Value::Null);
+        assert_eq!(eval_select("SELECT NULL AND 1 = 2").unwrap(), f);
+    }
+
+    #[test]
+    fn test_is_null() {
+        let t = Value::Boolean(true);
+        let f = Value::Boolean(false);
+        assert_eq!(eval_select("SELECT NULL IS NULL").unwrap(), t);
+        assert_eq!(eval_select("SELECT 1 + NULL IS NOT NULL").unwrap(), f);
+        assert_eq!(eval_select("SELECT 'a' IS NULL").unwrap(), f);
+        assert_eq!(eval_select("SELECT NOT 1 IS NULL").unwrap(), t);
+    }
+
+    #[test]
+    fn test_column_name() {
+        for (expr, name) in &[
+            ("a.age + 1", "a.age + 1"),
+            ("-a.age", "-a.age"),
+            ("upper(a.name) LIKE 'T%'", "upper(a.name) LIKE 'T%'"),
+            ("a.id IN ('v1', 'v2')", "a.id IN ('v1', 'v2')"),
+        ] {
+            let expr = Parser::parse_expression(expr).unwrap();
+            assert_eq!(column_name(&expr), *name);
+        }
+    }
+
+    #[test]
+    fn test_like() {
+        let like = |sql: &str| eval_select(&format!("SELECT {}", sql)).unwrap();
+        let t = Value::Boolean(true);
+        let f = Value::Boolean(false);
+        assert_eq!(like("'John' LIKE 'Jo%'"), t);
+        assert_eq!(like("'Jo' LIKE 'Jo%'"), t);
+        assert_eq!(like("'Mojo' LIKE 'Jo%'"), f);
+        assert_eq!(like("'john' LIKE 'Jo%'"), f);
+        assert_eq!(like("'John' LIKE 'J_hn'"), t);
+        assert_eq!(like("'Jhn' LIKE 'J_hn'"), f);
+        assert_eq!(like("'abcabd' LIKE '%ab_'"), t);
+        assert_eq!(like("'abcab' LIKE '%a%b%c'"), f);
+        assert_eq!(like("'' LIKE '%'"), t);
+        assert_eq!(like("'añb' LIKE 'a_b'"), t);
+        assert_eq!(like("120 LIKE '1%'"), t);
+        assert_eq!(like("NULL LIKE '%'"), Value::Null);
+        assert_eq!(like("NOT 'ab' LIKE 'a%' OR FALSE"), f);
+        assert!(eval_select("SELECT 'a' LIKE 1").is_err());
+
+        // case insensitive under the case insensitive collation
+        let mut row = ExecutionMemory::new();
+        row.set_collation(Collation::CaseInsensitive);
+        let expr = Parser::parse_expression("'john' LIKE 'JO%'").unwrap();
+        assert_eq!(evaluate(&expr, &row).unwrap(), t);
+    }
+
+    #[test]
+    fn test_in_list() {
+        let t = Value::Boolean(true);
+        let f = Value::Boolean(false);
+        assert_eq!(eval_select("SELECT 2 IN (1, 2, 3)").unwrap(), t);
+        assert_eq!(eval_select("SELECT '2' IN (1.0, 2.0)").unwrap(), t);
+        assert_eq!(eval_select("SELECT 'b' IN ('a', 'c')").unwrap(), f);
+        assert_eq!(eval_select("SELECT 'b' NOT IN ('a', 'c')").unwrap(), t);
+        assert_eq!(eval_select("SELECT 1 IN (NULL, 1)").unwrap(), t);
+        assert_eq!(eval_select("SELECT 1 IN (NULL, 2)").unwrap(), Value::Null);
+        assert_eq!(eval_select("SELECT NULL IN (1)").unwrap(), Value::Null);
+        assert_eq!(
+            eval_select("SELECT 1 NOT IN (NULL, 2)").unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_join_key() {
+        let key = |value: Value| join_key(&value, Collation::CaseInsensitive);
+        assert_eq!(
+            key(Value::String("1".to_string())),
+            key(Value::Number("1.0".to_string()))
+        );
+        assert_eq!(
+            key(Value::String("Tom".to_string())),
+            key(Value::String("tom".to_string()))
+        );
+        assert_ne!(
+            key(Value::String("true".to_string())),
+            key(Value::Boolean(true))
+        );
+        assert_eq!(key(Value::Null), None);
+    }
+}
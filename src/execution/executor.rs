@@ -1,38 +1,195 @@
-use std::ops::Deref;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+use std::io;
 use std::rc::Rc;
 
-use crate::datamodel::base::{Cardinality, EdgeMultiplicity};
-use crate::datamodel::vertex::Vertex;
-use crate::execution::memory::ExecutionMemory;
+use crate::datamodel::base::{
+    Cardinality, EdgeDirection, EdgeLabelId, EdgeMultiplicity, PropertyKeyId, VertexLabelId,
+};
+use crate::datamodel::edge::Edge;
+use crate::datamodel::edge_label::EdgeLabel;
+use crate::datamodel::property::{Properties, PropertyValue};
+use crate::datamodel::property_key::PropertyKey;
+use crate::execution::aggregate::Accumulator;
+use crate::execution::eval::{
+    column_name, compare_sort_rows, evaluate, join_key, value_to_string, Collation,
+};
+use crate::execution::integrity::IntegrityIssue;
+use crate::execution::memory::{Catalog, ExecutionMemory};
 use crate::execution::operator::Operator;
-use crate::execution::output::QueryOutput;
+use crate::execution::output::{QueryOutput, QueryStats};
 use crate::execution::planner::Planner;
-use crate::execution::scope::{Comparator, Scope, VertexPattern};
+use crate::execution::statistics::IndexStatistics;
+use crate::handlers::chunked_scan::{CancellationToken, ScanError};
 use crate::handlers::edge_handler::EdgeHandler;
-use crate::handlers::schema_handler::SchemaHandler;
-use crate::handlers::sled_engine::SledEngine;
+use crate::handlers::schema_handler::{NewSchemaElement, SchemaError, SchemaHandler};
+use crate::handlers::sled_engine::{SledEngine, StorageError};
 use crate::handlers::vertex_handler::VertexHandler;
-use crate::parser::ast::{Expr, GraphPattern, Statement, Value};
+use crate::handlers::write_observer::WriteObserver;
+use crate::parser::ast::{Expr, GraphPattern, OrderByExpr, Statement, Value, VertexMatch};
+use crate::parser::operator::BinaryOperator;
 
+type Rows = Box<dyn Iterator<Item = Result<ExecutionMemory, ExecutionError>>>;
+
+#[derive(Clone)]
 pub struct QueryExecutor {
     schema_handler: SchemaHandler,
     vertex_handler: VertexHandler,
     edge_handler: EdgeHandler,
+    collation: Collation,
+    cancellation: Option<CancellationToken>,
+    disable_pushdown: bool,
 }
 
 impl QueryExecutor {
-    pub fn new(engine: Rc<Box<SledEngine>>) -> Self {
+    pub fn new(engine: Rc<SledEngine>) -> Self {
         QueryExecutor {
-            schema_handler: SchemaHandler {
-                engine: engine.clone(),
-            },
+            schema_handler: SchemaHandler::new(engine.clone()),
             vertex_handler: VertexHandler {
                 engine: engine.clone(),
+                observer: None,
             },
             edge_handler: EdgeHandler {
                 engine: engine.clone(),
+                observer: None,
+                single_copy_properties: false,
             },
+            collation: Collation::default(),
+            cancellation: None,
+            disable_pushdown: false,
+        }
+    }
+
+    pub fn set_write_observer(&mut self, observer: Option<Rc<dyn WriteObserver>>) {
+        self.vertex_handler.observer = observer.clone();
+        self.edge_handler.observer = observer;
+    }
+
+    /// collation of string comparisons and sorting in queries. `Binary` by default.
+    pub fn set_collation(&mut self, collation: Collation) {
+        self.collation = collation;
+    }
+
+    /// token checked by the full scans of queries, which fail once it is cancelled.
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// plan queries without pushing predicates down, see `Planner::set_disable_pushdown`.
+    pub fn set_disable_pushdown(&mut self, disabled: bool) {
+        self.disable_pushdown = disabled;
+    }
+
+    /// store edge properties on the out copy only, see `EdgeHandler::single_copy_properties`.
+    pub fn set_single_copy_edge_properties(&mut self, enabled: bool) {
+        self.edge_handler.single_copy_properties = enabled;
+    }
+
+    /// check every vertex and edge of the graph against the schema, and every edge against
+    /// its vertices.
+    pub fn check_integrity(&self) -> Result<Vec<IntegrityIssue>, ExecutionError> {
+        let keys: HashSet<PropertyKeyId> = self
+            .schema_handler
+            .get_property_keys()?
+            .into_iter()
+            .map(|key| key.id)
+            .collect();
+        let mut issues = vec![];
+        let check = |element: String, properties: &Properties, issues: &mut Vec<_>| {
+            for property in properties.get_properties() {
+                if !keys.contains(&property.key) {
+                    issues.push(IntegrityIssue::DanglingPropertyKey {
+                        element: element.to_owned(),
+                        key_id: property.key,
+                    });
+                }
+            }
+        };
+        let mut vertex_ids = HashSet::new();
+        for vertex in self.vertex_handler.iter_all_vertices()? {
+            let vertex = vertex?;
+            check(
+                format!("vertex {}", vertex.id),
+                &vertex.properties,
+                &mut issues,
+            );
+            vertex_ids.insert(vertex.id);
+        }
+        for edge in self.edge_handler.iter_all_out_edges(None)? {
+            let edge = edge?;
+            let element = format!(
+                "edge {} {}->{}",
+                edge.edge_id, edge.src_vertex_id, edge.dst_vertex_id
+            );
+            check(element.to_owned(), &edge.properties, &mut issues);
+            for vertex_id in &[&edge.src_vertex_id, &edge.dst_vertex_id] {
+                if !vertex_ids.contains(*vertex_id) {
+                    issues.push(IntegrityIssue::OrphanEdge {
+                        edge: element.to_owned(),
+                        vertex_id: vertex_id.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(issues)
+    }
+
+    /// write the vertices and out edges of the graph as a graphviz digraph, each vertex
+    /// named by its id and labelled `id:label`, each edge labelled by its label. the
+    /// elements are written as they are scanned.
+    pub fn export_dot<W: io::Write>(&self, w: &mut W) -> Result<(), ExecutionError> {
+        let vertex_labels: HashMap<VertexLabelId, String> = self
+            .schema_handler
+            .get_vertex_labels()?
+            .into_iter()
+            .map(|label| (label.id, label.name))
+            .collect();
+        let edge_labels: HashMap<EdgeLabelId, String> = self
+            .schema_handler
+            .get_edge_labels()?
+            .into_iter()
+            .map(|label| (label.id, label.name))
+            .collect();
+        // a label missing from the schema is shown by its id
+        fn name<T: Eq + Hash + Display>(labels: &HashMap<T, String>, id: T) -> String {
+            match labels.get(&id) {
+                Some(name) => name.to_owned(),
+                None => id.to_string(),
+            }
+        }
+        writeln!(w, "digraph {{")?;
+        for vertex in self.vertex_handler.iter_all_vertices()? {
+            let vertex = vertex?;
+            writeln!(
+                w,
+                "  {} [label={}];",
+                dot_string(&vertex.id),
+                dot_string(&format!(
+                    "{}:{}",
+                    vertex.id,
+                    name(&vertex_labels, vertex.label)
+                ))
+            )?;
         }
+        for edge in self.edge_handler.iter_all_out_edges(None)? {
+            let edge = edge?;
+            writeln!(
+                w,
+                "  {} -> {} [label={}];",
+                dot_string(&edge.src_vertex_id),
+                dot_string(&edge.dst_vertex_id),
+                dot_string(&name(&edge_labels, edge.label))
+            )?;
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    pub(crate) fn schema_handler(&self) -> &SchemaHandler {
+        &self.schema_handler
     }
 
     pub fn execute_statement(
@@ -40,83 +197,84 @@ impl QueryExecutor {
         statement: &Statement,
     ) -> Result<QueryOutput, ExecutionError> {
         match statement {
-            Statement::CreateVertexLabel { name } => {
-                let id = self.schema_handler.create_vertex_label(&name);
-                let output = QueryOutput {
-                    columns: vec!["id".to_owned(), "name".to_owned(), "status".to_owned()],
-                    items: Box::new(
-                        vec![vec![id.to_string(), name.to_owned(), "CREATED".to_string()]]
-                            .into_iter(),
-                    ),
-                };
-                Ok(output)
-            }
-            Statement::CreateEdgeLabel { name, multiplicity } => {
-                let id = self
-                    .schema_handler
-                    .create_edge_label(&name, EdgeMultiplicity::from(multiplicity.as_str()));
-                let output = QueryOutput {
-                    columns: vec!["id".to_owned(), "name".to_owned(), "status".to_owned()],
-                    items: Box::new(
-                        vec![vec![id.to_string(), name.to_owned(), "CREATED".to_string()]]
-                            .into_iter(),
-                    ),
-                };
-                Ok(output)
-            }
-            Statement::CreatePropertyKey { name, cardinality } => {
-                let id = self
-                    .schema_handler
-                    .create_property_key(&name, Cardinality::from(cardinality.as_str()));
-                let output = QueryOutput {
-                    columns: vec!["id".to_owned(), "name".to_owned(), "status".to_owned()],
-                    items: Box::new(
-                        vec![vec![id.to_string(), name.to_owned(), "CREATED".to_string()]]
-                            .into_iter(),
-                    ),
-                };
-                Ok(output)
+            Statement::CreateVertexLabel { .. }
+            | Statement::CreateEdgeLabel { .. }
+            | Statement::CreatePropertyKey { .. } => {
+                self.execute_create_schema(std::slice::from_ref(statement))
+            }
+            Statement::CreateSchema { statements } => self.execute_create_schema(statements),
+            Statement::DropVertexLabel { name }
+            | Statement::DropEdgeLabel { name }
+            | Statement::DropPropertyKey { name } => {
+                let dropped = self.drop_schema_element(statement)?;
+                Ok(QueryOutput::from_rows(
+                    vec!["name".to_owned(), "status".to_owned()],
+                    vec![vec![
+                        name.to_owned(),
+                        if dropped { "DROPPED" } else { "NOT_FOUND" }.to_owned(),
+                    ]],
+                ))
             }
             Statement::ShowVertexLabels => Ok(QueryOutput {
                 columns: vec!["id".to_owned(), "name".to_owned()],
                 items: Box::new(
                     self.schema_handler
-                        .get_vertex_labels()
+                        .get_vertex_labels()?
                         .into_iter()
-                        .map(|label| vec![label.id.to_string(), label.name]),
+                        .map(|label| vec![label.id.to_string(), label.name])
+                        .map(Ok),
                 ),
+                stats: QueryStats::default(),
             }),
-            Statement::ShowEdgeLabels => {
-                Ok(QueryOutput {
-                    columns: vec![
-                        "id".to_owned(),
-                        "name".to_owned(),
-                        "multiplicity".to_owned(),
-                    ],
-                    items: Box::new(self.schema_handler.get_edge_labels().into_iter().map(
-                        |label| {
+            Statement::ShowEdgeLabels => Ok(QueryOutput {
+                columns: vec![
+                    "id".to_owned(),
+                    "name".to_owned(),
+                    "multiplicity".to_owned(),
+                ],
+                items: Box::new(
+                    self.schema_handler
+                        .get_edge_labels()?
+                        .into_iter()
+                        .map(|label| {
                             vec![
                                 label.id.to_string(),
                                 label.name,
                                 label.multiplicity.to_string(),
                             ]
-                        },
-                    )),
-                })
-            }
-            Statement::ShowPropertyKeys => {
-                Ok(QueryOutput {
-                    columns: vec!["id".to_owned(), "name".to_owned(), "cardinality".to_owned()],
-                    items: Box::new(self.schema_handler.get_property_keys().into_iter().map(
-                        |label| {
+                        })
+                        .map(Ok),
+                ),
+                stats: QueryStats::default(),
+            }),
+            Statement::ShowPropertyKeys => Ok(QueryOutput {
+                columns: vec!["id".to_owned(), "name".to_owned(), "cardinality".to_owned()],
+                items: Box::new(
+                    self.schema_handler
+                        .get_property_keys()?
+                        .into_iter()
+                        .map(|label| {
                             vec![
                                 label.id.to_string(),
                                 label.name,
                                 label.cardinality.to_string(),
                             ]
-                        },
-                    )),
-                })
+                        })
+                        .map(Ok),
+                ),
+                stats: QueryStats::default(),
+            }),
+            Statement::ShowCreateVertexLabel { name } => {
+                match self.schema_handler.get_vertex_label_by_name(name)? {
+                    Some(label) => Ok(Self::show_create_output(name, label.create_statement())),
+                    None => Err(self.execute_error(format!("No Vertex Label named {}", name))),
+                }
+            }
+            Statement::ShowCreateEdgeLabel { name } => {
+                match self.schema_handler.get_edge_label_by_name(name)? {
+                    Some(label) => Ok(Self::show_create_output(name, label.create_statement())),
+                    None => Err(self.execute_error(format!("No Edge Label named {}", name))),
+                }
             }
             Statement::InsertVertex {
                 label,
@@ -124,78 +282,1073 @@ impl QueryExecutor {
                 vertex_id,
                 values,
             } => self.execute_insert_vertex(label, properties, vertex_id, values),
+            Statement::InsertEdge {
+                label,
+                properties,
+                src_vertex_id,
+                dst_vertex_id,
+                values,
+            } => self.execute_insert_edge(label, properties, src_vertex_id, dst_vertex_id, values),
+            Statement::InsertEdgeBetween {
+                label,
+                src,
+                dst,
+                cartesian,
+                properties,
+                values,
+            } => self.execute_insert_edge_between(label, src, dst, *cartesian, properties, values),
+            Statement::Delete {
+                elements,
+                graph_pattern,
+                condition,
+            } => self.execute_delete(elements, graph_pattern, condition),
+            Statement::Select { .. } => {
+                let op = self.plan_select(statement)?;
+                self.execute_plan(&op)
+            }
+            Statement::Explain { statement } => {
+                let op = self.plan_select(statement)?;
+                Ok(QueryOutput::from_rows(
+                    vec!["plan".to_string()],
+                    vec![vec![format!("{:?}", op)]],
+                ))
+            }
+            _ => Err(self.execute_error("not impl".to_string())),
+        }
+    }
+
+    fn show_create_output(name: &str, statement: String) -> QueryOutput {
+        QueryOutput::from_rows(
+            vec!["name".to_owned(), "statement".to_owned()],
+            vec![vec![name.to_owned(), statement]],
+        )
+    }
+
+    /// the element a create statement describes, checked but not created yet.
+    fn schema_element(&self, statement: &Statement) -> Result<NewSchemaElement, ExecutionError> {
+        match statement {
+            Statement::CreateVertexLabel { name } => Ok(NewSchemaElement::VertexLabel {
+                name: name.to_owned(),
+            }),
+            Statement::CreateEdgeLabel { name, multiplicity } => Ok(NewSchemaElement::EdgeLabel {
+                name: name.to_owned(),
+                multiplicity: multiplicity.parse::<EdgeMultiplicity>()?,
+            }),
+            Statement::CreatePropertyKey {
+                name,
+                cardinality,
+                default_value,
+            } => Ok(NewSchemaElement::PropertyKey {
+                name: name.to_owned(),
+                cardinality: cardinality.parse::<Cardinality>()?,
+                default_value: match default_value {
+                    Some(expr) => Some(self.execute_expr(expr)?),
+                    None => None,
+                },
+            }),
+            _ => Err(self.execute_error(format!("{:?} is not a schema element", statement))),
+        }
+    }
+
+    /// create all of `statements`, or none of them if one fails.
+    fn execute_create_schema(
+        &self,
+        statements: &[Statement],
+    ) -> Result<QueryOutput, ExecutionError> {
+        let elements = statements
+            .iter()
+            .map(|statement| self.schema_element(statement))
+            .collect::<Result<Vec<_>, _>>()?;
+        let names = elements
+            .iter()
+            .map(|element| match element {
+                NewSchemaElement::VertexLabel { name }
+                | NewSchemaElement::EdgeLabel { name, .. }
+                | NewSchemaElement::PropertyKey { name, .. } => name.to_owned(),
+            })
+            .collect::<Vec<_>>();
+        let ids = self.schema_handler.create_schema(elements)?;
+        Ok(Self::created_output(ids.into_iter().zip(names).collect()))
+    }
+
+    /// drop a vertex label, edge label or property key by name. returns whether it existed.
+    fn drop_schema_element(&self, statement: &Statement) -> Result<bool, ExecutionError> {
+        let handler = &self.schema_handler;
+        Ok(match statement {
+            Statement::DropVertexLabel { name } => match handler.get_vertex_label_by_name(name)? {
+                Some(label) => handler.remove_vertex_label(label.id)?,
+                None => false,
+            },
+            Statement::DropEdgeLabel { name } => match handler.get_edge_label_by_name(name)? {
+                Some(label) => handler.remove_edge_label(label.id)?,
+                None => false,
+            },
+            Statement::DropPropertyKey { name } => match handler.get_property_key_by_name(name)? {
+                Some(key) => handler.remove_property_key(key.id)?,
+                None => false,
+            },
+            _ => return Err(self.execute_error(format!("{:?} is not a schema element", statement))),
+        })
+    }
+
+    fn created_output(elements: Vec<(u64, String)>) -> QueryOutput {
+        QueryOutput {
+            columns: vec!["id".to_owned(), "name".to_owned(), "status".to_owned()],
+            items: Box::new(
+                elements
+                    .into_iter()
+                    .map(|(id, name)| vec![id.to_string(), name, "CREATED".to_string()])
+                    .map(Ok),
+            ),
+            stats: QueryStats::default(),
+        }
+    }
+
+    fn plan_select(&self, statement: &Statement) -> Result<Operator, ExecutionError> {
+        match statement {
             Statement::Select {
                 items,
+                aliases,
+                graph_pattern,
+                condition,
+                group_by,
+                order_by,
+                sample,
+                limit,
+                offset,
+            } => self.planner().build_select_query(
+                items,
+                aliases,
                 graph_pattern,
                 condition,
-            } => self.execute_select(items, graph_pattern, condition),
-            _ => return Err(self.execute_error("not impl".to_string())),
+                group_by,
+                order_by,
+                sample,
+                limit,
+                offset,
+            ),
+            _ => Err(self.execute_error("only SELECT can be planned".to_string())),
         }
     }
 
-    fn execute_select(
-        &mut self,
-        items: &Vec<Expr>,
-        graph_pattern: &GraphPattern,
-        condition: &Option<Expr>,
+    fn planner(&self) -> Planner {
+        let mut planner = Planner::with_statistics(Box::new(IndexStatistics::new(
+            self.schema_handler.clone(),
+            self.vertex_handler.clone(),
+        )));
+        planner.set_disable_pushdown(self.disable_pushdown);
+        planner
+    }
+
+    /// execute a plan built by hand or by `Planner`, without going through sql. the root
+    /// of the plan is the `Projection` of the output columns.
+    pub fn execute_plan(&self, op: &Operator) -> Result<QueryOutput, ExecutionError> {
+        let memory = self.new_memory()?;
+        match op {
+            Operator::Projection {
+                source,
+                items,
+                aliases,
+            } => self.execute_projection(source, items, aliases, &memory),
+            _ => Err(self.execute_error(format!(
+                "the root of a plan must be a Projection but got {:?}",
+                op
+            ))),
+        }
+    }
+
+    fn execute_projection(
+        &self,
+        source: &Operator,
+        items: &[Expr],
+        aliases: &[Option<String>],
+        memory: &ExecutionMemory,
     ) -> Result<QueryOutput, ExecutionError> {
-        let mut planner = Planner::new();
-        let op = planner.build_select_query(items, graph_pattern, condition);
-        // self.execute_operator(&op, &mut ExecutionMemory::new())?;
-        Err(self.execute_error("not impl".to_string()))
+        let stats = QueryStats::new(memory.scanned_counter());
+        let counter = stats.clone();
+        let projected = items.to_vec();
+        let rows = self.execute_operator(source, memory)?.map(move |row| {
+            let row = row?;
+            let columns = projected
+                .iter()
+                .map(|item| Ok(value_to_string(evaluate(item, &row)?)))
+                .collect::<Result<Vec<_>, ExecutionError>>()?;
+            counter.count_returned();
+            Ok(columns)
+        });
+        Ok(QueryOutput {
+            columns: items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| match aliases.get(i) {
+                    Some(Some(alias)) => alias.to_owned(),
+                    _ => column_name(item),
+                })
+                .collect(),
+            stats,
+            items: Box::new(rows),
+        })
     }
 
+    /// execute an operator with the elements already bound in `memory`. every yielded
+    /// row is a copy of `memory` extended with the elements bound by the operator.
     fn execute_operator(
-        &mut self,
+        &self,
         operator: &Operator,
-        memory: &mut ExecutionMemory,
-    ) -> Result<Box<dyn Iterator<Item = Vec<String>>>, ExecutionError> {
+        memory: &ExecutionMemory,
+    ) -> Result<Rows, ExecutionError> {
         match operator {
-            _ => panic!("todo"),
+            Operator::VertexFullScan { element_name } => {
+                let memory = memory.clone();
+                let element_name = element_name.to_owned();
+                let vertices = self
+                    .vertex_handler
+                    .scan_vertices(self.cancellation.clone())?;
+                Ok(Box::new(vertices.map(move |vertex| {
+                    let mut row = memory.clone();
+                    row.bind_vertex(&element_name, vertex?);
+                    row.count_scanned();
+                    Ok(row)
+                })))
+            }
+            Operator::VertexIdRangeScan {
+                element_name,
+                range,
+            } => {
+                // ids are compared under the collation, the keys byte by byte
+                if memory.collation() != Collation::Binary {
+                    let scan = Operator::VertexFullScan {
+                        element_name: element_name.to_owned(),
+                    };
+                    return self.execute_operator(&scan, memory);
+                }
+                let mut bounds = vec![];
+                for bound in [&range.0, &range.1].iter() {
+                    bounds.push(match bound {
+                        Some(bound) => match evaluate(bound, memory)? {
+                            // nothing compares to NULL
+                            Value::Null => return Ok(Box::new(std::iter::empty())),
+                            value => Some(value_to_string(value)),
+                        },
+                        None => None,
+                    });
+                }
+                let memory = memory.clone();
+                let element_name = element_name.to_owned();
+                let vertices = self.vertex_handler.range_vertices(
+                    bounds[0].as_deref(),
+                    bounds[1].as_deref(),
+                    self.cancellation.clone(),
+                )?;
+                Ok(Box::new(vertices.map(move |vertex| {
+                    let mut row = memory.clone();
+                    row.bind_vertex(&element_name, vertex?);
+                    row.count_scanned();
+                    Ok(row)
+                })))
+            }
+            Operator::VertexLookup {
+                element_name,
+                vertex_id,
+            } => {
+                // the id may refer to an element bound by a previous operator, e.g. `e.dst`
+                let vertex = match evaluate(vertex_id, memory)? {
+                    Value::Null => None,
+                    id => self.vertex_handler.get_vertex(&value_to_string(id))?,
+                };
+                let row = vertex.map(|vertex| {
+                    let mut row = memory.clone();
+                    row.bind_vertex(element_name, vertex);
+                    row.count_scanned();
+                    Ok(row)
+                });
+                Ok(Box::new(row.into_iter()))
+            }
+            Operator::VertexPropertyIndexLookup {
+                element_name,
+                property_key,
+                value,
+            } => {
+                let value = evaluate(value, memory)?;
+                // the index holds binary collation keys, other collations have to scan
+                if memory.collation() != Collation::Binary
+                    && join_key(&value, memory.collation()) != join_key(&value, Collation::Binary)
+                {
+                    let scan = Operator::VertexFullScan {
+                        element_name: element_name.to_owned(),
+                    };
+                    return self.execute_operator(&scan, memory);
+                }
+                let key_id = memory.catalog().property_keys.get(property_key);
+                let ids = match (key_id, value) {
+                    (None, _) | (_, Value::Null) => vec![],
+                    (Some(key_id), value) => self
+                        .vertex_handler
+                        .lookup_by_property(*key_id, &self.execute_expr(&Expr::Value(value))?)?,
+                };
+                Ok(self.bind_vertices(element_name, ids, memory))
+            }
+            Operator::VertexPropertyIndexRangeScan {
+                element_name,
+                property_key,
+                range,
+            } => {
+                let ids = match memory.catalog().property_keys.get(property_key) {
+                    Some(key_id) => self
+                        .vertex_handler
+                        .range_by_property(*key_id, range.0, range.1)?,
+                    None => vec![],
+                };
+                Ok(self.bind_vertices(element_name, ids, memory))
+            }
+            Operator::Sort { source, order_by } => {
+                // sorting needs every row, so the source is drained here
+                let mut keyed = vec![];
+                for row in self.execute_operator(source, memory)? {
+                    let row = row?;
+                    keyed.push((Self::sort_keys(order_by, &row)?, row));
+                }
+                let collation = memory.collation();
+                // stable, so that rows with equal keys keep their input order
+                keyed.sort_by(|(left, _), (right, _)| {
+                    compare_sort_rows(order_by, left, right, collation)
+                });
+                Ok(Box::new(keyed.into_iter().map(|(_, row)| Ok(row))))
+            }
+            Operator::OutEdgeSeqScan {
+                element_name,
+                edge_label,
+                src,
+            } => {
+                let label = match edge_label {
+                    Some(expr) => match self.resolve_edge_label(expr, memory)? {
+                        Some(label) => Some(label),
+                        None => return Ok(Box::new(std::iter::empty())),
+                    },
+                    None => None,
+                };
+                let edges = match src {
+                    // src not bound yet, scan the out edges of every vertex
+                    Some(Expr::Identifier(name)) if memory.get_vertex(name).is_none() => {
+                        self.edge_handler.iter_all_out_edges(label)?
+                    }
+                    Some(expr) => match evaluate(expr, memory)? {
+                        Value::Null => return Ok(Box::new(std::iter::empty())),
+                        id => self
+                            .edge_handler
+                            .scan_out_edges(&value_to_string(id), label)?,
+                    },
+                    None => self.edge_handler.iter_all_out_edges(label)?,
+                };
+                let memory = memory.clone();
+                let element_name = element_name.to_owned();
+                Ok(Box::new(edges.map(move |edge| {
+                    let mut row = memory.clone();
+                    row.bind_edge(&element_name, edge?);
+                    row.count_scanned();
+                    Ok(row)
+                })))
+            }
+            Operator::InEdgeSeqScan {
+                element_name,
+                edge_label,
+                dst,
+            } => {
+                let label = match edge_label {
+                    Some(expr) => match self.resolve_edge_label(expr, memory)? {
+                        Some(label) => Some(label),
+                        None => return Ok(Box::new(std::iter::empty())),
+                    },
+                    None => None,
+                };
+                let edges = match dst {
+                    // dst not bound yet, every edge is the in edge of some vertex
+                    Some(Expr::Identifier(name)) if memory.get_vertex(name).is_none() => {
+                        self.edge_handler.iter_all_out_edges(label)?
+                    }
+                    Some(expr) => match evaluate(expr, memory)? {
+                        Value::Null => return Ok(Box::new(std::iter::empty())),
+                        id => self
+                            .edge_handler
+                            .scan_in_edges(&value_to_string(id), label)?,
+                    },
+                    None => self.edge_handler.iter_all_out_edges(label)?,
+                };
+                let memory = memory.clone();
+                let element_name = element_name.to_owned();
+                Ok(Box::new(edges.map(move |edge| {
+                    let mut row = memory.clone();
+                    row.bind_edge(&element_name, edge?);
+                    row.count_scanned();
+                    Ok(row)
+                })))
+            }
+            Operator::OutEdgeLookup {
+                element_name,
+                edge_label,
+                src,
+                dst,
+            }
+            | Operator::InEdgeLookup {
+                element_name,
+                edge_label,
+                src,
+                dst,
+            } => {
+                let direction = match operator {
+                    Operator::OutEdgeLookup { .. } => EdgeDirection::Out,
+                    _ => EdgeDirection::In,
+                };
+                let label = match self.resolve_edge_label(edge_label, memory)? {
+                    Some(label) => label,
+                    None => return Ok(Box::new(std::iter::empty())),
+                };
+                let edges = match (evaluate(src, memory)?, evaluate(dst, memory)?) {
+                    (Value::Null, _) | (_, Value::Null) => return Ok(Box::new(std::iter::empty())),
+                    (src, dst) => self.edge_handler.scan_edges_between(
+                        &value_to_string(src),
+                        &value_to_string(dst),
+                        label,
+                        direction,
+                    )?,
+                };
+                let memory = memory.clone();
+                let element_name = element_name.to_owned();
+                Ok(Box::new(edges.map(move |edge| {
+                    let mut row = memory.clone();
+                    row.bind_edge(&element_name, edge?);
+                    row.count_scanned();
+                    Ok(row)
+                })))
+            }
+            Operator::Union { operators } => {
+                let mut rows: Rows = Box::new(std::iter::empty());
+                for operator in operators {
+                    rows = Box::new(rows.chain(self.execute_operator(operator, memory)?));
+                }
+                Ok(rows)
+            }
+            Operator::OutEdgeExpand {
+                element_name,
+                edge_label,
+                src,
+                hops,
+            } => {
+                let label = match edge_label {
+                    Some(expr) => match self.resolve_edge_label(expr, memory)? {
+                        Some(label) => Some(label),
+                        None => return Ok(Box::new(std::iter::empty())),
+                    },
+                    None => None,
+                };
+                let edges = match evaluate(src, memory)? {
+                    Value::Null => vec![],
+                    id => self.expand_out_edges(&value_to_string(id), label, *hops)?,
+                };
+                let memory = memory.clone();
+                let element_name = element_name.to_owned();
+                Ok(Box::new(edges.into_iter().map(move |edge| {
+                    let mut row = memory.clone();
+                    row.bind_edge(&element_name, edge);
+                    row.count_scanned();
+                    Ok(row)
+                })))
+            }
+            Operator::HashJoin {
+                left,
+                right,
+                left_key,
+                right_key,
+            } => self.execute_hash_join(left, right, left_key, right_key, memory),
+            Operator::SimplePathJoin { operators } => self.execute_join(operators, memory),
+            Operator::Limit {
+                source,
+                limit,
+                offset,
+            } => {
+                let rows = match source.as_ref() {
+                    Operator::Sort { source, order_by } => {
+                        self.execute_top_n(source, order_by, limit.saturating_add(*offset), memory)?
+                    }
+                    source => self.execute_operator(source, memory)?,
+                };
+                Ok(Box::new(rows.skip(*offset as usize).take(*limit as usize)))
+            }
+            Operator::Aggregate {
+                source,
+                group_keys,
+                aggregates,
+            } => self.execute_aggregate(source, group_keys, aggregates, memory),
+            Operator::Sample { source, n } => {
+                Ok(Self::sample(self.execute_operator(source, memory)?, *n))
+            }
+            Operator::PredicateFilter { source, predicates } => {
+                let predicates = predicates.clone();
+                let rows = self.execute_operator(source, memory)?;
+                Ok(Box::new(rows.filter_map(move |row| {
+                    let row = match row {
+                        Ok(row) => row,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    // NULL or FALSE filters the row out
+                    for predicate in &predicates {
+                        match evaluate(predicate, &row) {
+                            Ok(Value::Boolean(true)) => {}
+                            Ok(_) => return None,
+                            Err(e) => return Some(Err(e)),
+                        }
+                    }
+                    Some(Ok(row))
+                })))
+            }
+            Operator::SemiJoin {
+                source,
+                pattern,
+                anti,
+            } => {
+                let (pattern, anti) = (pattern.as_ref().clone(), *anti);
+                let executor = self.clone();
+                let rows = self.execute_operator(source, memory)?;
+                Ok(Box::new(rows.filter_map(move |row| {
+                    let row = match row {
+                        Ok(row) => row,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    // the first match decides
+                    let matched = match executor.execute_operator(&pattern, &row) {
+                        Ok(mut matches) => match matches.next() {
+                            Some(Err(e)) => return Some(Err(e)),
+                            matched => matched.is_some(),
+                        },
+                        Err(e) => return Some(Err(e)),
+                    };
+                    (matched != anti).then_some(Ok(row))
+                })))
+            }
+            _ => Err(self.execute_error(format!("operator {:?} not impl", operator))),
+        }
+    }
+
+    /// id of the edge label named by `expr`, None if there is no such label.
+    fn resolve_edge_label(
+        &self,
+        expr: &Expr,
+        memory: &ExecutionMemory,
+    ) -> Result<Option<EdgeLabelId>, ExecutionError> {
+        let name = value_to_string(evaluate(expr, memory)?);
+        Ok(memory
+            .catalog()
+            .edge_labels
+            .iter()
+            .find(|(_, label)| **label == name)
+            .map(|(id, _)| *id))
+    }
+
+    /// nested-loop join: every row of the first operator drives the remaining ones, so
+    /// later operators can refer to the elements bound before them, e.g. `e.dst`.
+    fn execute_join(
+        &self,
+        operators: &[Operator],
+        memory: &ExecutionMemory,
+    ) -> Result<Rows, ExecutionError> {
+        let (first, rest) = match operators.split_first() {
+            Some((first, [])) => return self.execute_operator(first, memory),
+            Some((first, rest)) => (first, rest.to_vec()),
+            None => return Ok(Box::new(std::iter::once(Ok(memory.clone())))),
+        };
+        let executor = self.clone();
+        Ok(Box::new(self.execute_operator(first, memory)?.flat_map(
+            move |row| -> Rows {
+                match row.and_then(|row| executor.execute_join(&rest, &row)) {
+                    Ok(rows) => rows,
+                    Err(e) => Box::new(std::iter::once(Err(e))),
+                }
+            },
+        )))
+    }
+
+    fn execute_hash_join(
+        &self,
+        left: &Operator,
+        right: &Operator,
+        left_key: &Expr,
+        right_key: &Expr,
+        memory: &ExecutionMemory,
+    ) -> Result<Rows, ExecutionError> {
+        let collation = memory.collation();
+        let mut table: HashMap<String, Vec<ExecutionMemory>> = HashMap::new();
+        for row in self.execute_operator(right, memory)? {
+            let row = row?;
+            if let Some(key) = join_key(&evaluate(right_key, &row)?, collation) {
+                table.entry(key).or_default().push(row);
+            }
+        }
+        // keys only narrow down the candidates, the equality decides
+        let equal = Expr::BinaryOp {
+            op: BinaryOperator::Eq,
+            left: Box::new(left_key.clone()),
+            right: Box::new(right_key.clone()),
+        };
+        let left_key = left_key.clone();
+        let rows = self.execute_operator(left, memory)?;
+        Ok(Box::new(rows.flat_map(move |row| -> Rows {
+            let row = match row {
+                Ok(row) => row,
+                Err(e) => return Box::new(std::iter::once(Err(e))),
+            };
+            let key = match evaluate(&left_key, &row) {
+                Ok(value) => join_key(&value, collation),
+                Err(e) => return Box::new(std::iter::once(Err(e))),
+            };
+            let mut joined = vec![];
+            for candidate in key.and_then(|key| table.get(&key)).into_iter().flatten() {
+                let mut joined_row = row.clone();
+                joined_row.extend(candidate);
+                match evaluate(&equal, &joined_row) {
+                    Ok(Value::Boolean(true)) => joined.push(Ok(joined_row)),
+                    Ok(_) => {}
+                    Err(e) => joined.push(Err(e)),
+                }
+            }
+            Box::new(joined.into_iter())
+        })))
+    }
+
+    /// breadth-first search over the out edges from `src`, with a depth of at most `hops.1`.
+    /// returns the edge through which each vertex `hops.0` to `hops.1` hops away was first
+    /// reached. a vertex is visited once, so cycles end the search.
+    fn expand_out_edges(
+        &self,
+        src: &str,
+        label: Option<EdgeLabelId>,
+        hops: (u32, u32),
+    ) -> Result<Vec<Edge>, ExecutionError> {
+        let mut visited = HashSet::new();
+        visited.insert(src.to_owned());
+        let mut frontier = vec![src.to_owned()];
+        let mut reached = vec![];
+        for depth in 1..=hops.1 {
+            let mut next = vec![];
+            for id in &frontier {
+                for edge in self.edge_handler.scan_out_edges(id, label)? {
+                    let edge = edge?;
+                    if !visited.insert(edge.dst_vertex_id.to_owned()) {
+                        continue;
+                    }
+                    next.push(edge.dst_vertex_id.to_owned());
+                    if depth >= hops.0 {
+                        reached.push(edge);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        Ok(reached)
+    }
+
+    /// a row binding each of the vertices `ids` that still exists to `element_name`.
+    fn bind_vertices(
+        &self,
+        element_name: &str,
+        ids: Vec<String>,
+        memory: &ExecutionMemory,
+    ) -> Rows {
+        let memory = memory.clone();
+        let element_name = element_name.to_owned();
+        let vertex_handler = self.vertex_handler.clone();
+        Box::new(
+            ids.into_iter()
+                .filter_map(move |id| match vertex_handler.get_vertex(&id) {
+                    Ok(Some(vertex)) => {
+                        let mut row = memory.clone();
+                        row.bind_vertex(&element_name, vertex);
+                        row.count_scanned();
+                        Some(Ok(row))
+                    }
+                    Ok(None) => None,
+                    Err(e) => Some(Err(e.into())),
+                }),
+        )
+    }
+
+    /// the first `limit` rows in the order of `order_by`, keeping only those while sorting.
+    fn execute_top_n(
+        &self,
+        source: &Operator,
+        order_by: &[OrderByExpr],
+        limit: u64,
+        memory: &ExecutionMemory,
+    ) -> Result<Rows, ExecutionError> {
+        let limit = limit as usize;
+        let collation = memory.collation();
+        // grown as rows arrive, a huge limit must not be allocated up front
+        let mut top: Vec<(Vec<Value>, ExecutionMemory)> = vec![];
+        for row in self.execute_operator(source, memory)? {
+            let row = row?;
+            let key = Self::sort_keys(order_by, &row)?;
+            // after the equal keys, so that ties keep their input order like Sort
+            let position = top.partition_point(|(other, _)| {
+                compare_sort_rows(order_by, other, &key, collation) != Ordering::Greater
+            });
+            if position < limit {
+                top.insert(position, (key, row));
+                top.truncate(limit);
+            }
+        }
+        Ok(Box::new(top.into_iter().map(|(_, row)| Ok(row))))
+    }
+
+    fn sort_keys(
+        order_by: &[OrderByExpr],
+        row: &ExecutionMemory,
+    ) -> Result<Vec<Value>, ExecutionError> {
+        order_by
+            .iter()
+            .map(|order_by| evaluate(&order_by.expr, row))
+            .collect()
+    }
+
+    fn execute_aggregate(
+        &self,
+        source: &Operator,
+        group_keys: &[Expr],
+        aggregates: &[Expr],
+        memory: &ExecutionMemory,
+    ) -> Result<Rows, ExecutionError> {
+        let collation = memory.collation();
+        // groups in the order they are first seen, each with its first row
+        let mut groups: Vec<(ExecutionMemory, Vec<Accumulator>)> = vec![];
+        let mut index: HashMap<Vec<Option<String>>, usize> = HashMap::new();
+        for row in self.execute_operator(source, memory)? {
+            let row = row?;
+            let mut key = vec![];
+            for group_key in group_keys {
+                key.push(join_key(&evaluate(group_key, &row)?, collation));
+            }
+            let i = match index.get(&key) {
+                Some(i) => *i,
+                None => {
+                    let accumulators = aggregates
+                        .iter()
+                        .map(Accumulator::new)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    groups.push((row.clone(), accumulators));
+                    index.insert(key, groups.len() - 1);
+                    groups.len() - 1
+                }
+            };
+            for accumulator in &mut groups[i].1 {
+                accumulator.update(&row)?;
+            }
         }
-        Err(self.execute_error("not impl".to_string()))
+        // without GROUP BY there is exactly one group, even over no rows
+        if groups.is_empty() && group_keys.is_empty() {
+            let accumulators = aggregates
+                .iter()
+                .map(Accumulator::new)
+                .collect::<Result<Vec<_>, _>>()?;
+            groups.push((memory.clone(), accumulators));
+        }
+        let aggregates = aggregates.to_vec();
+        Ok(Box::new(groups.into_iter().map(
+            move |(mut row, accumulators)| {
+                for (aggregate, accumulator) in aggregates.iter().zip(accumulators) {
+                    row.bind_value(&column_name(aggregate), accumulator.finish());
+                }
+                Ok(row)
+            },
+        )))
+    }
+
+    /// rows are pulled lazily, so the source reads no more than n rows.
+    fn sample(rows: Rows, n: u64) -> Rows {
+        Box::new(rows.take(n as usize))
     }
 
     fn execute_insert_vertex(
         &self,
         label: &Expr,
-        properties: &Vec<String>,
+        properties: &[String],
         vertex_id: &Expr,
-        values: &Vec<Expr>,
+        values: &[Expr],
     ) -> Result<QueryOutput, ExecutionError> {
         let label_name = self.parse_label_name(label)?;
         let vid = self.parse_vertex_id(vertex_id)?;
-        let props = properties
-            .into_iter()
-            .map(|name| self.schema_handler.get_property_key_by_name(name).unwrap());
-        let values = values
-            .into_iter()
-            .map(|expr| self.execute_expr(expr).unwrap());
-        return match self.schema_handler.get_vertex_label_by_name(&label_name) {
+        let props = self.resolve_properties(properties, values)?;
+        match self.schema_handler.get_vertex_label_by_name(&label_name)? {
             Some(label) => {
-                let mut vertex = self.vertex_handler.create_vertex(&vid, label.id);
-                props.zip(values).for_each(|(prop, value)| {
-                    self.vertex_handler.add_property(&mut vertex, &prop, &value)
-                });
+                let mut vertex = self.vertex_handler.create_vertex(&vid, label.id)?;
+                for (prop, value) in &props {
+                    self.vertex_handler.add_property(&mut vertex, prop, value)?;
+                }
+                for key in self.schema_handler.get_defaulted_property_keys()? {
+                    if let Some(default_value) = &key.default_value {
+                        if !properties.contains(&key.name) {
+                            self.vertex_handler
+                                .add_property(&mut vertex, &key, default_value)?;
+                        }
+                    }
+                }
                 Ok(QueryOutput {
                     columns: vec!["CREATED".to_string()],
-                    items: Box::new(vec![vec!["1".to_string()]].into_iter()),
+                    items: Box::new(vec![vec!["1".to_string()]].into_iter().map(Ok)),
+                    stats: QueryStats::default(),
                 })
             }
             None => Err(self.execute_error(format!("No Vertex Label named {}", label_name))),
+        }
+    }
+
+    fn execute_insert_edge(
+        &self,
+        label: &Expr,
+        properties: &[String],
+        src_vertex_id: &Expr,
+        dst_vertex_id: &Expr,
+        values: &[Expr],
+    ) -> Result<QueryOutput, ExecutionError> {
+        let label = self.resolve_edge_label_by_name(label)?;
+        let src_id = self.parse_vertex_id(src_vertex_id)?;
+        let dst_id = self.parse_vertex_id(dst_vertex_id)?;
+        let props = self.resolve_properties(properties, values)?;
+        self.create_edge(&src_id, &dst_id, label.id, &props)?;
+        Ok(QueryOutput {
+            columns: vec!["CREATED".to_string()],
+            items: Box::new(vec![vec!["1".to_string()]].into_iter().map(Ok)),
+            stats: QueryStats::default(),
+        })
+    }
+
+    fn execute_insert_edge_between(
+        &self,
+        label: &Expr,
+        src: &VertexMatch,
+        dst: &VertexMatch,
+        cartesian: bool,
+        properties: &[String],
+        values: &[Expr],
+    ) -> Result<QueryOutput, ExecutionError> {
+        let label = self.resolve_edge_label_by_name(label)?;
+        let props = self.resolve_properties(properties, values)?;
+
+        let memory = self.new_memory()?;
+        let src_ids = self.match_vertices(src, &memory)?;
+        let dst_ids = self.match_vertices(dst, &memory)?;
+        if !cartesian && (src_ids.len() != 1 || dst_ids.len() != 1) {
+            return Err(self.execute_error(format!(
+                "expect exactly one vertex on each side but matched {} and {}. use BETWEEN ALL to connect every pair",
+                src_ids.len(),
+                dst_ids.len()
+            )));
+        }
+        let mut created = 0;
+        for src_id in &src_ids {
+            for dst_id in &dst_ids {
+                self.create_edge(src_id, dst_id, label.id, &props)?;
+                created += 1;
+            }
+        }
+        Ok(QueryOutput {
+            columns: vec!["CREATED".to_string()],
+            items: Box::new(vec![vec![created.to_string()]].into_iter().map(Ok)),
+            stats: QueryStats::default(),
+        })
+    }
+
+    fn create_edge(
+        &self,
+        src_id: &str,
+        dst_id: &str,
+        label: EdgeLabelId,
+        props: &[(PropertyKey, PropertyValue)],
+    ) -> Result<(), ExecutionError> {
+        let mut edge = self.edge_handler.create_edge(src_id, dst_id, label)?;
+        for (prop, value) in props {
+            self.edge_handler.add_property(&mut edge, prop, value)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_edge_label_by_name(&self, label: &Expr) -> Result<EdgeLabel, ExecutionError> {
+        let label_name = self.parse_label_name(label)?;
+        match self.schema_handler.get_edge_label_by_name(&label_name)? {
+            Some(label) => Ok(label),
+            None => Err(self.execute_error(format!("No Edge Label named {}", label_name))),
+        }
+    }
+
+    /// property keys named by `properties`, paired with the evaluated `values`.
+    fn resolve_properties(
+        &self,
+        properties: &[String],
+        values: &[Expr],
+    ) -> Result<Vec<(PropertyKey, PropertyValue)>, ExecutionError> {
+        let mut props = vec![];
+        for (name, value) in properties.iter().zip(values) {
+            match self.schema_handler.get_property_key_by_name(name)? {
+                Some(prop) => props.push((prop, self.execute_expr(value)?)),
+                None => return Err(self.execute_error(format!("No Property Key named {}", name))),
+            }
+        }
+        Ok(props)
+    }
+
+    /// delete the matched elements. deleting a vertex also deletes its in and out edges.
+    fn execute_delete(
+        &self,
+        elements: &[Expr],
+        graph_pattern: &GraphPattern,
+        condition: &Option<Expr>,
+    ) -> Result<QueryOutput, ExecutionError> {
+        let op = self.planner().build_select_query(
+            &elements.to_vec(),
+            &[],
+            graph_pattern,
+            condition,
+            &[],
+            &[],
+            &None,
+            &None,
+            &None,
+        )?;
+        let source = match op {
+            Operator::Projection { source, .. } => source,
+            op => Box::new(op),
         };
+        let mut names = vec![];
+        for element in elements {
+            match element {
+                Expr::Identifier(name) => names.push(name),
+                _ => return Err(self.execute_error(format!("can not delete {:?}", element))),
+            }
+        }
+        // collect first, the scans must not observe their own deletes
+        let mut vertex_ids = vec![];
+        let mut edges = vec![];
+        for row in self.execute_operator(&source, &self.new_memory()?)? {
+            let row = row?;
+            for name in &names {
+                if let Some(vertex) = row.get_vertex(name) {
+                    if !vertex_ids.contains(&vertex.id) {
+                        vertex_ids.push(vertex.id.to_owned());
+                    }
+                } else if let Some(edge) = row.get_edge(name) {
+                    if !edges.contains(edge) {
+                        edges.push(edge.clone());
+                    }
+                } else {
+                    return Err(self.execute_error(format!("No element named {}", name)));
+                }
+            }
+        }
+        let mut deleted = 0;
+        for edge in &edges {
+            self.edge_handler.remove_edge(edge)?;
+            deleted += 1;
+        }
+        for id in &vertex_ids {
+            // a self loop is both an out and an in edge
+            let mut incident = vec![];
+            for edge in self
+                .edge_handler
+                .scan_out_edges(id, None)?
+                .chain(self.edge_handler.scan_in_edges(id, None)?)
+            {
+                let edge = edge?;
+                if !incident.contains(&edge) {
+                    incident.push(edge);
+                }
+            }
+            for edge in &incident {
+                self.edge_handler.remove_edge(edge)?;
+                deleted += 1;
+            }
+            self.vertex_handler.remove_vertex(id)?;
+            deleted += 1;
+        }
+        Ok(QueryOutput {
+            columns: vec!["DELETED".to_string()],
+            items: Box::new(vec![vec![deleted.to_string()]].into_iter().map(Ok)),
+            stats: QueryStats::default(),
+        })
+    }
+
+    /// ids of the vertices satisfying the condition of `vertex_match`.
+    fn match_vertices(
+        &self,
+        vertex_match: &VertexMatch,
+        memory: &ExecutionMemory,
+    ) -> Result<Vec<String>, ExecutionError> {
+        let mut ids = vec![];
+        for vertex in self.vertex_handler.iter_all_vertices()? {
+            let vertex = vertex?;
+            let id = vertex.id.to_owned();
+            let mut row = memory.clone();
+            row.bind_vertex(&vertex_match.name, vertex);
+            let matched = match &vertex_match.condition {
+                Some(condition) => evaluate(condition, &row)? == Value::Boolean(true),
+                None => true,
+            };
+            if matched {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
     }
 
-    fn execute_expr(&self, expr: &Expr) -> Result<String, ExecutionError> {
+    fn new_memory(&self) -> Result<ExecutionMemory, ExecutionError> {
+        let mut memory = ExecutionMemory::with_catalog(self.build_catalog()?);
+        memory.set_collation(self.collation);
+        Ok(memory)
+    }
+
+    fn build_catalog(&self) -> Result<Catalog, ExecutionError> {
+        Ok(Catalog {
+            vertex_labels: self
+                .schema_handler
+                .get_vertex_labels()?
+                .into_iter()
+                .map(|label| (label.id, label.name))
+                .collect(),
+            edge_labels: self
+                .schema_handler
+                .get_edge_labels()?
+                .into_iter()
+                .map(|label| (label.id, label.name))
+                .collect(),
+            property_keys: self
+                .schema_handler
+                .get_property_keys()?
+                .into_iter()
+                .map(|key| (key.name, key.id))
+                .collect(),
+        })
+    }
+
+    /// the property value of a literal. integers that fit in i64 are stored as such, other
+    /// numbers as floats.
+    fn execute_expr(&self, expr: &Expr) -> Result<PropertyValue, ExecutionError> {
         match expr {
-            Expr::Value(Value::String(s)) => Ok(s.to_string()),
+            Expr::Value(Value::Null) => {
+                Err(self.execute_error("NULL is not a valid value".to_string()))
+            }
+            Expr::Value(Value::Number(n)) => match (n.parse::<i64>(), n.parse::<f64>()) {
+                (Ok(i), _) => Ok(PropertyValue::Int(i)),
+                (_, Ok(f)) => Ok(PropertyValue::Float(f)),
+                _ => Err(self.execute_error(format!("invalid number {}", n))),
+            },
+            Expr::Value(Value::Boolean(b)) => Ok(PropertyValue::Bool(*b)),
+            Expr::Value(Value::String(s)) => Ok(PropertyValue::String(s.to_owned())),
             _ => Err(self.execute_error("not impl".to_string())),
         }
     }
 
     fn parse_label_name(&self, label: &Expr) -> Result<String, ExecutionError> {
         match label {
-            Expr::Identifier(s) => Ok(s.to_string()),
+            Expr::Identifier(s) | Expr::Value(Value::String(s)) => Ok(s.to_string()),
             _ => Err(self.execute_error("not impl".to_string())),
         }
     }
@@ -212,75 +1365,1769 @@ impl QueryExecutor {
     }
 }
 
+/// `s` as a quoted graphviz id.
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 #[derive(Debug, Clone)]
 pub struct ExecutionError {
     msg: String,
 }
 
-#[cfg(test)]
-mod test {
-    use crate::parser::parser::Parser;
+impl ExecutionError {
+    pub fn new(msg: String) -> Self {
+        ExecutionError { msg }
+    }
+}
 
-    use super::*;
+impl Display for ExecutionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
 
-    fn print_output(output: QueryOutput) {
-        println!("--------------");
-        println!("{:?}", output.columns);
-        output.items.for_each(|x| println!("{:?}", x));
-        println!("--------------");
+impl From<ScanError> for ExecutionError {
+    fn from(e: ScanError) -> Self {
+        ExecutionError::new(e.to_string())
     }
+}
 
-    #[test]
-    fn test_create_schema() {
-        let engine = Rc::new(Box::new(SledEngine::new_tmp()));
-        let mut qe = QueryExecutor::new(engine.clone());
+impl From<io::Error> for ExecutionError {
+    fn from(e: io::Error) -> Self {
+        ExecutionError::new(e.to_string())
+    }
+}
 
-        let stmt = &Parser::parse_sql("CREATE VERTEX LABEL vertex_label").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+impl From<StorageError> for ExecutionError {
+    fn from(e: StorageError) -> Self {
+        ExecutionError::new(e.to_string())
+    }
+}
 
-        let stmt = &Parser::parse_sql("CREATE EDGE LABEL (edge_label, one2one)").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+impl From<SchemaError> for ExecutionError {
+    fn from(e: SchemaError) -> Self {
+        ExecutionError::new(e.to_string())
+    }
+}
 
-        let stmt = &Parser::parse_sql("CREATE PROPERTY KEY (property_key, single)").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+#[cfg(test)]
+mod test {
+    use crate::datamodel::base::ElementType;
+    use crate::parser::parser::{Parser, ParserOptions};
+    use crate::testing::{rows, TestDatabase};
 
-        let stmt = &Parser::parse_sql("SHOW VERTEX LABEL").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+    use super::*;
 
-        let stmt = &Parser::parse_sql("SHOW EDGE LABEL").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+    #[test]
+    fn test_create_schema() {
+        let mut db = TestDatabase::new();
+        assert_eq!(
+            db.query("CREATE VERTEX LABEL vertex_label"),
+            (
+                vec!["id".to_string(), "name".to_string(), "status".to_string()],
+                rows(&[&["0", "vertex_label", "CREATED"]])
+            )
+        );
+        assert_eq!(
+            db.run("CREATE EDGE LABEL (edge_label, one2one)"),
+            rows(&[&["1", "edge_label", "CREATED"]])
+        );
+        assert_eq!(
+            db.run("CREATE PROPERTY KEY (property_key, single)"),
+            rows(&[&["2", "property_key", "CREATED"]])
+        );
 
-        let stmt = &Parser::parse_sql("SHOW PROPERTY KEY").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+        assert_eq!(db.run("SHOW VERTEX LABEL"), rows(&[&["0", "vertex_label"]]));
+        assert_eq!(
+            db.query("SHOW EDGE LABEL"),
+            (
+                vec![
+                    "id".to_string(),
+                    "name".to_string(),
+                    "multiplicity".to_string()
+                ],
+                rows(&[&["1", "edge_label", "One2One"]])
+            )
+        );
+        assert_eq!(
+            db.run("SHOW PROPERTY KEY"),
+            rows(&[&["2", "property_key", "Single"]])
+        );
+    }
+
+    #[test]
+    fn test_drop_schema() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, one2many)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        assert_eq!(
+            db.query("DROP VERTEX LABEL person"),
+            (
+                vec!["name".to_string(), "status".to_string()],
+                rows(&[&["person", "DROPPED"]])
+            )
+        );
+        assert_eq!(
+            db.run("DROP VERTEX LABEL person"),
+            rows(&[&["person", "NOT_FOUND"]])
+        );
+        assert_eq!(
+            db.run("DROP EDGE LABEL knows"),
+            rows(&[&["knows", "DROPPED"]])
+        );
+        assert_eq!(
+            db.run("DROP EDGE LABEL person"),
+            rows(&[&["person", "NOT_FOUND"]])
+        );
+        assert_eq!(
+            db.run("DROP PROPERTY KEY name"),
+            rows(&[&["name", "DROPPED"]])
+        );
+        assert_eq!(
+            db.run("DROP PROPERTY KEY nonexistent"),
+            rows(&[&["nonexistent", "NOT_FOUND"]])
+        );
+        assert!(db.run("SHOW VERTEX LABEL").is_empty());
+        assert!(db.run("SHOW EDGE LABEL").is_empty());
+        assert!(db.run("SHOW PROPERTY KEY").is_empty());
+    }
+
+    #[test]
+    fn test_export_dot() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE VERTEX LABEL software");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (created, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')");
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v\"2'):('jerry')");
+        db.run("INSERT VERTEX software PROPERTIES (name) VALUES ('s1'):('angelina')");
+        db.run("INSERT EDGE knows BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 'v\"2')");
+        db.run("INSERT EDGE created BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 's1')");
+
+        let mut dot = vec![];
+        db.db.export_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        let mut lines = dot.lines().collect::<Vec<_>>();
+        assert_eq!(lines.remove(0), "digraph {");
+        assert_eq!(lines.pop(), Some("}"));
+        lines.sort_unstable();
+        assert_eq!(
+            lines,
+            vec![
+                r#"  "s1" [label="s1:software"];"#,
+                r#"  "v1" -> "s1" [label="created"];"#,
+                r#"  "v1" -> "v\"2" [label="knows"];"#,
+                r#"  "v1" [label="v1:person"];"#,
+                r#"  "v\"2" [label="v\"2:person"];"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_show_create_label() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, one2many)");
+        let (columns, vertex) = db.query("SHOW CREATE VERTEX LABEL person");
+        assert_eq!(columns, vec!["name".to_string(), "statement".to_string()]);
+        assert_eq!(vertex, rows(&[&["person", "CREATE VERTEX LABEL person"]]));
+        let edge = db.run("SHOW CREATE EDGE LABEL knows");
+        assert_eq!(
+            edge,
+            rows(&[&["knows", "CREATE EDGE LABEL (knows, One2Many)"]])
+        );
+        assert!(db
+            .run_err("SHOW CREATE EDGE LABEL person")
+            .to_string()
+            .contains("No Edge Label named person"));
+
+        // the statements recreate the same labels
+        let mut other = TestDatabase::new();
+        other.run(&vertex[0][1]);
+        other.run(&edge[0][1]);
+        assert_eq!(
+            other.run("SHOW EDGE LABEL"),
+            rows(&[&["1", "knows", "One2Many"]])
+        );
+        assert_eq!(other.run("SHOW CREATE VERTEX LABEL person"), vertex);
+        assert_eq!(other.run("SHOW CREATE EDGE LABEL knows"), edge);
     }
 
     #[test]
     fn test_insert_vertex() {
-        let engine = Rc::new(Box::new(SledEngine::new_tmp()));
-        let mut qe = QueryExecutor::new(engine.clone());
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL vertex_label");
+        db.run("CREATE PROPERTY KEY (prop1, single)");
+        db.run("CREATE PROPERTY KEY (prop2, single)");
 
-        let stmt = &Parser::parse_sql("CREATE VERTEX LABEL vertex_label").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+        assert_eq!(
+            db.run(
+                "INSERT VERTEX vertex_label PROPERTIES (prop1, prop2) VALUES ('id1'):('v1', 'v2') "
+            ),
+            rows(&[&["1"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id, a.prop1, a.prop2 FROM (a)"),
+            rows(&[&["id1", "v1", "v2"]])
+        );
 
-        let stmt = &Parser::parse_sql("CREATE PROPERTY KEY (prop1, single)").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
-        let stmt = &Parser::parse_sql("CREATE PROPERTY KEY (prop2, single)").unwrap()[0];
-        let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+        db.run_err("INSERT VERTEX no_label PROPERTIES (prop1) VALUES ('id2'):('v1')");
+        assert_eq!(db.run("SELECT a.id FROM (a)"), rows(&[&["id1"]]));
+    }
+
+    fn out_edges(engine: &SledEngine) -> Vec<(String, String)> {
+        engine
+            .open_tree("EDGE")
+            .unwrap()
+            .scan_prefix([ElementType::OutEdge as u8])
+            .map(|res| {
+                let (key, value) = res.unwrap();
+                let edge = Edge::deserialize(&key, &value).unwrap();
+                (edge.src_vertex_id, edge.dst_vertex_id)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default_property_value() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        db.run("CREATE PROPERTY KEY (status, single, default 'active')");
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')");
+        db.run("INSERT VERTEX person PROPERTIES (name, status) VALUES ('v2'):('jerry', 'away')");
+
+        assert_eq!(
+            db.run("SELECT a.id, a.status FROM (a)"),
+            rows(&[&["v1", "active"], &["v2", "away"]])
+        );
+    }
+
+    #[test]
+    fn test_typed_property_values() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        db.run("CREATE PROPERTY KEY (score, single, default 0.5)");
+        db.run("CREATE PROPERTY KEY (active, single)");
+        db.run("INSERT VERTEX person PROPERTIES (age, active) VALUES ('v1'):(10, true)");
+        db.run("INSERT VERTEX person PROPERTIES (age, score) VALUES ('v2'):(9, -2)");
+        db.run("INSERT VERTEX person PROPERTIES (age) VALUES ('v3'):('9a')");
+
+        // numeric, not lexicographic
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.age > 9"),
+            rows(&[&["v1"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id, a.score FROM (a) ORDER BY a.age"),
+            rows(&[&["v2", "-2"], &["v1", "0.5"], &["v3", "0.5"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.active = true"),
+            rows(&[&["v1"]])
+        );
+    }
+
+    #[test]
+    fn test_insert_edge_between() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let mut qe = QueryExecutor::new(engine.clone());
+        for sql in &[
+            "CREATE VERTEX LABEL person",
+            "CREATE EDGE LABEL (knows, many2manymulti)",
+            "CREATE PROPERTY KEY (name, single)",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('jerry')",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v3'):('jerry')",
+        ] {
+            qe.execute_statement(&Parser::parse_sql(sql).unwrap()[0])
+                .unwrap();
+        }
 
         let stmt = &Parser::parse_sql(
-            "INSERT VERTEX vertex_label PROPERTIES (prop1, prop2) VALUES ('id1'):('v1', 'v2') ",
+            "INSERT EDGE knows BETWEEN (a WHERE a.name = 'tom') AND (b WHERE b.id = 'v2')",
         )
         .unwrap()[0];
         let output = qe.execute_statement(stmt).unwrap();
-        print_output(output);
+        assert_eq!(output.into_rows().unwrap(), vec![vec!["1"]]);
+        assert_eq!(
+            out_edges(&engine),
+            vec![("v1".to_string(), "v2".to_string())]
+        );
+
+        // two vertices named jerry
+        let sql =
+            "INSERT EDGE knows BETWEEN (a WHERE a.name = 'tom') AND (b WHERE b.name = 'jerry')";
+        assert!(qe
+            .execute_statement(&Parser::parse_sql(sql).unwrap()[0])
+            .is_err());
+        assert_eq!(out_edges(&engine).len(), 1);
+
+        let sql =
+            "INSERT EDGE knows BETWEEN ALL (a WHERE a.name = 'tom') AND (b WHERE b.name = 'jerry')";
+        let output = qe
+            .execute_statement(&Parser::parse_sql(sql).unwrap()[0])
+            .unwrap();
+        assert_eq!(output.into_rows().unwrap(), vec![vec!["2"]]);
+        assert_eq!(out_edges(&engine).len(), 3);
+    }
+
+    #[test]
+    fn test_insert_edge() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (since, single)");
+        db.run("INSERT VERTEX person PROPERTIES (since) VALUES ('v1'):('2000')");
+        db.run("INSERT VERTEX person PROPERTIES (since) VALUES ('v2'):('2001')");
+
+        assert_eq!(
+            db.run("INSERT EDGE knows PROPERTIES (since) VALUES ('v1' -> 'v2'):('2020')"),
+            rows(&[&["1"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id, e.label, e.since, b.id FROM (a)-[e]->(b)"),
+            rows(&[&["v1", "knows", "2020", "v2"]])
+        );
+
+        let err = db.run_err("INSERT EDGE likes PROPERTIES (since) VALUES ('v1' -> 'v2'):('2020')");
+        assert!(err.to_string().contains("No Edge Label named likes"));
+    }
+
+    #[test]
+    fn test_variable_length_paths() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (likes, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (since, single)");
+        for id in &["v1", "v2", "v3", "v4", "v5"] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (since) VALUES ('{}'):(1)",
+                id
+            ));
+        }
+        // v1 -> v2 -> v3 -> v4 -> v1, v1 -> v3 and v2 -likes-> v5
+        for (src, dst) in &[
+            ("v1", "v2"),
+            ("v2", "v3"),
+            ("v3", "v4"),
+            ("v4", "v1"),
+            ("v1", "v3"),
+        ] {
+            db.run(&format!(
+                "INSERT EDGE knows PROPERTIES (since) VALUES ('{}' -> '{}'):(1)",
+                src, dst
+            ));
+        }
+        db.run("INSERT EDGE likes PROPERTIES (since) VALUES ('v2' -> 'v5'):(1)");
+
+        let friends = |db: &mut TestDatabase, hops: &str| {
+            db.run(&format!(
+                "SELECT b.id FROM (a)-[e{}]->(b) \
+                 WHERE a.id = 'v1' AND e.label = 'knows' ORDER BY b.id",
+                hops
+            ))
+        };
+        // friends of friends that are not friends, the cycle back to v1 is not followed
+        assert_eq!(friends(&mut db, "*2"), rows(&[&["v4"]]));
+        assert_eq!(
+            friends(&mut db, "*1..3"),
+            rows(&[&["v2"], &["v3"], &["v4"]])
+        );
+        assert_eq!(friends(&mut db, "*3..5").len(), 0);
+        // without a label every edge is followed
+        assert_eq!(
+            db.run("SELECT b.id, e.src FROM (a)-[e*2]->(b) WHERE a.id = 'v1' ORDER BY b.id"),
+            rows(&[&["v4", "v3"], &["v5", "v2"]])
+        );
+    }
+
+    #[test]
+    fn test_inline_labels() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE VERTEX LABEL dog");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (owns, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (since, single)");
+        for (id, label) in &[("v1", "person"), ("v2", "person"), ("v3", "dog")] {
+            db.run(&format!(
+                "INSERT VERTEX {} PROPERTIES (since) VALUES ('{}'):(1)",
+                label, id
+            ));
+        }
+        db.run("INSERT EDGE knows PROPERTIES (since) VALUES ('v1' -> 'v2'):(1)");
+        db.run("INSERT EDGE owns PROPERTIES (since) VALUES ('v1' -> 'v3'):(1)");
+
+        assert_eq!(
+            db.run("SELECT b.id FROM (a:person)-[e:knows]->(b) ORDER BY b.id"),
+            rows(&[&["v2"]])
+        );
+        assert_eq!(
+            db.run("SELECT b.id FROM (a:person)-[e]->(b:dog)"),
+            rows(&[&["v3"]])
+        );
+        // same as the label in the condition
+        assert_eq!(
+            db.run("SELECT b.id FROM (a)-[e:owns]->(b)"),
+            db.run("SELECT b.id FROM (a)-[e]->(b) WHERE e.label = 'owns'")
+        );
+        // a different label in the condition does not replace the inline one
+        assert!(db
+            .run("SELECT a.id FROM (a:person) WHERE a.label = 'dog'")
+            .is_empty());
+        assert!(db
+            .run("SELECT b.id FROM (a)-[e:knows]->(b) WHERE e.label = 'owns'")
+            .is_empty());
+        assert_eq!(
+            db.run("SELECT a.id FROM (a:person) WHERE a.label = 'person' ORDER BY a.id"),
+            rows(&[&["v1"], &["v2"]])
+        );
+    }
+
+    #[test]
+    fn test_anonymous_elements() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (since, single)");
+        for id in &["v1", "v2", "v3"] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (since) VALUES ('{}'):(1)",
+                id
+            ));
+        }
+        db.run("INSERT EDGE knows PROPERTIES (since) VALUES ('v1' -> 'v2'):(1)");
+        db.run("INSERT EDGE knows PROPERTIES (since) VALUES ('v2' -> 'v3'):(1)");
+
+        assert_eq!(
+            db.run("SELECT b.id FROM (a)-[]->(b) WHERE a.id = 'v1'"),
+            rows(&[&["v2"]])
+        );
+        assert_eq!(
+            db.run("SELECT b.id FROM (a)-[]->()-[:knows]->(b) WHERE a.id = 'v1'"),
+            rows(&[&["v3"]])
+        );
+        // the anonymous elements are not part of *
+        let output = db.query("SELECT * FROM (a)-[]->(b) WHERE a.id = 'v1'");
+        assert_eq!(output.0, vec!["a.id", "a.label", "b.id", "b.label"]);
+    }
+
+    #[test]
+    fn test_query_stats() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        for (id, age) in &[("v1", 10), ("v2", 20), ("v3", 30)] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (age) VALUES ('{}'):({})",
+                id, age
+            ));
+        }
+        // (rows_scanned, rows_returned) once all the items are consumed
+        let stats = |db: &mut TestDatabase, sql: &str| {
+            let output = db.db.execute(sql).unwrap();
+            let stats = output.stats.clone();
+            output.items.for_each(drop);
+            (stats.rows_scanned(), stats.rows_returned())
+        };
+
+        // a full scan reads every vertex
+        let filtered = stats(&mut db, "SELECT a.id FROM (a) WHERE a.age > 15");
+        assert_eq!(filtered, (3, 2));
+        let lookup = stats(&mut db, "SELECT a.id FROM (a) WHERE a.id = 'v2'");
+        assert_eq!(lookup, (1, 1));
+        assert_eq!(stats(&mut db, "SHOW VERTEX LABEL"), (0, 0));
+
+        // rows are projected as they are pulled
+        let mut output = db.db.execute("SELECT a.id FROM (a)").unwrap();
+        assert_eq!(output.stats.rows_returned(), 0);
+        assert_eq!(output.items.next().unwrap().unwrap(), vec!["v1"]);
+        assert_eq!(
+            (output.stats.rows_scanned(), output.stats.rows_returned()),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn test_not_exists() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (likes, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        for (id, age) in &[("v1", 10), ("v2", 20), ("v3", 30), ("v4", 40)] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (age) VALUES ('{}'):({})",
+                id, age
+            ));
+        }
+        db.run("INSERT EDGE knows PROPERTIES (age) VALUES ('v1' -> 'v2'):(1)");
+        db.run("INSERT EDGE knows PROPERTIES (age) VALUES ('v1' -> 'v3'):(1)");
+        db.run("INSERT EDGE knows PROPERTIES (age) VALUES ('v3' -> 'v1'):(1)");
+        db.run("INSERT EDGE likes PROPERTIES (age) VALUES ('v2' -> 'v1'):(1)");
+
+        assert_eq!(
+            db.run(
+                "SELECT a.id FROM (a) WHERE NOT EXISTS((a)-[:knows]->()) AND a.age > 10 \
+                 ORDER BY a.id"
+            ),
+            rows(&[&["v2"], &["v4"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE EXISTS((a)-[:knows]->()) ORDER BY a.id"),
+            rows(&[&["v1"], &["v3"]])
+        );
+        // the pattern may refer to more than one bound element
+        assert_eq!(
+            db.run(
+                "SELECT a.id, b.id FROM (a)-[e:knows]->(b) \
+                 WHERE NOT EXISTS((b)-[:knows]->(a)) ORDER BY b.id"
+            ),
+            rows(&[&["v1", "v2"]])
+        );
+        db.run_err("SELECT a.id FROM (a) WHERE a.age > 10 OR EXISTS((a)-[]->())");
+    }
+
+    #[test]
+    fn test_has_value() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        db.run("CREATE PROPERTY KEY (city, single)");
+        db.run("INSERT VERTEX person PROPERTIES (name, city) VALUES ('v1'):('foobar', 'paris')");
+        db.run("INSERT VERTEX person PROPERTIES (name, city) VALUES ('v2'):('alice', 'xfoo')");
+        db.run("INSERT VERTEX person PROPERTIES (name, city) VALUES ('v3'):('bob', 'rome')");
+        db.run("INSERT VERTEX person PROPERTIES (city) VALUES ('v4'):('berlin')");
+
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE has_value(a, '%foo%') ORDER BY a.id"),
+            rows(&[&["v1"], &["v2"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE NOT has_value(a, 'b%') ORDER BY a.id"),
+            rows(&[&["v1"], &["v2"]])
+        );
+        assert_eq!(
+            db.run("SELECT has_value(a, NULL) FROM (a) WHERE a.id = 'v1'"),
+            rows(&[&["NULL"]])
+        );
+    }
+
+    #[test]
+    fn test_disable_pushdown() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE VERTEX LABEL dog");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (owns, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        for (id, label, age) in &[
+            ("v1", "person", 30),
+            ("v2", "person", 40),
+            ("v3", "dog", 3),
+            ("v4", "person", 20),
+        ] {
+            db.run(&format!(
+                "INSERT VERTEX {} PROPERTIES (age) VALUES ('{}'):({})",
+                label, id, age
+            ));
+        }
+        for (label, src, dst) in &[
+            ("knows", "v1", "v2"),
+            ("knows", "v2", "v4"),
+            ("owns", "v1", "v3"),
+            ("knows", "v4", "v1"),
+        ] {
+            db.run(&format!(
+                "INSERT EDGE {} PROPERTIES (age) VALUES ('{}' -> '{}'):(1)",
+                label, src, dst
+            ));
+        }
+
+        for sql in &[
+            "SELECT a.id, b.id FROM (a)-[e]->(b) WHERE a.label = 'person' AND e.label = 'knows' \
+             AND b.age > 25 ORDER BY a.id",
+            "SELECT a.id, e.label FROM (b)<-[e]-(a) WHERE b.id = 'v3'",
+            "SELECT a.id, c.id FROM (a)-[e]->(b)-[e2]->(c), (d) WHERE a.age >= 30 \
+             AND d.label = 'dog' ORDER BY a.id",
+            "SELECT * FROM (a)-[e*1..2]->(b) WHERE a.id = 'v1' AND e.label = 'knows' \
+             ORDER BY b.id",
+            "SELECT a.label, count(*) FROM (a) WHERE a.age < 35 GROUP BY a.label ORDER BY a.label",
+            // the sample is taken before the condition filters it
+            "SELECT a.id FROM (a) SAMPLE 2 WHERE a.age < 35",
+            "SELECT a.id, b.id FROM (a)-[e]->(b) SAMPLE 3 WHERE e.label = 'knows'",
+        ] {
+            db.db.set_disable_pushdown(false);
+            let optimized = db.query(sql);
+            db.db.set_disable_pushdown(true);
+            assert_eq!(db.query(sql), optimized, "{}", sql);
+            assert!(!optimized.1.is_empty(), "{}", sql);
+        }
+
+        let plan = db.run("EXPLAIN SELECT a.id FROM (a) WHERE a.id = 'v1'");
+        assert!(plan[0][0].contains("VertexFullScan"), "{:?}", plan);
+    }
+
+    #[test]
+    fn test_qualified_columns() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (since, single)");
+        db.run("INSERT VERTEX person PROPERTIES (since) VALUES ('v1'):('2000')");
+        db.run("INSERT VERTEX person PROPERTIES (since) VALUES ('v2'):('2001')");
+        db.run("INSERT EDGE knows PROPERTIES (since) VALUES ('v1' -> 'v2'):('2020')");
+
+        // the same property of several elements gets distinct headers
+        let (columns, items) =
+            db.query("SELECT a.since, e.since, b.since, b.since AS later FROM (a)-[e]->(b)");
+        assert_eq!(columns, vec!["a.since", "e.since", "b.since", "later"]);
+        assert_eq!(items, rows(&[&["2000", "2020", "2001", "2001"]]));
+    }
+
+    #[test]
+    fn test_check_integrity() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let mut qe = QueryExecutor::new(engine);
+        for sql in &[
+            "CREATE VERTEX LABEL person",
+            "CREATE EDGE LABEL (knows, many2manymulti)",
+            "CREATE PROPERTY KEY (name, single)",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('jerry')",
+            "INSERT EDGE knows PROPERTIES (name) VALUES ('v1' -> 'v2'):('friends')",
+        ] {
+            qe.execute_statement(&Parser::parse_sql(sql).unwrap()[0])
+                .unwrap();
+        }
+        assert!(qe.check_integrity().unwrap().is_empty());
+
+        // a key id that was never created, as if restored from another schema
+        let dangling = PropertyKey {
+            id: PropertyKeyId(99),
+            name: "dangling".to_string(),
+            cardinality: Cardinality::Single,
+            default_value: None,
+        };
+        let mut vertex = qe.vertex_handler.get_vertex("v2").unwrap().unwrap();
+        qe.vertex_handler
+            .add_property(&mut vertex, &dangling, &"x".into())
+            .unwrap();
+        let mut edge = qe
+            .edge_handler
+            .scan_out_edges("v1", None)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        qe.edge_handler
+            .add_property(&mut edge, &dangling, &"y".into())
+            .unwrap();
+        assert_eq!(
+            qe.check_integrity().unwrap(),
+            vec![
+                IntegrityIssue::DanglingPropertyKey {
+                    element: "vertex v2".to_string(),
+                    key_id: PropertyKeyId(99)
+                },
+                IntegrityIssue::DanglingPropertyKey {
+                    element: "edge 0 v1->v2".to_string(),
+                    key_id: PropertyKeyId(99)
+                },
+            ]
+        );
+        assert_eq!(
+            qe.check_integrity().unwrap()[0].to_string(),
+            "vertex v2 has a property of unknown key 99"
+        );
+    }
+
+    #[test]
+    fn test_execute_plan() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let mut qe = QueryExecutor::new(engine);
+        for sql in &[
+            "CREATE VERTEX LABEL person",
+            "CREATE PROPERTY KEY (name, single)",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('jerry')",
+        ] {
+            qe.execute_statement(&Parser::parse_sql(sql).unwrap()[0])
+                .unwrap();
+        }
+        let scan = Operator::VertexFullScan {
+            element_name: "a".to_owned(),
+        };
+        let plan = Operator::Projection {
+            source: Box::new(scan.clone()),
+            items: vec![
+                Expr::IdExpr("a".to_owned()),
+                Parser::parse_expression("upper(a.name)").unwrap(),
+            ],
+            aliases: vec![None, Some("name".to_owned())],
+        };
+        let output = qe.execute_plan(&plan).unwrap();
+        assert_eq!(output.columns, vec!["a.id", "name"]);
+        assert_eq!(
+            output.into_rows().unwrap(),
+            rows(&[&["v1", "TOM"], &["v2", "JERRY"]])
+        );
+        assert!(qe.execute_plan(&scan).is_err());
+    }
+
+    #[test]
+    fn test_in_edge_traversal() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (likes, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        for (id, name) in &[("v1", "tom"), ("v2", "jerry"), ("v3", "spike")] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('{}'):('{}')",
+                id, name
+            ));
+        }
+        for (label, src, dst) in &[
+            ("knows", "v1", "v2"),
+            ("knows", "v3", "v2"),
+            ("knows", "v1", "v3"),
+            ("likes", "v1", "v2"),
+        ] {
+            db.run(&format!(
+                "INSERT EDGE {} BETWEEN (a WHERE a.id = '{}') AND (b WHERE b.id = '{}')",
+                label, src, dst
+            ));
+        }
+
+        // read from the in edges of b
+        let sql = "SELECT a.id FROM (b)<-[e:knows]-(a) WHERE b.name = 'jerry'";
+        let plan = &db.run(&format!("EXPLAIN {}", sql))[0][0];
+        assert!(
+            plan.contains(r#"InEdgeSeqScan { element_name: "e", edge_label: Some(Value(String("knows"))), dst: Some(Identifier("b")) }"#),
+            "{}",
+            plan
+        );
+        assert_eq!(db.run(sql), rows(&[&["v1"], &["v3"]]));
+        // both ends bound, so the second edge is looked up
+        let sql = "SELECT a.id, b.id FROM (b)<-[e:knows]-(a)-[e2:likes]->(b)";
+        let plan = &db.run(&format!("EXPLAIN {}", sql))[0][0];
+        assert!(plan.contains("OutEdgeLookup"), "{}", plan);
+        assert_eq!(db.run(sql), rows(&[&["v1", "v2"]]));
+
+        let lookup = |name: &str, id: &str| Operator::VertexLookup {
+            element_name: name.to_owned(),
+            vertex_id: Expr::Value(Value::String(id.to_owned())),
+        };
+        for (a, b, expected) in &[("v3", "v2", 1), ("v2", "v3", 0), ("v1", "v3", 1)] {
+            let edge = Operator::InEdgeLookup {
+                element_name: "e".to_owned(),
+                edge_label: Expr::Value(Value::String("knows".to_owned())),
+                src: Expr::Identifier("a".to_owned()),
+                dst: Expr::Identifier("b".to_owned()),
+            };
+            let plan = Operator::Projection {
+                source: Box::new(Operator::SimplePathJoin {
+                    operators: vec![lookup("a", a), lookup("b", b), edge],
+                }),
+                items: vec![Parser::parse_expression("e.src").unwrap()],
+                aliases: vec![None],
+            };
+            assert_eq!(
+                db.executor().execute_plan(&plan).unwrap().items.count(),
+                *expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        for (id, name) in &[("v1", "tom"), ("v2", "jerry"), ("v3", "spike")] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('{}'):('{}')",
+                id, name
+            ));
+        }
+        for (src, dst, name) in &[
+            ("v1", "v2", "a"),
+            ("v2", "v3", "b"),
+            ("v3", "v1", "c"),
+            ("v2", "v2", "d"),
+        ] {
+            db.run(&format!(
+                "INSERT EDGE knows PROPERTIES (name) VALUES ('{}' -> '{}'):('{}')",
+                src, dst, name
+            ));
+        }
+
+        // only the edge
+        assert_eq!(
+            db.run("DELETE [e] FROM (a)-[e]->(b) WHERE e.name = 'c'"),
+            rows(&[&["1"]])
+        );
+        assert_eq!(db.run("SELECT e.id FROM (a)-[e]->(b)").len(), 3);
+
+        // v2 with its out, in and self loop edges
+        assert_eq!(
+            db.run("DELETE (a) FROM (a) WHERE a.name = 'jerry'"),
+            rows(&[&["4"]])
+        );
+        assert!(db.run("SELECT e.id FROM (a)-[e]->(b)").is_empty());
+        // no in copy is left behind either
+        assert_eq!(
+            db.executor()
+                .edge_handler
+                .engine
+                .open_tree("EDGE")
+                .unwrap()
+                .scan_prefix([ElementType::InEdge as u8])
+                .count(),
+            0
+        );
+        assert_eq!(db.run("SELECT a.id FROM (a)"), rows(&[&["v1"], &["v3"]]));
+    }
+
+    #[test]
+    fn test_vertex_full_scan() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let mut qe = QueryExecutor::new(engine);
+        for sql in &[
+            "CREATE VERTEX LABEL person",
+            "CREATE PROPERTY KEY (name, single)",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('jerry')",
+            "INSERT VERTEX person PROPERTIES (name) VALUES ('v3'):('spike')",
+        ] {
+            qe.execute_statement(&Parser::parse_sql(sql).unwrap()[0])
+                .unwrap();
+        }
+
+        let op = Operator::VertexFullScan {
+            element_name: "a".to_string(),
+        };
+        let ids: Vec<String> = qe
+            .execute_operator(&op, &ExecutionMemory::new())
+            .unwrap()
+            .map(|row| row.unwrap().get_vertex("a").unwrap().id.to_owned())
+            .collect();
+        assert_eq!(ids, vec!["v1", "v2", "v3"]);
+
+        let stmt = &Parser::parse_sql("SELECT a.id, a.name FROM (a)").unwrap()[0];
+        let rows: Vec<Vec<String>> = qe.execute_statement(stmt).unwrap().into_rows().unwrap();
+        assert_eq!(
+            rows,
+            vec![vec!["v1", "tom"], vec!["v2", "jerry"], vec!["v3", "spike"]]
+        );
+    }
+
+    #[test]
+    fn test_vertex_lookup() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')");
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('jerry')");
+        let qe = db.executor();
+
+        let lookup = |id: &str| Operator::VertexLookup {
+            element_name: "a".to_string(),
+            vertex_id: Expr::Value(Value::String(id.to_string())),
+        };
+        let rows: Vec<ExecutionMemory> = qe
+            .execute_operator(&lookup("v2"), &ExecutionMemory::new())
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_vertex("a").unwrap().id, "v2");
+
+        assert_eq!(
+            qe.execute_operator(&lookup("v3"), &ExecutionMemory::new())
+                .unwrap()
+                .count(),
+            0
+        );
+        let null_id = Operator::VertexLookup {
+            element_name: "a".to_string(),
+            vertex_id: Expr::Value(Value::Null),
+        };
+        assert_eq!(
+            qe.execute_operator(&null_id, &ExecutionMemory::new())
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_predicate_filter() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE VERTEX LABEL software");
+        db.run("CREATE PROPERTY KEY (prop1, single)");
+        db.run("CREATE PROPERTY KEY (prop2, single)");
+        db.run("INSERT VERTEX person PROPERTIES (prop1, prop2) VALUES ('v1'):('a', '3')");
+        db.run("INSERT VERTEX person PROPERTIES (prop1, prop2) VALUES ('v2'):('b', '5')");
+        db.run("INSERT VERTEX software PROPERTIES (prop1, prop2) VALUES ('v3'):('c', '1')");
+        db.run("INSERT VERTEX person PROPERTIES (prop1) VALUES ('v4'):('d')");
+
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.label = 'person' AND a.prop2 < '4'"),
+            rows(&[&["v1"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.prop2 >= 3 AND a.prop2 <= 5"),
+            rows(&[&["v1"], &["v2"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE NOT a.label = 'person'"),
+            rows(&[&["v3"]])
+        );
+        // a missing property is NULL, which never passes the filter
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.prop2 != '3'"),
+            rows(&[&["v2"], &["v3"]])
+        );
+    }
+
+    #[test]
+    fn test_projection() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (prop1, single)");
+        db.run("CREATE PROPERTY KEY (prop2, single)");
+        db.run("INSERT VERTEX person PROPERTIES (prop1) VALUES ('v1'):('a')");
+        db.run("INSERT VERTEX person PROPERTIES (prop2) VALUES ('v2'):('b')");
+
+        let (columns, items) = db.query("SELECT a.label, a.prop1 FROM (a)");
+        assert_eq!(columns, vec!["a.label", "a.prop1"]);
+        assert_eq!(items, rows(&[&["person", "a"], &["person", "NULL"]]));
+
+        let (columns, items) = db.query("SELECT * FROM (a) WHERE a.id = 'v1'");
+        assert_eq!(columns, vec!["a.id", "a.label"]);
+        assert_eq!(items, rows(&[&["v1", "person"]]));
+        let (columns, _) = db.query("SELECT a.*, a.prop1 FROM (a)");
+        assert_eq!(columns, vec!["a.id", "a.label", "a.prop1"]);
+
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) ORDER BY a.prop1 DESC"),
+            rows(&[&["v2"], &["v1"]])
+        );
+    }
+
+    #[test]
+    fn test_column_aliases() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (prop1, single)");
+        db.run("INSERT VERTEX person PROPERTIES (prop1) VALUES ('v1'):('a')");
+        db.run("INSERT VERTEX person PROPERTIES (prop1) VALUES ('v2'):('a')");
+
+        let (columns, items) =
+            db.query("SELECT a.prop1 AS name, a.id, * FROM (a) WHERE a.id = 'v1'");
+        assert_eq!(columns, vec!["name", "a.id", "a.id", "a.label"]);
+        assert_eq!(items, rows(&[&["a", "v1", "v1", "person"]]));
+
+        let (columns, items) =
+            db.query("SELECT a.prop1 AS name, count(*) AS total FROM (a) GROUP BY a.prop1");
+        assert_eq!(columns, vec!["name", "total"]);
+        assert_eq!(items, rows(&[&["a", "2"]]));
+
+        let (columns, items) =
+            db.query("SELECT upper(concat(a.prop1, lower('B'))) FROM (a) WHERE a.id = 'v2'");
+        assert_eq!(columns, vec!["upper(concat(a.prop1, lower(B)))"]);
+        assert_eq!(items, rows(&[&["AB"]]));
+    }
+
+    #[test]
+    fn test_is_null() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        db.run("INSERT VERTEX person PROPERTIES (name, age) VALUES ('v1'):('a', 30)");
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('b')");
+        db.run("INSERT EDGE knows PROPERTIES (age) VALUES ('v1' -> 'v2'):(3)");
+
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.age IS NULL"),
+            rows(&[&["v2"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.age IS NOT NULL"),
+            rows(&[&["v1"]])
+        );
+        let (columns, items) = db.query(
+            "SELECT a.name IS NULL, a.nope IS NULL, e.age IS NULL, e.name IS NULL, e.src IS NULL \
+             FROM (a)-[e]->(b)",
+        );
+        assert_eq!(columns[0], "a.name IS NULL");
+        assert_eq!(items, rows(&[&["false", "true", "false", "true", "false"]]));
+    }
+
+    #[test]
+    fn test_like() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        for (id, name) in &[("v1", "John"), ("v2", "Joe"), ("v3", "jo"), ("v4", "Mojo")] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('{}'):('{}')",
+                id, name
+            ));
+        }
+        assert_eq!(
+            db.run("SELECT a.name FROM (a) WHERE a.name LIKE 'Jo%' ORDER BY a.name"),
+            rows(&[&["Joe"], &["John"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.name FROM (a) WHERE a.name LIKE '_o%' AND a.name LIKE '%j%' ORDER BY a.id"),
+            rows(&[&["jo"], &["Mojo"]])
+        );
+    }
+
+    #[test]
+    fn test_in_list() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE VERTEX LABEL dog");
+        db.run("CREATE VERTEX LABEL software");
+        db.run("CREATE PROPERTY KEY (prop1, single)");
+        db.run("INSERT VERTEX person PROPERTIES (prop1) VALUES ('v1'):('a')");
+        db.run("INSERT VERTEX dog PROPERTIES (prop1) VALUES ('v2'):('b')");
+        db.run("INSERT VERTEX software PROPERTIES (prop1) VALUES ('v3'):('c')");
+
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.label IN ('person', 'dog') ORDER BY a.id"),
+            rows(&[&["v1"], &["v2"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.prop1 NOT IN ('a', 'b')"),
+            rows(&[&["v3"]])
+        );
+    }
+
+    #[test]
+    fn test_label_id() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE VERTEX LABEL software");
+        db.run("CREATE PROPERTY KEY (prop1, single)");
+        db.run("INSERT VERTEX software PROPERTIES (prop1) VALUES ('v1'):('a')");
+
+        // schema ids are allocated in order, software is 1
+        let (columns, items) = db.query("SELECT label_id(a), a.label FROM (a)");
+        assert_eq!(columns, vec!["label_id(a)", "a.label"]);
+        assert_eq!(items, rows(&[&["1", "software"]]));
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE label_id(a) = 1"),
+            rows(&[&["v1"]])
+        );
+    }
+
+    #[test]
+    fn test_vertex_property_index_lookup() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        db.run("INSERT VERTEX person PROPERTIES (name, age) VALUES ('v1'):('Ann', 9)");
+        db.run("INSERT VERTEX person PROPERTIES (name, age) VALUES ('v2'):('Bob', 9)");
+        db.run("INSERT VERTEX person PROPERTIES (name, age) VALUES ('v1'):('ann', 10)");
+
+        let sql = "SELECT a.id FROM (a) WHERE a.age = 9 AND a.name != 'Ann'";
+        let plan = match &Parser::parse_sql(sql).unwrap()[0] {
+            Statement::Select {
+                items,
+                graph_pattern,
+                condition,
+                ..
+            } => Planner::new()
+                .build_select_query(
+                    items,
+                    &[],
+                    graph_pattern,
+                    condition,
+                    &[],
+                    &[],
+                    &None,
+                    &None,
+                    &None,
+                )
+                .unwrap(),
+            _ => unreachable!(),
+        };
+        assert!(format!("{:?}", plan).contains("VertexPropertyIndexLookup"));
+        assert_eq!(db.run(sql), rows(&[&["v2"]]));
+        // the overwritten v1 is no longer indexed under its old values
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.name = 'Ann'"),
+            rows(&[])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE 10 = a.age"),
+            rows(&[&["v1"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.missing = 10"),
+            rows(&[])
+        );
+    }
+
+    #[test]
+    fn test_vertex_property_index_range_scan() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        for i in 0..200 {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (age) VALUES ('v{}'):({})",
+                i,
+                i % 100
+            ));
+        }
+        let plan =
+            |db: &mut TestDatabase, sql: &str| db.run(&format!("EXPLAIN {}", sql))[0][0].clone();
+
+        // a highly selective range is read from the index
+        let sql = "SELECT a.id FROM (a) WHERE a.age > 97 AND a.age <= 99";
+        assert!(plan(&mut db, sql).contains("VertexPropertyIndexRangeScan"));
+        assert_eq!(db.run(sql).len(), 4);
+        let sql = "SELECT a.id FROM (a) WHERE 2 > a.age";
+        assert!(plan(&mut db, sql).contains("range: (None, Some(2.0))"));
+        assert_eq!(db.run(sql).len(), 4);
+
+        // a near-full range scans
+        let sql = "SELECT a.id FROM (a) WHERE a.age >= 5";
+        assert!(plan(&mut db, sql).contains("VertexFullScan"));
+        assert_eq!(db.run(sql).len(), 190);
+        let sql = "SELECT a.id FROM (a) WHERE a.missing < 5";
+        assert!(plan(&mut db, sql).contains("VertexFullScan"));
+    }
+
+    #[test]
+    fn test_create_schema_block() {
+        let mut db = TestDatabase::new();
+        assert_eq!(
+            db.run(concat!(
+                "CREATE SCHEMA { VERTEX LABELS (person); EDGE LABELS ((knows, one2one)); ",
+                "PROPERTY KEYS ((name, single)) }"
+            )),
+            rows(&[
+                &["0", "person", "CREATED"],
+                &["1", "knows", "CREATED"],
+                &["2", "name", "CREATED"]
+            ])
+        );
+
+        // every element is checked before any is created
+        db.run_err(concat!(
+            "CREATE SCHEMA { VERTEX LABELS (software); EDGE LABELS ((created, one2many)); ",
+            "PROPERTY KEYS ((lang, single), (age, single, DEFAULT NULL)) }"
+        ));
+        db.run_err(concat!(
+            "CREATE SCHEMA { VERTEX LABELS (software); EDGE LABELS ((created, bogus)); ",
+            "PROPERTY KEYS ((lang, single)) }"
+        ));
+        assert_eq!(db.run("SHOW VERTEX LABEL"), rows(&[&["0", "person"]]));
+        assert_eq!(
+            db.run("SHOW EDGE LABEL"),
+            rows(&[&["1", "knows", "One2One"]])
+        );
+        assert_eq!(
+            db.run("SHOW PROPERTY KEY"),
+            rows(&[&["2", "name", "Single"]])
+        );
+        // and the failed blocks took no ids
+        assert_eq!(
+            db.run("CREATE VERTEX LABEL software"),
+            rows(&[&["3", "software", "CREATED"]])
+        );
+    }
+
+    #[test]
+    fn test_duplicate_schema_name() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL foo");
+        let err = db.run_err("CREATE VERTEX LABEL foo");
+        assert_eq!(
+            err.to_string(),
+            "ExecutionError: Vertex Label named foo already exists"
+        );
+        assert_eq!(db.run("SHOW VERTEX LABEL"), rows(&[&["0", "foo"]]));
+
+        // a duplicate inside a schema block creates nothing
+        db.run_err("CREATE SCHEMA { VERTEX LABELS (bar, foo) }");
+        assert_eq!(db.run("SHOW VERTEX LABEL"), rows(&[&["0", "foo"]]));
+        // as does a name repeated inside the block
+        let err = db.run_err("CREATE SCHEMA { VERTEX LABELS (bar, baz, bar) }");
+        assert_eq!(
+            err.to_string(),
+            "ExecutionError: Vertex Label named bar already exists"
+        );
+        assert_eq!(db.run("SHOW VERTEX LABEL"), rows(&[&["0", "foo"]]));
+        // the same name for different kinds of element is fine
+        db.run("CREATE SCHEMA { VERTEX LABELS (bar); EDGE LABELS ((bar, one2one)) }");
+    }
+
+    #[test]
+    fn test_bulk_insert_reuses_schema_names() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let mut qe = QueryExecutor::new(engine);
+        let execute = |qe: &mut QueryExecutor, sql: &str| {
+            qe.execute_statement(&Parser::parse_sql(sql).unwrap()[0])
+                .unwrap();
+        };
+        execute(&mut qe, "CREATE VERTEX LABEL person");
+        execute(&mut qe, "CREATE PROPERTY KEY (name, single)");
+        execute(&mut qe, "CREATE PROPERTY KEY (age, single, DEFAULT 0)");
+        execute(&mut qe, "CREATE PROPERTY KEY (city, single)");
+        let insert = |i| {
+            format!(
+                "INSERT VERTEX person PROPERTIES (name, city) VALUES ('v{}'):('n{}', 'c')",
+                i, i
+            )
+        };
+        execute(&mut qe, &insert(0));
+
+        let scans = qe.schema_handler.scan_count();
+        for i in 1..1000 {
+            execute(&mut qe, &insert(i));
+        }
+        assert_eq!(qe.schema_handler.scan_count(), scans);
+        assert_eq!(qe.vertex_handler.iter_all_vertices().unwrap().count(), 1000);
+
+        // a new key is visible to the next insert
+        execute(
+            &mut qe,
+            "CREATE PROPERTY KEY (country, single, DEFAULT 'x')",
+        );
+        execute(&mut qe, &insert(1000));
+        let vertex = qe.vertex_handler.get_vertex("v1000").unwrap().unwrap();
+        assert_eq!(vertex.properties.get_properties().count(), 4);
+    }
+
+    #[test]
+    fn test_simple_path_join() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (next, single)");
+        db.run("INSERT VERTEX person PROPERTIES (next) VALUES ('v1'):('v2')");
+        db.run("INSERT VERTEX person PROPERTIES (next) VALUES ('v2'):('v3')");
+
+        // cartesian product of two independent patterns
+        assert_eq!(
+            db.run("SELECT a.id, b.id FROM (a), (b)"),
+            rows(&[&["v1", "v1"], &["v1", "v2"], &["v2", "v1"], &["v2", "v2"]])
+        );
+
+        // the lookup of b is driven by the a bound before it
+        let join = Operator::SimplePathJoin {
+            operators: vec![
+                Operator::VertexFullScan {
+                    element_name: "a".to_string(),
+                },
+                Operator::VertexLookup {
+                    element_name: "b".to_string(),
+                    vertex_id: Expr::CompoundIdentifier(vec!["a".to_string(), "next".to_string()]),
+                },
+            ],
+        };
+        let qe = db.executor();
+        let pairs: Vec<(String, String)> = qe
+            .execute_operator(&join, &qe.new_memory().unwrap())
+            .unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (
+                    row.get_vertex("a").unwrap().id.to_owned(),
+                    row.get_vertex("b").unwrap().id.to_owned(),
+                )
+            })
+            .collect();
+        assert_eq!(pairs, vec![("v1".to_string(), "v2".to_string())]);
+    }
+
+    #[test]
+    fn test_hash_join() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        for (id, age) in &[("v1", "30"), ("v2", "25"), ("v3", "30"), ("v4", "40")] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (age) VALUES ('{}'):('{}')",
+                id, age
+            ));
+        }
+
+        let sql = "SELECT a.id, b.id FROM (a), (b) WHERE a.age = b.age AND a.id < b.id";
+        assert_eq!(db.run(sql), rows(&[&["v1", "v3"]]));
+        let plan = &db.run(&format!("EXPLAIN {}", sql))[0][0];
+        assert!(plan.contains("HashJoin"));
+
+        assert_eq!(
+            db.run("SELECT a.id, b.id FROM (a), (b) WHERE b.age = a.age AND a.id != b.id"),
+            rows(&[&["v1", "v3"], &["v3", "v1"]])
+        );
+    }
+
+    #[test]
+    fn test_out_edge_seq_scan() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (likes, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        for (id, name) in &[("v1", "tom"), ("v2", "jerry"), ("v3", "spike")] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('{}'):('{}')",
+                id, name
+            ));
+        }
+        db.run("INSERT EDGE knows BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 'v2')");
+        db.run("INSERT EDGE knows BETWEEN (a WHERE a.id = 'v2') AND (b WHERE b.id = 'v3')");
+        db.run("INSERT EDGE likes BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 'v3')");
+
+        let (columns, items) = db.query("SELECT * FROM (a)-[e]->(b)");
+        assert_eq!(
+            columns,
+            vec!["a.id", "a.label", "e.id", "e.label", "b.id", "b.label"]
+        );
+        assert_eq!(
+            items,
+            rows(&[
+                &["v1", "person", "0", "knows", "v2", "person"],
+                &["v1", "person", "2", "likes", "v3", "person"],
+                &["v2", "person", "1", "knows", "v3", "person"],
+            ])
+        );
+        assert_eq!(
+            db.run("SELECT a.name, e.src, e.dst, b.name FROM (a)-[e]->(b) WHERE a.id = 'v2'"),
+            rows(&[&["jerry", "v2", "v3", "spike"]])
+        );
+
+        // full scan of the edges of an unbound src, restricted to a label
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (likes, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')");
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v2'):('jerry')");
+        db.run("INSERT EDGE knows BETWEEN ALL (a) AND (b)");
+        db.run("INSERT EDGE likes BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 'v2')");
+        let qe = db.executor();
+        let scan = |label: &str| Operator::OutEdgeSeqScan {
+            element_name: "e".to_string(),
+            edge_label: Some(Expr::Value(Value::String(label.to_string()))),
+            src: Some(Expr::Identifier("a".to_string())),
+        };
+        let edges = |op: &Operator| -> Vec<String> {
+            qe.execute_operator(op, &qe.new_memory().unwrap())
+                .unwrap()
+                .map(|row| {
+                    let edge = row.unwrap().get_edge("e").unwrap().clone();
+                    format!("{}->{}", edge.src_vertex_id, edge.dst_vertex_id)
+                })
+                .collect()
+        };
+        assert_eq!(
+            edges(&scan("knows")),
+            vec!["v1->v1", "v1->v2", "v2->v1", "v2->v2"]
+        );
+        assert_eq!(edges(&scan("likes")), vec!["v1->v2"]);
+        assert!(edges(&scan("hates")).is_empty());
+    }
+
+    #[test]
+    fn test_edge_label_filter() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (likes, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        for id in &["v1", "v2", "v3"] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('{}'):('{}')",
+                id, id
+            ));
+        }
+        db.run("INSERT EDGE knows BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 'v2')");
+        db.run("INSERT EDGE likes BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 'v3')");
+        db.run("INSERT EDGE likes BETWEEN (a WHERE a.id = 'v3') AND (b WHERE b.id = 'v2')");
+
+        for sql in &[
+            "SELECT a.id, b.id FROM (a)-[e]->(b) WHERE e.label = 'likes'",
+            "SELECT a.id, b.id FROM (b)<-[e]-(a) WHERE e.label = 'likes'",
+            "SELECT a.id, b.id FROM (b)<-[e]-(a) WHERE 'likes' = e.label",
+        ] {
+            let plan = &db.run(&format!("EXPLAIN {}", sql))[0][0];
+            assert!(plan.contains(r#"edge_label: Some(Value(String("likes")))"#));
+            // `(b)<-[e]-(a)` is read from b
+            let mut result = db.run(sql);
+            result.sort();
+            assert_eq!(result, rows(&[&["v1", "v3"], &["v3", "v2"]]), "{}", sql);
+        }
+
+        // the same scan with the function form of lenient structural accessors
+        db.db.set_parser_options(ParserOptions {
+            strict_structural_accessors: false,
+            ..ParserOptions::default()
+        });
+        let sql = "SELECT id(a), id(b) FROM (b)<-[e]-(a) WHERE label(e) = 'knows'";
+        let plan = &db.run(&format!("EXPLAIN {}", sql))[0][0];
+        assert!(plan.contains(r#"edge_label: Some(Value(String("knows")))"#));
+        assert_eq!(db.run(sql), rows(&[&["v1", "v2"]]));
+    }
+
+    #[test]
+    fn test_undirected_edges() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE EDGE LABEL (knows, many2manymulti)");
+        db.run("CREATE EDGE LABEL (likes, many2manymulti)");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        for id in &["v1", "v2", "v3"] {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('{}'):('{}')",
+                id, id
+            ));
+        }
+        db.run("INSERT EDGE knows BETWEEN (a WHERE a.id = 'v1') AND (b WHERE b.id = 'v2')");
+        db.run("INSERT EDGE knows BETWEEN (a WHERE a.id = 'v3') AND (b WHERE b.id = 'v1')");
+        db.run("INSERT EDGE knows BETWEEN (a WHERE a.id = 'v2') AND (b WHERE b.id = 'v2')");
+        db.run("INSERT EDGE likes BETWEEN (a WHERE a.id = 'v2') AND (b WHERE b.id = 'v3')");
+
+        let sql = "SELECT a.id, b.id, e.src FROM (a)-[e:knows]-(b) ORDER BY a.id, b.id";
+        let expected = rows(&[
+            &["v1", "v2", "v1"],
+            &["v1", "v3", "v3"],
+            &["v2", "v1", "v1"],
+            // the self loop once
+            &["v2", "v2", "v2"],
+            &["v3", "v1", "v3"],
+        ]);
+        assert_eq!(db.run(sql), expected);
+        // whichever way the edge is stored
+        assert_eq!(
+            db.run("SELECT b.id FROM (a)-[e:knows]-(b) WHERE a.id = 'v1' ORDER BY b.id"),
+            rows(&[&["v2"], &["v3"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a)-[e]-(b) WHERE b.id = 'v3' ORDER BY a.id"),
+            rows(&[&["v1"], &["v2"]])
+        );
+        db.db.set_disable_pushdown(true);
+        assert_eq!(db.run(sql), expected);
+    }
+
+    #[test]
+    fn test_no_such_element() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        assert_eq!(
+            db.run_err("SELECT a.id FROM (a) WHERE z.prop = 1")
+                .to_string(),
+            "ExecutionError: no such element z"
+        );
+        db.run_err("SELECT a.id FROM (a) WHERE EXISTS((a)-[]->(b)) AND z.label = 'person'");
+        assert_eq!(
+            db.run_err("SELECT 1").to_string(),
+            "ExecutionError: the graph pattern has no elements"
+        );
+        assert_eq!(
+            db.run_err("SELECT b.id FROM (a)-[a]->(b)").to_string(),
+            "ExecutionError: duplicate element name a"
+        );
+        // the db is still usable
+        assert!(db.run("SELECT a.id FROM (a)").is_empty());
+    }
+
+    #[test]
+    fn test_vertex_id_range_scan() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        for i in 1..=9 {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('id{}'):('n')",
+                i
+            ));
+        }
+        let ids = |ids: &[&str]| {
+            ids.iter()
+                .map(|id| vec![id.to_string()])
+                .collect::<Vec<_>>()
+        };
+
+        let sql = "SELECT a.id FROM (a) WHERE a.id >= 'id3' AND a.id < 'id7'";
+        let plan = &db.run(&format!("EXPLAIN {}", sql))[0][0];
+        assert!(
+            plan.contains(r#"range: (Some(Value(String("id3"))), Some(Value(String("id7"))))"#),
+            "{}",
+            plan
+        );
+        assert_eq!(db.run(sql), ids(&["id3", "id4", "id5", "id6"]));
+        // the tightest of several bounds, and open bounds
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.id > 'id2' AND a.id > 'id6' AND a.id <= 'id8'"),
+            ids(&["id7", "id8"])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.id > 'id7'"),
+            ids(&["id8", "id9"])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.id <= 'id2'"),
+            ids(&["id1", "id2"])
+        );
+        // lexicographic, so 'id10' would sort between 'id1' and 'id2'
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.id < 'id10'"),
+            ids(&["id1"])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE a.id = 'id4' AND a.id > 'id1'"),
+            ids(&["id4"])
+        );
+        assert!(db.run("SELECT a.id FROM (a) WHERE a.id > 'id9'").is_empty());
+        // the id on the right of the comparison
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE 'id5' > a.id AND 'id2' <= a.id"),
+            ids(&["id2", "id3", "id4"])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE 'id7' <= a.id"),
+            ids(&["id7", "id8", "id9"])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE 'id2' >= a.id"),
+            ids(&["id1", "id2"])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) WHERE 'id8' < a.id"),
+            ids(&["id9"])
+        );
+    }
+
+    #[test]
+    fn test_aggregate_functions() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE VERTEX LABEL software");
+        db.run("CREATE PROPERTY KEY (name, single)");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        for (id, label, name, age) in &[
+            ("v1", "person", "tom", "30"),
+            ("v2", "person", "ann", "25"),
+            ("v4", "software", "lop", "2.5"),
+        ] {
+            db.run(&format!(
+                "INSERT VERTEX {} PROPERTIES (name, age) VALUES ('{}'):('{}', {})",
+                label, id, name, age
+            ));
+        }
+        // without an age
+        db.run("INSERT VERTEX person PROPERTIES (name) VALUES ('v3'):('bob')");
+
+        let (columns, items) = db.query(concat!(
+            "SELECT a.label, count(*), count(a.age), sum(a.age), avg(a.age), ",
+            "min(a.name), max(a.age) FROM (a) GROUP BY a.label"
+        ));
+        assert_eq!(
+            columns,
+            vec![
+                "a.label",
+                "count(*)",
+                "count(a.age)",
+                "sum(a.age)",
+                "avg(a.age)",
+                "min(a.name)",
+                "max(a.age)"
+            ]
+        );
+        assert_eq!(
+            items,
+            rows(&[
+                &["person", "3", "2", "55", "27.5", "ann", "30"],
+                &["software", "1", "1", "2.5", "2.5", "lop", "2.5"]
+            ])
+        );
+        // no values
+        assert_eq!(
+            db.run("SELECT count(a.age), sum(a.age), avg(a.age), min(a.age) FROM (a) WHERE a.id = 'v3'"),
+            rows(&[&["0", "NULL", "NULL", "NULL"]])
+        );
+        assert_eq!(
+            db.run_err("SELECT a.id, count(*) FROM (a)").to_string(),
+            "ExecutionError: a.id must appear in the GROUP BY clause or be used in an aggregate function"
+        );
+        db.run_err("SELECT a.id FROM (a) ORDER BY max(a.age) DESC LIMIT 1");
+        db.run_err("SELECT a.age FROM (a) GROUP BY a.label");
+        db.run_err("SELECT * FROM (a) GROUP BY a.label");
+        // sorting the groups by an ungrouped column
+        assert_eq!(
+            db.run_err("SELECT count(*) FROM (a) ORDER BY a.id").to_string(),
+            "ExecutionError: a.id must appear in the GROUP BY clause or be used in an aggregate function"
+        );
+        assert_eq!(
+            db.run("SELECT a.label, count(*) * 2 + 1, 'x' FROM (a) GROUP BY a.label"),
+            rows(&[&["person", "7", "x"], &["software", "3", "x"]])
+        );
+        assert_eq!(
+            db.run_err("SELECT sum(a.name) FROM (a)").to_string(),
+            "ExecutionError: sum expects numbers but got String(\"tom\")"
+        );
+    }
+
+    #[test]
+    fn test_top_n_groups() {
+        let mut db = TestDatabase::new();
+        for label in &["person", "software", "city", "country"] {
+            db.run(&format!("CREATE VERTEX LABEL {}", label));
+        }
+        db.run("CREATE PROPERTY KEY (name, single)");
+        let counts = [("person", 4), ("software", 1), ("city", 3), ("country", 2)];
+        for (label, count) in &counts {
+            for i in 0..*count {
+                db.run(&format!(
+                    "INSERT VERTEX {} PROPERTIES (name) VALUES ('{}{}'):('n')",
+                    label, label, i
+                ));
+            }
+        }
+
+        let sql =
+            "SELECT a.label, count(*) FROM (a) GROUP BY a.label ORDER BY count(*) DESC LIMIT 2";
+        let (columns, items) = db.query(sql);
+        assert_eq!(columns, vec!["a.label", "count(*)"]);
+        assert_eq!(items, rows(&[&["person", "4"], &["city", "3"]]));
+        let plan = &db.run(&format!("EXPLAIN {}", sql))[0][0];
+        assert!(plan.contains("Limit { source: Sort { source: Aggregate"));
+
+        assert_eq!(
+            db.run("SELECT count(*) FROM (a) WHERE a.label = 'city'"),
+            rows(&[&["3"]])
+        );
+        assert_eq!(
+            db.run("SELECT count(*) FROM (a) WHERE a.label = 'dog'"),
+            rows(&[&["0"]])
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) ORDER BY a.id LIMIT 3"),
+            rows(&[&["city0"], &["city1"], &["city2"]])
+        );
+        assert_eq!(db.run("SELECT a.id FROM (a) LIMIT 4").len(), 4);
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) ORDER BY a.id LIMIT 2 OFFSET 1"),
+            rows(&[&["city1"], &["city2"]])
+        );
+        assert_eq!(
+            db.run(&sql.replace("LIMIT 2", "LIMIT 2 OFFSET 1")),
+            rows(&[&["city", "3"], &["country", "2"]])
+        );
+        assert_eq!(db.run("SELECT a.id FROM (a) LIMIT 4 OFFSET 8").len(), 2);
+        assert_eq!(db.run("SELECT a.id FROM (a) LIMIT 2 OFFSET 10").len(), 0);
+        // a huge limit neither overflows with the offset nor is allocated up front
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) ORDER BY a.id LIMIT 18446744073709551615 OFFSET 1")
+                .len(),
+            9
+        );
+        assert_eq!(
+            db.run("SELECT a.id FROM (a) ORDER BY a.id LIMIT 100000000000")
+                .len(),
+            10
+        );
+    }
+
+    #[test]
+    fn test_order_by_several_keys() {
+        let mut db = TestDatabase::new();
+        db.run("CREATE VERTEX LABEL person");
+        db.run("CREATE VERTEX LABEL dog");
+        db.run("CREATE PROPERTY KEY (age, single)");
+        for (id, label, age) in &[
+            ("v1", "person", 30),
+            ("v2", "dog", 3),
+            ("v3", "person", 40),
+            ("v4", "dog", 5),
+            ("v5", "person", 30),
+            ("v6", "dog", 3),
+        ] {
+            db.run(&format!(
+                "INSERT VERTEX {} PROPERTIES (age) VALUES ('{}'):({})",
+                label, id, age
+            ));
+        }
+
+        // ties on both keys keep the order of the scan, by id
+        let sql = "SELECT a.id, a.label, a.age FROM (a) ORDER BY a.label ASC, a.age DESC";
+        let sorted = rows(&[
+            &["v4", "dog", "5"],
+            &["v2", "dog", "3"],
+            &["v6", "dog", "3"],
+            &["v3", "person", "40"],
+            &["v1", "person", "30"],
+            &["v5", "person", "30"],
+        ]);
+        assert_eq!(db.run(sql), sorted);
+        // the same order when only the first rows are kept
+        for limit in 1..=6 {
+            assert_eq!(
+                db.run(&format!("{} LIMIT {}", sql, limit)),
+                sorted[..limit].to_vec()
+            );
+        }
+        assert_eq!(
+            db.run(&format!("{} LIMIT 2 OFFSET 2", sql)),
+            sorted[2..4].to_vec()
+        );
+    }
+
+    #[test]
+    fn test_sample() {
+        let engine = SledEngine::new_tmp();
+        let scanned = engine.scanned_counter();
+        let mut db = TestDatabase::with_engine(engine);
+        db.run("CREATE VERTEX LABEL person; CREATE PROPERTY KEY (name, single)");
+        for i in 0..10 {
+            db.run(&format!(
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('v{}'):('n{}')",
+                i, i
+            ));
+        }
+
+        // (rows, vertices read from the engine)
+        let mut sample = |sql: &str| {
+            let before = scanned.get();
+            let rows = db.run(sql);
+            (rows, scanned.get() - before)
+        };
+        assert_eq!(sample("SELECT a.id FROM (a)").1, 10);
+        assert_eq!(
+            sample("SELECT a.id FROM (a) SAMPLE 3"),
+            (rows(&[&["v0"], &["v1"], &["v2"]]), 3)
+        );
+        // the condition filters the sample, it doesn't read more vertices to fill it
+        assert_eq!(
+            sample("SELECT a.id FROM (a) SAMPLE 3 WHERE a.name > 'n1'"),
+            (rows(&[&["v2"]]), 3)
+        );
+
+        let plan = db.run("EXPLAIN SELECT a.id FROM (a) SAMPLE 3");
+        assert!(plan[0][0].contains("Sample { source: VertexFullScan"));
     }
 }
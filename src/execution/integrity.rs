@@ -0,0 +1,31 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::datamodel::base::PropertyKeyId;
+
+/// an inconsistency between the stored elements and the schema, found by `check_integrity`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum IntegrityIssue {
+    /// a property whose key id has no property key in the schema, e.g. after the key was
+    /// dropped, or a restore brought in elements of another schema.
+    DanglingPropertyKey {
+        element: String,
+        key_id: PropertyKeyId,
+    },
+    /// an edge whose src or dst vertex does not exist, e.g. after a vertex was removed
+    /// without its edges.
+    OrphanEdge { edge: String, vertex_id: String },
+}
+
+impl Display for IntegrityIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::DanglingPropertyKey { element, key_id } => {
+                write!(f, "{} has a property of unknown key {}", element, key_id)
+            }
+            IntegrityIssue::OrphanEdge { edge, vertex_id } => {
+                write!(f, "{} refers to missing vertex {}", edge, vertex_id)
+            }
+        }
+    }
+}
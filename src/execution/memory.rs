@@ -1,18 +1,105 @@
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
+use crate::datamodel::base::{EdgeLabelId, PropertyKeyId, VertexLabelId};
 use crate::datamodel::edge::Edge;
 use crate::datamodel::vertex::Vertex;
+use crate::execution::eval::Collation;
+use crate::parser::ast::Value;
 
+/// names of the schema elements, resolved once per query so that expressions can be
+/// evaluated without going back to the schema tree.
+#[derive(Debug, Default)]
+pub struct Catalog {
+    pub vertex_labels: HashMap<VertexLabelId, String>,
+    pub edge_labels: HashMap<EdgeLabelId, String>,
+    pub property_keys: HashMap<String, PropertyKeyId>,
+}
+
+/// elements bound by name while executing a query.
+#[derive(Debug, Clone, Default)]
 pub struct ExecutionMemory {
+    catalog: Rc<Catalog>,
+    collation: Collation,
     vertices: HashMap<String, Vertex>,
     edges: HashMap<String, Edge>,
+    /// plain values by name, e.g. `age` or `a.age`, for evaluating outside of a graph.
+    values: HashMap<String, Value>,
+    /// elements read from storage, shared by all the rows of a query
+    scanned: Rc<Cell<u64>>,
 }
 
 impl ExecutionMemory {
     pub fn new() -> Self {
+        Self::with_catalog(Catalog::default())
+    }
+
+    pub fn with_catalog(catalog: Catalog) -> Self {
         ExecutionMemory {
+            catalog: Rc::new(catalog),
+            collation: Collation::default(),
             vertices: Default::default(),
             edges: Default::default(),
+            values: Default::default(),
+            scanned: Default::default(),
         }
     }
+
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    pub fn set_collation(&mut self, collation: Collation) {
+        self.collation = collation;
+    }
+
+    pub fn count_scanned(&self) {
+        self.scanned.set(self.scanned.get() + 1);
+    }
+
+    /// the counter of `rows_scanned`, shared with every copy of this memory.
+    pub(crate) fn scanned_counter(&self) -> Rc<Cell<u64>> {
+        self.scanned.clone()
+    }
+
+    /// number of elements read from storage by the query of this row so far.
+    pub fn rows_scanned(&self) -> u64 {
+        self.scanned.get()
+    }
+
+    pub fn bind_vertex(&mut self, name: &str, vertex: Vertex) {
+        self.vertices.insert(name.to_owned(), vertex);
+    }
+
+    pub fn bind_edge(&mut self, name: &str, edge: Edge) {
+        self.edges.insert(name.to_owned(), edge);
+    }
+
+    /// bind all the elements bound in `other`.
+    pub fn extend(&mut self, other: &ExecutionMemory) {
+        self.vertices.extend(other.vertices.clone());
+        self.edges.extend(other.edges.clone());
+        self.values.extend(other.values.clone());
+    }
+
+    pub fn bind_value(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_owned(), value);
+    }
+
+    pub fn get_value(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn get_vertex(&self, name: &str) -> Option<&Vertex> {
+        self.vertices.get(name)
+    }
+
+    pub fn get_edge(&self, name: &str) -> Option<&Edge> {
+        self.edges.get(name)
+    }
 }
@@ -1,6 +1,10 @@
+pub mod aggregate;
+pub mod eval;
 pub mod executor;
+pub mod integrity;
 pub mod memory;
 pub mod operator;
 pub mod output;
 pub mod planner;
 pub mod scope;
+pub mod statistics;
@@ -1,4 +1,4 @@
-use crate::parser::ast::Expr;
+use crate::parser::ast::{Expr, OrderByExpr};
 
 #[derive(Debug, Clone)]
 pub enum Operator {
@@ -13,11 +13,33 @@ pub enum Operator {
         element_name: String,
         vertex_id: Expr,
     },
+    /// vertices with a `property_key` property equal to `value`, read from the vertex
+    /// property index.
+    VertexPropertyIndexLookup {
+        element_name: String,
+        property_key: String,
+        value: Expr,
+    },
+    /// vertices with a numeric `property_key` property within `range`, read from the vertex
+    /// property index. an open bound is unbounded.
+    VertexPropertyIndexRangeScan {
+        element_name: String,
+        property_key: String,
+        range: (Option<f64>, Option<f64>),
+    },
     OutEdgeSeqScan {
         element_name: String,
         edge_label: Option<Expr>,
         src: Option<Expr>,
     },
+    /// the vertices `hops.0` to `hops.1` out edges away from `src`, each reached once by a
+    /// breadth-first search. binds the last edge of the path to each of them.
+    OutEdgeExpand {
+        element_name: String,
+        edge_label: Option<Expr>,
+        src: Expr,
+        hops: (u32, u32),
+    },
     InEdgeSeqScan {
         element_name: String,
         edge_label: Option<Expr>,
@@ -35,15 +57,61 @@ pub enum Operator {
         src: Expr,
         dst: Expr,
     },
+    /// the rows of every operator in turn.
+    Union {
+        operators: Vec<Operator>,
+    },
     PredicateFilter {
         source: Box<Operator>,
         predicates: Vec<Expr>,
     },
+    /// `aliases` name the output columns of `items`, a missing alias names a column after
+    /// its expression.
     Projection {
         source: Box<Operator>,
         items: Vec<Expr>,
+        aliases: Vec<Option<String>>,
+    },
+    /// stop reading `source` after n rows
+    Sample {
+        source: Box<Operator>,
+        n: u64,
+    },
+    /// stable sort by the keys of `order_by`, the first key first.
+    Sort {
+        source: Box<Operator>,
+        order_by: Vec<OrderByExpr>,
+    },
+    /// skip `offset` rows, then stop after `limit` rows. on top of a Sort, only the first
+    /// `offset + limit` rows are kept while sorting.
+    Limit {
+        source: Box<Operator>,
+        limit: u64,
+        offset: u64,
+    },
+    /// one row per distinct value of `group_keys`, or a single row if there are none.
+    /// the value of each of `aggregates` is bound under its column name.
+    Aggregate {
+        source: Box<Operator>,
+        group_keys: Vec<Expr>,
+        aggregates: Vec<Expr>,
     },
     SimplePathJoin {
         operators: Vec<Operator>,
     },
+    /// rows of `source` for which `pattern` matches at least once, or never if `anti`, as
+    /// for `EXISTS` and `NOT EXISTS`. `pattern` runs once per row, with its elements bound.
+    SemiJoin {
+        source: Box<Operator>,
+        pattern: Box<Operator>,
+        anti: bool,
+    },
+    /// join of two independent operators on `left_key = right_key`. `right` is read once
+    /// into a hash table that every row of `left` probes.
+    HashJoin {
+        left: Box<Operator>,
+        right: Box<Operator>,
+        left_key: Expr,
+        right_key: Expr,
+    },
 }
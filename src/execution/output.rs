@@ -1,4 +1,386 @@
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fmt::Write;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::rc::Rc;
+
+use crate::execution::executor::ExecutionError;
+
 pub struct QueryOutput {
-    pub(crate) columns: Vec<String>,
-    pub items: Box<dyn Iterator<Item = Vec<String>>>,
+    pub columns: Vec<String>,
+    /// rows computed lazily as they are consumed, so a row that fails to compute, e.g. a
+    /// cancelled scan, is an error of its own.
+    pub items: Box<dyn Iterator<Item = Result<Vec<String>, ExecutionError>>>,
+    pub stats: QueryStats,
+}
+
+/// counters of a SELECT, all zero for the other statements. clones share the counters,
+/// which the items of the output update as they are consumed, so they are final once the
+/// items are.
+#[derive(Debug, Clone, Default)]
+pub struct QueryStats {
+    rows_scanned: Rc<Cell<u64>>,
+    rows_returned: Rc<Cell<u64>>,
+}
+
+impl QueryStats {
+    /// stats counting the scanned rows in `rows_scanned`.
+    pub(crate) fn new(rows_scanned: Rc<Cell<u64>>) -> Self {
+        QueryStats {
+            rows_scanned,
+            rows_returned: Default::default(),
+        }
+    }
+
+    pub(crate) fn count_returned(&self) {
+        self.rows_returned.set(self.rows_returned.get() + 1);
+    }
+
+    /// elements read from storage by the scans and lookups so far, e.g. a filtered full
+    /// scan reads every vertex.
+    pub fn rows_scanned(&self) -> u64 {
+        self.rows_scanned.get()
+    }
+
+    /// items of the output consumed so far.
+    pub fn rows_returned(&self) -> u64 {
+        self.rows_returned.get()
+    }
+}
+
+impl QueryOutput {
+    pub fn empty() -> Self {
+        QueryOutput {
+            columns: vec![],
+            items: Box::new(vec![].into_iter()),
+            stats: QueryStats::default(),
+        }
+    }
+
+    /// an output of the rows computed beforehand.
+    pub fn from_rows(columns: Vec<String>, rows: Vec<Vec<String>>) -> Self {
+        QueryOutput {
+            columns,
+            items: Box::new(rows.into_iter().map(Ok)),
+            stats: QueryStats::default(),
+        }
+    }
+
+    /// all the rows, or the first error computing them. consumes the output.
+    pub fn into_rows(self) -> Result<Vec<Vec<String>>, ExecutionError> {
+        self.items.collect()
+    }
+
+    /// order-sensitive hash of the columns and all rows. consumes the output.
+    pub fn fingerprint(self) -> Result<u64, ExecutionError> {
+        let mut hasher = DefaultHasher::new();
+        self.columns.hash(&mut hasher);
+        for row in self.items {
+            row?.hash(&mut hasher);
+        }
+        Ok(hasher.finish())
+    }
+
+    /// the values of each column, in the order of `columns`. consumes the output.
+    pub fn into_columns(self) -> Result<(Vec<String>, Vec<Vec<String>>), ExecutionError> {
+        let mut values = vec![vec![]; self.columns.len()];
+        for row in self.items {
+            for (column, value) in values.iter_mut().zip(row?) {
+                column.push(value);
+            }
+        }
+        Ok((self.columns, values))
+    }
+
+    /// a json array with an object per row, keyed by the column names. every value is a
+    /// json string. consumes the output.
+    pub fn to_json(self) -> Result<String, ExecutionError> {
+        let mut json = String::from("[");
+        for (i, row) in self.items.enumerate() {
+            let row = row?;
+            if i > 0 {
+                json.push(',');
+            }
+            json.push('{');
+            for (j, (column, value)) in self.columns.iter().zip(row.iter()).enumerate() {
+                if j > 0 {
+                    json.push(',');
+                }
+                push_json_string(&mut json, column);
+                json.push(':');
+                push_json_string(&mut json, value);
+            }
+            json.push('}');
+        }
+        json.push(']');
+        Ok(json)
+    }
+
+    /// write a header of the column names and a line per row, quoted as in RFC 4180 and
+    /// ended by CRLF. the rows are written as they are computed, so a row that fails ends
+    /// the csv. consumes the output.
+    pub fn write_csv<W: io::Write>(self, w: &mut W) -> Result<(), WriteError> {
+        write_csv_line(w, &self.columns)?;
+        for row in self.items {
+            write_csv_line(w, &row?)?;
+        }
+        Ok(())
+    }
+
+    /// write the output as a table with a column per column of the output, padded to its
+    /// widest value, followed by the number of rows. all the rows are computed before the
+    /// table is written. consumes the output.
+    pub fn write_table<W: io::Write>(self, w: &mut W) -> Result<(), WriteError> {
+        let rows = self.items.collect::<Result<Vec<_>, _>>()?;
+        let mut widths = self
+            .columns
+            .iter()
+            .map(|c| c.chars().count())
+            .collect::<Vec<_>>();
+        for row in &rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+        let separator = widths
+            .iter()
+            .map(|width| format!("+{}", "-".repeat(width + 2)))
+            .collect::<String>()
+            + "+";
+        let line = |values: &[String]| {
+            widths
+                .iter()
+                .zip(values)
+                .map(|(width, value)| format!("| {:width$} ", value, width = width))
+                .collect::<String>()
+                + "|"
+        };
+        writeln!(w, "{}", separator)?;
+        writeln!(w, "{}", line(&self.columns))?;
+        writeln!(w, "{}", separator)?;
+        for row in &rows {
+            writeln!(w, "{}", line(row))?;
+        }
+        if !rows.is_empty() {
+            writeln!(w, "{}", separator)?;
+        }
+        match rows.len() {
+            1 => writeln!(w, "(1 row)")?,
+            n => writeln!(w, "({} rows)", n)?,
+        }
+        Ok(())
+    }
+}
+
+/// an error of `QueryOutput::write_csv` or `QueryOutput::write_table`.
+#[derive(Debug)]
+pub enum WriteError {
+    /// a row of the output failed
+    Execution(ExecutionError),
+    Io(io::Error),
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Execution(e) => write!(f, "ExecutionError: {}", e),
+            WriteError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ExecutionError> for WriteError {
+    fn from(e: ExecutionError) -> Self {
+        WriteError::Execution(e)
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> Self {
+        WriteError::Io(e)
+    }
+}
+
+fn write_csv_line<W: io::Write>(w: &mut W, values: &[String]) -> io::Result<()> {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            w.write_all(b",")?;
+        }
+        if value.contains(&[',', '"', '\n', '\r'][..]) {
+            write!(w, "\"{}\"", value.replace('"', "\"\""))?;
+        } else {
+            w.write_all(value.as_bytes())?;
+        }
+    }
+    w.write_all(b"\r\n")
+}
+
+fn push_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod test {
+    use crate::database::Database;
+
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        let mut db = Database::open_tmp();
+        db.execute("CREATE VERTEX LABEL person").unwrap();
+        db.execute("CREATE VERTEX LABEL software").unwrap();
+        db.execute("CREATE EDGE LABEL (knows, one2one)").unwrap();
+
+        let fingerprint = db
+            .execute("SHOW VERTEX LABEL")
+            .unwrap()
+            .fingerprint()
+            .unwrap();
+        assert_eq!(
+            fingerprint,
+            db.execute("SHOW VERTEX LABEL")
+                .unwrap()
+                .fingerprint()
+                .unwrap()
+        );
+        assert_ne!(
+            fingerprint,
+            db.execute("SHOW EDGE LABEL")
+                .unwrap()
+                .fingerprint()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_into_columns() {
+        let mut db = Database::open_tmp();
+        for label in &["person", "software", "city"] {
+            db.execute(&format!("CREATE VERTEX LABEL {}", label))
+                .unwrap();
+        }
+        let (columns, values) = db
+            .execute("SHOW VERTEX LABEL")
+            .unwrap()
+            .into_columns()
+            .unwrap();
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(
+            values,
+            vec![vec!["0", "1", "2"], vec!["person", "software", "city"]]
+        );
+
+        let (columns, values) = QueryOutput::empty().into_columns().unwrap();
+        assert!(columns.is_empty() && values.is_empty());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut db = Database::open_tmp();
+        db.execute("CREATE VERTEX LABEL person").unwrap();
+        db.execute("CREATE VERTEX LABEL software").unwrap();
+        assert_eq!(
+            db.execute("SHOW VERTEX LABEL").unwrap().to_json().unwrap(),
+            r#"[{"id":"0","name":"person"},{"id":"1","name":"software"}]"#
+        );
+        assert_eq!(QueryOutput::empty().to_json().unwrap(), "[]");
+
+        let output = QueryOutput::from_rows(
+            vec!["a\"b".to_owned()],
+            vec![vec!["line\n\\ \u{1}".to_owned()]],
+        );
+        assert_eq!(output.to_json().unwrap(), r#"[{"a\"b":"line\n\\ \u0001"}]"#);
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let output = QueryOutput::from_rows(
+            vec!["name".to_owned(), "note".to_owned()],
+            vec![
+                vec!["tom".to_owned(), "a, b".to_owned()],
+                vec!["jerry".to_owned(), "say \"hi\"\nbye".to_owned()],
+                vec!["".to_owned(), "plain".to_owned()],
+            ],
+        );
+        let mut csv = vec![];
+        output.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            concat!(
+                "name,note\r\n",
+                "tom,\"a, b\"\r\n",
+                "jerry,\"say \"\"hi\"\"\nbye\"\r\n",
+                ",plain\r\n"
+            )
+        );
+
+        let mut csv = vec![];
+        QueryOutput::empty().write_csv(&mut csv).unwrap();
+        assert_eq!(csv, b"\r\n");
+
+        // a row failing half way fails the write
+        let output = QueryOutput {
+            columns: vec!["name".to_owned()],
+            items: Box::new(
+                vec![
+                    Ok(vec!["tom".to_owned()]),
+                    Err(ExecutionError::new("scan cancelled".to_owned())),
+                ]
+                .into_iter(),
+            ),
+            stats: QueryStats::default(),
+        };
+        let mut csv = vec![];
+        match output.write_csv(&mut csv) {
+            Err(WriteError::Execution(e)) => assert_eq!(e.to_string(), "scan cancelled"),
+            _ => panic!("expect an execution error"),
+        }
+    }
+
+    #[test]
+    fn test_write_table() {
+        let mut db = Database::open_tmp();
+        db.execute("CREATE VERTEX LABEL person").unwrap();
+        db.execute("CREATE VERTEX LABEL software").unwrap();
+        let mut table = vec![];
+        db.execute("SHOW VERTEX LABEL")
+            .unwrap()
+            .write_table(&mut table)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(table).unwrap(),
+            concat!(
+                "+----+----------+\n",
+                "| id | name     |\n",
+                "+----+----------+\n",
+                "| 0  | person   |\n",
+                "| 1  | software |\n",
+                "+----+----------+\n",
+                "(2 rows)\n",
+            )
+        );
+
+        let mut table = vec![];
+        db.execute("SHOW EDGE LABEL")
+            .unwrap()
+            .write_table(&mut table)
+            .unwrap();
+        assert!(String::from_utf8(table).unwrap().ends_with("+\n(0 rows)\n"));
+    }
 }
@@ -1,144 +1,780 @@
-use std::borrow::BorrowMut;
-use std::collections::HashSet;
-use std::ops::Deref;
+use std::collections::{HashMap, HashSet};
 
-use crate::datamodel::constants::MAX_ID_LENGTH;
+use crate::execution::eval::{column_name, is_aggregate};
+use crate::execution::executor::ExecutionError;
 use crate::execution::operator::Operator;
-use crate::execution::operator::Operator::OutEdgeSeqScan;
 use crate::execution::scope::{Comparator, EdgePattern, Scope, VertexPattern};
+use crate::execution::statistics::Statistics;
 use crate::parser::ast::Value;
-use crate::parser::ast::{Expr, GraphPattern};
-use crate::parser::operator::BinaryOperator;
-use crate::parser::tokenizer::Token::Keyword;
+use crate::parser::ast::{Expr, GraphPattern, OrderByExpr, ANONYMOUS_PREFIX};
+use crate::parser::operator::{BinaryOperator, UnaryOperator};
+
+/// the largest estimated selectivity for which a property range is read from the index
+/// rather than by a full scan, as every match costs a random read of its vertex.
+const INDEX_RANGE_MAX_SELECTIVITY: f64 = 0.2;
+
+/// inclusive bounds, an open bound is unbounded
+type NumericRange = (Option<f64>, Option<f64>);
 
 pub struct Planner {
     scope: Scope,
+    statistics: Option<Box<dyn Statistics>>,
+    disable_pushdown: bool,
+}
+
+impl Default for Planner {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Planner {
     pub fn new() -> Self {
         Planner {
             scope: Scope::new(),
+            statistics: None,
+            disable_pushdown: false,
+        }
+    }
+
+    /// a planner that weighs index range scans against full scans with `statistics`.
+    pub fn with_statistics(statistics: Box<dyn Statistics>) -> Self {
+        Planner {
+            scope: Scope::new(),
+            statistics: Some(statistics),
+            disable_pushdown: false,
         }
     }
 
+    /// build naive plans that scan every element and filter all the conditions at the top,
+    /// to check the results of the optimized plans when debugging.
+    pub fn set_disable_pushdown(&mut self, disabled: bool) {
+        self.disable_pushdown = disabled;
+    }
+
+    /// estimated fraction of the numeric values of the property `key` within `range`.
+    /// None without statistics for the key.
+    pub fn estimate_selectivity(&self, key: &str, range: NumericRange) -> Option<f64> {
+        let histogram = self.statistics.as_ref()?.histogram(key)?;
+        Some(histogram.estimate_selectivity(range.0, range.1))
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn build_select_query(
         &mut self,
         items: &Vec<Expr>,
+        aliases: &[Option<String>],
         graph_pattern: &GraphPattern,
         condition: &Option<Expr>,
-    ) -> Operator {
+        group_by: &[Expr],
+        order_by: &[OrderByExpr],
+        sample: &Option<u64>,
+        limit: &Option<u64>,
+        offset: &Option<u64>,
+    ) -> Result<Operator, ExecutionError> {
+        let mut exists = vec![];
+        let condition = &match condition {
+            Some(condition) => Self::split_exists(condition, &mut exists),
+            None => None,
+        };
+        // SAMPLE bounds the rows read from the graph pattern and WHERE filters the sampled
+        // rows, so nothing of the condition is pushed down into a sampled pattern
+        let pushed = match sample {
+            Some(_) => &None,
+            None => condition,
+        };
         self.scope
-            .parse_select_query(items, graph_pattern, condition);
-
-        let mut elements = HashSet::new();
-        // paths
-        let mut path_ops = vec![];
-        for (src, edge, dst) in &self.scope.paths.clone() {
-            let src_pattern = self.scope.vertices.get(src).unwrap().clone();
-            if !elements.contains(&src_pattern.name) {
-                path_ops.push(self.build_vertex_pattern(&src_pattern));
-                elements.insert(src_pattern.name.to_owned());
-            }
-            let edge_pattern = self.scope.edges.get(edge).unwrap().clone();
-            if !elements.contains(&edge_pattern.name) {
-                path_ops.push(self.build_edge_pattern(&edge_pattern));
-                elements.insert(edge_pattern.name.to_owned());
-            }
-            let mut dst_pattern = self.scope.vertices.get(dst).unwrap().clone();
-            match &dst_pattern.id[..] {
-                [] => {
-                    dst_pattern.id = vec![Comparator::Eq(Expr::CompoundIdentifier(vec![
-                        edge_pattern.name,
-                        "dst".to_string(),
-                    ]))]
-                }
-                _ => dst_pattern.predicates.push(Expr::BinaryOp {
-                    op: BinaryOperator::Eq,
-                    left: Box::new(Expr::IdExpr(dst_pattern.name.to_owned())),
-                    right: Box::new(Expr::CompoundIdentifier(vec![
-                        edge_pattern.name,
-                        "dst".to_string(),
-                    ])),
-                }),
-            }
-            if !elements.contains(&dst_pattern.name) {
-                path_ops.push(self.build_vertex_pattern(&dst_pattern));
-                elements.insert(dst_pattern.name.to_owned());
-            }
-        }
-        let mut op = match &path_ops[..] {
-            [] => panic!("invalid path specification"),
-            [op] => op.clone(),
-            ops => Operator::SimplePathJoin {
-                operators: ops.to_vec(),
-            },
+            .parse_select_query(items, graph_pattern, pushed)?;
+        let (mut op, elements) = match self.disable_pushdown {
+            true => self.build_naive_pattern(graph_pattern),
+            false => self.build_graph_pattern(graph_pattern, pushed)?,
         };
+        if let Some(n) = sample {
+            op = Operator::Sample {
+                source: Box::new(op),
+                n: *n,
+            }
+        }
         if let Some(expr) = condition {
             op = Operator::PredicateFilter {
                 source: Box::new(op),
                 predicates: vec![expr.clone()],
             }
         }
+        for (pattern, anti) in exists {
+            op = Operator::SemiJoin {
+                source: Box::new(op),
+                pattern: Box::new(self.build_exists_pattern(pattern, &elements)?),
+                anti,
+            }
+        }
+        let mut aggregates = vec![];
+        for expr in items
+            .iter()
+            .chain(order_by.iter().map(|order_by| &order_by.expr))
+        {
+            Self::collect_aggregates(expr, &mut aggregates);
+        }
+        if !group_by.is_empty() || !aggregates.is_empty() {
+            for item in items
+                .iter()
+                .chain(order_by.iter().map(|order_by| &order_by.expr))
+            {
+                Self::check_grouped(item, group_by)?;
+            }
+            op = Operator::Aggregate {
+                source: Box::new(op),
+                group_keys: group_by.to_vec(),
+                aggregates,
+            }
+        }
+        if !order_by.is_empty() {
+            op = Operator::Sort {
+                source: Box::new(op),
+                order_by: order_by.to_vec(),
+            }
+        }
+        if let Some(limit) = limit {
+            op = Operator::Limit {
+                source: Box::new(op),
+                limit: *limit,
+                offset: offset.unwrap_or(0),
+            }
+        }
+        let (items, aliases) = Self::expand_wildcards(items, aliases, &elements);
         op = Operator::Projection {
             source: Box::new(op),
-            items: items.clone(),
+            items,
+            aliases,
         };
-        op
+        Ok(op)
     }
 
-    pub fn build_vertex_pattern(&mut self, vertex: &VertexPattern) -> Operator {
-        // default FullScan all vertices.
-        let mut op;
+    /// the joined operators matching `graph_pattern`, and the bound element names in pattern
+    /// order.
+    fn build_graph_pattern(
+        &mut self,
+        graph_pattern: &GraphPattern,
+        condition: &Option<Expr>,
+    ) -> Result<(Operator, Vec<String>), ExecutionError> {
+        // bound element names, in binding order
+        let mut elements: Vec<String> = vec![];
+        // (operator, bound element, whether the operator depends on elements bound before it)
+        let mut path_ops = vec![];
+        for (src, edge, dst) in &self.order_paths() {
+            let edge_pattern = self.scope.edges.get(edge).unwrap().clone();
+            // start from the endpoint bound already, else from the more selective one. a
+            // variable-length edge is only followed from its src
+            let from_dst = edge_pattern.num == (1, 1)
+                && !elements.contains(src)
+                && (elements.contains(dst) || self.starts_from_dst(graph_pattern, src, dst));
+            let (near, far) = match from_dst {
+                true => (dst, src),
+                false => (src, dst),
+            };
+            if !elements.contains(near) {
+                let pattern = self.scope.vertices.get(near).unwrap().clone();
+                path_ops.push((self.build_vertex_pattern(&pattern), false));
+                elements.push(near.to_owned());
+            }
+            if !elements.contains(&edge_pattern.name) {
+                let far_bound = elements.contains(far);
+                path_ops.push((
+                    self.build_edge_pattern(&edge_pattern, from_dst, far_bound),
+                    true,
+                ));
+                elements.push(edge_pattern.name.to_owned());
+            }
+            if !elements.contains(far) {
+                let mut far_pattern = self.scope.vertices.get(far).unwrap().clone();
+                let far_id = Self::far_end(&edge_pattern, from_dst);
+                match &far_pattern.id[..] {
+                    [] => far_pattern.id = vec![Comparator::Eq(far_id)],
+                    _ => far_pattern.predicates.push(Expr::BinaryOp {
+                        op: BinaryOperator::Eq,
+                        left: Box::new(Expr::IdExpr(far.to_owned())),
+                        right: Box::new(far_id),
+                    }),
+                }
+                path_ops.push((self.build_vertex_pattern(&far_pattern), true));
+                elements.push(far.to_owned());
+            }
+        }
+        // vertices out of any path
+        for vertex in &graph_pattern.vertices {
+            if let Expr::Identifier(name) = vertex {
+                if !elements.contains(name) {
+                    let pattern = self.scope.vertices.get(name).unwrap().clone();
+                    path_ops.push((self.build_vertex_pattern(&pattern), false));
+                    elements.push(name.to_owned());
+                }
+            }
+        }
+        if path_ops.is_empty() {
+            return Err(ExecutionError::new(
+                "the graph pattern has no elements".to_owned(),
+            ));
+        }
+        let (path_ops, join_order) = Self::order_components(path_ops, elements);
+        Ok((
+            Self::build_joins(path_ops, &join_order, condition),
+            self.pattern_order(graph_pattern),
+        ))
+    }
+
+    /// the paths of the pattern in join order, greedily: first the path with the most
+    /// selective vertex, then the paths from the vertices bound before, so that e.g. the
+    /// lookup of `c` drives `(a)-[e]->(b)-[e2]->(c) WHERE c.id = 'v1'`. independent paths
+    /// are ordered by `order_components` later. ties keep the pattern order.
+    fn order_paths(&mut self) -> Vec<(String, String, String)> {
+        let mut ranks = HashMap::new();
+        for (name, pattern) in self.scope.vertices.clone() {
+            ranks.insert(
+                name,
+                Self::driving_rank(&self.build_vertex_pattern(&pattern)),
+            );
+        }
+        let mut remaining = self.scope.paths.clone();
+        let mut bound: HashSet<String> = HashSet::new();
+        let mut ordered = vec![];
+        while !remaining.is_empty() {
+            // a bound vertex costs nothing, the others their rank
+            let cost = |name: &String| match bound.contains(name) {
+                true => 0,
+                false => ranks[name] + 1,
+            };
+            let i = (0..remaining.len())
+                .min_by_key(|i| {
+                    let (src, edge, dst) = &remaining[*i];
+                    // a variable-length edge is only followed from its src
+                    match self.scope.edges[edge].num {
+                        (1, 1) => cost(src).min(cost(dst)),
+                        _ => cost(src),
+                    }
+                })
+                .unwrap();
+            let (src, edge, dst) = remaining.remove(i);
+            bound.insert(src.to_owned());
+            bound.insert(dst.to_owned());
+            ordered.push((src, edge, dst));
+        }
+        ordered
+    }
+
+    /// the elements of the pattern, the path elements from src to dst.
+    fn pattern_order(&self, graph_pattern: &GraphPattern) -> Vec<String> {
+        let mut elements: Vec<String> = vec![];
+        for (src, edge, dst) in &self.scope.paths {
+            for name in &[src, edge, dst] {
+                if !elements.contains(name) {
+                    elements.push(name.to_string());
+                }
+            }
+        }
+        for vertex in &graph_pattern.vertices {
+            if let Expr::Identifier(name) = vertex {
+                if !elements.contains(name) {
+                    elements.push(name.to_owned());
+                }
+            }
+        }
+        elements
+    }
+
+    /// whether an edge between the unbound vertices `src` and `dst` is better read from
+    /// the in edges of `dst`: it is read more selectively, or as selectively but written
+    /// first, e.g. `b` of `(b)<-[e]-(a)`.
+    fn starts_from_dst(&mut self, graph_pattern: &GraphPattern, src: &str, dst: &str) -> bool {
+        let mut rank = |name: &str| {
+            let pattern = self.scope.vertices.get(name).unwrap().clone();
+            Self::driving_rank(&self.build_vertex_pattern(&pattern))
+        };
+        let (src_rank, dst_rank) = (rank(src), rank(dst));
+        dst_rank < src_rank
+            || (dst_rank == src_rank && Self::written_before(graph_pattern, dst, src))
+    }
+
+    /// whether the vertex `a` appears before `b` in the pattern.
+    fn written_before(graph_pattern: &GraphPattern, a: &str, b: &str) -> bool {
+        let position = |name: &str| {
+            graph_pattern
+                .vertices
+                .iter()
+                .position(|vertex| *vertex == Expr::Identifier(name.to_owned()))
+        };
+        position(a) < position(b)
+    }
+
+    /// id of the endpoint of `edge` reached by following it from src, or from dst if
+    /// `from_dst`. an undirected edge may point either way.
+    fn far_end(edge: &EdgePattern, from_dst: bool) -> Expr {
+        let (near, far_end) = match from_dst {
+            true => (&edge.dst_name, "src"),
+            false => (&edge.src_name, "dst"),
+        };
+        match edge.undirected {
+            true => Expr::Function {
+                func_name: "other_end".to_string(),
+                arguments: vec![
+                    Expr::Identifier(edge.name.to_owned()),
+                    Expr::Identifier(near.to_owned()),
+                ],
+            },
+            false => Expr::CompoundIdentifier(vec![edge.name.to_owned(), far_end.to_string()]),
+        }
+    }
+
+    /// move the `EXISTS(pattern)` and `NOT EXISTS(pattern)` conjuncts of `condition` to
+    /// `exists`, with whether they are negated. returns the remaining conjuncts.
+    fn split_exists<'a>(
+        condition: &'a Expr,
+        exists: &mut Vec<(&'a GraphPattern, bool)>,
+    ) -> Option<Expr> {
+        let mut conjuncts = vec![];
+        Self::collect_conjuncts(condition, &mut conjuncts);
+        let mut remaining: Option<Expr> = None;
+        for conjunct in conjuncts {
+            let (expr, anti) = match conjunct {
+                Expr::UnaryOp {
+                    op: UnaryOperator::Not,
+                    expr,
+                } => (expr.as_ref(), true),
+                conjunct => (conjunct, false),
+            };
+            match expr {
+                Expr::Exists(pattern) => exists.push((pattern, anti)),
+                _ => {
+                    remaining = Some(match remaining {
+                        Some(left) => Expr::BinaryOp {
+                            op: BinaryOperator::And,
+                            left: Box::new(left),
+                            right: Box::new(conjunct.clone()),
+                        },
+                        None => conjunct.clone(),
+                    })
+                }
+            }
+        }
+        remaining
+    }
+
+    /// the operators matching the pattern of an EXISTS for a row binding `bound`. the
+    /// vertices bound by the row are looked up by their id instead of being scanned.
+    fn build_exists_pattern(
+        &self,
+        pattern: &GraphPattern,
+        bound: &[String],
+    ) -> Result<Operator, ExecutionError> {
+        let mut planner = Planner::new();
+        planner.scope.parse_select_query(&vec![], pattern, &None)?;
+        for (name, vertex) in planner.scope.vertices.iter_mut() {
+            if bound.contains(name) {
+                vertex.id = vec![Comparator::Eq(Expr::IdExpr(name.to_owned()))];
+            }
+        }
+        Ok(planner.build_graph_pattern(pattern, &None)?.0)
+    }
+
+    /// a full scan per element, joined by a filter on the edge endpoints and the inline
+    /// labels. only the label of a variable-length edge is kept, as it decides which edges
+    /// are followed.
+    fn build_naive_pattern(&self, graph_pattern: &GraphPattern) -> (Operator, Vec<String>) {
+        let endpoint = |edge: &str, end: &str, vertex: &str| Expr::BinaryOp {
+            op: BinaryOperator::Eq,
+            left: Box::new(Expr::CompoundIdentifier(vec![
+                edge.to_owned(),
+                end.to_owned(),
+            ])),
+            right: Box::new(Expr::IdExpr(vertex.to_owned())),
+        };
+        let both = |left: Expr, right: Expr| Expr::BinaryOp {
+            op: BinaryOperator::And,
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+        let vertex_scan = |name: &str| Operator::VertexFullScan {
+            element_name: name.to_owned(),
+        };
+        let mut elements: Vec<String> = vec![];
+        let mut vertex_ops = vec![];
+        let mut edge_ops = vec![];
         let mut predicates = vec![];
-        // id range.
-        if vertex.id.is_empty() {
-            op = Operator::VertexFullScan {
-                element_name: vertex.name.to_string(),
+        for (src, edge, dst) in &self.scope.paths {
+            if !elements.contains(src) {
+                vertex_ops.push(vertex_scan(src));
+                elements.push(src.to_owned());
+            }
+            if !elements.contains(edge) {
+                let pattern = self.scope.edges.get(edge).unwrap();
+                edge_ops.push(match pattern.num {
+                    (1, 1) if pattern.undirected => {
+                        predicates.push(Expr::BinaryOp {
+                            op: BinaryOperator::Or,
+                            left: Box::new(both(
+                                endpoint(edge, "src", src),
+                                endpoint(edge, "dst", dst),
+                            )),
+                            right: Box::new(both(
+                                endpoint(edge, "src", dst),
+                                endpoint(edge, "dst", src),
+                            )),
+                        });
+                        Operator::OutEdgeSeqScan {
+                            element_name: edge.to_owned(),
+                            edge_label: None,
+                            src: None,
+                        }
+                    }
+                    (1, 1) => {
+                        predicates.push(endpoint(edge, "src", src));
+                        predicates.push(endpoint(edge, "dst", dst));
+                        Operator::OutEdgeSeqScan {
+                            element_name: edge.to_owned(),
+                            edge_label: None,
+                            src: None,
+                        }
+                    }
+                    hops => Operator::OutEdgeExpand {
+                        element_name: edge.to_owned(),
+                        edge_label: pattern
+                            .label
+                            .as_ref()
+                            .map(|label| Expr::Value(Value::String(label.to_owned()))),
+                        src: Expr::Identifier(src.to_owned()),
+                        hops,
+                    },
+                });
+                if pattern.num != (1, 1) {
+                    predicates.push(endpoint(edge, "dst", dst));
+                }
+                elements.push(edge.to_owned());
+            }
+            if !elements.contains(dst) {
+                vertex_ops.push(vertex_scan(dst));
+                elements.push(dst.to_owned());
+            }
+        }
+        for vertex in &graph_pattern.vertices {
+            if let Expr::Identifier(name) = vertex {
+                if !elements.contains(name) {
+                    vertex_ops.push(vertex_scan(name));
+                    elements.push(name.to_owned());
+                }
+            }
+        }
+        // inline labels aren't part of the condition
+        for (name, label) in &graph_pattern.labels {
+            predicates.push(Expr::BinaryOp {
+                op: BinaryOperator::Eq,
+                left: Box::new(Expr::LabelExpr(name.to_owned())),
+                right: Box::new(Expr::Value(Value::String(label.to_owned()))),
+            });
+        }
+        // the variable-length edges need their src bound
+        vertex_ops.extend(edge_ops);
+        let mut op = Operator::SimplePathJoin {
+            operators: vertex_ops,
+        };
+        if !predicates.is_empty() {
+            op = Operator::PredicateFilter {
+                source: Box::new(op),
+                predicates,
+            }
+        }
+        (op, elements)
+    }
+
+    /// a select item of an aggregating query may only refer to elements through the
+    /// GROUP BY keys and aggregates.
+    fn check_grouped(expr: &Expr, group_by: &[Expr]) -> Result<(), ExecutionError> {
+        if group_by.contains(expr) {
+            return Ok(());
+        }
+        match expr {
+            Expr::Function { func_name, .. } if is_aggregate(func_name) => Ok(()),
+            Expr::Value(_) => Ok(()),
+            Expr::Function { arguments, .. } => arguments
+                .iter()
+                .try_for_each(|arg| Self::check_grouped(arg, group_by)),
+            Expr::UnaryOp { expr, .. } | Expr::IsNull { expr, .. } | Expr::Nested(expr) => {
+                Self::check_grouped(expr, group_by)
+            }
+            Expr::BinaryOp { left, right, .. } => {
+                Self::check_grouped(left, group_by)?;
+                Self::check_grouped(right, group_by)
+            }
+            Expr::InList { expr, list } => {
+                Self::check_grouped(expr, group_by)?;
+                list.iter()
+                    .try_for_each(|item| Self::check_grouped(item, group_by))
+            }
+            _ => Err(ExecutionError::new(format!(
+                "{} must appear in the GROUP BY clause or be used in an aggregate function",
+                column_name(expr)
+            ))),
+        }
+    }
+
+    /// reorder the independent components of the pattern, each an independent operator
+    /// followed by the operators depending on it, so that the most selective one drives
+    /// the join. `elements` are the elements bound by `path_ops`, reordered alike.
+    fn order_components(
+        path_ops: Vec<(Operator, bool)>,
+        elements: Vec<String>,
+    ) -> (Vec<(Operator, bool)>, Vec<String>) {
+        let mut components: Vec<Vec<((Operator, bool), String)>> = vec![];
+        for (path_op, element) in path_ops.into_iter().zip(elements) {
+            if !path_op.1 || components.is_empty() {
+                components.push(vec![]);
+            }
+            components.last_mut().unwrap().push((path_op, element));
+        }
+        // stable, so that equally selective components keep the pattern order
+        components.sort_by_key(|component| Self::driving_rank(&(component[0].0).0));
+        components.into_iter().flatten().unzip()
+    }
+
+    /// how many vertices an operator driving a component is expected to read, by rank.
+    /// an id lookup reads one, a full scan every vertex.
+    fn driving_rank(op: &Operator) -> u8 {
+        match op {
+            Operator::VertexLookup { .. } => 0,
+            Operator::VertexPropertyIndexLookup { .. } => 1,
+            Operator::VertexPropertyIndexRangeScan { .. } => 2,
+            Operator::VertexIdRangeScan { .. } => 3,
+            Operator::PredicateFilter { source, .. } | Operator::Projection { source, .. } => {
+                Self::driving_rank(source)
+            }
+            _ => 4,
+        }
+    }
+
+    /// chain the operators of the pattern with nested-loop joins. an independent operator
+    /// that is compared for equality with the elements bound before it, e.g. `b` of
+    /// `FROM (a), (b) WHERE a.age = b.age`, is hash joined instead.
+    fn build_joins(
+        path_ops: Vec<(Operator, bool)>,
+        elements: &[String],
+        condition: &Option<Expr>,
+    ) -> Operator {
+        let mut conjuncts = vec![];
+        if let Some(condition) = condition {
+            Self::collect_conjuncts(condition, &mut conjuncts);
+        }
+        let mut joined: Vec<Operator> = vec![];
+        for (i, (op, dependent)) in path_ops.into_iter().enumerate() {
+            let equi_keys = match dependent || joined.is_empty() {
+                true => None,
+                false => Self::find_equi_keys(&conjuncts, &elements[..i], &elements[i]),
             };
-        } else if vertex.id.len() == 1 {
-            match vertex.id.get(0).unwrap() {
+            match equi_keys {
+                Some((left_key, right_key)) => {
+                    let left = Self::chain(joined);
+                    joined = vec![Operator::HashJoin {
+                        left: Box::new(left),
+                        right: Box::new(op),
+                        left_key,
+                        right_key,
+                    }];
+                }
+                None => joined.push(op),
+            }
+        }
+        Self::chain(joined)
+    }
+
+    fn chain(mut ops: Vec<Operator>) -> Operator {
+        match ops.len() {
+            1 => ops.remove(0),
+            _ => Operator::SimplePathJoin { operators: ops },
+        }
+    }
+
+    fn collect_conjuncts<'a>(expr: &'a Expr, conjuncts: &mut Vec<&'a Expr>) {
+        match expr {
+            Expr::BinaryOp {
+                op: BinaryOperator::And,
+                left,
+                right,
+            } => {
+                Self::collect_conjuncts(left, conjuncts);
+                Self::collect_conjuncts(right, conjuncts);
+            }
+            Expr::Nested(expr) => Self::collect_conjuncts(expr, conjuncts),
+            expr => conjuncts.push(expr),
+        }
+    }
+
+    /// (key over `bound`, key over `element`) of the first conjunct `x = y` such that one
+    /// side only refers to elements in `bound` and the other only to `element`.
+    fn find_equi_keys(
+        conjuncts: &[&Expr],
+        bound: &[String],
+        element: &str,
+    ) -> Option<(Expr, Expr)> {
+        for conjunct in conjuncts {
+            if let Expr::BinaryOp {
+                op: BinaryOperator::Eq,
+                left,
+                right,
+            } = conjunct
+            {
+                let (mut left_elements, mut right_elements) = (vec![], vec![]);
+                Self::collect_elements(left, &mut left_elements);
+                Self::collect_elements(right, &mut right_elements);
+                let binds_only = |names: &[String], allowed: &[String]| {
+                    !names.is_empty() && names.iter().all(|name| allowed.contains(name))
+                };
+                let element = [element.to_owned()];
+                if binds_only(&left_elements, bound) && binds_only(&right_elements, &element) {
+                    return Some((*left.clone(), *right.clone()));
+                }
+                if binds_only(&right_elements, bound) && binds_only(&left_elements, &element) {
+                    return Some((*right.clone(), *left.clone()));
+                }
+            }
+        }
+        None
+    }
+
+    fn collect_elements(expr: &Expr, elements: &mut Vec<String>) {
+        match expr {
+            Expr::Identifier(name) | Expr::LabelExpr(name) | Expr::IdExpr(name) => {
+                elements.push(name.to_owned())
+            }
+            Expr::CompoundIdentifier(idents) | Expr::CompoundWildcard(idents) => {
+                elements.push(idents[0].to_owned())
+            }
+            Expr::Function { arguments, .. } => {
+                for arg in arguments {
+                    Self::collect_elements(arg, elements);
+                }
+            }
+            Expr::UnaryOp { expr, .. } | Expr::IsNull { expr, .. } | Expr::Nested(expr) => {
+                Self::collect_elements(expr, elements)
+            }
+            Expr::BinaryOp { left, right, .. } => {
+                Self::collect_elements(left, elements);
+                Self::collect_elements(right, elements);
+            }
+            Expr::InList { expr, list } => {
+                Self::collect_elements(expr, elements);
+                for item in list {
+                    Self::collect_elements(item, elements);
+                }
+            }
+            Expr::Value(_) | Expr::Wildcard | Expr::Exists(_) => {}
+        }
+    }
+
+    /// aggregate function calls in `expr`, without duplicates.
+    fn collect_aggregates(expr: &Expr, aggregates: &mut Vec<Expr>) {
+        match expr {
+            Expr::Function { func_name, .. } if is_aggregate(func_name) => {
+                if aggregates.contains(expr) {
+                    return;
+                }
+                aggregates.push(expr.clone());
+            }
+            Expr::Function { arguments, .. } => {
+                for arg in arguments {
+                    Self::collect_aggregates(arg, aggregates);
+                }
+            }
+            Expr::UnaryOp { expr, .. } | Expr::IsNull { expr, .. } | Expr::Nested(expr) => {
+                Self::collect_aggregates(expr, aggregates)
+            }
+            Expr::BinaryOp { left, right, .. } => {
+                Self::collect_aggregates(left, aggregates);
+                Self::collect_aggregates(right, aggregates);
+            }
+            Expr::InList { expr, list } => {
+                Self::collect_aggregates(expr, aggregates);
+                for item in list {
+                    Self::collect_aggregates(item, aggregates);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `*` expands to the id and label of every bound element but the anonymous ones, `a.*`
+    /// to those of `a`.
+    /// returns the expanded items with their aliases, missing aliases are None.
+    fn expand_wildcards(
+        items: &[Expr],
+        aliases: &[Option<String>],
+        elements: &[String],
+    ) -> (Vec<Expr>, Vec<Option<String>>) {
+        let mut expanded = vec![];
+        for (i, item) in items.iter().enumerate() {
+            match item {
+                Expr::Wildcard => {
+                    for name in elements.iter().filter(|n| !n.starts_with(ANONYMOUS_PREFIX)) {
+                        expanded.push((Expr::IdExpr(name.to_owned()), None));
+                        expanded.push((Expr::LabelExpr(name.to_owned()), None));
+                    }
+                }
+                Expr::CompoundWildcard(idents) => {
+                    expanded.push((Expr::IdExpr(idents[0].to_owned()), None));
+                    expanded.push((Expr::LabelExpr(idents[0].to_owned()), None));
+                }
+                item => expanded.push((item.clone(), aliases.get(i).cloned().flatten())),
+            }
+        }
+        expanded.into_iter().unzip()
+    }
+
+    /// lookup or range scan of the ids compared to `vertex.id`. of several bounds the
+    /// tightest is scanned, the predicates filter the vertices anyway.
+    fn build_id_scan(vertex: &VertexPattern) -> Operator {
+        let (mut low, mut high): (Option<&String>, Option<&String>) = (None, None);
+        for comp in &vertex.id {
+            match comp {
                 Comparator::Eq(value) => {
-                    op = Operator::VertexLookup {
+                    return Operator::VertexLookup {
                         element_name: vertex.name.to_string(),
                         vertex_id: value.clone(),
                     }
                 }
-                Comparator::Gte(value) => {
-                    op = Operator::VertexIdRangeScan {
-                        element_name: vertex.name.to_string(),
-                        range: (Some(value.clone()), None),
-                    }
+                Comparator::Gte(Expr::Value(Value::String(value))) => {
+                    low = low.max(Some(value));
                 }
-                Comparator::Lte(value) => {
-                    op = Operator::VertexIdRangeScan {
-                        element_name: vertex.name.to_string(),
-                        range: (None, Some(value.clone())),
-                    }
+                Comparator::Lte(Expr::Value(Value::String(value))) => {
+                    high = Some(high.map_or(value, |high| high.min(value)));
                 }
+                // other bounds can't be compared here, dropping them only scans more
+                Comparator::Gte(_) | Comparator::Lte(_) => {}
             }
-        } else {
-            let mut min_values: Vec<Expr> = vec![];
-            let mut max_values: Vec<Expr> = vec![];
-            for comp in &vertex.id {
-                match comp {
-                    Comparator::Eq(_) => panic!("invalid Equal operator"),
-                    Comparator::Gte(value) => min_values.push(value.clone()),
-                    Comparator::Lte(value) => max_values.push(value.clone()),
-                }
-            }
-            let min_value_expr = Expr::Function {
-                func_name: "min".to_string(),
-                arguments: min_values,
-            };
-            let max_value_expr = Expr::Function {
-                func_name: "max".to_string(),
-                arguments: max_values,
+        }
+        let bound = |value: Option<&String>| value.map(|v| Expr::Value(Value::String(v.clone())));
+        Operator::VertexIdRangeScan {
+            element_name: vertex.name.to_string(),
+            range: (bound(low), bound(high)),
+        }
+    }
+
+    pub fn build_vertex_pattern(&mut self, vertex: &VertexPattern) -> Operator {
+        // default FullScan all vertices.
+        let mut op;
+        let mut predicates = vec![];
+        // id range.
+        if vertex.id.is_empty() {
+            op = match Self::find_indexed_equality(vertex) {
+                Some((property_key, value)) => Operator::VertexPropertyIndexLookup {
+                    element_name: vertex.name.to_string(),
+                    property_key,
+                    value,
+                },
+                None => match self.build_index_range_scan(vertex) {
+                    Some(op) => op,
+                    None => Operator::VertexFullScan {
+                        element_name: vertex.name.to_string(),
+                    },
+                },
             };
-            op = Operator::VertexIdRangeScan {
-                element_name: vertex.name.to_string(),
-                range: (Some(min_value_expr), Some(max_value_expr)),
-            }
+        } else {
+            op = Self::build_id_scan(vertex);
         }
         // label
         if let Some(label) = &vertex.label {
@@ -161,32 +797,236 @@ impl Planner {
             op = Operator::Projection {
                 source: Box::new(op),
                 items: vertex.projections.clone(),
+                aliases: vec![],
             }
         }
         op
     }
 
-    pub fn build_edge_pattern(&mut self, edge: &EdgePattern) -> Operator {
-        let edge_label = match &edge.label {
-            Some(label) => Some(Expr::Value(Value::String(label.to_owned()))),
-            None => None,
+    /// (property key, value) of the first predicate `v.prop = value` of `vertex` whose
+    /// value doesn't refer to any element. properties are all indexed.
+    /// the predicate itself stays in the filter.
+    fn find_indexed_equality(vertex: &VertexPattern) -> Option<(String, Expr)> {
+        vertex
+            .predicates
+            .iter()
+            .find_map(|predicate| match predicate {
+                Expr::BinaryOp {
+                    op: BinaryOperator::Eq,
+                    left,
+                    right,
+                } => {
+                    let property_of = |expr: &Expr| Self::property_of(vertex, expr);
+                    let constant = |expr: &Expr| {
+                        let mut elements = vec![];
+                        Self::collect_elements(expr, &mut elements);
+                        elements.is_empty()
+                    };
+                    match (property_of(left), property_of(right)) {
+                        (Some(key), _) if constant(right) => Some((key, *right.clone())),
+                        (_, Some(key)) if constant(left) => Some((key, *left.clone())),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+    }
+
+    /// an index range scan of the first property of `vertex` with numeric bounds, if its
+    /// estimated selectivity is low enough. the predicates stay in the filter.
+    fn build_index_range_scan(&self, vertex: &VertexPattern) -> Option<Operator> {
+        let (property_key, range) = Self::find_indexed_range(vertex)?;
+        if self.estimate_selectivity(&property_key, range)? > INDEX_RANGE_MAX_SELECTIVITY {
+            return None;
+        }
+        Some(Operator::VertexPropertyIndexRangeScan {
+            element_name: vertex.name.to_string(),
+            property_key,
+            range,
+        })
+    }
+
+    /// (property key, range) of the first property of `vertex` compared with a number by
+    /// `>`, `>=`, `<` or `<=`, narrowed by all such predicates of the property. strict
+    /// bounds are kept inclusive.
+    fn find_indexed_range(vertex: &VertexPattern) -> Option<(String, NumericRange)> {
+        let mut ranges: Vec<(String, NumericRange)> = vec![];
+        for predicate in &vertex.predicates {
+            let (op, left, right) = match predicate {
+                Expr::BinaryOp { op, left, right } => (op, left, right),
+                _ => continue,
+            };
+            // (key, bound, whether the bound is the lower one)
+            let (key, bound, lower) = match (
+                Self::property_of(vertex, left),
+                Self::property_of(vertex, right),
+            ) {
+                (Some(key), _) => match (op, Self::literal_number(right)) {
+                    (BinaryOperator::Gt, Some(n)) | (BinaryOperator::Gte, Some(n)) => {
+                        (key, n, true)
+                    }
+                    (BinaryOperator::Lt, Some(n)) | (BinaryOperator::Lte, Some(n)) => {
+                        (key, n, false)
+                    }
+                    _ => continue,
+                },
+                (_, Some(key)) => match (op, Self::literal_number(left)) {
+                    (BinaryOperator::Gt, Some(n)) | (BinaryOperator::Gte, Some(n)) => {
+                        (key, n, false)
+                    }
+                    (BinaryOperator::Lt, Some(n)) | (BinaryOperator::Lte, Some(n)) => {
+                        (key, n, true)
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            let position = match ranges.iter().position(|(name, _)| *name == key) {
+                Some(position) => position,
+                None => {
+                    ranges.push((key, (None, None)));
+                    ranges.len() - 1
+                }
+            };
+            let (low, high) = &mut ranges[position].1;
+            if lower {
+                *low = Some(low.map_or(bound, |low| low.max(bound)));
+            } else {
+                *high = Some(high.map_or(bound, |high| high.min(bound)));
+            }
+        }
+        ranges.into_iter().next()
+    }
+
+    /// the property name of `vertex.prop`.
+    fn property_of(vertex: &VertexPattern, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::CompoundIdentifier(idents) if idents.len() == 2 && idents[0] == vertex.name => {
+                Some(idents[1].to_owned())
+            }
+            _ => None,
+        }
+    }
+
+    /// the value of a number literal such as `1.5` or `-2`.
+    fn literal_number(expr: &Expr) -> Option<f64> {
+        match expr {
+            Expr::Value(Value::Number(n)) => n.parse().ok(),
+            Expr::UnaryOp {
+                op: UnaryOperator::Minus,
+                expr,
+            } => Self::literal_number(expr).map(|n| -n),
+            Expr::Nested(expr) => Self::literal_number(expr),
+            _ => None,
+        }
+    }
+
+    /// the edges of `edge` from its bound src, or from its bound dst if `from_dst`. the
+    /// edges to the other endpoint are looked up if it is bound too, `far_bound`.
+    pub fn build_edge_pattern(
+        &mut self,
+        edge: &EdgePattern,
+        from_dst: bool,
+        far_bound: bool,
+    ) -> Operator {
+        let edge_label = edge
+            .label
+            .as_ref()
+            .map(|label| Expr::Value(Value::String(label.to_owned())));
+        let src = Expr::Identifier(edge.src_name.to_owned());
+        let dst = Expr::Identifier(edge.dst_name.to_owned());
+        let near = match from_dst {
+            true => dst.clone(),
+            false => src.clone(),
         };
-        let src = Some(Expr::Identifier(edge.src_name.to_owned()));
-        let mut op = Operator::OutEdgeSeqScan {
-            element_name: edge.name.to_string(),
-            edge_label,
-            src,
+        let mut predicates = edge.predicates.clone();
+        // every hop follows edges of the label, the predicates only filter the last edge
+        let mut op = match (edge.num, edge_label) {
+            ((1, 1), Some(edge_label)) if far_bound && !edge.undirected => match from_dst {
+                true => Operator::InEdgeLookup {
+                    element_name: edge.name.to_string(),
+                    edge_label,
+                    src,
+                    dst,
+                },
+                false => Operator::OutEdgeLookup {
+                    element_name: edge.name.to_string(),
+                    edge_label,
+                    src,
+                    dst,
+                },
+            },
+            // self loops are out edges too, so they are only read once
+            ((1, 1), edge_label) if edge.undirected => Operator::Union {
+                operators: vec![
+                    Operator::OutEdgeSeqScan {
+                        element_name: edge.name.to_string(),
+                        edge_label: edge_label.clone(),
+                        src: Some(near.clone()),
+                    },
+                    Operator::PredicateFilter {
+                        source: Box::new(Operator::InEdgeSeqScan {
+                            element_name: edge.name.to_string(),
+                            edge_label,
+                            dst: Some(near),
+                        }),
+                        predicates: vec![Expr::BinaryOp {
+                            op: BinaryOperator::NotEq,
+                            left: Box::new(Expr::CompoundIdentifier(vec![
+                                edge.name.to_owned(),
+                                "src".to_owned(),
+                            ])),
+                            right: Box::new(Expr::CompoundIdentifier(vec![
+                                edge.name.to_owned(),
+                                "dst".to_owned(),
+                            ])),
+                        }],
+                    },
+                ],
+            },
+            ((1, 1), edge_label) if from_dst => Operator::InEdgeSeqScan {
+                element_name: edge.name.to_string(),
+                edge_label,
+                dst: Some(near),
+            },
+            ((1, 1), edge_label) => Operator::OutEdgeSeqScan {
+                element_name: edge.name.to_string(),
+                edge_label,
+                src: Some(near),
+            },
+            (hops, edge_label) => Operator::OutEdgeExpand {
+                element_name: edge.name.to_string(),
+                edge_label,
+                src,
+                hops,
+            },
         };
-        if !edge.predicates.is_empty() {
+        let looked_up = matches!(
+            op,
+            Operator::OutEdgeLookup { .. } | Operator::InEdgeLookup { .. }
+        );
+        if far_bound && !looked_up {
+            let far = match from_dst {
+                true => &edge.src_name,
+                false => &edge.dst_name,
+            };
+            predicates.push(Expr::BinaryOp {
+                op: BinaryOperator::Eq,
+                left: Box::new(Expr::IdExpr(far.to_owned())),
+                right: Box::new(Self::far_end(edge, from_dst)),
+            });
+        }
+        if !predicates.is_empty() {
             op = Operator::PredicateFilter {
                 source: Box::new(op),
-                predicates: edge.predicates.clone(),
+                predicates,
             };
         }
         if !edge.projections.is_empty() {
             op = Operator::Projection {
                 source: Box::new(op),
                 items: edge.projections.clone(),
+                aliases: vec![],
             }
         }
         op
@@ -210,13 +1050,130 @@ mod test {
         match &stmts[0] {
             Statement::Select {
                 items,
+                aliases,
                 graph_pattern,
                 condition,
+                group_by,
+                order_by,
+                sample,
+                limit,
+                offset,
             } => {
-                let op = planner.build_select_query(items, graph_pattern, condition);
-                println!("{:?}", op);
+                let op = planner.build_select_query(
+                    items,
+                    aliases,
+                    graph_pattern,
+                    condition,
+                    group_by,
+                    order_by,
+                    sample,
+                    limit,
+                    offset,
+                );
+                println!("{:?}", op.unwrap());
             }
             _ => panic!("error"),
         }
     }
+
+    fn plan(sql: &str) -> String {
+        match &Parser::parse_sql(sql).unwrap()[0] {
+            Statement::Select {
+                items,
+                graph_pattern,
+                condition,
+                ..
+            } => format!(
+                "{:?}",
+                Planner::new()
+                    .build_select_query(
+                        items,
+                        &[],
+                        graph_pattern,
+                        condition,
+                        &[],
+                        &[],
+                        &None,
+                        &None,
+                        &None
+                    )
+                    .unwrap()
+            ),
+            stmt => panic!("not a select: {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_join_order() {
+        // the id-pinned vertex drives the join ahead of the full scan
+        let op = plan("SELECT a.id, b.id FROM (a), (b) WHERE b.id = 'v1'");
+        let lookup = op.find("VertexLookup { element_name: \"b\"").unwrap();
+        let scan = op.find("VertexFullScan { element_name: \"a\"").unwrap();
+        assert!(op.contains("SimplePathJoin") && lookup < scan, "{}", op);
+
+        // a path moves with the vertex it starts from
+        let op = plan("SELECT * FROM (a)-[e]->(b), (c) WHERE c.id = 'v1'");
+        let lookup = op.find("VertexLookup { element_name: \"c\"").unwrap();
+        let scan = op.find("VertexFullScan { element_name: \"a\"").unwrap();
+        let edges = op.find("OutEdgeSeqScan").unwrap();
+        assert!(lookup < scan && scan < edges, "{}", op);
+        // wildcards keep the pattern order
+        let columns = op.rfind("items:").unwrap();
+        assert!(
+            op[columns..].find("\"a\"") < op[columns..].find("\"c\""),
+            "{}",
+            op
+        );
+
+        // equally selective components keep the pattern order
+        let op = plan("SELECT a.id, b.id FROM (a), (b)");
+        assert!(op.find("\"a\"") < op.find("\"b\""), "{}", op);
+
+        // the path from the lookup first, then the paths from its end
+        let op = plan("SELECT * FROM (a)-[e]->(b)-[e2]->(c) WHERE c.id = 'v1'");
+        let lookup = op.find(r#"VertexLookup { element_name: "c", vertex_id: Value"#);
+        let e2 = op.find(r#"InEdgeSeqScan { element_name: "e2""#);
+        let e = op.find(r#"InEdgeSeqScan { element_name: "e""#);
+        assert!(lookup.is_some() && lookup < e2 && e2 < e, "{}", op);
+        assert!(!op.contains("VertexFullScan"), "{}", op);
+        let columns = op.rfind("items:").unwrap();
+        assert!(
+            op[columns..].find("\"a\"") < op[columns..].find("\"c\""),
+            "{}",
+            op
+        );
+    }
+
+    #[test]
+    fn test_edge_scan_direction() {
+        let in_scan =
+            r#"InEdgeSeqScan { element_name: "e", edge_label: None, dst: Some(Identifier("b")) }"#;
+        let out_scan =
+            r#"OutEdgeSeqScan { element_name: "e", edge_label: None, src: Some(Identifier("a")) }"#;
+        // from the vertex written first
+        let op = plan("SELECT a.id FROM (b) <- [e] - (a)");
+        assert!(op.contains(in_scan), "{}", op);
+        assert!(
+            op.contains(
+                r#"VertexLookup { element_name: "a", vertex_id: CompoundIdentifier(["e", "src"]) }"#
+            ),
+            "{}",
+            op
+        );
+        let op = plan("SELECT a.id FROM (a) - [e] -> (b)");
+        assert!(op.contains(out_scan), "{}", op);
+
+        // from the more selective vertex
+        let op = plan("SELECT a.id FROM (a) - [e] -> (b) WHERE b.id = 'v1'");
+        assert!(op.contains(in_scan), "{}", op);
+        let op = plan("SELECT a.id FROM (b) <- [e] - (a) WHERE a.id = 'v1'");
+        assert!(op.contains(out_scan), "{}", op);
+
+        // from the vertex bound by a previous edge
+        let op = plan("SELECT a.id FROM (c) - [e2] -> (b), (a) - [e] -> (b)");
+        assert!(op.contains(in_scan), "{}", op);
+        // a variable-length edge is followed from its src
+        let op = plan("SELECT a.id FROM (b) <- [e*1..2] - (a)");
+        assert!(op.contains("OutEdgeExpand"), "{}", op);
+    }
 }
@@ -1,8 +1,7 @@
-use std::borrow::BorrowMut;
 use std::collections::HashMap;
 
 use crate::execution::executor::ExecutionError;
-use crate::parser::ast::{Expr, GraphPattern, Statement, Value};
+use crate::parser::ast::{Expr, GraphPattern, Value};
 use crate::parser::operator::{BinaryOperator, UnaryOperator};
 
 #[derive(Debug)]
@@ -52,7 +51,16 @@ pub struct EdgePattern {
     pub dst_name: String,
     pub predicates: Vec<Expr>,
     pub projections: Vec<Expr>,
+    /// (min, max) number of hops from src to dst
     pub num: (u32, u32),
+    /// whether the edge also matches from dst to src
+    pub undirected: bool,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Scope {
@@ -66,35 +74,46 @@ impl Scope {
         }
     }
 
+    /// fails if the query refers to an element not in its pattern.
     pub fn parse_select_query(
         &mut self,
         items: &Vec<Expr>,
         graph_pattern: &GraphPattern,
         condition: &Option<Expr>,
-    ) {
-        self.parse_graph_pattern(graph_pattern);
-        match condition {
-            Some(cond) => self.parse_condition(&cond),
-            None => {}
+    ) -> Result<(), ExecutionError> {
+        self.parse_graph_pattern(graph_pattern)?;
+        if let Some(cond) = condition {
+            self.parse_condition(cond)?;
         }
         self.parse_select_items(items);
+        Ok(())
     }
 
-    fn parse_graph_pattern(&mut self, graph_pattern: &GraphPattern) {
+    fn parse_graph_pattern(&mut self, graph_pattern: &GraphPattern) -> Result<(), ExecutionError> {
+        for vertex in &graph_pattern.vertices {
+            match vertex {
+                Expr::Identifier(s) => {
+                    self.vertices
+                        .entry(s.to_owned())
+                        .or_insert_with(|| VertexPattern::new(s));
+                }
+                vertex => return Err(Self::not_identifier("vertex", vertex)),
+            }
+        }
         for triplet in &graph_pattern.triplets {
             let src_name = match triplet.src.as_ref() {
                 Expr::Identifier(s) => {
-                    self.vertices.insert(s.to_owned(), VertexPattern::new(&s));
+                    self.vertices.insert(s.to_owned(), VertexPattern::new(s));
                     s.to_owned()
                 }
-                _ => panic!("vertex should be identifier"),
+                vertex => return Err(Self::not_identifier("vertex", vertex)),
             };
             let dst_name = match triplet.dst.as_ref() {
                 Expr::Identifier(s) => {
-                    self.vertices.insert(s.to_owned(), VertexPattern::new(&s));
+                    self.vertices.insert(s.to_owned(), VertexPattern::new(s));
                     s.to_owned()
                 }
-                _ => panic!("vertex should be identifier"),
+                vertex => return Err(Self::not_identifier("vertex", vertex)),
             };
             match triplet.edge.as_ref() {
                 Expr::Identifier(edge_name) => {
@@ -107,7 +126,8 @@ impl Scope {
                             dst_name: dst_name.to_owned(),
                             predicates: vec![],
                             projections: vec![],
-                            num: (0, 0),
+                            num: triplet.hops,
+                            undirected: triplet.undirected,
                         },
                     );
                     self.paths.push((
@@ -116,33 +136,70 @@ impl Scope {
                         dst_name.to_owned(),
                     ))
                 }
-                _ => panic!("edge should be identifier"),
+                edge => return Err(Self::not_identifier("edge", edge)),
             };
         }
+        if graph_pattern.vertices.is_empty() && graph_pattern.triplets.is_empty() {
+            return Err(ExecutionError::new(
+                "the graph pattern has no elements".to_owned(),
+            ));
+        }
+        if let Some(name) = self
+            .edges
+            .keys()
+            .find(|name| self.vertices.contains_key(*name))
+        {
+            return Err(ExecutionError::new(format!(
+                "duplicate element name {}",
+                name
+            )));
+        }
+        for (name, label) in &graph_pattern.labels {
+            if let Some(vertex) = self.vertices.get_mut(name) {
+                vertex.label = Some(label.to_owned());
+            } else if let Some(edge) = self.edges.get_mut(name) {
+                edge.label = Some(label.to_owned());
+            }
+        }
+        Ok(())
     }
 
-    fn parse_condition(&mut self, condition: &Expr) {
+    fn not_identifier(kind: &str, expr: &Expr) -> ExecutionError {
+        ExecutionError::new(format!(
+            "{} should be an identifier, found {:?}",
+            kind, expr
+        ))
+    }
+
+    fn no_such_element(name: &str) -> ExecutionError {
+        ExecutionError::new(format!("no such element {}", name))
+    }
+
+    fn parse_condition(&mut self, condition: &Expr) -> Result<(), ExecutionError> {
         match condition {
             Expr::Function { .. } => self.push_conditions_into_scope(condition),
-            Expr::UnaryOp { .. } => self.push_conditions_into_scope(condition),
+            Expr::UnaryOp { .. } | Expr::IsNull { .. } | Expr::InList { .. } => {
+                self.push_conditions_into_scope(condition)
+            }
             Expr::BinaryOp { op, left, right } => match op {
-                op if op == &BinaryOperator::And || op == &BinaryOperator::Or => {
-                    self.parse_condition(left);
-                    self.parse_condition(right);
+                BinaryOperator::And => {
+                    self.parse_condition(left)?;
+                    self.parse_condition(right)
                 }
+                // either side may hold, so neither narrows its element on its own
+                BinaryOperator::Or => self.push_conditions_into_scope(condition),
                 _ => {
+                    let mut op = *op;
                     let mut expr_type = None;
                     let mut element_name = &"".to_string();
                     let mut value = "".to_string();
                     let mut comp = None;
                     match (left.as_ref(), right.as_ref()) {
-                        (Expr::LabelExpr(name), Expr::Value(Value::String(v))) => {
-                            element_name = name;
-                            value = v.to_owned();
-                            expr_type = Some("label")
-                        }
-                        (Expr::Value(Value::String(v)), Expr::LabelExpr(name)) => {
-                            element_name = name;
+                        (label, Expr::Value(Value::String(v)))
+                        | (Expr::Value(Value::String(v)), label)
+                            if Self::labelled_element(label).is_some() =>
+                        {
+                            element_name = Self::labelled_element(label).unwrap();
                             value = v.to_owned();
                             expr_type = Some("label")
                         }
@@ -151,9 +208,11 @@ impl Scope {
                             value = v.to_owned();
                             expr_type = Some("id")
                         }
+                        // 'id5' > a.id is a.id < 'id5'
                         (Expr::Value(Value::String(v)), Expr::IdExpr(name)) => {
                             element_name = name;
                             value = v.to_owned();
+                            op = op.flipped();
                             expr_type = Some("id")
                         }
                         _ => {}
@@ -186,44 +245,75 @@ impl Scope {
                     match expr_type {
                         Some("label") => {
                             if let Some(Comparator::Eq(Expr::Value(Value::String(value)))) = comp {
-                                if self.vertices.contains_key(element_name) {
-                                    self.vertices.get_mut(element_name).unwrap().label = Some(value)
-                                } else if self.edges.contains_key(element_name) {
-                                    self.edges.get_mut(element_name).unwrap().label = Some(value)
-                                } else {
-                                    panic!("no such element")
+                                let label =
+                                    if let Some(vertex) = self.vertices.get_mut(element_name) {
+                                        &mut vertex.label
+                                    } else if let Some(edge) = self.edges.get_mut(element_name) {
+                                        &mut edge.label
+                                    } else {
+                                        return Err(Self::no_such_element(element_name));
+                                    };
+                                match label {
+                                    // another label, e.g. an inline one, stays and this is
+                                    // checked as a predicate
+                                    Some(label) if *label != value => {
+                                        self.push_conditions_into_scope(condition)
+                                    }
+                                    _ => {
+                                        *label = Some(value);
+                                        Ok(())
+                                    }
                                 }
                             } else {
                                 self.push_conditions_into_scope(condition)
                             }
                         }
-                        Some("id") => {
-                            if self.vertices.contains_key(element_name) {
-                                self.vertices
-                                    .get_mut(element_name)
-                                    .unwrap()
-                                    .id
-                                    .push(comp.unwrap());
-                                if !neq.is_empty() {
-                                    neq.insert(0, Expr::IdExpr(element_name.to_owned()));
-                                    self.push_conditions_into_scope(&Expr::UnaryOp {
-                                        op: UnaryOperator::Not,
-                                        expr: Box::new(Expr::Function {
-                                            func_name: "in".to_string(),
-                                            arguments: neq,
-                                        }),
-                                    })
-                                }
-                            } else {
-                                panic!("no such element")
+                        // other comparisons and edge ids are element predicates
+                        Some("id")
+                            if comp.is_some() && self.vertices.contains_key(element_name) =>
+                        {
+                            self.vertices
+                                .get_mut(element_name)
+                                .unwrap()
+                                .id
+                                .push(comp.unwrap());
+                            if !neq.is_empty() {
+                                self.push_conditions_into_scope(&Expr::UnaryOp {
+                                    op: UnaryOperator::Not,
+                                    expr: Box::new(Expr::InList {
+                                        expr: Box::new(Expr::IdExpr(element_name.to_owned())),
+                                        list: neq,
+                                    }),
+                                })?
                             }
+                            Ok(())
                         }
                         _ => self.push_conditions_into_scope(condition),
                     }
                 }
             },
             Expr::Nested(expr) => self.parse_condition(expr),
-            _ => panic!("unknown where condition."),
+            // only planned as a conjunct, see `Planner::split_exists`
+            Expr::Exists(_) => Ok(()),
+            condition => Err(ExecutionError::new(format!(
+                "unknown where condition {:?}",
+                condition
+            ))),
+        }
+    }
+
+    /// the element of `a.label` or `label(a)`, whichever way the pattern points.
+    fn labelled_element(expr: &Expr) -> Option<&String> {
+        match expr {
+            Expr::LabelExpr(name) => Some(name),
+            Expr::Function {
+                func_name,
+                arguments,
+            } if func_name.eq_ignore_ascii_case("label") => match arguments.as_slice() {
+                [Expr::Identifier(name)] => Some(name),
+                _ => None,
+            },
+            _ => None,
         }
     }
 
@@ -240,9 +330,12 @@ impl Scope {
     ///     - a.prop = 1, b.label != 'person', func(e.prop2) > 3
     ///   - these are not element predicates  (expression contains more than one elements)
     ///     - a.prop1 = b.prop2, func(a.prop1, e.prop2) < 2
-    fn push_conditions_into_scope(&mut self, condition: &Expr) {
+    fn push_conditions_into_scope(&mut self, condition: &Expr) -> Result<(), ExecutionError> {
         let mut elements_in_func = vec![];
-        self.collect_elements_in_graph(condition, &mut elements_in_func);
+        self.collect_elements_in_graph(condition, &mut elements_in_func)?;
+        // e.g. `a.x = 1 OR a.y = 2` names `a` twice
+        elements_in_func.sort();
+        elements_in_func.dedup();
         if elements_in_func.len() == 1 {
             let element_name = &elements_in_func[0];
             if self.vertices.contains_key(element_name) {
@@ -258,81 +351,80 @@ impl Scope {
                     .predicates
                     .push(condition.clone())
             } else {
-                panic!("no such element")
+                return Err(Self::no_such_element(element_name));
             }
         } else {
             // TODO if elements_in_func is empty ?
             self.conditions.push(condition.clone())
         }
+        Ok(())
     }
 
-    fn collect_elements_in_graph<'a>(
+    fn collect_elements_in_graph(
         &self,
         expr: &Expr,
-        elements: &'a mut Vec<String>,
-    ) -> &'a mut Vec<String> {
+        elements: &mut Vec<String>,
+    ) -> Result<(), ExecutionError> {
         match expr {
             Expr::Identifier(_) => {}
             Expr::Value(_) => {}
-            Expr::CompoundIdentifier(idents) => {
-                if idents.len() != 2 {
-                    panic!("unknown identifiers")
-                }
-
-                let element_name = &idents[0];
-                if self.is_graph_element(element_name) {
-                    elements.push(element_name.to_owned());
-                } else {
-                    panic!("no such element")
+            // planned separately, see `Planner::split_exists`
+            Expr::Exists(_) => {}
+            Expr::CompoundIdentifier(idents) => match &idents[..] {
+                [element_name, _] => self.push_element(element_name, elements)?,
+                _ => {
+                    return Err(ExecutionError::new(format!(
+                        "can not resolve {}",
+                        idents.join(".")
+                    )))
                 }
-            }
+            },
             Expr::Wildcard => {}
-            Expr::CompoundWildcard(idents) => {
-                if idents.len() != 1 {
-                    panic!("unknown identifiers")
-                }
-
-                let element_name = &idents[0];
-                if self.is_graph_element(element_name) {
-                    elements.push(element_name.to_owned());
-                } else {
-                    panic!("no such element")
+            Expr::CompoundWildcard(idents) => match &idents[..] {
+                [element_name] => self.push_element(element_name, elements)?,
+                _ => {
+                    return Err(ExecutionError::new(format!(
+                        "can not resolve {}.*",
+                        idents.join(".")
+                    )))
                 }
-            }
-            Expr::Function {
-                func_name,
-                arguments,
-            } => {
+            },
+            Expr::Function { arguments, .. } => {
                 for arg in arguments {
-                    self.collect_elements_in_graph(arg, elements);
+                    self.collect_elements_in_graph(arg, elements)?;
                 }
             }
-            Expr::UnaryOp { op, expr } => {
-                self.collect_elements_in_graph(expr, elements);
-            }
-            Expr::BinaryOp { op, left, right } => {
-                self.collect_elements_in_graph(left, elements);
-                self.collect_elements_in_graph(right, elements);
+            Expr::UnaryOp { expr, .. } | Expr::IsNull { expr, .. } => {
+                self.collect_elements_in_graph(expr, elements)?;
             }
-            Expr::Nested(expr) => {
-                self.collect_elements_in_graph(expr, elements);
+            Expr::BinaryOp { left, right, .. } => {
+                self.collect_elements_in_graph(left, elements)?;
+                self.collect_elements_in_graph(right, elements)?;
             }
-            Expr::LabelExpr(element_name) => {
-                if self.is_graph_element(element_name) {
-                    elements.push(element_name.to_owned());
-                } else {
-                    panic!("no such element")
+            Expr::InList { expr, list } => {
+                self.collect_elements_in_graph(expr, elements)?;
+                for item in list {
+                    self.collect_elements_in_graph(item, elements)?;
                 }
             }
-            Expr::IdExpr(element_name) => {
-                if self.is_graph_element(element_name) {
-                    elements.push(element_name.to_owned());
-                } else {
-                    panic!("no such element")
-                }
+            Expr::Nested(expr) => {
+                self.collect_elements_in_graph(expr, elements)?;
+            }
+            Expr::LabelExpr(element_name) | Expr::IdExpr(element_name) => {
+                self.push_element(element_name, elements)?
             }
         };
-        elements
+        Ok(())
+    }
+
+    fn push_element(&self, name: &str, elements: &mut Vec<String>) -> Result<(), ExecutionError> {
+        match self.is_graph_element(name) {
+            true => {
+                elements.push(name.to_owned());
+                Ok(())
+            }
+            false => Err(Self::no_such_element(name)),
+        }
     }
 
     fn is_graph_element(&self, element_name: &str) -> bool {
@@ -342,6 +434,7 @@ impl Scope {
 
 #[cfg(test)]
 mod test {
+    use crate::parser::ast::Statement;
     use crate::parser::parser::Parser;
 
     use super::*;
@@ -358,11 +451,117 @@ mod test {
                 items,
                 graph_pattern,
                 condition,
+                ..
             } => {
-                scope.parse_select_query(items, graph_pattern, condition);
+                scope
+                    .parse_select_query(items, graph_pattern, condition)
+                    .unwrap();
                 println!("{:?}", scope);
             }
             _ => panic!("error"),
         }
     }
+
+    fn parse_scope(sql: &str) -> Scope {
+        let mut scope = Scope::new();
+        match &Parser::parse_sql(sql).unwrap()[0] {
+            Statement::Select {
+                items,
+                graph_pattern,
+                condition,
+                ..
+            } => scope
+                .parse_select_query(items, graph_pattern, condition)
+                .unwrap(),
+            _ => panic!("error"),
+        }
+        scope
+    }
+
+    #[test]
+    fn test_or_condition() {
+        let scope = parse_scope("SELECT a.id FROM (a)-[e]->(b) WHERE a.x = 1 OR b.y = 2");
+        assert_eq!(scope.conditions.len(), 1);
+        assert!(matches!(
+            scope.conditions[0],
+            Expr::BinaryOp {
+                op: BinaryOperator::Or,
+                ..
+            }
+        ));
+        assert!(scope.vertices["a"].predicates.is_empty());
+        assert!(scope.vertices["b"].predicates.is_empty());
+
+        // an OR of a single element is one predicate of it, and no id lookup
+        let scope = parse_scope(
+            "SELECT a.id FROM (a)-[e]->(b) WHERE (a.id = 'v1' OR a.id = 'v2') AND b.y = 2",
+        );
+        assert!(scope.conditions.is_empty());
+        assert!(scope.vertices["a"].id.is_empty());
+        assert_eq!(scope.vertices["a"].predicates.len(), 1);
+        assert_eq!(scope.vertices["b"].predicates.len(), 1);
+    }
+
+    #[test]
+    fn test_no_such_element() {
+        for sql in &[
+            "SELECT a.id FROM (a) WHERE z.prop = 1",
+            "SELECT a.id FROM (a) WHERE z.label = 'person'",
+            "SELECT a.id FROM (a) WHERE z.id = 'v1'",
+            "SELECT a.id FROM (a) WHERE a.prop = 1 AND (z.prop = 1 OR a.prop = 2)",
+        ] {
+            let mut scope = Scope::new();
+            let err = match &Parser::parse_sql(sql).unwrap()[0] {
+                Statement::Select {
+                    items,
+                    graph_pattern,
+                    condition,
+                    ..
+                } => scope
+                    .parse_select_query(items, graph_pattern, condition)
+                    .unwrap_err(),
+                _ => panic!("error"),
+            };
+            assert_eq!(err.to_string(), "no such element z", "{}", sql);
+        }
+        let mut scope = Scope::new();
+        match &Parser::parse_sql("SELECT b.id FROM (a)-[a]->(b)").unwrap()[0] {
+            Statement::Select { graph_pattern, .. } => assert_eq!(
+                scope
+                    .parse_graph_pattern(graph_pattern)
+                    .unwrap_err()
+                    .to_string(),
+                "duplicate element name a"
+            ),
+            _ => panic!("error"),
+        }
+        // not an id lookup, but no panic either
+        let scope = parse_scope("SELECT a.id FROM (a)-[e]->(b) WHERE a.id != 'v1' AND e.id = '0'");
+        assert!(scope.vertices["a"].id.is_empty());
+        assert_eq!(scope.edges["e"].predicates.len(), 1);
+    }
+
+    #[test]
+    fn test_inline_labels() {
+        let stmts =
+            Parser::parse_sql("SELECT b.id FROM (a:person)-[e:knows]->(b) WHERE b.label = 'dog'")
+                .unwrap();
+        let mut scope = Scope::new();
+        match &stmts[0] {
+            Statement::Select {
+                items,
+                graph_pattern,
+                condition,
+                ..
+            } => {
+                scope
+                    .parse_select_query(items, graph_pattern, condition)
+                    .unwrap();
+                assert_eq!(scope.vertices["a"].label, Some("person".to_owned()));
+                assert_eq!(scope.vertices["b"].label, Some("dog".to_owned()));
+                assert_eq!(scope.edges["e"].label, Some("knows".to_owned()));
+            }
+            _ => panic!("error"),
+        }
+    }
 }
@@ -0,0 +1,122 @@
+use crate::handlers::schema_handler::SchemaHandler;
+use crate::handlers::vertex_handler::VertexHandler;
+
+/// number of buckets of the histograms built for the planner.
+pub const HISTOGRAM_BUCKETS: usize = 32;
+
+/// statistics the planner reads to estimate the cost of the operators.
+pub trait Statistics {
+    /// histogram of the numeric values of the vertex property `property_key`, if any.
+    fn histogram(&self, property_key: &str) -> Option<Histogram>;
+}
+
+/// equi-depth histogram: every bucket holds about the same number of values, so dense
+/// ranges get narrow buckets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// sorted bucket boundaries, bucket `i` spans `bounds[i]..=bounds[i + 1]`.
+    bounds: Vec<f64>,
+    count: usize,
+}
+
+impl Histogram {
+    pub fn build(mut values: Vec<f64>, buckets: usize) -> Histogram {
+        values.retain(|value| !value.is_nan());
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let count = values.len();
+        let buckets = buckets.min(count);
+        let bounds = if buckets == 0 {
+            vec![]
+        } else {
+            (0..=buckets)
+                .map(|i| values[i * (count - 1) / buckets])
+                .collect()
+        };
+        Histogram { bounds, count }
+    }
+
+    /// number of values.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// estimated fraction of the values within `low..=high`. an open bound is unbounded.
+    pub fn estimate_selectivity(&self, low: Option<f64>, high: Option<f64>) -> f64 {
+        if self.bounds.is_empty() {
+            return 0.0;
+        }
+        let below_high = high.map_or(1.0, |high| self.fraction_below(high));
+        let below_low = low.map_or(0.0, |low| self.fraction_below(low));
+        (below_high - below_low).max(0.0)
+    }
+
+    /// fraction of the values below `value`, interpolated linearly within its bucket.
+    fn fraction_below(&self, value: f64) -> f64 {
+        let (first, last) = (self.bounds[0], self.bounds[self.bounds.len() - 1]);
+        if value < first {
+            return 0.0;
+        }
+        if value >= last {
+            return 1.0;
+        }
+        // first <= value < last, so bounds[i] <= value < bounds[i + 1]
+        let i = self.bounds.partition_point(|bound| *bound <= value) - 1;
+        let (start, end) = (self.bounds[i], self.bounds[i + 1]);
+        let buckets = (self.bounds.len() - 1) as f64;
+        (i as f64 + (value - start) / (end - start)) / buckets
+    }
+}
+
+/// statistics built on demand from the vertex property index.
+pub struct IndexStatistics {
+    schema_handler: SchemaHandler,
+    vertex_handler: VertexHandler,
+}
+
+impl IndexStatistics {
+    pub fn new(schema_handler: SchemaHandler, vertex_handler: VertexHandler) -> Self {
+        IndexStatistics {
+            schema_handler,
+            vertex_handler,
+        }
+    }
+}
+
+impl Statistics for IndexStatistics {
+    fn histogram(&self, property_key: &str) -> Option<Histogram> {
+        // no statistics if they can not be read, the planner then falls back to its rules
+        let key = self
+            .schema_handler
+            .get_property_key_by_name(property_key)
+            .ok()??;
+        self.vertex_handler
+            .property_histogram(key.id, HISTOGRAM_BUCKETS)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let histogram = Histogram::build((0..1000).map(|i| i as f64).collect(), 10);
+        assert_eq!(histogram.count(), 1000);
+        let estimate = histogram.estimate_selectivity(Some(100.0), Some(199.0));
+        assert!((estimate - 0.1).abs() < 0.01, "{}", estimate);
+        assert_eq!(histogram.estimate_selectivity(None, None), 1.0);
+        assert_eq!(histogram.estimate_selectivity(Some(2000.0), None), 0.0);
+        assert_eq!(histogram.estimate_selectivity(Some(10.0), Some(5.0)), 0.0);
+
+        // skewed values: most of them are 1
+        let mut values = vec![1.0; 900];
+        values.extend((0..100).map(|i| 100.0 + i as f64));
+        let histogram = Histogram::build(values, 10);
+        assert!(histogram.estimate_selectivity(Some(100.0), None) < 0.15);
+        assert!(histogram.estimate_selectivity(None, Some(1.0)) > 0.85);
+
+        let empty = Histogram::build(vec![], 10);
+        assert_eq!(empty.estimate_selectivity(None, Some(1.0)), 0.0);
+    }
+}
@@ -0,0 +1,273 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use sled::{IVec, Tree};
+
+use crate::datamodel::buffer::DecodeError;
+use crate::handlers::sled_engine::StorageError;
+
+/// number of entries a `ChunkedScan` reads from sled at a time.
+pub const SCAN_CHUNK_SIZE: usize = 256;
+
+/// cooperative cancellation of long scans. clones share the same state, so a token can be
+/// cancelled from another thread while a query runs. it also cancels itself once its
+/// deadline passes.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a token cancelled after `timeout`.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        CancellationToken {
+            cancelled: Default::default(),
+            deadline: Some(Instant::now() + timeout),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// a scan stopped by its cancellation token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "scan cancelled")
+    }
+}
+
+/// why a scan of elements ended early: it was cancelled, or an entry could not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanError {
+    Cancelled,
+    Storage(StorageError),
+}
+
+impl Display for ScanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::Cancelled => write!(f, "{}", Cancelled),
+            ScanError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<Cancelled> for ScanError {
+    fn from(_: Cancelled) -> Self {
+        ScanError::Cancelled
+    }
+}
+
+impl From<DecodeError> for ScanError {
+    fn from(e: DecodeError) -> Self {
+        ScanError::Storage(e.into())
+    }
+}
+
+/// lazy prefix scan of a tree that reads `chunk_size` entries at a time, each chunk from a
+/// new range after the last key read. only one chunk is held in memory, and the token is
+/// checked before reading the next one. a cancelled scan yields `Cancelled` once, then
+/// ends.
+pub struct ChunkedScan {
+    tree: Tree,
+    prefix: Vec<u8>,
+    /// first key to read, at least `prefix`.
+    start: Vec<u8>,
+    /// last key to read, if any.
+    end: Option<Vec<u8>>,
+    chunk_size: usize,
+    token: Option<CancellationToken>,
+    chunk: VecDeque<(IVec, IVec)>,
+    last_key: Option<IVec>,
+    done: bool,
+    chunks_read: usize,
+    /// incremented for each entry yielded
+    counter: Option<Rc<Cell<u64>>>,
+}
+
+impl ChunkedScan {
+    pub fn new(tree: Tree, prefix: &[u8], token: Option<CancellationToken>) -> Self {
+        Self::with_chunk_size(tree, prefix, token, SCAN_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(
+        tree: Tree,
+        prefix: &[u8],
+        token: Option<CancellationToken>,
+        chunk_size: usize,
+    ) -> Self {
+        ChunkedScan {
+            tree,
+            prefix: prefix.to_vec(),
+            start: prefix.to_vec(),
+            end: None,
+            chunk_size: chunk_size.max(1),
+            token,
+            chunk: VecDeque::new(),
+            last_key: None,
+            done: false,
+            chunks_read: 0,
+            counter: None,
+        }
+    }
+
+    /// only read the keys of the prefix within `start..=end`. an open bound is unbounded.
+    pub fn with_range(mut self, start: Option<&[u8]>, end: Option<&[u8]>) -> Self {
+        if let Some(start) = start {
+            self.start = self.start.max(start.to_vec());
+        }
+        self.end = end.map(<[u8]>::to_vec);
+        self
+    }
+
+    /// count the entries yielded in `counter`.
+    pub fn with_counter(mut self, counter: Rc<Cell<u64>>) -> Self {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// number of chunks read from sled so far.
+    pub fn chunks_read(&self) -> usize {
+        self.chunks_read
+    }
+
+    fn read_chunk(&mut self) {
+        let entries = match &self.last_key {
+            Some(last_key) => self
+                .tree
+                .range::<IVec, _>((Bound::Excluded(last_key.clone()), Bound::Unbounded)),
+            None => self.tree.range(self.start.clone()..),
+        };
+        for entry in entries.take(self.chunk_size) {
+            let (key, value) = entry.unwrap();
+            if !key.starts_with(&self.prefix)
+                || self
+                    .end
+                    .as_ref()
+                    .is_some_and(|end| key.as_ref() > end.as_slice())
+            {
+                self.done = true;
+                break;
+            }
+            self.chunk.push_back((key, value));
+        }
+        if self.chunk.len() < self.chunk_size {
+            self.done = true;
+        }
+        self.last_key = self.chunk.back().map(|(key, _)| key.clone());
+        self.chunks_read += 1;
+    }
+}
+
+impl Iterator for ChunkedScan {
+    type Item = Result<(IVec, IVec), Cancelled>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.chunk.is_empty() {
+            if self.done {
+                return None;
+            }
+            if self
+                .token
+                .as_ref()
+                .is_some_and(|token| token.is_cancelled())
+            {
+                self.done = true;
+                return Some(Err(Cancelled));
+            }
+            self.read_chunk();
+        }
+        let entry = self.chunk.pop_front()?;
+        if let Some(counter) = &self.counter {
+            counter.set(counter.get() + 1);
+        }
+        Some(Ok(entry))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::handlers::sled_engine::SledEngine;
+
+    use super::*;
+
+    fn tree_with_entries(n: u32) -> Tree {
+        let sled = SledEngine::new_tmp();
+        for i in 0..n {
+            sled.insert("tree", &[&[1u8][..], &i.to_be_bytes()].concat(), &[])
+                .unwrap();
+        }
+        // outside of the prefix
+        sled.insert("tree", &[0], &[]).unwrap();
+        sled.insert("tree", &[2], &[]).unwrap();
+        sled.open_tree("tree").unwrap()
+    }
+
+    #[test]
+    fn test_chunked_scan() {
+        let tree = tree_with_entries(1000);
+        let counter = Rc::new(Cell::new(0));
+        let mut scan = ChunkedScan::with_chunk_size(tree.clone(), &[1], None, 100)
+            .with_counter(counter.clone());
+        assert_eq!(scan.by_ref().take(150).count(), 150);
+        // only the chunks needed so far were read, and only the entries pulled are counted
+        assert_eq!(scan.chunks_read(), 2);
+        assert_eq!(counter.get(), 150);
+        assert_eq!(scan.count(), 850);
+
+        let scan = ChunkedScan::with_chunk_size(tree.clone(), &[3], None, 100);
+        assert_eq!(scan.count(), 0);
+
+        let key = |i: u32| [&[1u8][..], &i.to_be_bytes()].concat();
+        let scan = ChunkedScan::with_chunk_size(tree.clone(), &[1], None, 100)
+            .with_range(Some(&key(300)), Some(&key(549)));
+        assert_eq!(scan.count(), 250);
+        let scan = ChunkedScan::new(tree.clone(), &[1], None).with_range(None, Some(&key(9)));
+        assert_eq!(scan.count(), 10);
+        // a start before the prefix still starts at the prefix
+        let scan = ChunkedScan::new(tree, &[1], None).with_range(Some(&[0]), None);
+        assert_eq!(scan.count(), 1000);
+    }
+
+    #[test]
+    fn test_cancel_scan() {
+        let tree = tree_with_entries(1000);
+        let token = CancellationToken::new();
+        let mut scan = ChunkedScan::with_chunk_size(tree.clone(), &[1], Some(token.clone()), 100);
+        assert!(scan.by_ref().take(10).all(|entry| entry.is_ok()));
+        token.cancel();
+        // the chunk already read is drained first
+        let rest: Vec<_> = scan.collect();
+        assert_eq!(rest.len(), 91);
+        assert_eq!(rest.last(), Some(&Err(Cancelled)));
+
+        let expired = CancellationToken::with_timeout(Duration::from_secs(0));
+        let mut scan = ChunkedScan::new(tree, &[1], Some(expired));
+        assert_eq!(scan.next(), Some(Err(Cancelled)));
+        assert_eq!(scan.next(), None);
+    }
+}
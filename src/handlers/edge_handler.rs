@@ -1,20 +1,36 @@
 use std::rc::Rc;
 
-use crate::datamodel::base::EdgeDirection;
+use sled::Batch;
+
+use crate::datamodel::base::{EdgeDirection, EdgeId, EdgeLabelId, ElementType};
+use crate::datamodel::buffer::Buffer;
+use crate::datamodel::constants::PROPERTIES_ON_OUT_EDGE;
 use crate::datamodel::edge::Edge;
-use crate::datamodel::property::Properties;
+use crate::datamodel::property::{Properties, PropertyValue};
 use crate::datamodel::property_key::PropertyKey;
-use crate::handlers::sled_engine::SledEngine;
+use crate::handlers::sled_engine::{SledEngine, StorageError};
+use crate::handlers::write_observer::WriteObserver;
 
 static EDGE_TABLE_NAME: &str = "EDGE";
 
+#[derive(Clone)]
 pub struct EdgeHandler {
-    pub(crate) engine: Rc<Box<SledEngine>>,
+    pub(crate) engine: Rc<SledEngine>,
+    pub(crate) observer: Option<Rc<dyn WriteObserver>>,
+    /// store the properties of an edge on its out copy only. the in copy then holds
+    /// `PROPERTIES_ON_OUT_EDGE` and reads of it fetch the out copy. edges written either way
+    /// are read correctly.
+    pub(crate) single_copy_properties: bool,
 }
 
 impl EdgeHandler {
-    pub fn create_edge(&self, src_vertex_id: &str, dst_vertex_id: &str, label: u64) -> Edge {
-        let edge_id = self.generate_next_edge_id();
+    pub fn create_edge(
+        &self,
+        src_vertex_id: &str,
+        dst_vertex_id: &str,
+        label: EdgeLabelId,
+    ) -> Result<Edge, StorageError> {
+        let edge_id = self.generate_next_edge_id()?;
         let edge = Edge {
             src_vertex_id: src_vertex_id.to_owned(),
             dst_vertex_id: dst_vertex_id.to_owned(),
@@ -22,59 +38,195 @@ impl EdgeHandler {
             label,
             properties: Properties { data: Vec::new() },
         };
-        let (out_key, out_value) = edge.serialize(EdgeDirection::Out);
-        let (in_key, in_value) = edge.serialize(EdgeDirection::In);
-        self.engine.insert(EDGE_TABLE_NAME, &in_key, &in_value);
-        self.engine.insert(EDGE_TABLE_NAME, &out_key, &out_value);
-        edge
+        self.write_edge(&edge)?;
+        self.notify(None, Some(&edge));
+        Ok(edge)
     }
 
-    pub fn remove_edge(&self, edge: &Edge) {
-        let in_key = edge.generate_key(EdgeDirection::In);
-        let out_key = edge.generate_key(EdgeDirection::Out);
-        self.engine.remove(EDGE_TABLE_NAME, &in_key);
-        self.engine.remove(EDGE_TABLE_NAME, &out_key);
+    pub fn remove_edge(&self, edge: &Edge) -> Result<(), StorageError> {
+        let before = match self.observer {
+            Some(_) => self.get_edge(
+                &edge.src_vertex_id,
+                &edge.dst_vertex_id,
+                edge.label,
+                edge.edge_id,
+                EdgeDirection::Out,
+            )?,
+            None => None,
+        };
+        let mut batch = Batch::default();
+        batch.remove(edge.generate_key(EdgeDirection::In));
+        batch.remove(edge.generate_key(EdgeDirection::Out));
+        self.engine.apply_batch(EDGE_TABLE_NAME, batch)?;
+        if before.is_some() {
+            self.notify(before.as_ref(), None);
+        }
+        Ok(())
     }
 
-    pub fn add_property(&self, edge: &mut Edge, property_key: &PropertyKey, value: &str) {
-        let prop_id = self.generate_next_prop_id(edge.edge_id);
+    pub fn add_property(
+        &self,
+        edge: &mut Edge,
+        property_key: &PropertyKey,
+        value: &PropertyValue,
+    ) -> Result<(), StorageError> {
+        let before = self.observer.as_ref().map(|_| edge.clone());
+        if !edge.properties.prepare_add(property_key, value) {
+            return Ok(());
+        }
+        let prop_id = self.generate_next_prop_id(edge.edge_id)?;
         edge.properties
             .add_property(property_key.id, prop_id, value);
-        let (out_key, out_value) = edge.serialize(EdgeDirection::Out);
-        let (in_key, in_value) = edge.serialize(EdgeDirection::In);
-        self.engine.insert(EDGE_TABLE_NAME, &in_key, &in_value);
-        self.engine.insert(EDGE_TABLE_NAME, &out_key, &out_value);
+        self.write_edge(edge)?;
+        self.notify(before.as_ref(), Some(edge));
+        Ok(())
     }
 
-    pub fn remove_property(&self, edge: &mut Edge, property_key: &PropertyKey, prop_id: Vec<u64>) {
+    pub fn remove_property(
+        &self,
+        edge: &mut Edge,
+        property_key: &PropertyKey,
+        prop_id: Vec<u64>,
+    ) -> Result<(), StorageError> {
+        let before = self.observer.as_ref().map(|_| edge.clone());
         edge.properties.remove_property(property_key.id, prop_id);
-        let (out_key, out_value) = edge.serialize(EdgeDirection::Out);
-        let (in_key, in_value) = edge.serialize(EdgeDirection::In);
-        self.engine.insert(EDGE_TABLE_NAME, &in_key, &in_value);
-        self.engine.insert(EDGE_TABLE_NAME, &out_key, &out_value);
+        self.write_edge(edge)?;
+        self.notify(before.as_ref(), Some(edge));
+        Ok(())
     }
 
     pub fn get_edge(
         &self,
         src_id: &str,
         dst_id: &str,
-        label: u64,
-        edge_id: u64,
+        label: EdgeLabelId,
+        edge_id: EdgeId,
         direction: EdgeDirection,
-    ) -> Option<Edge> {
+    ) -> Result<Option<Edge>, StorageError> {
         let key = Edge::build_key(src_id, dst_id, label, edge_id, direction);
-        match self.engine.get(EDGE_TABLE_NAME, &key) {
-            Some(value) => Some(Edge::deserialize(&key, &value)),
-            None => None,
+        match self.engine.get(EDGE_TABLE_NAME, &key)? {
+            Some(value) => Ok(Some(
+                self.resolve_properties(Edge::deserialize(&key, &value)?)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// out edges of `src_id`, only those of `label` if given.
+    pub fn scan_out_edges(
+        &self,
+        src_id: &str,
+        label: Option<EdgeLabelId>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Edge, StorageError>>>, StorageError> {
+        let mut prefix = Buffer::new();
+        prefix.put_u8(ElementType::OutEdge as u8);
+        prefix.put_escaped_string(src_id);
+        if let Some(label) = label {
+            prefix.put_u64(label.0);
+        }
+        self.scan_edges(prefix.to_vec())
+    }
+
+    /// in edges of `dst_id`, only those of `label` if given.
+    pub fn scan_in_edges(
+        &self,
+        dst_id: &str,
+        label: Option<EdgeLabelId>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Edge, StorageError>>>, StorageError> {
+        let mut prefix = Buffer::new();
+        prefix.put_u8(ElementType::InEdge as u8);
+        prefix.put_escaped_string(dst_id);
+        if let Some(label) = label {
+            prefix.put_u64(label.0);
         }
+        self.scan_edges(prefix.to_vec())
     }
 
-    fn generate_next_edge_id(&self) -> u64 {
+    /// the edges of `label` from `src_id` to `dst_id`, read from their copies of `direction`.
+    /// a multi label may have several.
+    pub fn scan_edges_between(
+        &self,
+        src_id: &str,
+        dst_id: &str,
+        label: EdgeLabelId,
+        direction: EdgeDirection,
+    ) -> Result<Box<dyn Iterator<Item = Result<Edge, StorageError>>>, StorageError> {
+        // the keys of these edges only differ in their trailing edge id
+        let mut prefix = Edge::build_key(src_id, dst_id, label, EdgeId(0), direction);
+        prefix.truncate(prefix.len() - 8);
+        self.scan_edges(prefix)
+    }
+
+    /// out edges of all vertices, only those of `label` if given.
+    pub fn iter_all_out_edges(
+        &self,
+        label: Option<EdgeLabelId>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Edge, StorageError>>>, StorageError> {
+        let edges = self.scan_edges(vec![ElementType::OutEdge as u8])?;
+        Ok(match label {
+            Some(label) => Box::new(
+                edges.filter(move |edge| edge.as_ref().map_or(true, |edge| edge.label == label)),
+            ),
+            None => edges,
+        })
+    }
+
+    /// an entry that can't be read or deserialized ends the scan with its error.
+    fn scan_edges(
+        &self,
+        prefix: Vec<u8>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Edge, StorageError>>>, StorageError> {
+        let handler = self.clone();
+        Ok(Box::new(
+            self.engine
+                .open_tree(EDGE_TABLE_NAME)?
+                .scan_prefix(prefix)
+                .map(move |res| {
+                    let (key, value) = res?;
+                    handler.resolve_properties(Edge::deserialize(&key, &value)?)
+                }),
+        ))
+    }
+
+    /// write both copies of `edge` in one batch, so that they never diverge. see
+    /// `single_copy_properties`.
+    fn write_edge(&self, edge: &Edge) -> Result<(), StorageError> {
+        let (out_key, out_value) = edge.serialize(EdgeDirection::Out);
+        let (in_key, in_value) = edge.serialize(EdgeDirection::In);
+        let in_value = match self.single_copy_properties && !in_value.is_empty() {
+            true => PROPERTIES_ON_OUT_EDGE,
+            false => &in_value,
+        };
+        let mut batch = Batch::default();
+        batch.insert(in_key, in_value);
+        batch.insert(out_key, out_value);
+        self.engine.apply_batch(EDGE_TABLE_NAME, batch)
+    }
+
+    /// fetch the properties of an in copy holding `PROPERTIES_ON_OUT_EDGE` from its out copy.
+    fn resolve_properties(&self, mut edge: Edge) -> Result<Edge, StorageError> {
+        if edge.properties.data == PROPERTIES_ON_OUT_EDGE {
+            let out_key = edge.generate_key(EdgeDirection::Out);
+            let data = self.engine.get(EDGE_TABLE_NAME, &out_key)?;
+            edge.properties = Properties::decode(&data.unwrap_or_default())?;
+        }
+        Ok(edge)
+    }
+
+    fn notify(&self, before: Option<&Edge>, after: Option<&Edge>) {
+        if let Some(observer) = &self.observer {
+            observer.on_edge_write(before, after);
+        }
+    }
+
+    fn generate_next_edge_id(&self) -> Result<EdgeId, StorageError> {
         let auto_increment_key = "EDGE_AUTO_INCREMENT_ID";
-        self.engine.increment(EDGE_TABLE_NAME, &auto_increment_key)
+        Ok(EdgeId(
+            self.engine.increment(EDGE_TABLE_NAME, auto_increment_key)?,
+        ))
     }
 
-    fn generate_next_prop_id(&self, edge_id: u64) -> u64 {
+    fn generate_next_prop_id(&self, edge_id: EdgeId) -> Result<u64, StorageError> {
         let auto_increment_key = format!("EDGE_PROP_AUTO_INCREMENT_ID_{}", edge_id);
         self.engine.increment(EDGE_TABLE_NAME, &auto_increment_key)
     }
@@ -82,24 +234,33 @@ impl EdgeHandler {
 
 #[cfg(test)]
 mod test {
+    use std::env;
+    use std::fs;
+
     use super::*;
-    use crate::datamodel::base::Cardinality;
+    use crate::datamodel::base::{Cardinality, EdgeMultiplicity, PropertyKeyId};
+    use crate::handlers::schema_handler::SchemaHandler;
 
     #[test]
     fn test_edge_crud() {
-        let engine = Rc::new(Box::new(SledEngine::new_tmp()));
+        let engine = Rc::new(SledEngine::new_tmp());
 
-        let handler = EdgeHandler { engine };
+        let handler = EdgeHandler {
+            engine,
+            observer: None,
+            single_copy_properties: false,
+        };
 
         let key = PropertyKey {
-            id: 1,
+            id: PropertyKeyId(1),
             name: "aa".to_string(),
-            cardinality: Cardinality::Single,
+            cardinality: Cardinality::List,
+            default_value: None,
         };
 
-        let mut e = handler.create_edge("xx_1", "xx_2", 1);
-        handler.add_property(&mut e, &key, "test1");
-        handler.add_property(&mut e, &key, "test2");
+        let mut e = handler.create_edge("xx_1", "xx_2", EdgeLabelId(1)).unwrap();
+        handler.add_property(&mut e, &key, &"test1".into()).unwrap();
+        handler.add_property(&mut e, &key, &"test2".into()).unwrap();
         let e2 = handler
             .get_edge(
                 &e.src_vertex_id,
@@ -108,17 +269,18 @@ mod test {
                 e.edge_id,
                 EdgeDirection::Out,
             )
+            .unwrap()
             .unwrap();
         assert_eq!(
             e2.properties
                 .get_properties()
-                .map(|x| { x.value })
+                .map(|x| { x.value.to_string() })
                 .collect::<Vec<String>>(),
             vec!["test1", "test2"]
         );
         assert_eq!(e2.edge_id, e.edge_id);
 
-        handler.remove_property(&mut e, &key, vec![]);
+        handler.remove_property(&mut e, &key, vec![]).unwrap();
         let e2 = handler
             .get_edge(
                 &e.src_vertex_id,
@@ -127,22 +289,301 @@ mod test {
                 e.edge_id,
                 EdgeDirection::Out,
             )
+            .unwrap()
             .unwrap();
+        assert_eq!(e2.properties.get_properties().count(), 0);
+
+        handler.remove_edge(&e).unwrap();
         assert_eq!(
-            e2.properties.get_properties().map(|x| { x.value }).count(),
-            0
+            handler
+                .get_edge(
+                    &e.src_vertex_id,
+                    &e.dst_vertex_id,
+                    e.label,
+                    e.edge_id,
+                    EdgeDirection::Out
+                )
+                .unwrap(),
+            None
         );
+    }
+
+    #[test]
+    fn test_both_copies_written() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = EdgeHandler {
+            engine: engine.clone(),
+            observer: None,
+            single_copy_properties: false,
+        };
+        let copies = |edge: &Edge| {
+            vec![EdgeDirection::In, EdgeDirection::Out]
+                .into_iter()
+                .map(|direction| edge.generate_key(direction))
+                .filter(|key| engine.get(EDGE_TABLE_NAME, key).unwrap().is_some())
+                .count()
+        };
+
+        let e = handler.create_edge("v1", "v2", EdgeLabelId(1)).unwrap();
+        assert_eq!(copies(&e), 2);
+        handler.remove_edge(&e).unwrap();
+        assert_eq!(copies(&e), 0);
+    }
 
-        handler.remove_edge(&e);
+    #[test]
+    fn test_scan_edges_between() {
+        let handler = EdgeHandler {
+            engine: Rc::new(SledEngine::new_tmp()),
+            observer: None,
+            single_copy_properties: false,
+        };
+        let e1 = handler.create_edge("v1", "v2", EdgeLabelId(1)).unwrap();
+        let e2 = handler.create_edge("v1", "v2", EdgeLabelId(1)).unwrap();
+        handler.create_edge("v1", "v2", EdgeLabelId(2)).unwrap();
+        handler.create_edge("v1", "v22", EdgeLabelId(1)).unwrap();
+        handler.create_edge("v2", "v1", EdgeLabelId(1)).unwrap();
+        for direction in [EdgeDirection::Out, EdgeDirection::In] {
+            let ids = handler
+                .scan_edges_between("v1", "v2", EdgeLabelId(1), direction)
+                .unwrap()
+                .map(Result::unwrap)
+                .map(|edge| (edge.src_vertex_id, edge.dst_vertex_id, edge.edge_id))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                ids,
+                vec![
+                    ("v1".to_owned(), "v2".to_owned(), e1.edge_id),
+                    ("v1".to_owned(), "v2".to_owned(), e2.edge_id)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_cardinality() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = EdgeHandler {
+            engine,
+            observer: None,
+            single_copy_properties: false,
+        };
+        let key = PropertyKey {
+            id: PropertyKeyId(1),
+            name: "aa".to_string(),
+            cardinality: Cardinality::Single,
+            default_value: None,
+        };
+
+        let mut e = handler.create_edge("xx_1", "xx_2", EdgeLabelId(1)).unwrap();
+        handler.add_property(&mut e, &key, &"test1".into()).unwrap();
+        handler.add_property(&mut e, &key, &"test2".into()).unwrap();
+        for direction in [EdgeDirection::Out, EdgeDirection::In] {
+            let e2 = handler
+                .get_edge("xx_1", "xx_2", EdgeLabelId(1), e.edge_id, direction)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                e2.properties
+                    .get_properties()
+                    .map(|x| { x.value.to_string() })
+                    .collect::<Vec<String>>(),
+                vec!["test2"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_copy_properties() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = EdgeHandler {
+            engine: engine.clone(),
+            observer: None,
+            single_copy_properties: true,
+        };
+        let key = PropertyKey {
+            id: PropertyKeyId(1),
+            name: "aa".to_string(),
+            cardinality: Cardinality::Single,
+            default_value: None,
+        };
+
+        let mut e = handler.create_edge("v1", "v2", EdgeLabelId(1)).unwrap();
+        handler.add_property(&mut e, &key, &"test1".into()).unwrap();
+        // the value is only stored on the out copy
+        let in_key = e.generate_key(EdgeDirection::In);
         assert_eq!(
-            handler.get_edge(
-                &e.src_vertex_id,
-                &e.dst_vertex_id,
-                e.label,
-                e.edge_id,
-                EdgeDirection::Out
+            engine.get(EDGE_TABLE_NAME, &in_key).unwrap().unwrap(),
+            PROPERTIES_ON_OUT_EDGE
+        );
+        let values = |edge: Edge| {
+            edge.properties
+                .get_properties()
+                .map(|x| x.value.to_string())
+                .collect::<Vec<String>>()
+        };
+        for direction in [EdgeDirection::Out, EdgeDirection::In] {
+            let e2 = handler
+                .get_edge("v1", "v2", EdgeLabelId(1), e.edge_id, direction)
+                .unwrap()
+                .unwrap();
+            assert_eq!(values(e2), vec!["test1"]);
+        }
+        assert_eq!(
+            handler
+                .scan_in_edges("v2", None)
+                .unwrap()
+                .map(Result::unwrap)
+                .map(values)
+                .collect::<Vec<_>>(),
+            vec![vec!["test1"]]
+        );
+
+        // edges written with both copies are still read
+        let both = EdgeHandler {
+            single_copy_properties: false,
+            ..handler.clone()
+        };
+        let mut e = both.create_edge("v3", "v2", EdgeLabelId(1)).unwrap();
+        both.add_property(&mut e, &key, &"test2".into()).unwrap();
+        assert_eq!(handler.scan_in_edges("v2", None).unwrap().count(), 2);
+        handler.remove_property(&mut e, &key, vec![]).unwrap();
+        assert!(handler
+            .scan_in_edges("v2", Some(EdgeLabelId(1)))
+            .unwrap()
+            .map(Result::unwrap)
+            .all(|edge| edge.src_vertex_id == "v1" || edge.properties.data.is_empty()));
+    }
+
+    #[test]
+    fn test_scan_out_edges() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = EdgeHandler {
+            engine,
+            observer: None,
+            single_copy_properties: false,
+        };
+        handler.create_edge("v1", "v2", EdgeLabelId(1)).unwrap();
+        handler.create_edge("v1", "v3", EdgeLabelId(2)).unwrap();
+        handler.create_edge("v10", "v1", EdgeLabelId(1)).unwrap();
+        handler.create_edge("v2", "v1", EdgeLabelId(1)).unwrap();
+
+        let pairs = |edges: Box<dyn Iterator<Item = Result<Edge, StorageError>>>| {
+            edges
+                .map(Result::unwrap)
+                .map(|edge| format!("{}->{}", edge.src_vertex_id, edge.dst_vertex_id))
+                .collect::<Vec<String>>()
+        };
+        // the src id is terminated, so v10 is not an out edge of v1
+        assert_eq!(
+            pairs(handler.scan_out_edges("v1", None).unwrap()),
+            vec!["v1->v2", "v1->v3"]
+        );
+        assert_eq!(
+            pairs(handler.scan_out_edges("v1", Some(EdgeLabelId(2))).unwrap()),
+            vec!["v1->v3"]
+        );
+        assert!(pairs(handler.scan_out_edges("v3", None).unwrap()).is_empty());
+        assert_eq!(
+            pairs(handler.scan_in_edges("v1", None).unwrap()),
+            vec!["v10->v1", "v2->v1"]
+        );
+        assert_eq!(
+            pairs(handler.iter_all_out_edges(Some(EdgeLabelId(1))).unwrap()),
+            vec!["v1->v2", "v10->v1", "v2->v1"]
+        );
+        assert_eq!(handler.iter_all_out_edges(None).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn test_ids_with_string_term() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = EdgeHandler {
+            engine,
+            observer: None,
+            single_copy_properties: false,
+        };
+        let e = handler.create_edge("v1\0x", "v2", EdgeLabelId(1)).unwrap();
+        handler
+            .create_edge("v1", "v2\0\u{1}", EdgeLabelId(1))
+            .unwrap();
+
+        for direction in [EdgeDirection::Out, EdgeDirection::In] {
+            let e2 = handler
+                .get_edge("v1\0x", "v2", EdgeLabelId(1), e.edge_id, direction)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                (e2.src_vertex_id, e2.dst_vertex_id),
+                ("v1\0x".into(), "v2".into())
+            );
+        }
+        let dsts = |edges: Box<dyn Iterator<Item = Result<Edge, StorageError>>>| {
+            edges
+                .map(Result::unwrap)
+                .map(|edge| edge.dst_vertex_id)
+                .collect::<Vec<String>>()
+        };
+        // `v1\0x` is not split into the out edges of v1
+        assert_eq!(
+            dsts(handler.scan_out_edges("v1", None).unwrap()),
+            vec!["v2\0\u{1}"]
+        );
+        assert_eq!(
+            dsts(
+                handler
+                    .scan_out_edges("v1\0x", Some(EdgeLabelId(1)))
+                    .unwrap()
             ),
-            None
+            vec!["v2"]
+        );
+        assert_eq!(
+            handler
+                .scan_in_edges("v2", None)
+                .unwrap()
+                .map(Result::unwrap)
+                .map(|edge| edge.src_vertex_id)
+                .collect::<Vec<String>>(),
+            vec!["v1\0x"]
         );
+        assert_eq!(handler.scan_in_edges("v2\0\u{1}", None).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_edge_id_survives_reopen() {
+        let path = env::temp_dir().join(format!("angelina_edge_id_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let path = path.to_str().unwrap();
+        let open = || {
+            let engine = Rc::new(SledEngine::new(path).unwrap());
+            let handler = EdgeHandler {
+                engine: engine.clone(),
+                observer: None,
+                single_copy_properties: false,
+            };
+            (SchemaHandler::new(engine), handler)
+        };
+
+        let mut ids = vec![];
+        {
+            let (schema_handler, handler) = open();
+            let label = schema_handler
+                .create_edge_label("knows", EdgeMultiplicity::Many2ManyMulti)
+                .unwrap();
+            for _ in 0..3 {
+                ids.push(handler.create_edge("v1", "v2", label).unwrap().edge_id);
+            }
+        }
+        {
+            let (schema_handler, handler) = open();
+            // the schema counter is separate and must not move the edge counter
+            let label = schema_handler
+                .create_edge_label("created", EdgeMultiplicity::Many2ManyMulti)
+                .unwrap();
+            let edge_id = handler.create_edge("v1", "v3", label).unwrap().edge_id;
+            assert!(ids.iter().all(|id| edge_id > *id));
+            ids.push(edge_id);
+        }
+        assert_eq!(ids, vec![EdgeId(0), EdgeId(1), EdgeId(2), EdgeId(3)]);
+        fs::remove_dir_all(path).unwrap();
     }
 }
@@ -1,4 +1,6 @@
-pub mod sled_engine;
+pub mod chunked_scan;
+pub mod edge_handler;
 pub mod schema_handler;
+pub mod sled_engine;
 pub mod vertex_handler;
-pub mod edge_handler;
+pub mod write_observer;
@@ -1,79 +1,157 @@
 extern crate bytes;
 
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use std::rc::Rc;
 
-use crate::datamodel::base::{BaseSchemaModel, Cardinality, EdgeMultiplicity};
+use sled::Batch;
+
+use crate::datamodel::base::{
+    BaseSchemaModel, Cardinality, EdgeLabelId, EdgeMultiplicity, PropertyKeyId, VertexLabelId,
+};
 use crate::datamodel::edge_label::EdgeLabel;
+use crate::datamodel::property::PropertyValue;
 use crate::datamodel::property_key::PropertyKey;
 use crate::datamodel::vertex_label::VertexLabel;
-use crate::handlers::sled_engine::SledEngine;
+use crate::handlers::sled_engine::{SledEngine, StorageError};
 
 static SCHEMA_TABLE_NAME: &str = "SCHEMA";
 static AUTO_INCREMENT_SCHEMA_ID_KEY: &str = "SCHEMA_ID";
 
+/// name to id of each kind of schema element, loaded by the first lookup by name and
+/// dropped when an element of that kind is created, updated or removed.
+#[derive(Default)]
+struct NameCache {
+    vertex_labels: Option<HashMap<String, VertexLabelId>>,
+    edge_labels: Option<HashMap<String, EdgeLabelId>>,
+    property_keys: Option<PropertyKeyNames>,
+}
+
+struct PropertyKeyNames {
+    ids: HashMap<String, PropertyKeyId>,
+    /// keys with a default value
+    defaulted: Vec<PropertyKeyId>,
+}
+
+/// clones share the name cache. schema writes must go through handlers sharing it.
+#[derive(Clone)]
 pub struct SchemaHandler {
-    pub(crate) engine: Rc<Box<SledEngine>>,
+    pub(crate) engine: Rc<SledEngine>,
+    cache: Rc<RefCell<NameCache>>,
+    /// number of full scans of the schema tree
+    scans: Rc<Cell<u64>>,
 }
 
 impl SchemaHandler {
+    pub fn new(engine: Rc<SledEngine>) -> Self {
+        SchemaHandler {
+            engine,
+            cache: Default::default(),
+            scans: Default::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn scan_count(&self) -> u64 {
+        self.scans.get()
+    }
+
     // ============== VERTEX LABEL ==============
-    pub fn create_vertex_label(&self, name: &str) -> u64 {
-        let id = self.generate_next_id();
+    pub fn create_vertex_label(&self, name: &str) -> Result<VertexLabelId, SchemaError> {
+        if self.get_vertex_label_by_name(name)?.is_some() {
+            return Err(SchemaError::DuplicateName("Vertex Label", name.to_owned()));
+        }
+        self.cache.borrow_mut().vertex_labels = None;
+        let id = VertexLabelId(self.generate_next_id()?);
         let model = VertexLabel {
             id,
             name: name.to_owned(),
         };
 
         let (key, value) = model.serialize();
-        self.engine.insert(SCHEMA_TABLE_NAME, &key, &value);
-        id
+        self.engine.insert(SCHEMA_TABLE_NAME, &key, &value)?;
+        Ok(id)
     }
 
-    pub fn get_vertex_label(&self, id: u64) -> Option<VertexLabel> {
+    pub fn get_vertex_label(&self, id: VertexLabelId) -> Result<Option<VertexLabel>, StorageError> {
         match self
             .engine
-            .get(SCHEMA_TABLE_NAME, &VertexLabel::build_key(id))
+            .get(SCHEMA_TABLE_NAME, &VertexLabel::build_key(id.0))?
         {
-            Some(value) => Some(VertexLabel::deserialize_value(id, &value)),
-            None => None,
+            Some(value) => Ok(Some(VertexLabel::deserialize_value(id.0, &value)?)),
+            None => Ok(None),
         }
     }
 
-    pub fn get_vertex_labels(&self) -> Vec<VertexLabel> {
+    pub fn get_vertex_labels(&self) -> Result<Vec<VertexLabel>, StorageError> {
+        self.scans.set(self.scans.get() + 1);
         self.engine
-            .open_tree(SCHEMA_TABLE_NAME)
+            .open_tree(SCHEMA_TABLE_NAME)?
             .scan_prefix(VertexLabel::get_prefix())
             .map(|res| {
-                let key_value = res.unwrap();
-                VertexLabel::deserialize(&key_value.0, &key_value.1)
+                let (key, value) = res?;
+                Ok(VertexLabel::deserialize(&key, &value)?)
             })
             .collect()
     }
 
-    pub fn update_vertex_label(&self, id: u64, name: &str) {
+    pub fn update_vertex_label(&self, id: VertexLabelId, name: &str) -> Result<(), StorageError> {
+        self.cache.borrow_mut().vertex_labels = None;
         let model = VertexLabel {
             id,
             name: name.to_owned(),
         };
         let (key, value) = model.serialize();
-        self.engine.insert(SCHEMA_TABLE_NAME, &key, &value);
+        self.engine.insert(SCHEMA_TABLE_NAME, &key, &value)
     }
 
-    pub fn remove_vertex_label(&self, id: u64) {
-        let stored_id = VertexLabel::build_key(id);
-        self.engine.remove(SCHEMA_TABLE_NAME, &stored_id);
+    /// returns whether there was an element with `id` to remove.
+    pub fn remove_vertex_label(&self, id: VertexLabelId) -> Result<bool, StorageError> {
+        if self.get_vertex_label(id)?.is_none() {
+            return Ok(false);
+        }
+        self.cache.borrow_mut().vertex_labels = None;
+        let stored_id = VertexLabel::build_key(id.0);
+        self.engine.remove(SCHEMA_TABLE_NAME, &stored_id)?;
+        Ok(true)
     }
 
-    pub fn get_vertex_label_by_name(&self, name: &str) -> Option<VertexLabel> {
-        self.get_vertex_labels()
-            .into_iter()
-            .filter(|label| label.name == name)
-            .next()
+    pub fn get_vertex_label_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<VertexLabel>, StorageError> {
+        let id = {
+            let mut cache = self.cache.borrow_mut();
+            if cache.vertex_labels.is_none() {
+                let labels = self.get_vertex_labels()?;
+                cache.vertex_labels = Some(
+                    labels
+                        .into_iter()
+                        .map(|label| (label.name, label.id))
+                        .collect(),
+                );
+            }
+            cache.vertex_labels.as_ref().unwrap().get(name).copied()
+        };
+        match id {
+            Some(id) => self.get_vertex_label(id),
+            None => Ok(None),
+        }
     }
 
     // ============== EDGE LABEL ==============
-    pub fn create_edge_label(&self, name: &str, multiplicity: EdgeMultiplicity) -> u64 {
-        let id = self.generate_next_id();
+    pub fn create_edge_label(
+        &self,
+        name: &str,
+        multiplicity: EdgeMultiplicity,
+    ) -> Result<EdgeLabelId, SchemaError> {
+        if self.get_edge_label_by_name(name)?.is_some() {
+            return Err(SchemaError::DuplicateName("Edge Label", name.to_owned()));
+        }
+        self.cache.borrow_mut().edge_labels = None;
+        let id = EdgeLabelId(self.generate_next_id()?);
         let model = EdgeLabel {
             id,
             name: name.to_owned(),
@@ -81,150 +159,348 @@ impl SchemaHandler {
         };
 
         let (key, value) = model.serialize();
-        self.engine.insert(SCHEMA_TABLE_NAME, &key, &value);
-        id
+        self.engine.insert(SCHEMA_TABLE_NAME, &key, &value)?;
+        Ok(id)
     }
 
-    pub fn get_edge_label(&self, id: u64) -> Option<EdgeLabel> {
+    pub fn get_edge_label(&self, id: EdgeLabelId) -> Result<Option<EdgeLabel>, StorageError> {
         match self
             .engine
-            .get(SCHEMA_TABLE_NAME, &EdgeLabel::build_key(id))
+            .get(SCHEMA_TABLE_NAME, &EdgeLabel::build_key(id.0))?
         {
-            Some(value) => Some(EdgeLabel::deserialize_value(id, &value)),
-            None => None,
+            Some(value) => Ok(Some(EdgeLabel::deserialize_value(id.0, &value)?)),
+            None => Ok(None),
         }
     }
 
-    pub fn get_edge_labels(&self) -> Vec<EdgeLabel> {
+    pub fn get_edge_labels(&self) -> Result<Vec<EdgeLabel>, StorageError> {
+        self.scans.set(self.scans.get() + 1);
         self.engine
-            .open_tree(SCHEMA_TABLE_NAME)
+            .open_tree(SCHEMA_TABLE_NAME)?
             .scan_prefix(EdgeLabel::get_prefix())
             .map(|res| {
-                let key_value = res.unwrap();
-                EdgeLabel::deserialize(&key_value.0, &key_value.1)
+                let (key, value) = res?;
+                Ok(EdgeLabel::deserialize(&key, &value)?)
             })
             .collect()
     }
 
-    pub fn update_edge_label(&self, id: u64, name: &str) {
-        let stored_key = EdgeLabel::build_key(id);
-        self.engine.open_tree(SCHEMA_TABLE_NAME).update_and_fetch(
+    pub fn update_edge_label(&self, id: EdgeLabelId, name: &str) -> Result<(), StorageError> {
+        self.cache.borrow_mut().edge_labels = None;
+        let stored_key = EdgeLabel::build_key(id.0);
+        // a label that can't be read is left as it is
+        let mut corrupt = None;
+        self.engine.open_tree(SCHEMA_TABLE_NAME)?.update_and_fetch(
             stored_key,
             |old_value| -> Option<Vec<u8>> {
                 match old_value {
-                    Some(value) => {
-                        let old_edge_label = EdgeLabel::deserialize_value(id, value);
-                        let new_edge_label = EdgeLabel {
-                            id,
-                            name: name.to_owned(),
-                            multiplicity: old_edge_label.multiplicity,
-                        };
-                        Some(new_edge_label.serialize().1)
-                    }
+                    Some(value) => match EdgeLabel::deserialize_value(id.0, value) {
+                        Ok(old_edge_label) => {
+                            let new_edge_label = EdgeLabel {
+                                id,
+                                name: name.to_owned(),
+                                multiplicity: old_edge_label.multiplicity,
+                            };
+                            Some(new_edge_label.serialize().1)
+                        }
+                        Err(e) => {
+                            corrupt = Some(e);
+                            Some(value.to_vec())
+                        }
+                    },
                     None => panic!("No such EdgeLabel"),
                 }
             },
-        );
+        )?;
+        match corrupt {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
     }
 
-    pub fn remove_edge_label(&self, id: u64) {
-        let stored_id = EdgeLabel::build_key(id);
-        self.engine.remove(SCHEMA_TABLE_NAME, &stored_id);
+    /// returns whether there was an element with `id` to remove.
+    pub fn remove_edge_label(&self, id: EdgeLabelId) -> Result<bool, StorageError> {
+        if self.get_edge_label(id)?.is_none() {
+            return Ok(false);
+        }
+        self.cache.borrow_mut().edge_labels = None;
+        let stored_id = EdgeLabel::build_key(id.0);
+        self.engine.remove(SCHEMA_TABLE_NAME, &stored_id)?;
+        Ok(true)
     }
 
-    pub fn get_edge_label_by_name(&self, name: &str) -> Option<EdgeLabel> {
-        self.get_edge_labels()
-            .into_iter()
-            .filter(|label| label.name == name)
-            .next()
+    pub fn get_edge_label_by_name(&self, name: &str) -> Result<Option<EdgeLabel>, StorageError> {
+        let id = {
+            let mut cache = self.cache.borrow_mut();
+            if cache.edge_labels.is_none() {
+                let labels = self.get_edge_labels()?;
+                cache.edge_labels = Some(
+                    labels
+                        .into_iter()
+                        .map(|label| (label.name, label.id))
+                        .collect(),
+                );
+            }
+            cache.edge_labels.as_ref().unwrap().get(name).copied()
+        };
+        match id {
+            Some(id) => self.get_edge_label(id),
+            None => Ok(None),
+        }
     }
 
     // ============== PROPERTY KEY ==============
-    pub fn create_property_key(&self, name: &str, cardinality: Cardinality) -> u64 {
-        let id = self.generate_next_id();
+    pub fn create_property_key(
+        &self,
+        name: &str,
+        cardinality: Cardinality,
+        default_value: Option<PropertyValue>,
+    ) -> Result<PropertyKeyId, SchemaError> {
+        if self.get_property_key_by_name(name)?.is_some() {
+            return Err(SchemaError::DuplicateName("Property Key", name.to_owned()));
+        }
+        self.cache.borrow_mut().property_keys = None;
+        let id = PropertyKeyId(self.generate_next_id()?);
         let model = PropertyKey {
             id,
             name: name.to_owned(),
             cardinality,
+            default_value,
         };
 
         let (key, value) = model.serialize();
-        self.engine.insert(SCHEMA_TABLE_NAME, &key, &value);
-        id
+        self.engine.insert(SCHEMA_TABLE_NAME, &key, &value)?;
+        Ok(id)
     }
 
-    pub fn get_property_key(&self, id: u64) -> Option<PropertyKey> {
+    pub fn get_property_key(&self, id: PropertyKeyId) -> Result<Option<PropertyKey>, StorageError> {
         match self
             .engine
-            .get(SCHEMA_TABLE_NAME, &PropertyKey::build_key(id))
+            .get(SCHEMA_TABLE_NAME, &PropertyKey::build_key(id.0))?
         {
-            Some(value) => Some(PropertyKey::deserialize_value(id, &value)),
-            None => None,
+            Some(value) => Ok(Some(PropertyKey::deserialize_value(id.0, &value)?)),
+            None => Ok(None),
         }
     }
 
-    pub fn get_property_keys(&self) -> Vec<PropertyKey> {
+    pub fn get_property_keys(&self) -> Result<Vec<PropertyKey>, StorageError> {
+        self.scans.set(self.scans.get() + 1);
         self.engine
-            .open_tree(SCHEMA_TABLE_NAME)
+            .open_tree(SCHEMA_TABLE_NAME)?
             .scan_prefix(PropertyKey::get_prefix())
             .map(|res| {
-                let key_value = res.unwrap();
-                PropertyKey::deserialize(&key_value.0, &key_value.1)
+                let (key, value) = res?;
+                Ok(PropertyKey::deserialize(&key, &value)?)
             })
             .collect()
     }
 
-    pub fn update_property_key(&self, id: u64, name: &str) {
-        let stored_key = PropertyKey::build_key(id);
-        self.engine.open_tree(SCHEMA_TABLE_NAME).update_and_fetch(
+    pub fn update_property_key(&self, id: PropertyKeyId, name: &str) -> Result<(), StorageError> {
+        self.cache.borrow_mut().property_keys = None;
+        let stored_key = PropertyKey::build_key(id.0);
+        // a key that can't be read is left as it is
+        let mut corrupt = None;
+        self.engine.open_tree(SCHEMA_TABLE_NAME)?.update_and_fetch(
             stored_key,
             |old_value| -> Option<Vec<u8>> {
                 match old_value {
-                    Some(value) => {
-                        let old_property_key = PropertyKey::deserialize_value(id, value);
-                        let new_property_key = PropertyKey {
-                            id,
-                            name: name.to_owned(),
-                            cardinality: old_property_key.cardinality,
-                        };
-                        Some(new_property_key.serialize().1)
-                    }
+                    Some(value) => match PropertyKey::deserialize_value(id.0, value) {
+                        Ok(old_property_key) => {
+                            let new_property_key = PropertyKey {
+                                id,
+                                name: name.to_owned(),
+                                cardinality: old_property_key.cardinality,
+                                default_value: old_property_key.default_value,
+                            };
+                            Some(new_property_key.serialize().1)
+                        }
+                        Err(e) => {
+                            corrupt = Some(e);
+                            Some(value.to_vec())
+                        }
+                    },
                     None => panic!("No such Property Key"),
                 }
             },
-        );
+        )?;
+        match corrupt {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
     }
 
-    pub fn remove_property_key(&self, id: u64) {
-        let stored_id = PropertyKey::build_key(id);
-        self.engine.remove(SCHEMA_TABLE_NAME, &stored_id);
+    /// returns whether there was an element with `id` to remove.
+    pub fn remove_property_key(&self, id: PropertyKeyId) -> Result<bool, StorageError> {
+        if self.get_property_key(id)?.is_none() {
+            return Ok(false);
+        }
+        self.cache.borrow_mut().property_keys = None;
+        let stored_id = PropertyKey::build_key(id.0);
+        self.engine.remove(SCHEMA_TABLE_NAME, &stored_id)?;
+        Ok(true)
+    }
+
+    pub fn get_property_key_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<PropertyKey>, StorageError> {
+        match self.with_property_key_names(|names| names.ids.get(name).copied())? {
+            Some(id) => self.get_property_key(id),
+            None => Ok(None),
+        }
+    }
+
+    /// property keys that have a default value.
+    pub fn get_defaulted_property_keys(&self) -> Result<Vec<PropertyKey>, StorageError> {
+        let mut keys = vec![];
+        for id in self.with_property_key_names(|names| names.defaulted.clone())? {
+            keys.extend(self.get_property_key(id)?);
+        }
+        Ok(keys)
     }
 
-    pub fn get_property_key_by_name(&self, name: &str) -> Option<PropertyKey> {
-        self.get_property_keys()
-            .into_iter()
-            .filter(|key| key.name == name)
-            .next()
+    fn with_property_key_names<T>(
+        &self,
+        f: impl FnOnce(&PropertyKeyNames) -> T,
+    ) -> Result<T, StorageError> {
+        let mut cache = self.cache.borrow_mut();
+        if cache.property_keys.is_none() {
+            let keys = self.get_property_keys()?;
+            cache.property_keys = Some(PropertyKeyNames {
+                defaulted: keys
+                    .iter()
+                    .filter(|key| key.default_value.is_some())
+                    .map(|key| key.id)
+                    .collect(),
+                ids: keys.into_iter().map(|key| (key.name, key.id)).collect(),
+            });
+        }
+        Ok(f(cache.property_keys.as_ref().unwrap()))
+    }
+
+    // ============== SCHEMA ==============
+    /// create all of `elements` with a single batch, or none of them if a name is taken,
+    /// by an existing element or by an earlier one of `elements`. returns the ids in order.
+    pub fn create_schema(&self, elements: Vec<NewSchemaElement>) -> Result<Vec<u64>, SchemaError> {
+        let mut names = HashSet::new();
+        for element in &elements {
+            let (kind, name, exists) = match element {
+                NewSchemaElement::VertexLabel { name } => (
+                    "Vertex Label",
+                    name,
+                    self.get_vertex_label_by_name(name)?.is_some(),
+                ),
+                NewSchemaElement::EdgeLabel { name, .. } => (
+                    "Edge Label",
+                    name,
+                    self.get_edge_label_by_name(name)?.is_some(),
+                ),
+                NewSchemaElement::PropertyKey { name, .. } => (
+                    "Property Key",
+                    name,
+                    self.get_property_key_by_name(name)?.is_some(),
+                ),
+            };
+            if exists || !names.insert((kind, name)) {
+                return Err(SchemaError::DuplicateName(kind, name.to_owned()));
+            }
+        }
+
+        let mut batch = Batch::default();
+        let mut ids = vec![];
+        for element in elements {
+            let id = self.generate_next_id()?;
+            let (key, value) = match element {
+                NewSchemaElement::VertexLabel { name } => VertexLabel {
+                    id: VertexLabelId(id),
+                    name,
+                }
+                .serialize(),
+                NewSchemaElement::EdgeLabel { name, multiplicity } => EdgeLabel {
+                    id: EdgeLabelId(id),
+                    name,
+                    multiplicity,
+                }
+                .serialize(),
+                NewSchemaElement::PropertyKey {
+                    name,
+                    cardinality,
+                    default_value,
+                } => PropertyKey {
+                    id: PropertyKeyId(id),
+                    name,
+                    cardinality,
+                    default_value,
+                }
+                .serialize(),
+            };
+            batch.insert(key, value);
+            ids.push(id);
+        }
+        *self.cache.borrow_mut() = NameCache::default();
+        self.engine.apply_batch(SCHEMA_TABLE_NAME, batch)?;
+        Ok(ids)
     }
 
-    fn generate_next_id(&self) -> u64 {
+    fn generate_next_id(&self) -> Result<u64, StorageError> {
         self.engine
             .increment(SCHEMA_TABLE_NAME, AUTO_INCREMENT_SCHEMA_ID_KEY)
     }
 }
 
+/// an element of a schema created with `SchemaHandler::create_schema`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NewSchemaElement {
+    VertexLabel {
+        name: String,
+    },
+    EdgeLabel {
+        name: String,
+        multiplicity: EdgeMultiplicity,
+    },
+    PropertyKey {
+        name: String,
+        cardinality: Cardinality,
+        default_value: Option<PropertyValue>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    /// (kind of schema element, name) of a name that is already taken by that kind
+    DuplicateName(&'static str, String),
+    Storage(StorageError),
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::DuplicateName(kind, name) => {
+                write!(f, "{} named {} already exists", kind, name)
+            }
+            SchemaError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<StorageError> for SchemaError {
+    fn from(e: StorageError) -> Self {
+        SchemaError::Storage(e)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
     fn test_vertex_label_crud() {
-        let engine = Rc::new(Box::new(SledEngine::new_tmp()));
+        let engine = Rc::new(SledEngine::new_tmp());
         let name = "name";
         let name2 = "name2";
-        let handler = SchemaHandler { engine };
-        let id = handler.create_vertex_label(name);
-        let vertex_label = handler.get_vertex_label(id).unwrap();
+        let handler = SchemaHandler::new(engine);
+        let id = handler.create_vertex_label(name).unwrap();
+        let vertex_label = handler.get_vertex_label(id).unwrap().unwrap();
         assert_eq!(
             vertex_label,
             VertexLabel {
@@ -233,8 +509,8 @@ mod test {
             }
         );
 
-        handler.update_vertex_label(id, name2);
-        let vertex_label = handler.get_vertex_label(id).unwrap();
+        handler.update_vertex_label(id, name2).unwrap();
+        let vertex_label = handler.get_vertex_label(id).unwrap().unwrap();
         assert_eq!(
             vertex_label,
             VertexLabel {
@@ -243,20 +519,21 @@ mod test {
             }
         );
 
-        handler.remove_vertex_label(id);
-        let vertex_label = handler.get_vertex_label(id);
+        assert!(handler.remove_vertex_label(id).unwrap());
+        let vertex_label = handler.get_vertex_label(id).unwrap();
         assert_eq!(vertex_label, None);
+        assert!(!handler.remove_vertex_label(id).unwrap());
     }
 
     #[test]
     fn test_edge_label_crud() {
-        let engine = Rc::new(Box::new(SledEngine::new_tmp()));
+        let engine = Rc::new(SledEngine::new_tmp());
         let name = "name";
         let name2 = "name2";
         let multiplicity = EdgeMultiplicity::One2One;
-        let handler = SchemaHandler { engine };
-        let id = handler.create_edge_label(name, multiplicity);
-        let label = handler.get_edge_label(id).unwrap();
+        let handler = SchemaHandler::new(engine);
+        let id = handler.create_edge_label(name, multiplicity).unwrap();
+        let label = handler.get_edge_label(id).unwrap().unwrap();
         assert_eq!(
             label,
             EdgeLabel {
@@ -266,8 +543,8 @@ mod test {
             }
         );
 
-        handler.update_edge_label(id, name2);
-        let label = handler.get_edge_label(id).unwrap();
+        handler.update_edge_label(id, name2).unwrap();
+        let label = handler.get_edge_label(id).unwrap().unwrap();
         assert_eq!(
             label,
             EdgeLabel {
@@ -277,42 +554,126 @@ mod test {
             }
         );
 
-        handler.remove_edge_label(id);
-        let label = handler.get_edge_label(id);
+        assert!(handler.remove_edge_label(id).unwrap());
+        let label = handler.get_edge_label(id).unwrap();
         assert_eq!(label, None);
+        assert!(!handler.remove_edge_label(id).unwrap());
     }
 
     #[test]
     fn test_property_key_crud() {
-        let engine = Rc::new(Box::new(SledEngine::new_tmp()));
+        let engine = Rc::new(SledEngine::new_tmp());
         let name = "name";
         let name2 = "name2";
         let cardinality = Cardinality::Single;
-        let handler = SchemaHandler { engine };
-        let id = handler.create_property_key(name, cardinality);
-        let p = handler.get_property_key(id).unwrap();
+        let handler = SchemaHandler::new(engine);
+        let id = handler
+            .create_property_key(name, cardinality, None)
+            .unwrap();
+        let p = handler.get_property_key(id).unwrap().unwrap();
         assert_eq!(
             p,
             PropertyKey {
                 id,
                 name: name.to_owned(),
                 cardinality,
+                default_value: None,
             }
         );
 
-        handler.update_property_key(id, name2);
-        let p = handler.get_property_key(id).unwrap();
+        handler.update_property_key(id, name2).unwrap();
+        let p = handler.get_property_key(id).unwrap().unwrap();
         assert_eq!(
             p,
             PropertyKey {
                 id,
                 name: name2.to_owned(),
                 cardinality,
+                default_value: None,
             }
         );
 
-        handler.remove_property_key(id);
-        let p = handler.get_property_key(id);
+        assert!(handler.remove_property_key(id).unwrap());
+        let p = handler.get_property_key(id).unwrap();
         assert_eq!(p, None);
+        assert!(!handler.remove_property_key(id).unwrap());
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = SchemaHandler::new(engine);
+        handler.create_vertex_label("foo").unwrap();
+        assert_eq!(
+            handler.create_vertex_label("foo"),
+            Err(SchemaError::DuplicateName("Vertex Label", "foo".to_owned()))
+        );
+        // names are unique per kind of element
+        handler
+            .create_edge_label("foo", EdgeMultiplicity::One2One)
+            .unwrap();
+        assert!(handler
+            .create_edge_label("foo", EdgeMultiplicity::One2Many)
+            .is_err());
+        handler
+            .create_property_key("foo", Cardinality::Single, None)
+            .unwrap();
+        assert!(handler
+            .create_property_key("foo", Cardinality::List, None)
+            .is_err());
+        assert_eq!(handler.get_vertex_labels().unwrap().len(), 1);
+        assert_eq!(handler.get_edge_labels().unwrap().len(), 1);
+        assert_eq!(handler.get_property_keys().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_create_schema() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = SchemaHandler::new(engine);
+        let person = || NewSchemaElement::VertexLabel {
+            name: "person".to_owned(),
+        };
+        let knows = NewSchemaElement::EdgeLabel {
+            name: "knows".to_owned(),
+            multiplicity: EdgeMultiplicity::One2One,
+        };
+        assert_eq!(
+            handler.create_schema(vec![person(), knows.clone()]),
+            Ok(vec![0, 1])
+        );
+
+        // a name taken by an earlier element of the same schema
+        let name = NewSchemaElement::PropertyKey {
+            name: "name".to_owned(),
+            cardinality: Cardinality::Single,
+            default_value: None,
+        };
+        assert_eq!(
+            handler.create_schema(vec![name.clone(), name.clone()]),
+            Err(SchemaError::DuplicateName(
+                "Property Key",
+                "name".to_owned()
+            ))
+        );
+        // a name taken by an existing element
+        assert_eq!(
+            handler.create_schema(vec![name.clone(), person()]),
+            Err(SchemaError::DuplicateName(
+                "Vertex Label",
+                "person".to_owned()
+            ))
+        );
+        assert!(handler.get_property_keys().unwrap().is_empty());
+
+        // failed schemas take no ids
+        assert_eq!(handler.create_schema(vec![name]), Ok(vec![2]));
+        assert_eq!(
+            handler
+                .get_property_key_by_name("name")
+                .unwrap()
+                .unwrap()
+                .id,
+            PropertyKeyId(2)
+        );
     }
 }
@@ -1,74 +1,230 @@
+use std::cell::Cell;
 use std::convert::TryInto;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
 
-use sled::{Config, Db, Tree};
+use sled::{Batch, Config, Db, Tree};
+
+use crate::datamodel::buffer::DecodeError;
+use crate::handlers::chunked_scan::{CancellationToken, ChunkedScan};
+
+/// an error of sled, e.g. an I/O error or a corrupt db.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageError {
+    msg: String,
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "StorageError: {}", self.msg)
+    }
+}
+
+impl From<sled::Error> for StorageError {
+    fn from(e: sled::Error) -> Self {
+        StorageError { msg: e.to_string() }
+    }
+}
+
+impl From<DecodeError> for StorageError {
+    fn from(e: DecodeError) -> Self {
+        StorageError { msg: e.to_string() }
+    }
+}
 
 pub struct SledEngine {
     path: String,
     db: Db,
+    namespace: String,
+    /// entries yielded by the scans of the db, shared by all its namespaces
+    scanned: Rc<Cell<u64>>,
 }
 
 impl SledEngine {
-    pub fn new(path: &str) -> Self {
-        SledEngine {
+    pub fn new(path: &str) -> Result<Self, StorageError> {
+        Ok(SledEngine {
             path: path.to_owned(),
-            db: sled::open(&path).unwrap(),
-        }
+            db: sled::open(path)?,
+            namespace: "".to_owned(),
+            scanned: Default::default(),
+        })
+    }
+
+    /// open the db at `path` flushing every `flush_every_ms` in the background, or only on
+    /// `flush` if `None`, with a page cache of `cache_capacity` bytes.
+    pub fn with_config(
+        path: &str,
+        flush_every_ms: Option<u64>,
+        cache_capacity: u64,
+    ) -> Result<Self, StorageError> {
+        let db = Config::new()
+            .path(path)
+            .flush_every_ms(flush_every_ms)
+            .cache_capacity(cache_capacity)
+            .open()?;
+        Ok(SledEngine {
+            path: path.to_owned(),
+            db,
+            namespace: "".to_owned(),
+            scanned: Default::default(),
+        })
     }
 
     pub fn new_tmp() -> Self {
         SledEngine {
             path: "".to_owned(),
             db: Config::new().temporary(true).open().unwrap(),
+            namespace: "".to_owned(),
+            scanned: Default::default(),
         }
     }
 
-    pub fn open_tree(&self, name: &str) -> Tree {
-        self.db.open_tree(name).unwrap()
+    /// a view of the same db whose trees are all prefixed by `namespace`.
+    pub fn with_namespace(&self, namespace: &str) -> Self {
+        SledEngine {
+            path: self.path.to_owned(),
+            db: self.db.clone(),
+            namespace: namespace.to_owned(),
+            scanned: self.scanned.clone(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// write all the pending writes of every namespace to disk, returning once they are
+    /// durable.
+    pub fn flush(&self) -> Result<(), StorageError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn open_tree(&self, name: &str) -> Result<Tree, StorageError> {
+        Ok(self.db.open_tree(self.tree_name(name))?)
     }
 
-    pub fn drop_tree(&self, name: &str) {
-        self.db.drop_tree(name);
+    pub fn drop_tree(&self, name: &str) -> Result<(), StorageError> {
+        self.db.drop_tree(self.tree_name(name))?;
+        Ok(())
     }
 
-    pub fn get(&self, tree_name: &str, key: &[u8]) -> Option<Vec<u8>> {
-        match self.open_tree(tree_name).get(key).unwrap() {
-            Some(res) => Some(res.to_vec()),
-            None => None,
+    /// drop every tree of this namespace, which must not be empty.
+    pub fn drop_namespace(&self) -> Result<(), StorageError> {
+        if self.namespace.is_empty() {
+            return Err(StorageError {
+                msg: "can not drop the empty namespace".to_owned(),
+            });
         }
+        let prefix = self.tree_name("");
+        for name in self.db.tree_names() {
+            if name.starts_with(prefix.as_bytes()) {
+                self.db.drop_tree(name)?;
+            }
+        }
+        Ok(())
     }
 
-    pub fn insert(&self, tree_name: &str, key: &[u8], value: &[u8]) {
-        self.open_tree(tree_name).insert(key, value);
+    /// lazy scan of the keys of `tree_name` starting with `prefix`, read in chunks.
+    pub fn scan(
+        &self,
+        tree_name: &str,
+        prefix: &[u8],
+        token: Option<CancellationToken>,
+    ) -> Result<ChunkedScan, StorageError> {
+        Ok(ChunkedScan::new(self.open_tree(tree_name)?, prefix, token)
+            .with_counter(self.scanned.clone()))
     }
 
-    pub fn remove(&self, tree_name: &str, key: &[u8]) {
-        self.open_tree(tree_name).remove(key);
+    /// number of entries yielded by the scans of the db so far, in any namespace.
+    #[cfg(test)]
+    pub(crate) fn scanned_counter(&self) -> Rc<Cell<u64>> {
+        self.scanned.clone()
     }
 
-    pub fn increment(&self, tree_name: &str, key: &str) -> u64 {
-        let tree = self.open_tree(tree_name);
-        Self::bytes_to_long(
-            &tree
-                .update_and_fetch(key.as_bytes(), |old| -> Option<Vec<u8>> {
-                    let number = match old {
-                        Some(bytes) => {
-                            let number = Self::bytes_to_long(bytes);
-                            number + 1
-                        }
-                        None => 0,
-                    };
-                    Some(number.to_be_bytes().to_vec())
-                })
-                .unwrap()
-                .unwrap()
-                .to_vec()
-                .to_owned(),
-        )
+    pub fn get(&self, tree_name: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.open_tree(tree_name)?.get(key)?.map(|res| res.to_vec()))
+    }
+
+    pub fn insert(&self, tree_name: &str, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        self.open_tree(tree_name)?.insert(key, value)?;
+        Ok(())
+    }
+
+    /// insert only if the key is absent, as a single conditional write.
+    /// returns the current value if the key already exists.
+    pub fn insert_if_absent(
+        &self,
+        tree_name: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<Vec<u8>>, StorageError> {
+        match self.open_tree(tree_name)?.compare_and_swap(
+            key,
+            None as Option<&[u8]>,
+            Some(value),
+        )? {
+            Ok(()) => Ok(None),
+            Err(e) => Ok(e.current.map(|current| current.to_vec())),
+        }
     }
 
-    fn bytes_to_long(bytes: &[u8]) -> u64 {
-        let array: [u8; 8] = bytes.try_into().unwrap();
-        u64::from_be_bytes(array)
+    /// apply all the inserts and removes of `batch` atomically: either all of them are
+    /// visible or none is, even after a crash.
+    pub fn apply_batch(&self, tree_name: &str, batch: Batch) -> Result<(), StorageError> {
+        self.open_tree(tree_name)?.apply_batch(batch)?;
+        Ok(())
+    }
+
+    pub fn remove(&self, tree_name: &str, key: &[u8]) -> Result<(), StorageError> {
+        self.open_tree(tree_name)?.remove(key)?;
+        Ok(())
+    }
+
+    pub fn increment(&self, tree_name: &str, key: &str) -> Result<u64, StorageError> {
+        let tree = self.open_tree(tree_name)?;
+        let mut corrupt = None;
+        let number = tree.update_and_fetch(key.as_bytes(), |old| -> Option<Vec<u8>> {
+            corrupt = None;
+            let number = match old.map(Self::bytes_to_long) {
+                Some(Ok(number)) => number + 1,
+                // left as it is
+                Some(Err(e)) => {
+                    corrupt = Some(e);
+                    return old.map(<[u8]>::to_vec);
+                }
+                None => 0,
+            };
+            Some(number.to_be_bytes().to_vec())
+        })?;
+        if let Some(e) = corrupt {
+            return Err(e.into());
+        }
+        // the update always stores a number
+        Ok(Self::bytes_to_long(&number.unwrap_or_default())?)
+    }
+
+    fn tree_name(&self, name: &str) -> String {
+        if self.namespace.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}/{}", self.namespace, name)
+        }
+    }
+
+    fn bytes_to_long(bytes: &[u8]) -> Result<u64, DecodeError> {
+        let array: [u8; 8] = bytes.try_into().map_err(|_| {
+            DecodeError::new(format!(
+                "expected a number of 8 bytes, found {}",
+                bytes.len()
+            ))
+        })?;
+        Ok(u64::from_be_bytes(array))
     }
 }
 
@@ -82,34 +238,117 @@ mod test {
         let tree1 = "test0";
         let tree2 = "test1";
         let key1 = "key1".as_bytes();
-        let key2 = "key2".as_bytes();
         let value1 = "v1".as_bytes();
         let value2 = "v2".as_bytes();
 
-        sled.insert(tree1, key1, value1);
-        assert_eq!(sled.get(tree1, key1).unwrap(), value1);
-        sled.insert(tree1, key1, value2);
-        assert_eq!(sled.get(tree1, key1).unwrap(), value2);
+        sled.insert(tree1, key1, value1).unwrap();
+        assert_eq!(sled.get(tree1, key1).unwrap().unwrap(), value1);
+        sled.insert(tree1, key1, value2).unwrap();
+        assert_eq!(sled.get(tree1, key1).unwrap().unwrap(), value2);
+
+        sled.insert(tree2, key1, value1).unwrap();
+        assert_eq!(sled.get(tree2, key1).unwrap().unwrap(), value1);
+        sled.insert(tree2, key1, value2).unwrap();
+        assert_eq!(sled.get(tree2, key1).unwrap().unwrap(), value2);
+
+        sled.remove(tree1, key1).unwrap();
+        assert_eq!(sled.get(tree1, key1).unwrap(), None);
+        sled.remove(tree2, key1).unwrap();
+        assert_eq!(sled.get(tree2, key1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_apply_batch() {
+        let sled = SledEngine::new_tmp();
+        sled.insert("tree", b"k1", b"v1").unwrap();
+        let mut batch = Batch::default();
+        batch.insert(&b"k2"[..], &b"v2"[..]);
+        batch.remove(&b"k1"[..]);
+        sled.apply_batch("tree", batch).unwrap();
+        assert_eq!(sled.get("tree", b"k1").unwrap(), None);
+        assert_eq!(sled.get("tree", b"k2").unwrap().unwrap(), b"v2");
+    }
+
+    #[test]
+    fn test_insert_if_absent() {
+        let sled = SledEngine::new_tmp();
+        let key = "key".as_bytes();
+        assert_eq!(
+            sled.insert_if_absent("tree", key, "v1".as_bytes()).unwrap(),
+            None
+        );
+        assert_eq!(
+            sled.insert_if_absent("tree", key, "v2".as_bytes())
+                .unwrap()
+                .unwrap(),
+            "v1".as_bytes()
+        );
+        assert_eq!(sled.get("tree", key).unwrap().unwrap(), "v1".as_bytes());
+    }
+
+    #[test]
+    fn test_namespace() {
+        let sled = SledEngine::new_tmp();
+        let ns1 = sled.with_namespace("ns1");
+        let ns2 = sled.with_namespace("ns2");
+        let key = "key".as_bytes();
+
+        ns1.insert("tree", key, "v1".as_bytes()).unwrap();
+        assert_eq!(ns1.get("tree", key).unwrap().unwrap(), "v1".as_bytes());
+        assert_eq!(ns2.get("tree", key).unwrap(), None);
+        assert_eq!(sled.get("tree", key).unwrap(), None);
 
-        sled.insert(tree2, key1, value1);
-        assert_eq!(sled.get(tree2, key1).unwrap(), value1);
-        sled.insert(tree2, key1, value2);
-        assert_eq!(sled.get(tree2, key1).unwrap(), value2);
+        ns2.insert("tree", key, "v2".as_bytes()).unwrap();
+        ns1.drop_namespace().unwrap();
+        assert_eq!(ns1.get("tree", key).unwrap(), None);
+        assert_eq!(ns2.get("tree", key).unwrap().unwrap(), "v2".as_bytes());
 
-        sled.remove(tree1, key1);
-        assert_eq!(sled.get(tree1, key1), None);
-        sled.remove(tree2, key1);
-        assert_eq!(sled.get(tree2, key1), None);
+        // the trees out of any namespace stay
+        assert!(sled.drop_namespace().is_err());
+        assert_eq!(ns2.get("tree", key).unwrap().unwrap(), "v2".as_bytes());
+    }
+
+    #[test]
+    fn test_open_invalid_path() {
+        let file = std::env::temp_dir().join("angelina_sled_engine_not_a_dir");
+        std::fs::write(&file, "").unwrap();
+        // a db under a regular file can not be created
+        let path = file.join("db");
+        assert!(SledEngine::new(path.to_str().unwrap()).is_err());
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_flush_and_reopen() {
+        let path = std::env::temp_dir().join(format!("angelina_flush_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_dir_all(path);
+        {
+            let sled = SledEngine::with_config(path, None, 1024 * 1024).unwrap();
+            sled.insert("tree", b"key", b"value").unwrap();
+            sled.flush().unwrap();
+        }
+        let sled = SledEngine::new(path).unwrap();
+        assert_eq!(sled.get("tree", b"key").unwrap().unwrap(), b"value");
+        drop(sled);
+        std::fs::remove_dir_all(path).unwrap();
     }
 
     #[test]
     fn test_increment() {
         let sled = SledEngine::new_tmp();
-        let i = sled.increment("tree1", "11");
+        let i = sled.increment("tree1", "11").unwrap();
         assert_eq!(i, 0);
-        let i = sled.increment("tree1", "11");
+        let i = sled.increment("tree1", "11").unwrap();
         assert_eq!(i, 1);
-        let i = sled.increment("tree1", "11");
+        let i = sled.increment("tree1", "11").unwrap();
         assert_eq!(i, 2);
+
+        sled.insert("tree1", b"12", &[1, 2, 3]).unwrap();
+        assert_eq!(
+            sled.increment("tree1", "12").unwrap_err().to_string(),
+            "StorageError: corrupt data: expected a number of 8 bytes, found 3"
+        );
+        assert_eq!(sled.get("tree1", b"12").unwrap(), Some(vec![1, 2, 3]));
     }
 }
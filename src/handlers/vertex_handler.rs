@@ -1,40 +1,106 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{BufRead, BufReader, Read};
 use std::rc::Rc;
 
-use crate::datamodel::property::Properties;
+use sled::Batch;
+
+use crate::datamodel::base::{ElementType, PropertyKeyId, VertexLabelId};
+use crate::datamodel::buffer::Buffer;
+use crate::datamodel::property::{Properties, PropertyValue};
 use crate::datamodel::property_key::PropertyKey;
 use crate::datamodel::vertex::Vertex;
-use crate::handlers::sled_engine::SledEngine;
+use crate::execution::eval::{join_key, property_to_value, Collation};
+use crate::execution::statistics::Histogram;
+use crate::handlers::chunked_scan::{CancellationToken, ScanError};
+use crate::handlers::sled_engine::{SledEngine, StorageError};
+use crate::handlers::write_observer::WriteObserver;
 
 static VERTEX_TABLE_NAME: &str = "VERTEX";
+/// `property_key_id | value | vertex_id` of every vertex property. the value is its
+/// binary collation join key, so that values equal under `=` share an entry.
+static VERTEX_PROP_INDEX_TABLE_NAME: &str = "VERTEX_PROP_INDEX";
+/// rows of a csv import written per batch
+static IMPORT_BATCH_ROWS: usize = 1000;
 
+#[derive(Clone)]
 pub struct VertexHandler {
-    pub(crate) engine: Rc<Box<SledEngine>>,
+    pub(crate) engine: Rc<SledEngine>,
+    pub(crate) observer: Option<Rc<dyn WriteObserver>>,
 }
 
 impl VertexHandler {
-    pub fn create_vertex(&self, id: &str, label: u64) -> Vertex {
+    pub fn create_vertex(&self, id: &str, label: VertexLabelId) -> Result<Vertex, StorageError> {
         let vertex = Vertex {
             id: id.to_string(),
             label,
             properties: Properties { data: Vec::new() },
         };
+        let before = self.get_vertex(id)?;
         let (key, value) = vertex.serialize();
-        self.engine.insert(VERTEX_TABLE_NAME, &key, &value);
-        vertex
+        self.engine.insert(VERTEX_TABLE_NAME, &key, &value)?;
+        self.reindex(before.as_ref(), Some(&vertex))?;
+        self.notify(before.as_ref(), Some(&vertex));
+        Ok(vertex)
+    }
+
+    /// ensure a vertex exists. returns the vertex and whether it was created.
+    /// an existing vertex is returned untouched, even if its label differs.
+    pub fn get_or_create(
+        &self,
+        id: &str,
+        label: VertexLabelId,
+    ) -> Result<(Vertex, bool), StorageError> {
+        let vertex = Vertex {
+            id: id.to_string(),
+            label,
+            properties: Properties { data: Vec::new() },
+        };
+        let (key, value) = vertex.serialize();
+        match self
+            .engine
+            .insert_if_absent(VERTEX_TABLE_NAME, &key, &value)?
+        {
+            Some(current) => Ok((Vertex::deserialize_value(id, &current)?, false)),
+            None => {
+                self.notify(None, Some(&vertex));
+                Ok((vertex, true))
+            }
+        }
     }
 
-    pub fn remove_vertex(&self, id: &str) {
+    pub fn remove_vertex(&self, id: &str) -> Result<(), StorageError> {
+        let before = self.get_vertex(id)?;
         let key = Vertex::build_key(id);
-        self.engine.remove(VERTEX_TABLE_NAME, &key);
+        self.engine.remove(VERTEX_TABLE_NAME, &key)?;
+        self.reindex(before.as_ref(), None)?;
+        if before.is_some() {
+            self.notify(before.as_ref(), None);
+        }
+        Ok(())
     }
 
-    pub fn add_property(&self, vertex: &mut Vertex, property_key: &PropertyKey, value: &str) {
-        let prop_id = self.generate_next_prop_id(&vertex.id);
+    pub fn add_property(
+        &self,
+        vertex: &mut Vertex,
+        property_key: &PropertyKey,
+        value: &PropertyValue,
+    ) -> Result<(), StorageError> {
+        let before = vertex.clone();
+        if !vertex.properties.prepare_add(property_key, value) {
+            return Ok(());
+        }
+        let prop_id = self.generate_next_prop_id(&vertex.id)?;
         vertex
             .properties
             .add_property(property_key.id, prop_id, value);
         let (key, value) = vertex.serialize();
-        self.engine.insert(VERTEX_TABLE_NAME, &key, &value);
+        self.engine.insert(VERTEX_TABLE_NAME, &key, &value)?;
+        self.reindex(Some(&before), Some(vertex))?;
+        self.notify(Some(&before), Some(vertex));
+        Ok(())
     }
 
     pub fn remove_property(
@@ -42,79 +108,784 @@ impl VertexHandler {
         vertex: &mut Vertex,
         property_key: &PropertyKey,
         prop_id: Vec<u64>,
-    ) {
+    ) -> Result<(), StorageError> {
+        let before = vertex.clone();
         vertex.properties.remove_property(property_key.id, prop_id);
         let (key, value) = vertex.serialize();
-        self.engine.insert(VERTEX_TABLE_NAME, &key, &value);
+        self.engine.insert(VERTEX_TABLE_NAME, &key, &value)?;
+        self.reindex(Some(&before), Some(vertex))?;
+        self.notify(Some(&before), Some(vertex));
+        Ok(())
+    }
+
+    /// create a vertex of `label` per csv record of `reader`, quoted as in RFC 4180. the
+    /// first field is the id and the others the properties of `property_keys`, in order. an
+    /// empty field is no property, the others are ints, floats or bools if they parse as
+    /// such and strings otherwise. a vertex that already exists is replaced. the vertices
+    /// are written `IMPORT_BATCH_ROWS` at a time, so a failed import may have written the
+    /// batches before the failure. returns the number of records imported.
+    pub fn import_csv<R: Read>(
+        &self,
+        label: VertexLabelId,
+        property_keys: &[PropertyKeyId],
+        has_header: bool,
+        reader: R,
+    ) -> Result<usize, ImportError> {
+        let mut reader = BufReader::new(reader);
+        let mut line = 0;
+        if has_header {
+            read_csv_record(&mut reader, &mut line)?;
+        }
+        let mut imported = 0;
+        // vertices of the current batch in import order, with the vertex each replaces, and
+        // their positions by id
+        let mut batch: Vec<(Option<Vertex>, Vertex)> = vec![];
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        while let Some(fields) = read_csv_record(&mut reader, &mut line)? {
+            if fields.len() == 1 && fields[0].is_empty() {
+                continue;
+            }
+            if fields[0].is_empty() {
+                return Err(ImportError::Csv(line, "empty vertex id".to_owned()));
+            }
+            if fields.len() > property_keys.len() + 1 {
+                return Err(ImportError::Csv(
+                    line,
+                    format!("expect at most {} fields", property_keys.len() + 1),
+                ));
+            }
+            let mut fields = fields.into_iter();
+            let id = fields.next().unwrap();
+            let mut vertex = Vertex {
+                id: id.clone(),
+                label,
+                properties: Properties { data: Vec::new() },
+            };
+            for (prop_id, (key, field)) in property_keys
+                .iter()
+                .zip(fields)
+                .filter(|(_, field)| !field.is_empty())
+                .enumerate()
+            {
+                vertex
+                    .properties
+                    .add_property(*key, prop_id as u64, &parse_csv_value(field));
+            }
+            // a vertex imported twice in a batch replaces what was stored before both
+            match positions.get(&id) {
+                Some(&position) => batch[position].1 = vertex,
+                None => {
+                    let before = self.get_vertex(&id)?;
+                    positions.insert(id, batch.len());
+                    batch.push((before, vertex));
+                }
+            }
+            imported += 1;
+            if batch.len() == IMPORT_BATCH_ROWS {
+                positions.clear();
+                self.write_import_batch(std::mem::take(&mut batch))?;
+            }
+        }
+        self.write_import_batch(batch)?;
+        Ok(imported)
+    }
+
+    /// write the (before, after) vertices of an import with a batch per tree.
+    fn write_import_batch(
+        &self,
+        vertices: Vec<(Option<Vertex>, Vertex)>,
+    ) -> Result<(), StorageError> {
+        let mut batch = Batch::default();
+        let mut index = Batch::default();
+        for (before, vertex) in &vertices {
+            let (key, value) = vertex.serialize();
+            batch.insert(key, value);
+            // the next property id follows those of the import
+            let props = vertex.properties.get_properties().count() as u64;
+            if props > 0 {
+                batch.insert(
+                    Self::prop_id_key(&vertex.id).as_bytes(),
+                    &(props - 1).to_be_bytes(),
+                );
+            }
+            for key in before.as_ref().map(Self::index_keys).unwrap_or_default() {
+                index.remove(key);
+            }
+            for key in Self::index_keys(vertex) {
+                index.insert(key, &[]);
+            }
+        }
+        self.engine.apply_batch(VERTEX_TABLE_NAME, batch)?;
+        self.engine
+            .apply_batch(VERTEX_PROP_INDEX_TABLE_NAME, index)?;
+        for (before, vertex) in &vertices {
+            self.notify(before.as_ref(), Some(vertex));
+        }
+        Ok(())
     }
 
-    pub fn get_vertex(&self, id: &str) -> Option<Vertex> {
+    pub fn get_vertex(&self, id: &str) -> Result<Option<Vertex>, StorageError> {
         let key = Vertex::build_key(id);
-        match self.engine.get(VERTEX_TABLE_NAME, &key) {
-            Some(value) => Some(Vertex::deserialize_value(id, &value)),
-            None => None,
+        match self.engine.get(VERTEX_TABLE_NAME, &key)? {
+            Some(value) => Ok(Some(Vertex::deserialize_value(id, &value)?)),
+            None => Ok(None),
         }
     }
 
-    pub fn iter_all_vertices(&self) -> Box<dyn Iterator<Item = Vertex>> {
-        Box::new(
+    pub fn iter_all_vertices(
+        &self,
+    ) -> Result<Box<dyn Iterator<Item = Result<Vertex, StorageError>>>, StorageError> {
+        Ok(Box::new(
             self.engine
-                .open_tree(VERTEX_TABLE_NAME)
-                .scan_prefix(&[])
+                .scan(VERTEX_TABLE_NAME, &[ElementType::Vertex as u8], None)?
                 .map(|res| {
+                    // never cancelled without a token
                     let (key, value) = res.unwrap();
-                    Vertex::deserialize(&key, &value)
+                    Ok(Vertex::deserialize(&key, &value)?)
+                }),
+        ))
+    }
+
+    /// all vertices, read lazily in chunks. stops with `ScanError::Cancelled` once `token`
+    /// is, and with the error of a vertex that can't be deserialized.
+    pub fn scan_vertices(
+        &self,
+        token: Option<CancellationToken>,
+    ) -> Result<impl Iterator<Item = Result<Vertex, ScanError>>, StorageError> {
+        Ok(self
+            .engine
+            .scan(VERTEX_TABLE_NAME, &[ElementType::Vertex as u8], token)?
+            .map(|res| {
+                let (key, value) = res?;
+                Ok(Vertex::deserialize(&key, &value)?)
+            }))
+    }
+
+    /// vertices with an id within `low..=high`, read lazily in chunks like `scan_vertices`.
+    /// ids are strings, so they are compared byte by byte like under the binary collation,
+    /// e.g. `'id10' < 'id9'`. an open bound is unbounded.
+    pub fn range_vertices(
+        &self,
+        low: Option<&str>,
+        high: Option<&str>,
+        token: Option<CancellationToken>,
+    ) -> Result<impl Iterator<Item = Result<Vertex, ScanError>>, StorageError> {
+        // escaped ids sort like the ids and none is a prefix of another, so the keys of
+        // the bounds delimit the keys of the range
+        let (low, high) = (low.map(Vertex::build_key), high.map(Vertex::build_key));
+        Ok(self
+            .engine
+            .scan(VERTEX_TABLE_NAME, &[ElementType::Vertex as u8], token)?
+            .with_range(low.as_deref(), high.as_deref())
+            .map(|res| {
+                let (key, value) = res?;
+                Ok(Vertex::deserialize(&key, &value)?)
+            }))
+    }
+
+    /// ids of the vertices with a property of `key_id` equal to `value`.
+    pub fn lookup_by_property(
+        &self,
+        key_id: PropertyKeyId,
+        value: &PropertyValue,
+    ) -> Result<Vec<String>, StorageError> {
+        let prefix = Self::index_prefix(key_id, value);
+        self.engine
+            .open_tree(VERTEX_PROP_INDEX_TABLE_NAME)?
+            .scan_prefix(&prefix)
+            .keys()
+            .map(|key| Ok(Buffer::from(&key?[prefix.len()..]).try_get_remaining_utf8()?))
+            .collect()
+    }
+
+    /// ids of the vertices with a numeric property of `key_id` within `low..=high`. an open
+    /// bound is unbounded. reads every index entry of the key.
+    pub fn range_by_property(
+        &self,
+        key_id: PropertyKeyId,
+        low: Option<f64>,
+        high: Option<f64>,
+    ) -> Result<Vec<String>, StorageError> {
+        self.numeric_index_entries(key_id)?
+            .filter(|entry| {
+                entry.as_ref().map_or(true, |(value, _)| {
+                    low.map_or(true, |low| *value >= low)
+                        && high.map_or(true, |high| *value <= high)
                 })
-                .into_iter(),
-        )
+            })
+            .map(|entry| entry.map(|(_, id)| id))
+            .collect()
+    }
+
+    /// equi-depth histogram of the numeric values of the properties of `key_id`.
+    pub fn property_histogram(
+        &self,
+        key_id: PropertyKeyId,
+        buckets: usize,
+    ) -> Result<Histogram, StorageError> {
+        let values = self
+            .numeric_index_entries(key_id)?
+            .map(|entry| entry.map(|(value, _)| value))
+            .collect::<Result<_, _>>()?;
+        Ok(Histogram::build(values, buckets))
+    }
+
+    /// (value, vertex id) of the index entries of `key_id` holding a number.
+    fn numeric_index_entries(
+        &self,
+        key_id: PropertyKeyId,
+    ) -> Result<impl Iterator<Item = Result<(f64, String), StorageError>>, StorageError> {
+        let mut prefix = Buffer::new();
+        prefix.put_u64(key_id.0);
+        Ok(self
+            .engine
+            .open_tree(VERTEX_PROP_INDEX_TABLE_NAME)?
+            .scan_prefix(prefix.bytes())
+            .keys()
+            .map(|key| Self::numeric_index_entry(&key?))
+            .filter_map(Result::transpose))
+    }
+
+    /// the entry of an index `key` past its key id, if its value is a number.
+    fn numeric_index_entry(key: &[u8]) -> Result<Option<(f64, String)>, StorageError> {
+        let mut buf = Buffer::from(&key[8..]);
+        let value = buf.try_get_string_utf8()?;
+        let id = buf.try_get_remaining_utf8()?;
+        Ok(value
+            .strip_prefix('n')
+            .and_then(|number| number.parse().ok())
+            .map(|number| (number, id)))
+    }
+
+    fn index_prefix(key_id: PropertyKeyId, value: &PropertyValue) -> Vec<u8> {
+        let mut buf = Buffer::new();
+        buf.put_u64(key_id.0);
+        // only NULL has no join key, and properties are never NULL
+        buf.put_string(&join_key(&property_to_value(value.clone()), Collation::Binary).unwrap());
+        buf.to_vec()
     }
 
-    fn generate_next_prop_id(&self, vertex_id: &str) -> u64 {
-        let auto_increment_key = format!("VERTEX_PROP_AUTO_INCREMENT_ID_{}", vertex_id);
+    fn index_keys(vertex: &Vertex) -> Vec<Vec<u8>> {
+        vertex
+            .properties
+            .get_properties()
+            .map(|p| {
+                let mut key = Self::index_prefix(p.key, &p.value);
+                key.extend_from_slice(vertex.id.as_bytes());
+                key
+            })
+            .collect()
+    }
+
+    /// replace the index entries of `before` with those of `after`.
+    fn reindex(&self, before: Option<&Vertex>, after: Option<&Vertex>) -> Result<(), StorageError> {
+        for key in before.map(Self::index_keys).unwrap_or_default() {
+            self.engine.remove(VERTEX_PROP_INDEX_TABLE_NAME, &key)?;
+        }
+        for key in after.map(Self::index_keys).unwrap_or_default() {
+            self.engine
+                .insert(VERTEX_PROP_INDEX_TABLE_NAME, &key, &[])?;
+        }
+        Ok(())
+    }
+
+    fn notify(&self, before: Option<&Vertex>, after: Option<&Vertex>) {
+        if let Some(observer) = &self.observer {
+            observer.on_vertex_write(before, after);
+        }
+    }
+
+    fn generate_next_prop_id(&self, vertex_id: &str) -> Result<u64, StorageError> {
         self.engine
-            .increment(VERTEX_TABLE_NAME, &auto_increment_key)
+            .increment(VERTEX_TABLE_NAME, &Self::prop_id_key(vertex_id))
+    }
+
+    fn prop_id_key(vertex_id: &str) -> String {
+        format!("VERTEX_PROP_AUTO_INCREMENT_ID_{}", vertex_id)
+    }
+}
+
+/// the fields of the next csv record of `reader`, or None at its end. a quoted field may
+/// span lines, which are counted in `line`.
+fn read_csv_record<R: BufRead>(
+    reader: &mut R,
+    line: &mut usize,
+) -> Result<Option<Vec<String>>, ImportError> {
+    let mut record = String::new();
+    loop {
+        if reader.read_line(&mut record)? == 0 {
+            if record.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+        *line += 1;
+        // an odd number of quotes leaves a quoted field open
+        if record.matches('"').count() % 2 == 0 {
+            break;
+        }
+    }
+    if record.ends_with('\n') {
+        record.pop();
+        if record.ends_with('\r') {
+            record.pop();
+        }
+    }
+
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(ImportError::Csv(
+            *line,
+            "unterminated quoted field".to_owned(),
+        ));
+    }
+    fields.push(field);
+    Ok(Some(fields))
+}
+
+fn parse_csv_value(field: String) -> PropertyValue {
+    if let Ok(i) = field.parse() {
+        PropertyValue::Int(i)
+    } else if let Ok(x) = field.parse() {
+        PropertyValue::Float(x)
+    } else if let Ok(b) = field.parse() {
+        PropertyValue::Bool(b)
+    } else {
+        PropertyValue::String(field)
+    }
+}
+
+/// an error of `VertexHandler::import_csv`.
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    /// (line, message) of a malformed record
+    Csv(usize, String),
+    Storage(StorageError),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "ImportError: {}", e),
+            ImportError::Csv(line, msg) => write!(f, "ImportError: {} at line {}", msg, line),
+            ImportError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<StorageError> for ImportError {
+    fn from(e: StorageError) -> Self {
+        ImportError::Storage(e)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::cell::RefCell;
+
     use crate::datamodel::base::Cardinality;
 
     use super::*;
 
     #[test]
     fn test_vertex_crud() {
-        let engine = Rc::new(Box::new(SledEngine::new_tmp()));
+        let engine = Rc::new(SledEngine::new_tmp());
 
-        let handler = VertexHandler { engine };
+        let handler = VertexHandler {
+            engine,
+            observer: None,
+        };
 
         let key = PropertyKey {
-            id: 1,
+            id: PropertyKeyId(1),
             name: "aa".to_string(),
-            cardinality: Cardinality::Single,
+            cardinality: Cardinality::List,
+            default_value: None,
         };
 
-        let mut v = handler.create_vertex("xx_1", 1);
-        handler.add_property(&mut v, &key, "test1");
-        handler.add_property(&mut v, &key, "test2");
-        let v2 = handler.get_vertex(&v.id).unwrap();
+        let mut v = handler.create_vertex("xx_1", VertexLabelId(1)).unwrap();
+        handler.add_property(&mut v, &key, &"test1".into()).unwrap();
+        handler.add_property(&mut v, &key, &"test2".into()).unwrap();
+        let v2 = handler.get_vertex(&v.id).unwrap().unwrap();
         assert_eq!(
             v2.properties
                 .get_properties()
-                .map(|x| { x.value })
+                .map(|x| { x.value.to_string() })
                 .collect::<Vec<String>>(),
             vec!["test1", "test2"]
         );
         assert_eq!(v2.id, v.id);
 
-        handler.remove_property(&mut v, &key, vec![]);
-        let v2 = handler.get_vertex(&v.id).unwrap();
+        handler.remove_property(&mut v, &key, vec![]).unwrap();
+        let v2 = handler.get_vertex(&v.id).unwrap().unwrap();
+        assert_eq!(v2.properties.get_properties().count(), 0);
+
+        handler.remove_vertex(&v.id).unwrap();
+        assert_eq!(handler.get_vertex(&v.id).unwrap(), None);
+
+        // ids with the string terminator scan back whole
+        handler.create_vertex("a\0b", VertexLabelId(1)).unwrap();
+        handler.create_vertex("a", VertexLabelId(1)).unwrap();
+        assert_eq!(
+            handler
+                .scan_vertices(None)
+                .unwrap()
+                .map(|v| v.unwrap().id)
+                .collect::<Vec<_>>(),
+            vec!["a", "a\0b"]
+        );
+    }
+
+    #[test]
+    fn test_get_corrupt_vertex() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = VertexHandler {
+            engine: engine.clone(),
+            observer: None,
+        };
+        // shorter than a label id
+        engine
+            .insert(VERTEX_TABLE_NAME, &Vertex::build_key("v1"), &[0, 1])
+            .unwrap();
+        assert!(handler.get_vertex("v1").is_err());
+        assert!(handler.get_or_create("v1", VertexLabelId(1)).is_err());
+
+        // a label id, then a property cut off after its key id and value length
+        let mut value = Buffer::new();
+        value.put_u64(1);
+        value.put_u64(7);
+        value.put_u64(5);
+        engine
+            .insert(VERTEX_TABLE_NAME, &Vertex::build_key("v1"), value.bytes())
+            .unwrap();
+        assert!(handler.get_vertex("v1").is_err());
+        let scanned = handler.scan_vertices(None).unwrap().collect::<Vec<_>>();
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(
+            scanned[0].as_ref().unwrap_err().to_string(),
+            "StorageError: corrupt data: expected 8 more bytes, found 0"
+        );
+        assert!(handler
+            .range_vertices(Some("v1"), None, None)
+            .unwrap()
+            .all(|vertex| vertex.is_err()));
+    }
+
+    #[test]
+    fn test_cardinality() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = VertexHandler {
+            engine,
+            observer: None,
+        };
+        let key = |id, cardinality| PropertyKey {
+            id: PropertyKeyId(id),
+            name: "aa".to_string(),
+            cardinality,
+            default_value: None,
+        };
+        let single = key(1, Cardinality::Single);
+        let set = key(2, Cardinality::Set);
+
+        let mut v = handler.create_vertex("xx_1", VertexLabelId(1)).unwrap();
+        handler
+            .add_property(&mut v, &single, &"test1".into())
+            .unwrap();
+        handler
+            .add_property(&mut v, &single, &"test2".into())
+            .unwrap();
+        handler.add_property(&mut v, &set, &"test1".into()).unwrap();
+        handler.add_property(&mut v, &set, &"test2".into()).unwrap();
+        handler.add_property(&mut v, &set, &"test1".into()).unwrap();
+        let v2 = handler.get_vertex(&v.id).unwrap().unwrap();
+        let values = |key_id| {
+            v2.properties
+                .get_property(PropertyKeyId(key_id))
+                .map(|x| x.value.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(values(1), vec!["test2"]);
+        assert_eq!(values(2), vec!["test1", "test2"]);
+    }
+
+    #[test]
+    fn test_property_index() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = VertexHandler {
+            engine,
+            observer: None,
+        };
+        let key = |id, cardinality| PropertyKey {
+            id: PropertyKeyId(id),
+            name: "aa".to_string(),
+            cardinality,
+            default_value: None,
+        };
+        let list = key(1, Cardinality::List);
+        let single = key(2, Cardinality::Single);
+
+        let mut v1 = handler.create_vertex("v1", VertexLabelId(1)).unwrap();
+        let mut v2 = handler.create_vertex("v2", VertexLabelId(1)).unwrap();
+        handler.add_property(&mut v1, &list, &"x".into()).unwrap();
+        handler.add_property(&mut v1, &list, &"y".into()).unwrap();
+        handler.add_property(&mut v2, &list, &"x".into()).unwrap();
+        handler
+            .add_property(&mut v2, &single, &PropertyValue::Int(9))
+            .unwrap();
+        assert_eq!(
+            handler
+                .lookup_by_property(PropertyKeyId(1), &"x".into())
+                .unwrap(),
+            vec!["v1", "v2"]
+        );
+        assert!(handler
+            .lookup_by_property(PropertyKeyId(2), &"x".into())
+            .unwrap()
+            .is_empty());
+        // equal under `=`
+        assert_eq!(
+            handler
+                .lookup_by_property(PropertyKeyId(2), &"9".into())
+                .unwrap(),
+            vec!["v2"]
+        );
+
+        let y = v1.properties.get_properties().last().unwrap();
+        handler.remove_property(&mut v1, &list, vec![y.id]).unwrap();
+        assert!(handler
+            .lookup_by_property(PropertyKeyId(1), &"y".into())
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            handler
+                .lookup_by_property(PropertyKeyId(1), &"x".into())
+                .unwrap(),
+            vec!["v1", "v2"]
+        );
+
+        handler
+            .add_property(&mut v2, &single, &PropertyValue::Int(10))
+            .unwrap();
+        assert!(handler
+            .lookup_by_property(PropertyKeyId(2), &PropertyValue::Int(9))
+            .unwrap()
+            .is_empty());
+        handler.remove_property(&mut v1, &list, vec![]).unwrap();
+        assert_eq!(
+            handler
+                .lookup_by_property(PropertyKeyId(1), &"x".into())
+                .unwrap(),
+            vec!["v2"]
+        );
+        handler.remove_vertex("v2").unwrap();
+        assert!(handler
+            .lookup_by_property(PropertyKeyId(1), &"x".into())
+            .unwrap()
+            .is_empty());
+        assert!(handler
+            .lookup_by_property(PropertyKeyId(2), &PropertyValue::Int(10))
+            .unwrap()
+            .is_empty());
+
+        // an index entry whose vertex id is not utf8
+        let mut key = VertexHandler::index_prefix(PropertyKeyId(2), &PropertyValue::Int(11));
+        key.push(0xff);
+        handler
+            .engine
+            .insert(VERTEX_PROP_INDEX_TABLE_NAME, &key, &[])
+            .unwrap();
+        assert!(handler
+            .lookup_by_property(PropertyKeyId(2), &PropertyValue::Int(11))
+            .is_err());
+        assert!(handler
+            .range_by_property(PropertyKeyId(2), None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_or_create() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = VertexHandler {
+            engine,
+            observer: None,
+        };
+        let key = PropertyKey {
+            id: PropertyKeyId(1),
+            name: "aa".to_string(),
+            cardinality: Cardinality::Single,
+            default_value: None,
+        };
+
+        let (mut v, created) = handler.get_or_create("xx_1", VertexLabelId(1)).unwrap();
+        assert!(created);
+        handler.add_property(&mut v, &key, &"test1".into()).unwrap();
+
+        let (v2, created) = handler.get_or_create("xx_1", VertexLabelId(1)).unwrap();
+        assert!(!created);
+        assert_eq!(v2, v);
+        assert_eq!(
+            v2.properties
+                .get_properties()
+                .map(|x| { x.value.to_string() })
+                .collect::<Vec<String>>(),
+            vec!["test1"]
+        );
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: RefCell<Vec<(Option<String>, Option<String>)>>,
+    }
+
+    impl WriteObserver for RecordingObserver {
+        fn on_vertex_write(&self, before: Option<&Vertex>, after: Option<&Vertex>) {
+            self.events.borrow_mut().push((
+                before.map(|v| v.id.to_owned()),
+                after.map(|v| v.id.to_owned()),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_write_observer() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let observer = Rc::new(RecordingObserver::default());
+        let handler = VertexHandler {
+            engine,
+            observer: Some(observer.clone()),
+        };
+
+        handler.create_vertex("xx_1", VertexLabelId(1)).unwrap();
+        handler.remove_vertex("xx_1").unwrap();
+        handler.remove_vertex("xx_2").unwrap();
+        assert_eq!(
+            *observer.events.borrow(),
+            vec![
+                (None, Some("xx_1".to_string())),
+                (Some("xx_1".to_string()), None)
+            ]
+        );
+
+        // the vertices of an import are notified in the order of the records
+        observer.events.borrow_mut().clear();
+        let ids = (0..20).map(|i| format!("v{}", i)).collect::<Vec<_>>();
+        handler
+            .import_csv(VertexLabelId(1), &[], false, ids.join("\n").as_bytes())
+            .unwrap();
         assert_eq!(
-            v2.properties.get_properties().map(|x| { x.value }).count(),
-            0
+            *observer.events.borrow(),
+            ids.into_iter()
+                .map(|id| (None, Some(id)))
+                .collect::<Vec<_>>()
         );
+    }
 
-        handler.remove_vertex(&v.id);
-        assert_eq!(handler.get_vertex(&v.id), None);
+    #[test]
+    fn test_import_csv() {
+        let engine = Rc::new(SledEngine::new_tmp());
+        let handler = VertexHandler {
+            engine,
+            observer: None,
+        };
+        let name = PropertyKey {
+            id: PropertyKeyId(1),
+            name: "name".to_string(),
+            cardinality: Cardinality::List,
+            default_value: None,
+        };
+        let keys = [PropertyKeyId(1), PropertyKeyId(2)];
+        let csv = concat!(
+            "id,name,age\r\n",
+            "v1,tom,12\r\n",
+            "v2,\"jerry, \"\"the\"\" mouse\",\r\n",
+            "\r\n",
+            "v3,\"two\nlines\",1.5\n",
+            "v1,tommy,13",
+        );
+        assert_eq!(
+            handler
+                .import_csv(VertexLabelId(1), &keys, true, csv.as_bytes())
+                .unwrap(),
+            4
+        );
+        let properties = |id| {
+            let v = handler.get_vertex(id).unwrap().unwrap();
+            assert_eq!(v.label, VertexLabelId(1));
+            v.properties
+                .get_properties()
+                .map(|p| (p.key.0, p.value))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            properties("v1"),
+            vec![(1, "tommy".into()), (2, PropertyValue::Int(13))]
+        );
+        assert_eq!(properties("v2"), vec![(1, "jerry, \"the\" mouse".into())]);
+        assert_eq!(
+            properties("v3"),
+            vec![(1, "two\nlines".into()), (2, PropertyValue::Float(1.5))]
+        );
+        // the replaced values of v1 are not indexed
+        assert!(handler
+            .lookup_by_property(PropertyKeyId(1), &"tom".into())
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            handler
+                .lookup_by_property(PropertyKeyId(1), &"tommy".into())
+                .unwrap(),
+            vec!["v1"]
+        );
+
+        // new properties don't reuse the ids of the imported ones
+        let mut v1 = handler.get_vertex("v1").unwrap().unwrap();
+        handler.add_property(&mut v1, &name, &"tc".into()).unwrap();
+        let ids = v1
+            .properties
+            .get_properties()
+            .map(|p| p.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![0, 1, 2]);
+
+        let err = |csv: &str| {
+            handler
+                .import_csv(VertexLabelId(1), &keys, false, csv.as_bytes())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("v4,a,1\nv5,a,1,x"),
+            "ImportError: expect at most 3 fields at line 2"
+        );
+        assert_eq!(err(",a"), "ImportError: empty vertex id at line 1");
+        assert_eq!(
+            err("v6,\"a\n"),
+            "ImportError: unterminated quoted field at line 1"
+        );
     }
 }
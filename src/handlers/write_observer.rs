@@ -0,0 +1,12 @@
+use crate::datamodel::edge::Edge;
+use crate::datamodel::vertex::Vertex;
+
+/// callbacks invoked by the handlers after a successful write, e.g. for change-data-capture.
+///   - insert: `before` is `None`
+///   - delete: `after` is `None`
+///   - update: both are set
+pub trait WriteObserver {
+    fn on_vertex_write(&self, _before: Option<&Vertex>, _after: Option<&Vertex>) {}
+
+    fn on_edge_write(&self, _before: Option<&Edge>, _after: Option<&Edge>) {}
+}
@@ -2,7 +2,10 @@ extern crate strum;
 #[macro_use]
 extern crate strum_macros;
 
-mod datamodel;
-mod execution;
-mod handlers;
-mod parser;
+pub mod database;
+pub mod datamodel;
+pub mod execution;
+pub mod handlers;
+pub mod parser;
+#[cfg(test)]
+mod testing;
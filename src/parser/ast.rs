@@ -1,15 +1,23 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 
 use crate::parser::operator::{BinaryOperator, UnaryOperator};
+use crate::parser::parser::BindingPower;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Statement {
     /// SHOW SCHEMA
     ShowVertexLabels,
     ShowEdgeLabels,
     ShowPropertyKeys,
+    /// SHOW CREATE VERTEX LABEL name
+    ShowCreateVertexLabel {
+        name: String,
+    },
+    /// SHOW CREATE EDGE LABEL name
+    ShowCreateEdgeLabel {
+        name: String,
+    },
     /// CREATE SCHEMA
     CreateGraph {
         name: String,
@@ -24,6 +32,11 @@ pub enum Statement {
     CreatePropertyKey {
         name: String,
         cardinality: String,
+        default_value: Option<Expr>,
+    },
+    /// vertex labels, edge labels and property keys created all or nothing
+    CreateSchema {
+        statements: Vec<Statement>,
     },
     /// DROP SCHEMA
     DropGraph {
@@ -38,6 +51,10 @@ pub enum Statement {
     DropPropertyKey {
         name: String,
     },
+    /// USE name, switching the active graph
+    UseGraph {
+        name: String,
+    },
     /// INSERT
     InsertVertex {
         label: Expr,
@@ -52,60 +69,102 @@ pub enum Statement {
         dst_vertex_id: Expr,
         values: Vec<Expr>,
     },
+    /// INSERT EDGE label BETWEEN [ALL] (a WHERE ...) AND (b WHERE ...)
+    InsertEdgeBetween {
+        label: Expr,
+        src: VertexMatch,
+        dst: VertexMatch,
+        /// connect every matched pair instead of requiring exactly one match per side
+        cartesian: bool,
+        properties: Vec<String>,
+        values: Vec<Expr>,
+    },
     /// UPDATE
     Update {
         operation: Vec<PropertyUpdateOp>,
     },
-    /// DELETE
+    /// DELETE (a), [e] FROM (a)-[e]->(b) WHERE ...
     Delete {
         elements: Vec<Expr>,
+        graph_pattern: GraphPattern,
+        condition: Option<Expr>,
     },
     /// Select
     Select {
         items: Vec<Expr>,
+        /// `AS` name of each of `items`
+        aliases: Vec<Option<String>>,
         graph_pattern: GraphPattern,
         condition: Option<Expr>,
+        group_by: Vec<Expr>,
+        /// ORDER BY k1 [ASC | DESC], k2 ... ties on a key are ordered by the next one
+        order_by: Vec<OrderByExpr>,
+        /// SAMPLE n. read at most n matches of the graph pattern, which WHERE then filters
+        sample: Option<u64>,
+        /// LIMIT n [OFFSET m]
+        limit: Option<u64>,
+        offset: Option<u64>,
+    },
+    /// EXPLAIN SELECT ...
+    Explain {
+        statement: Box<Statement>,
     },
 }
 
-#[derive(Debug)]
+/// ORDER BY expr [ASC | DESC] [NULLS FIRST | NULLS LAST]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderByExpr {
+    pub expr: Expr,
+    pub asc: bool,
+    pub nulls_first: Option<bool>,
+}
+
+impl OrderByExpr {
+    /// nulls sort as if they were larger than any value when not specified,
+    /// which puts them last for ASC and first for DESC.
+    pub fn nulls_first(&self) -> bool {
+        self.nulls_first.unwrap_or(!self.asc)
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub enum PropertyUpdateOp {
     Update { property: Expr, value: Expr },
     Delete { property: Expr },
 }
 
-#[derive(Debug)]
+/// prefix of the names generated for the anonymous elements of a pattern, `()` and `[]`.
+pub(crate) const ANONYMOUS_PREFIX: &str = "__anon_";
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct GraphPattern {
     pub(crate) triplets: Vec<GraphTriplet>,
+    /// every vertex of the pattern in order of appearance, including the ones out of any triplet
+    pub(crate) vertices: Vec<Expr>,
+    /// (element name, label) of the inline labels, `(a:person)` and `[e:knows]`
+    pub(crate) labels: Vec<(String, String)>,
+}
+
+/// (a WHERE a.name = 'x')
+#[derive(Debug, PartialEq)]
+pub struct VertexMatch {
+    pub name: String,
+    pub condition: Option<Expr>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct GraphTriplet {
     pub(crate) src: Box<Expr>,
     pub(crate) edge: Box<Expr>,
     pub(crate) dst: Box<Expr>,
+    /// (min, max) number of edges between src and dst, `[e*1..3]`. (1, 1) for `[e]`
+    pub(crate) hops: (u32, u32),
+    /// `(a)-[e]-(b)`, matching the edges from src to dst and from dst to src
+    pub(crate) undirected: bool,
 }
 
-#[derive(Debug)]
-pub struct GraphPath {
-    head: VertexExpr,
-}
-
-#[derive(Debug)]
-pub struct VertexExpr {
-    pub(crate) value: Expr,
-    pub(crate) out_edge: Option<Rc<RefCell<EdgeExpr>>>,
-    pub(crate) in_edge: Option<Rc<RefCell<EdgeExpr>>>,
-}
-
-#[derive(Debug)]
-pub struct EdgeExpr {
-    pub(crate) value: Expr,
-    pub(crate) src_vertex: Option<Rc<RefCell<VertexExpr>>>,
-    pub(crate) dst_vertex: Option<Rc<RefCell<VertexExpr>>>,
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Expr {
     /// literals
     Value(Value),
@@ -132,16 +191,426 @@ pub enum Expr {
     },
     /// (a + b), (a AND b)
     Nested(Box<Expr>),
+    /// a.label IN ('person', 'dog')
+    InList { expr: Box<Expr>, list: Vec<Expr> },
+    /// a.prop1 IS NULL, a.prop1 IS NOT NULL
+    IsNull { expr: Box<Expr>, negated: bool },
     /// a.label
     LabelExpr(String),
     /// a.id
     IdExpr(String),
+    /// EXISTS((a)-[:knows]->()), whether the pattern matches with the elements bound outside
+    /// of it
+    Exists(Box<GraphPattern>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Number(String),
     String(String),
     Boolean(bool),
     Null,
 }
+
+/// `items` separated by `separator`.
+fn fmt_separated<T: Display>(f: &mut Formatter<'_>, items: &[T], separator: &str) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::ShowVertexLabels => write!(f, "SHOW VERTEX LABEL"),
+            Statement::ShowEdgeLabels => write!(f, "SHOW EDGE LABEL"),
+            Statement::ShowPropertyKeys => write!(f, "SHOW PROPERTY KEY"),
+            Statement::ShowCreateVertexLabel { name } => {
+                write!(f, "SHOW CREATE VERTEX LABEL {}", name)
+            }
+            Statement::ShowCreateEdgeLabel { name } => write!(f, "SHOW CREATE EDGE LABEL {}", name),
+            Statement::CreateGraph { name } => write!(f, "CREATE GRAPH {}", name),
+            Statement::CreateSchema { statements } => {
+                write!(f, "CREATE SCHEMA {{")?;
+                let mut section = None;
+                for (kind, element) in statements.iter().filter_map(Statement::schema_element) {
+                    if section == Some(kind) {
+                        write!(f, ", ")?;
+                    } else {
+                        if section.is_some() {
+                            write!(f, ");")?;
+                        }
+                        write!(f, " {}S (", kind)?;
+                        section = Some(kind);
+                    }
+                    write!(f, "{}", element)?;
+                }
+                if section.is_some() {
+                    write!(f, ")")?;
+                }
+                write!(f, " }}")
+            }
+            Statement::CreateVertexLabel { .. }
+            | Statement::CreateEdgeLabel { .. }
+            | Statement::CreatePropertyKey { .. } => {
+                let (kind, element) = self.schema_element().unwrap();
+                write!(f, "CREATE {} {}", kind, element)
+            }
+            Statement::DropGraph { name } => write!(f, "DROP GRAPH {}", name),
+            Statement::DropVertexLabel { name } => write!(f, "DROP VERTEX LABEL {}", name),
+            Statement::DropEdgeLabel { name } => write!(f, "DROP EDGE LABEL {}", name),
+            Statement::DropPropertyKey { name } => write!(f, "DROP PROPERTY KEY {}", name),
+            Statement::UseGraph { name } => write!(f, "USE {}", name),
+            Statement::InsertVertex {
+                label,
+                properties,
+                vertex_id,
+                values,
+            } => {
+                write!(f, "INSERT VERTEX {} PROPERTIES (", label)?;
+                fmt_separated(f, properties, ", ")?;
+                write!(f, ") VALUES ({}):(", vertex_id)?;
+                fmt_separated(f, values, ", ")?;
+                write!(f, ")")
+            }
+            Statement::InsertEdge {
+                label,
+                properties,
+                src_vertex_id,
+                dst_vertex_id,
+                values,
+            } => {
+                write!(f, "INSERT EDGE {} PROPERTIES (", label)?;
+                fmt_separated(f, properties, ", ")?;
+                write!(f, ") VALUES ({} -> {}):(", src_vertex_id, dst_vertex_id)?;
+                fmt_separated(f, values, ", ")?;
+                write!(f, ")")
+            }
+            Statement::InsertEdgeBetween {
+                label,
+                src,
+                dst,
+                cartesian,
+                properties,
+                values,
+            } => {
+                write!(f, "INSERT EDGE {} BETWEEN ", label)?;
+                if *cartesian {
+                    write!(f, "ALL ")?;
+                }
+                write!(f, "{} AND {}", src, dst)?;
+                if !properties.is_empty() {
+                    write!(f, " PROPERTIES (")?;
+                    fmt_separated(f, properties, ", ")?;
+                    write!(f, ") VALUES (")?;
+                    fmt_separated(f, values, ", ")?;
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Statement::Update { operation } => {
+                write!(f, "UPDATE ")?;
+                fmt_separated(f, operation, ", ")
+            }
+            Statement::Delete {
+                elements,
+                graph_pattern,
+                condition,
+            } => {
+                write!(f, "DELETE ")?;
+                fmt_separated(f, elements, ", ")?;
+                write!(f, " FROM {}", graph_pattern)?;
+                if let Some(condition) = condition {
+                    write!(f, " WHERE {}", condition)?;
+                }
+                Ok(())
+            }
+            Statement::Select {
+                items,
+                aliases,
+                graph_pattern,
+                condition,
+                group_by,
+                order_by,
+                sample,
+                limit,
+                offset,
+            } => {
+                write!(f, "SELECT ")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                    if let Some(Some(alias)) = aliases.get(i) {
+                        write!(f, " AS {}", alias)?;
+                    }
+                }
+                if !graph_pattern.vertices.is_empty() {
+                    write!(f, " FROM {}", graph_pattern)?;
+                }
+                if let Some(sample) = sample {
+                    write!(f, " SAMPLE {}", sample)?;
+                }
+                if let Some(condition) = condition {
+                    write!(f, " WHERE {}", condition)?;
+                }
+                if !group_by.is_empty() {
+                    write!(f, " GROUP BY ")?;
+                    fmt_separated(f, group_by, ", ")?;
+                }
+                if !order_by.is_empty() {
+                    write!(f, " ORDER BY ")?;
+                    fmt_separated(f, order_by, ", ")?;
+                }
+                if let Some(limit) = limit {
+                    write!(f, " LIMIT {}", limit)?;
+                }
+                if let Some(offset) = offset {
+                    write!(f, " OFFSET {}", offset)?;
+                }
+                Ok(())
+            }
+            Statement::Explain { statement } => write!(f, "EXPLAIN {}", statement),
+        }
+    }
+}
+
+impl Statement {
+    /// the kind and the definition of a vertex label, an edge label or a property key, as
+    /// written after `CREATE`.
+    fn schema_element(&self) -> Option<(&'static str, String)> {
+        match self {
+            Statement::CreateVertexLabel { name } => Some(("VERTEX LABEL", name.to_owned())),
+            Statement::CreateEdgeLabel { name, multiplicity } => {
+                Some(("EDGE LABEL", format!("({}, {})", name, multiplicity)))
+            }
+            Statement::CreatePropertyKey {
+                name,
+                cardinality,
+                default_value,
+            } => {
+                let default_value = match default_value {
+                    Some(value) => format!(", DEFAULT {}", value),
+                    None => String::new(),
+                };
+                Some((
+                    "PROPERTY KEY",
+                    format!("({}, {}{})", name, cardinality, default_value),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for OrderByExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)?;
+        if !self.asc {
+            write!(f, " DESC")?;
+        }
+        match self.nulls_first {
+            Some(true) => write!(f, " NULLS FIRST"),
+            Some(false) => write!(f, " NULLS LAST"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for PropertyUpdateOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyUpdateOp::Update { property, value } => write!(f, "{} = {}", property, value),
+            PropertyUpdateOp::Delete { property } => write!(f, "DELETE {}", property),
+        }
+    }
+}
+
+impl Display for VertexMatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "({}", self.name)?;
+        if let Some(condition) = &self.condition {
+            write!(f, " WHERE {}", condition)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// number of the anonymous element `expr`, in the order the parser named them.
+fn anonymous_index(expr: &Expr) -> Option<usize> {
+    match expr {
+        Expr::Identifier(name) => name.strip_prefix(ANONYMOUS_PREFIX)?.parse().ok(),
+        _ => None,
+    }
+}
+
+impl GraphPattern {
+    /// whether `triplet` joins `prev` to `vertex` in a chain, `(prev)-[e]->(vertex)` or
+    /// `(prev)<-[e]-(vertex)`. `forward` is set for the former.
+    fn chains(triplet: &GraphTriplet, prev: &Expr, vertex: &Expr) -> Option<bool> {
+        // the edge of a chain is named before the vertex after it
+        if let (Some(edge), Some(vertex)) =
+            (anonymous_index(&triplet.edge), anonymous_index(vertex))
+        {
+            if vertex < edge {
+                return None;
+            }
+        }
+        if *triplet.src == *prev && *triplet.dst == *vertex {
+            Some(true)
+        } else if !triplet.undirected && *triplet.src == *vertex && *triplet.dst == *prev {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// the name of `element` and its inline label, written at the first occurrence of the
+    /// element after the previous label so the labels are read back in the same order.
+    fn fmt_element(
+        &self,
+        f: &mut Formatter<'_>,
+        element: &Expr,
+        next_label: &mut usize,
+    ) -> fmt::Result {
+        let name = match element {
+            Expr::Identifier(name) => name,
+            element => return write!(f, "{}", element),
+        };
+        if !name.starts_with(ANONYMOUS_PREFIX) {
+            write!(f, "{}", name)?;
+        }
+        match self.labels.get(*next_label) {
+            Some((labeled, label)) if labeled == name => {
+                *next_label += 1;
+                write!(f, ":{}", label)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for GraphPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut next_label = 0;
+        let mut triplets = self.triplets.iter().peekable();
+        for (i, vertex) in self.vertices.iter().enumerate() {
+            let chain = match (i, triplets.peek()) {
+                (0, _) | (_, None) => None,
+                (_, Some(triplet)) => Self::chains(triplet, &self.vertices[i - 1], vertex)
+                    .map(|forward| (*triplet, forward)),
+            };
+            match chain {
+                Some((triplet, forward)) => {
+                    triplets.next();
+                    write!(f, "{}[", if forward { "-" } else { "<-" })?;
+                    self.fmt_element(f, &triplet.edge, &mut next_label)?;
+                    match triplet.hops {
+                        (1, 1) => {}
+                        (min, max) if min == max => write!(f, "*{}", min)?,
+                        (min, max) => write!(f, "*{}..{}", min, max)?,
+                    }
+                    let arrow = if forward && !triplet.undirected {
+                        "->"
+                    } else {
+                        "-"
+                    };
+                    write!(f, "]{}", arrow)?;
+                }
+                None if i > 0 => write!(f, ", ")?,
+                None => {}
+            }
+            write!(f, "(")?;
+            self.fmt_element(f, vertex, &mut next_label)?;
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl Expr {
+    /// how tight the operator of the expression binds, `None` if it is not an operation.
+    fn binding_power(&self) -> Option<BindingPower> {
+        match self {
+            Expr::UnaryOp { op, .. } => Some(op.get_binding_power()),
+            Expr::BinaryOp { op, .. } => Some(op.get_binding_power()),
+            Expr::InList { .. } | Expr::IsNull { .. } => Some(BindingPower::Compare),
+            _ => None,
+        }
+    }
+
+    /// `self` as an operand of an operator binding with `power`, in parentheses if it binds
+    /// looser, or as loose when it would otherwise be read as the other operand's.
+    fn fmt_operand(&self, f: &mut Formatter<'_>, power: BindingPower, tie: bool) -> fmt::Result {
+        match self.binding_power() {
+            Some(own) if own < power || (tie && own == power) => write!(f, "({})", self),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Value(value) => write!(f, "{}", value),
+            Expr::Identifier(name) => write!(f, "{}", name),
+            Expr::CompoundIdentifier(names) => write!(f, "{}", names.join(".")),
+            Expr::Wildcard => write!(f, "*"),
+            Expr::CompoundWildcard(names) => write!(f, "{}.*", names.join(".")),
+            Expr::Function {
+                func_name,
+                arguments,
+            } => {
+                write!(f, "{}(", func_name)?;
+                fmt_separated(f, arguments, ", ")?;
+                write!(f, ")")
+            }
+            Expr::UnaryOp { op, expr } => {
+                match op {
+                    UnaryOperator::Not => write!(f, "{} ", op)?,
+                    _ => write!(f, "{}", op)?,
+                }
+                // `-a + b` is `(-a) + b`, and `--` starts a comment
+                expr.fmt_operand(f, op.get_binding_power(), true)
+            }
+            Expr::BinaryOp { op, left, right } => {
+                // `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`, the other operators are left associative
+                let right_associative = *op == BinaryOperator::Power;
+                left.fmt_operand(f, op.get_binding_power(), right_associative)?;
+                write!(f, " {} ", op)?;
+                right.fmt_operand(f, op.get_binding_power(), !right_associative)
+            }
+            Expr::Nested(expr) => write!(f, "({})", expr),
+            Expr::InList { expr, list } => {
+                expr.fmt_operand(f, BindingPower::Compare, false)?;
+                write!(f, " IN (")?;
+                fmt_separated(f, list, ", ")?;
+                write!(f, ")")
+            }
+            Expr::IsNull { expr, negated } => {
+                expr.fmt_operand(f, BindingPower::Compare, false)?;
+                write!(f, " IS {}NULL", if *negated { "NOT " } else { "" })
+            }
+            Expr::LabelExpr(name) => write!(f, "{}.label", name),
+            Expr::IdExpr(name) => write!(f, "{}.id", name),
+            Expr::Exists(graph_pattern) => write!(f, "EXISTS({})", graph_pattern),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "'{}'", s.replace('\'', "\\'")),
+            Value::Boolean(true) => write!(f, "TRUE"),
+            Value::Boolean(false) => write!(f, "FALSE"),
+            Value::Null => write!(f, "NULL"),
+        }
+    }
+}
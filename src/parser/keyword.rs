@@ -1,6 +1,3 @@
-use std::str::FromStr;
-use std::string::ToString;
-
 use strum_macros::EnumString;
 
 #[derive(Display, EnumString, Debug, Clone, Eq, PartialEq)]
@@ -29,10 +26,36 @@ pub enum Keyword {
     OR,
     TRUE,
     FALSE,
+    ORDER,
+    BY,
+    ASC,
+    DESC,
+    NULLS,
+    FIRST,
+    LAST,
+    BETWEEN,
+    IN,
+    LIKE,
+    EXISTS,
+    ALL,
+    SAMPLE,
+    EXPLAIN,
+    DEFAULT,
+    GROUP,
+    LIMIT,
+    AS,
+    OFFSET,
+    SCHEMA,
+    LABELS,
+    KEYS,
+    GRAPH,
+    USE,
 }
 
 #[cfg(test)]
 mod test {
+    use std::str::FromStr;
+
     use super::*;
 
     #[test]
@@ -42,7 +65,7 @@ mod test {
         assert_eq!("SELECT", keyword.to_string());
 
         match Keyword::from_str("xxx") {
-            Ok(keyword) => panic!("should error"),
+            Ok(_) => panic!("should error"),
             Err(error) => assert_eq!("Matching variant not found", error.to_string()),
         }
     }
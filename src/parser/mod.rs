@@ -1,5 +1,6 @@
-pub(crate) mod ast;
+pub mod ast;
 pub mod keyword;
 pub mod operator;
+#[allow(clippy::module_inception)]
 pub mod parser;
 pub mod tokenizer;
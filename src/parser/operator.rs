@@ -1,3 +1,6 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
 use crate::parser::keyword::Keyword;
 use crate::parser::parser::BindingPower;
 use crate::parser::tokenizer::Token;
@@ -14,7 +17,7 @@ impl UnaryOperator {
         match token {
             Token::Plus => Some(UnaryOperator::Plus),
             Token::Minus => Some(UnaryOperator::Minus),
-            Token::Keyword(Keyword::NOT) => Some(UnaryOperator::Not),
+            Token::Keyword(Keyword::NOT) | Token::Bang => Some(UnaryOperator::Not),
             _ => None,
         }
     }
@@ -34,6 +37,8 @@ pub enum BinaryOperator {
     Multiply,
     Divide,
     Modulus,
+    /// a ^ b, right associative
+    Power,
     Gt,
     Lt,
     Gte,
@@ -53,6 +58,7 @@ impl BinaryOperator {
             Token::Star => Some(BinaryOperator::Multiply),
             Token::Slash => Some(BinaryOperator::Divide),
             Token::Percent => Some(BinaryOperator::Modulus),
+            Token::Caret => Some(BinaryOperator::Power),
             Token::Gt => Some(BinaryOperator::Gt),
             Token::Lt => Some(BinaryOperator::Lt),
             Token::Gte => Some(BinaryOperator::Gte),
@@ -61,19 +67,64 @@ impl BinaryOperator {
             Token::Neq => Some(BinaryOperator::NotEq),
             Token::Keyword(Keyword::AND) => Some(BinaryOperator::And),
             Token::Keyword(Keyword::OR) => Some(BinaryOperator::Or),
+            Token::Keyword(Keyword::LIKE) => Some(BinaryOperator::Like),
             _ => None,
         }
     }
 
+    /// the operator of `b op a` for `a op b`, e.g. `<` for `>`.
+    pub fn flipped(&self) -> BinaryOperator {
+        match self {
+            Self::Gt => Self::Lt,
+            Self::Lt => Self::Gt,
+            Self::Gte => Self::Lte,
+            Self::Lte => Self::Gte,
+            op => *op,
+        }
+    }
+
     pub fn get_binding_power(&self) -> BindingPower {
         match self {
             Self::Plus | Self::Minus => BindingPower::PlusMinus,
             Self::Multiply | Self::Divide | Self::Modulus => BindingPower::MultDiv,
-            Self::Gt | Self::Lt | Self::Gte | Self::Lte | Self::Eq | Self::NotEq => {
+            Self::Power => BindingPower::Power,
+            Self::Gt | Self::Lt | Self::Gte | Self::Lte | Self::Eq | Self::NotEq | Self::Like => {
                 BindingPower::Compare
             }
             Self::And | Self::Or => BindingPower::AndOr,
-            _ => BindingPower::Lowest,
         }
     }
 }
+
+impl Display for UnaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOperator::Plus => write!(f, "+"),
+            UnaryOperator::Minus => write!(f, "-"),
+            UnaryOperator::Not => write!(f, "NOT"),
+        }
+    }
+}
+
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinaryOperator::Plus => "+",
+            BinaryOperator::Minus => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulus => "%",
+            BinaryOperator::Power => "^",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Gte => ">=",
+            BinaryOperator::Lte => "<=",
+            BinaryOperator::Eq => "=",
+            BinaryOperator::NotEq => "!=",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+            BinaryOperator::Like => "LIKE",
+        };
+        write!(f, "{}", op)
+    }
+}
@@ -1,50 +1,104 @@
-use std::borrow::BorrowMut;
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::fmt::Display;
-use std::rc::Rc;
-
-use crate::datamodel::edge::Edge;
-use crate::parser::ast::Expr::{BinaryOp, UnaryOp};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::parser::ast::Expr::UnaryOp;
 use crate::parser::ast::{Expr, GraphTriplet, Statement};
-use crate::parser::ast::{GraphPattern, Value};
+use crate::parser::ast::{GraphPattern, OrderByExpr, Value, VertexMatch, ANONYMOUS_PREFIX};
 use crate::parser::keyword::Keyword;
-use crate::parser::keyword::Keyword::KEY;
 use crate::parser::operator::{BinaryOperator, UnaryOperator};
-use crate::parser::parser::ParserError::TokenizerError;
-use crate::parser::tokenizer::{Token, Tokenizer};
+use crate::parser::tokenizer::{decimal_number, Span, Token, TokenizeError, Tokenizer};
+
+/// how the parser reads ambiguous syntax. the default is the historical behavior.
+#[derive(Debug, Copy, Clone)]
+pub struct ParserOptions {
+    /// read `a.label` and `a.id` as the label and the id of the element `a`. when off they
+    /// are ordinary properties, and the label and the id are `label(a)` and `id(a)`.
+    pub strict_structural_accessors: bool,
+    /// called with the tokens of every sql before it is parsed, for diagnostics.
+    pub trace: Option<fn(&[Token])>,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            strict_structural_accessors: true,
+            trace: None,
+        }
+    }
+}
 
 pub struct Parser {
     tokens: Vec<Token>,
+    /// positions of `tokens` reported by errors, empty if unknown
+    spans: Vec<Span>,
     index: usize,
+    options: ParserOptions,
+    /// number of anonymous elements named so far
+    anonymous: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, index: 0 }
+        Self::with_options(tokens, ParserOptions::default())
+    }
+
+    pub fn with_options(tokens: Vec<Token>, options: ParserOptions) -> Parser {
+        Parser {
+            tokens,
+            spans: vec![],
+            index: 0,
+            options,
+            anonymous: 0,
+        }
+    }
+
+    /// a parser of `sql` without its whitespaces and comments.
+    fn tokenize(sql: &str, options: ParserOptions) -> Result<Parser, ParserError> {
+        let tokens = Tokenizer::new(sql)
+            .tokenize_with_spans()
+            .map_err(ParserError::from)?;
+        let (tokens, spans) = tokens
+            .into_iter()
+            .filter(|(token, _)| !matches!(token, Token::Whitespace(_)))
+            .unzip();
+        let mut parser = Self::with_options(tokens, options);
+        parser.spans = spans;
+        Ok(parser)
     }
 
     pub fn parse_sql(sql: &str) -> Result<Vec<Statement>, ParserError> {
-        let mut tokenizer = Tokenizer::new(sql);
-        match tokenizer.tokenize() {
-            Ok(tokens) => {
-                let ws_skipped: Vec<Token> = tokens
-                    .into_iter()
-                    .filter(|t| match t {
-                        Token::Whitespace(ws) => false,
-                        _ => true,
-                    })
-                    .collect();
-                println!("{:?}", ws_skipped);
-                let mut parser = Self::new(ws_skipped);
-                let mut stmts = Vec::new();
-                while parser.peek_token() != Token::EOF {
-                    let stmt = parser.parse_statement()?;
-                    stmts.push(stmt);
-                }
-                Ok(stmts)
+        Self::parse_sql_with_options(sql, ParserOptions::default())
+    }
+
+    pub fn parse_sql_with_options(
+        sql: &str,
+        options: ParserOptions,
+    ) -> Result<Vec<Statement>, ParserError> {
+        let mut parser = Self::tokenize(sql, options)?;
+        if let Some(trace) = options.trace {
+            trace(&parser.tokens);
+        }
+        let mut stmts = Vec::new();
+        while parser.peek_token() != Token::EOF {
+            let stmt = parser.parse_statement()?;
+            stmts.push(stmt);
+            // statements are separated by `;`, which may also end the last one
+            if !parser.match_and_consume_token(&Token::SemiColon)
+                && parser.peek_token() != Token::EOF
+            {
+                return Err(parser.expect(";", parser.peek_token()));
             }
-            Err(e) => Err(ParserError::TokenizerError(e.message)),
+        }
+        Ok(stmts)
+    }
+
+    /// parse a single standalone expression, e.g. `a + 1 > b`.
+    pub fn parse_expression(expr: &str) -> Result<Expr, ParserError> {
+        let mut parser = Self::tokenize(expr, ParserOptions::default())?;
+        let expr = parser.parse_expr()?;
+        match parser.peek_token() {
+            Token::EOF => Ok(expr),
+            token => Err(parser.expect("end of expression", token)),
         }
     }
 
@@ -53,7 +107,9 @@ impl Parser {
             Token::Keyword(keyword) => {
                 match keyword {
                     Keyword::SHOW => {
-                        return if self
+                        if self.match_and_consume_token(&Token::Keyword(Keyword::CREATE)) {
+                            self.parse_show_create()
+                        } else if self
                             .match_and_consume_keywords(&[Keyword::VERTEX, Keyword::LABEL])
                         {
                             Ok(Statement::ShowVertexLabels)
@@ -66,14 +122,20 @@ impl Parser {
                             Ok(Statement::ShowPropertyKeys)
                         } else {
                             Err(self.parser_error("unknown query".to_string()))
-                        };
+                        }
                     }
                     Keyword::SELECT => self.parse_select(),
+                    Keyword::EXPLAIN => Ok(Statement::Explain {
+                        statement: Box::new(self.parse_statement()?),
+                    }),
                     Keyword::INSERT => self.parse_insert(),
                     // Keyword::UPDATE => self.parse_update(),
-                    // Keyword::DELETE => self.parse_delete(),
+                    Keyword::DELETE => self.parse_delete(),
                     Keyword::CREATE => self.parse_create(),
                     Keyword::DROP => self.parse_drop(),
+                    Keyword::USE => Ok(Statement::UseGraph {
+                        name: self.parse_identifier()?,
+                    }),
                     _ => Err(self.parser_error(format!("Unexpected keyword `{}`", keyword))),
                 }
             }
@@ -81,55 +143,215 @@ impl Parser {
         }
     }
 
+    /// SHOW CREATE { VERTEX | EDGE } LABEL name
+    fn parse_show_create(&mut self) -> Result<Statement, ParserError> {
+        let vertex = if self.match_and_consume_keywords(&[Keyword::VERTEX, Keyword::LABEL]) {
+            true
+        } else if self.match_and_consume_keywords(&[Keyword::EDGE, Keyword::LABEL]) {
+            false
+        } else {
+            return Err(self.expect("VERTEX LABEL or EDGE LABEL", self.peek_token()));
+        };
+        match self.consume_token() {
+            Token::Identifier(name) if vertex => Ok(Statement::ShowCreateVertexLabel { name }),
+            Token::Identifier(name) => Ok(Statement::ShowCreateEdgeLabel { name }),
+            token => Err(self.expect("Identifier", token)),
+        }
+    }
+
     fn parse_select(&mut self) -> Result<Statement, ParserError> {
-        let exprs = self.parse_separated(&Token::Comma, |parser| parser.parse_expr())?;
+        let (exprs, aliases) = self
+            .parse_separated(&Token::Comma, |parser| parser.parse_select_item())?
+            .into_iter()
+            .unzip();
         let graph_pattern = if self.match_and_consume_token(&Token::Keyword(Keyword::FROM)) {
             self.parse_graph_pattern()?
         } else {
-            GraphPattern { triplets: vec![] }
+            GraphPattern {
+                triplets: vec![],
+                vertices: vec![],
+                labels: vec![],
+            }
+        };
+        let sample = if self.match_and_consume_token(&Token::Keyword(Keyword::SAMPLE)) {
+            Some(self.parse_unsigned_integer()?)
+        } else {
+            None
         };
         let condition = if self.match_and_consume_token(&Token::Keyword(Keyword::WHERE)) {
             Some(self.parse_expr()?)
         } else {
             None
         };
+        let group_by = if self.match_and_consume_keywords(&[Keyword::GROUP, Keyword::BY]) {
+            self.parse_separated(&Token::Comma, |parser| parser.parse_expr())?
+        } else {
+            vec![]
+        };
+        let order_by = if self.match_and_consume_keywords(&[Keyword::ORDER, Keyword::BY]) {
+            self.parse_separated(&Token::Comma, |parser| parser.parse_order_by_expr())?
+        } else {
+            vec![]
+        };
+        let limit = if self.match_and_consume_token(&Token::Keyword(Keyword::LIMIT)) {
+            Some(self.parse_unsigned_integer()?)
+        } else {
+            None
+        };
+        let offset =
+            if limit.is_some() && self.match_and_consume_token(&Token::Keyword(Keyword::OFFSET)) {
+                Some(self.parse_unsigned_integer()?)
+            } else {
+                None
+            };
         Ok(Statement::Select {
             items: exprs,
+            aliases,
             graph_pattern,
             condition,
+            group_by,
+            order_by,
+            sample,
+            limit,
+            offset,
+        })
+    }
+
+    /// expr [AS alias]
+    fn parse_select_item(&mut self) -> Result<(Expr, Option<String>), ParserError> {
+        let expr = self.parse_expr()?;
+        if !self.match_and_consume_token(&Token::Keyword(Keyword::AS)) {
+            return Ok((expr, None));
+        }
+        if let Expr::Wildcard | Expr::CompoundWildcard(_) = expr {
+            return Err(self.parser_error("a wildcard can not have an alias".to_owned()));
+        }
+        match self.consume_token() {
+            Token::Identifier(alias) => Ok((expr, Some(alias))),
+            token => {
+                self.prev_token();
+                Err(self.expect("alias", token))
+            }
+        }
+    }
+
+    fn parse_delete(&mut self) -> Result<Statement, ParserError> {
+        // (a) for a vertex, [e] for an edge, or just the name
+        let elements = self.parse_separated(&Token::Comma, |parser| {
+            let close = match parser.peek_token() {
+                Token::LeftParen => Some(Token::RightParen),
+                Token::LeftBracket => Some(Token::RightBracket),
+                _ => None,
+            };
+            if close.is_some() {
+                parser.consume_token();
+            }
+            let element = match parser.consume_token() {
+                Token::Identifier(name) => Expr::Identifier(name),
+                token => {
+                    parser.prev_token();
+                    return Err(parser.expect("Identifier", token));
+                }
+            };
+            if let Some(close) = close {
+                parser.check_match_and_consume_token(&close)?;
+            }
+            Ok(element)
+        })?;
+        self.check_match_and_consume_token(&Token::Keyword(Keyword::FROM))?;
+        let graph_pattern = self.parse_graph_pattern()?;
+        let condition = if self.match_and_consume_token(&Token::Keyword(Keyword::WHERE)) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(Statement::Delete {
+            elements,
+            graph_pattern,
+            condition,
+        })
+    }
+
+    fn parse_unsigned_integer(&mut self) -> Result<u64, ParserError> {
+        match self.consume_token() {
+            Token::Number(n) => match decimal_number(&n).parse::<u64>() {
+                Ok(n) => Ok(n),
+                Err(_) => Err(self.expect("unsigned integer", n)),
+            },
+            token => {
+                self.prev_token();
+                Err(self.expect("unsigned integer", token))
+            }
+        }
+    }
+
+    fn parse_order_by_expr(&mut self) -> Result<OrderByExpr, ParserError> {
+        let expr = self.parse_expr()?;
+        let asc = !self.match_and_consume_keywords(&[Keyword::DESC]);
+        if asc {
+            self.match_and_consume_keywords(&[Keyword::ASC]);
+        }
+        let nulls_first = if self.match_and_consume_keywords(&[Keyword::NULLS, Keyword::FIRST]) {
+            Some(true)
+        } else if self.match_and_consume_keywords(&[Keyword::NULLS, Keyword::LAST]) {
+            Some(false)
+        } else {
+            None
+        };
+        Ok(OrderByExpr {
+            expr,
+            asc,
+            nulls_first,
         })
     }
 
     fn parse_graph_pattern(&mut self) -> Result<GraphPattern, ParserError> {
         let mut triplets = vec![];
-        let mut curr = Box::new(self.parse_vertex_expr()?);
+        let mut labels = vec![];
+        let mut curr = Box::new(self.parse_vertex_expr(&mut labels)?);
+        let mut vertices = vec![*curr.clone()];
 
         loop {
             match self.consume_token() {
                 Token::Minus => {
-                    let edge = Box::new(self.parse_edge_expr()?);
-                    self.check_match_and_consume_token(&Token::RightArrow);
-                    let dst = Box::new(self.parse_vertex_expr()?);
+                    let (edge, hops) = self.parse_edge_expr(&mut labels)?;
+                    let undirected = self.match_and_consume_token(&Token::Minus);
+                    if undirected && hops != (1, 1) {
+                        return Err(self.parser_error(
+                            "undirected edges can not have a variable length".to_owned(),
+                        ));
+                    }
+                    if !undirected {
+                        self.check_match_and_consume_token(&Token::RightArrow)?;
+                    }
+                    let dst = Box::new(self.parse_vertex_expr(&mut labels)?);
+                    vertices.push(*dst.clone());
                     triplets.push(GraphTriplet {
                         src: Box::clone(&curr),
-                        edge,
+                        edge: Box::new(edge),
                         dst: Box::clone(&dst),
+                        hops,
+                        undirected,
                     });
                     curr = Box::clone(&dst);
                 }
                 Token::LeftArrow => {
-                    let edge = Box::new(self.parse_edge_expr()?);
-                    self.check_match_and_consume_token(&Token::Minus);
-                    let src = Box::new(self.parse_vertex_expr()?);
+                    let (edge, hops) = self.parse_edge_expr(&mut labels)?;
+                    self.check_match_and_consume_token(&Token::Minus)?;
+                    let src = Box::new(self.parse_vertex_expr(&mut labels)?);
+                    vertices.push(*src.clone());
                     triplets.push(GraphTriplet {
                         src: Box::clone(&src),
-                        edge,
+                        edge: Box::new(edge),
                         dst: Box::clone(&curr),
+                        hops,
+                        undirected: false,
                     });
                     curr = Box::clone(&src);
                 }
                 Token::Comma => {
-                    curr = Box::new(self.parse_vertex_expr()?);
+                    *curr = self.parse_vertex_expr(&mut labels)?;
+                    vertices.push(*curr.clone());
                 }
                 _ => {
                     self.prev_token();
@@ -137,33 +359,103 @@ impl Parser {
                 }
             }
         }
-        Ok(GraphPattern { triplets })
+        Ok(GraphPattern {
+            triplets,
+            vertices,
+            labels,
+        })
     }
 
-    fn parse_vertex_expr(&mut self) -> Result<Expr, ParserError> {
+    /// `(a)`, or `(a:person)` adding the inline label to `labels`. `()` and `(:person)` are
+    /// anonymous.
+    fn parse_vertex_expr(
+        &mut self,
+        labels: &mut Vec<(String, String)>,
+    ) -> Result<Expr, ParserError> {
         self.check_match_and_consume_token(&Token::LeftParen)?;
-        let vertex_expr = match self.consume_token() {
-            Token::Identifier(s) => Expr::Identifier(s),
+        let vertex_expr = self.parse_element_name(&Token::RightParen, labels)?;
+        self.check_match_and_consume_token(&Token::RightParen)?;
+        Ok(vertex_expr)
+    }
+
+    /// `[e]`, or `[e*min..max]` / `[e*n]` for a variable number of hops. `[e:knows]` adds
+    /// the inline label to `labels`. `[]`, `[:knows]` and `[*2]` are anonymous.
+    fn parse_edge_expr(
+        &mut self,
+        labels: &mut Vec<(String, String)>,
+    ) -> Result<(Expr, (u32, u32)), ParserError> {
+        self.check_match_and_consume_token(&Token::LeftBracket)?;
+        let edge_expr = self.parse_element_name(&Token::RightBracket, labels)?;
+        let mut hops = (1, 1);
+        if self.match_and_consume_token(&Token::Star) {
+            let min = self.parse_unsigned_integer()?;
+            let max = match self.match_and_consume_tokens(&[Token::Dot, Token::Dot]) {
+                true => self.parse_unsigned_integer()?,
+                false => min,
+            };
+            if min < 1 || min > max || max > u32::MAX as u64 {
+                return Err(self.parser_error(format!("invalid hops {}..{}", min, max)));
+            }
+            hops = (min as u32, max as u32);
+        }
+        self.check_match_and_consume_token(&Token::RightBracket)?;
+        Ok((edge_expr, hops))
+    }
+
+    /// the name of an element and its inline label. an element without a name, followed by
+    /// its label, hops or `close`, gets a generated one.
+    fn parse_element_name(
+        &mut self,
+        close: &Token,
+        labels: &mut Vec<(String, String)>,
+    ) -> Result<Expr, ParserError> {
+        let name = match self.consume_token() {
+            // reserved for the generated names, which must not collide with the given ones
+            Token::Identifier(s) if s.starts_with(ANONYMOUS_PREFIX) => {
+                return Err(self.parser_error(format!("invalid element name {}", s)));
+            }
+            Token::Identifier(s) => s,
+            token if token == *close || token == Token::Colon || token == Token::Star => {
+                self.prev_token();
+                self.anonymous += 1;
+                format!("{}{}", ANONYMOUS_PREFIX, self.anonymous - 1)
+            }
             token => {
                 self.prev_token();
                 return Err(self.expect("Identifier", token));
             }
         };
-        self.check_match_and_consume_token(&Token::RightParen)?;
-        Ok(vertex_expr)
+        self.parse_inline_label(&name, labels)?;
+        Ok(Expr::Identifier(name))
     }
 
-    fn parse_edge_expr(&mut self) -> Result<Expr, ParserError> {
-        self.check_match_and_consume_token(&Token::LeftBracket)?;
-        let edge_expr = match self.consume_token() {
-            Token::Identifier(s) => Expr::Identifier(s),
+    /// the `:label` after the element `name`, if any. an element can only have one label.
+    fn parse_inline_label(
+        &mut self,
+        name: &str,
+        labels: &mut Vec<(String, String)>,
+    ) -> Result<(), ParserError> {
+        if !self.match_and_consume_token(&Token::Colon) {
+            return Ok(());
+        }
+        let label = match self.consume_token() {
+            Token::Identifier(label) => label,
             token => {
                 self.prev_token();
-                return Err(self.expect("Identifier", token));
+                return Err(self.expect("label", token));
             }
         };
-        self.check_match_and_consume_token(&Token::RightBracket)?;
-        Ok(edge_expr)
+        match labels.iter().find(|(element, _)| element == name) {
+            Some((_, other)) if *other != label => Err(self.parser_error(format!(
+                "conflicting labels {} and {} of {}",
+                other, label, name
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                labels.push((name.to_owned(), label));
+                Ok(())
+            }
+        }
     }
 
     fn parse_insert(&mut self) -> Result<Statement, ParserError> {
@@ -202,6 +494,10 @@ impl Parser {
     fn parse_insert_edge(&mut self) -> Result<Statement, ParserError> {
         let label = self.parse_expr()?;
 
+        if self.match_and_consume_token(&Token::Keyword(Keyword::BETWEEN)) {
+            return self.parse_insert_edge_between(label);
+        }
+
         self.check_match_and_consume_token(&Token::Keyword(Keyword::PROPERTIES))?;
 
         let properties = self.parse_properties()?;
@@ -223,6 +519,52 @@ impl Parser {
         })
     }
 
+    fn parse_insert_edge_between(&mut self, label: Expr) -> Result<Statement, ParserError> {
+        let cartesian = self.match_and_consume_token(&Token::Keyword(Keyword::ALL));
+
+        let src = self.parse_vertex_match()?;
+
+        self.check_match_and_consume_token(&Token::Keyword(Keyword::AND))?;
+
+        let dst = self.parse_vertex_match()?;
+
+        let (properties, values) =
+            if self.match_and_consume_token(&Token::Keyword(Keyword::PROPERTIES)) {
+                let properties = self.parse_properties()?;
+                self.check_match_and_consume_token(&Token::Keyword(Keyword::VALUES))?;
+                (properties, self.parse_values()?)
+            } else {
+                (vec![], vec![])
+            };
+
+        Ok(Statement::InsertEdgeBetween {
+            label,
+            src,
+            dst,
+            cartesian,
+            properties,
+            values,
+        })
+    }
+
+    fn parse_vertex_match(&mut self) -> Result<VertexMatch, ParserError> {
+        self.check_match_and_consume_token(&Token::LeftParen)?;
+        let name = match self.consume_token() {
+            Token::Identifier(s) => s,
+            token => {
+                self.prev_token();
+                return Err(self.expect("Identifier", token));
+            }
+        };
+        let condition = if self.match_and_consume_token(&Token::Keyword(Keyword::WHERE)) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        self.check_match_and_consume_token(&Token::RightParen)?;
+        Ok(VertexMatch { name, condition })
+    }
+
     fn parse_properties(&mut self) -> Result<Vec<String>, ParserError> {
         self.check_match_and_consume_token(&Token::LeftParen)?;
 
@@ -232,7 +574,10 @@ impl Parser {
                     parser.consume_token();
                     Ok(s)
                 }
-                _ => Err(parser.expect("Identifiers", parser.peek_token())),
+                _ => match parser.parse_structural_property_name() {
+                    Some(s) => Ok(s),
+                    None => Err(parser.expect("Identifiers", parser.peek_token())),
+                },
             })?;
 
         self.check_match_and_consume_token(&Token::RightParen)?;
@@ -267,12 +612,21 @@ impl Parser {
     fn parse_values(&mut self) -> Result<Vec<Expr>, ParserError> {
         self.check_match_and_consume_token(&Token::LeftParen)?;
 
-        let values = self.parse_separated(&Token::Comma, |parser| match parser.peek_token() {
-            Token::String(s) => {
-                parser.consume_token();
-                Ok(Expr::Value(Value::String(s)))
+        let values = self.parse_separated(&Token::Comma, |parser| {
+            // -1 is a literal here, not an expression
+            if parser.match_and_consume_token(&Token::Minus) {
+                return match parser.consume_token() {
+                    Token::Number(n) => Ok(Expr::Value(Value::Number(format!(
+                        "-{}",
+                        decimal_number(&n)
+                    )))),
+                    token => {
+                        parser.prev_token();
+                        Err(parser.expect("Number", token))
+                    }
+                };
             }
-            _ => Err(parser.parser_error("not impl".to_string())),
+            parser.parse_literal_value()
         })?;
 
         self.check_match_and_consume_token(&Token::RightParen)?;
@@ -287,11 +641,50 @@ impl Parser {
             self.parse_create_edge_label()
         } else if self.match_and_consume_keywords(&[Keyword::PROPERTY, Keyword::KEY]) {
             self.parse_create_property_key()
+        } else if self.match_and_consume_keywords(&[Keyword::SCHEMA]) {
+            self.parse_create_schema()
+        } else if self.match_and_consume_keywords(&[Keyword::GRAPH]) {
+            Ok(Statement::CreateGraph {
+                name: self.parse_identifier()?,
+            })
         } else {
             Err(self.parser_error(format!("Unexpected token `{}`", self.peek_token())))
         }
     }
 
+    /// CREATE SCHEMA {
+    ///     VERTEX LABELS (person, software);
+    ///     EDGE LABELS ((knows, many2manysimple));
+    ///     PROPERTY KEYS ((name, single), (age, single, DEFAULT 0))
+    /// }
+    fn parse_create_schema(&mut self) -> Result<Statement, ParserError> {
+        self.check_match_and_consume_token(&Token::LeftBrace)?;
+        let mut statements = vec![];
+        while !self.match_and_consume_token(&Token::RightBrace) {
+            let parse_element: fn(&mut Parser) -> Result<Statement, ParserError> =
+                if self.match_and_consume_keywords(&[Keyword::VERTEX, Keyword::LABELS]) {
+                    Parser::parse_create_vertex_label
+                } else if self.match_and_consume_keywords(&[Keyword::EDGE, Keyword::LABELS]) {
+                    Parser::parse_create_edge_label
+                } else if self.match_and_consume_keywords(&[Keyword::PROPERTY, Keyword::KEYS]) {
+                    Parser::parse_create_property_key
+                } else {
+                    return Err(self.expect(
+                        "VERTEX LABELS, EDGE LABELS or PROPERTY KEYS",
+                        self.peek_token(),
+                    ));
+                };
+            self.check_match_and_consume_token(&Token::LeftParen)?;
+            statements.extend(self.parse_separated(&Token::Comma, parse_element)?);
+            self.check_match_and_consume_token(&Token::RightParen)?;
+            if !self.match_and_consume_token(&Token::SemiColon) {
+                self.check_match_and_consume_token(&Token::RightBrace)?;
+                break;
+            }
+        }
+        Ok(Statement::CreateSchema { statements })
+    }
+
     fn parse_drop(&mut self) -> Result<Statement, ParserError> {
         if self.match_and_consume_keywords(&[Keyword::VERTEX, Keyword::LABEL]) {
             self.parse_drop_vertex_label()
@@ -299,11 +692,25 @@ impl Parser {
             self.parse_drop_edge_label()
         } else if self.match_and_consume_keywords(&[Keyword::PROPERTY, Keyword::KEY]) {
             self.parse_drop_property_key()
+        } else if self.match_and_consume_keywords(&[Keyword::GRAPH]) {
+            Ok(Statement::DropGraph {
+                name: self.parse_identifier()?,
+            })
         } else {
             Err(self.parser_error(format!("Unexpected token `{}`", self.peek_token())))
         }
     }
 
+    fn parse_identifier(&mut self) -> Result<String, ParserError> {
+        match self.peek_token() {
+            Token::Identifier(ident) => {
+                self.consume_token();
+                Ok(ident)
+            }
+            token => Err(self.expect("Identifier", token)),
+        }
+    }
+
     fn parse_create_vertex_label(&mut self) -> Result<Statement, ParserError> {
         let next_token = self.peek_token();
         match next_token {
@@ -341,13 +748,29 @@ impl Parser {
     fn parse_create_property_key(&mut self) -> Result<Statement, ParserError> {
         self.check_match_and_consume_token(&Token::LeftParen)?;
 
-        let exprs = self.parse_separated(&Token::Comma, |parser| match parser.peek_token() {
-            Token::Identifier(s) => {
-                parser.consume_token();
-                Ok(s)
+        // (name, cardinality[, DEFAULT value])
+        let mut exprs = vec![];
+        let mut default_value = None;
+        loop {
+            match self.peek_token() {
+                Token::Identifier(s) => {
+                    self.consume_token();
+                    exprs.push(s);
+                }
+                Token::Keyword(Keyword::DEFAULT) if exprs.len() == 2 => {
+                    self.consume_token();
+                    default_value = Some(self.parse_literal_value()?);
+                }
+                _ if exprs.is_empty() => match self.parse_structural_property_name() {
+                    Some(s) => exprs.push(s),
+                    None => return Err(self.expect("Identifiers", self.peek_token())),
+                },
+                _ => return Err(self.expect("Identifiers", self.peek_token())),
             }
-            _ => Err(parser.expect("Identifiers", parser.peek_token())),
-        })?;
+            if default_value.is_some() || !self.match_and_consume_token(&Token::Comma) {
+                break;
+            }
+        }
 
         if exprs.len() != 2 {
             return Err(self.parser_error("unexpected length of create edge clause".to_owned()));
@@ -358,6 +781,7 @@ impl Parser {
         Ok(Statement::CreatePropertyKey {
             name: exprs[0].to_string(),
             cardinality: exprs[1].to_string(),
+            default_value,
         })
     }
 
@@ -401,7 +825,7 @@ impl Parser {
     fn parse_literal_value(&mut self) -> Result<Expr, ParserError> {
         match self.consume_token() {
             Token::String(s) => Ok(Expr::Value(Value::String(s))),
-            Token::Number(n) => Ok(Expr::Value(Value::Number(n))),
+            Token::Number(n) => Ok(Expr::Value(Value::Number(decimal_number(&n)))),
             Token::Keyword(Keyword::TRUE) => Ok(Expr::Value(Value::Boolean(true))),
             Token::Keyword(Keyword::FALSE) => Ok(Expr::Value(Value::Boolean(false))),
             Token::Keyword(Keyword::NULL) => Ok(Expr::Value(Value::Null)),
@@ -412,20 +836,22 @@ impl Parser {
         }
     }
 
-    fn parse_identifier(&mut self) -> Result<Expr, ParserError> {
-        match self.consume_token() {
-            Token::Identifier(s) => Ok(Expr::Identifier(s)),
-            token => {
-                self.prev_token();
-                Err(self.expect("identifier", token))
-            }
-        }
-    }
-
     fn parse_identifiers_or_function(&mut self) -> Result<Expr, ParserError> {
         match self.consume_token() {
+            // label(a), id(a)
+            Token::Keyword(keyword @ Keyword::LABEL) | Token::Keyword(keyword @ Keyword::ID)
+                if self.match_and_consume_token(&Token::LeftParen) =>
+            {
+                let arguments =
+                    self.parse_separated(&Token::Comma, |parser| parser.parse_expr())?;
+                self.check_match_and_consume_token(&Token::RightParen)?;
+                Ok(Expr::Function {
+                    func_name: keyword.to_string().to_lowercase(),
+                    arguments,
+                })
+            }
             Token::Identifier(s) => {
-                return match self.consume_token() {
+                match self.consume_token() {
                     // function call. func(a, b, c)
                     Token::LeftParen => {
                         let arguments =
@@ -439,11 +865,13 @@ impl Parser {
                     // a.b.c, a.b.*
                     Token::Dot => {
                         let mut ident_vec = vec![s.to_string()];
-                        if self.match_and_consume_keywords(&[Keyword::LABEL]) {
-                            return Ok(Expr::LabelExpr(s.to_string()));
-                        }
-                        if self.match_and_consume_keywords(&[Keyword::ID]) {
-                            return Ok(Expr::IdExpr(s.to_string()));
+                        if self.options.strict_structural_accessors {
+                            if self.match_and_consume_keywords(&[Keyword::LABEL]) {
+                                return Ok(Expr::LabelExpr(s.to_string()));
+                            }
+                            if self.match_and_consume_keywords(&[Keyword::ID]) {
+                                return Ok(Expr::IdExpr(s.to_string()));
+                            }
                         }
                         loop {
                             match self.consume_token() {
@@ -451,7 +879,10 @@ impl Parser {
                                 Token::Star => return Ok(Expr::CompoundWildcard(ident_vec)),
                                 token => {
                                     self.prev_token();
-                                    return Err(self.expect("Identifier or *", token));
+                                    match self.parse_structural_property_name() {
+                                        Some(ss) => ident_vec.push(ss),
+                                        None => return Err(self.expect("Identifier or *", token)),
+                                    }
                                 }
                             }
                             if !self.match_and_consume_token(&Token::Dot) {
@@ -465,7 +896,7 @@ impl Parser {
                         self.prev_token();
                         Ok(Expr::Identifier(s))
                     }
-                };
+                }
             }
             token => {
                 self.prev_token();
@@ -474,6 +905,21 @@ impl Parser {
         }
     }
 
+    /// `label` or `id` used as a property name, which only ordinary identifiers are when
+    /// structural accessors are strict.
+    fn parse_structural_property_name(&mut self) -> Option<String> {
+        if self.options.strict_structural_accessors {
+            return None;
+        }
+        match self.peek_token() {
+            Token::Keyword(keyword @ Keyword::LABEL) | Token::Keyword(keyword @ Keyword::ID) => {
+                self.consume_token();
+                Some(keyword.to_string().to_lowercase())
+            }
+            _ => None,
+        }
+    }
+
     fn parse_expr_tdop(&mut self, rbp: BindingPower) -> Result<Expr, ParserError> {
         let mut expr = self.parse_prefix()?;
         loop {
@@ -491,6 +937,13 @@ impl Parser {
             return Ok(Expr::Wildcard);
         }
 
+        if self.match_and_consume_token(&Token::Keyword(Keyword::EXISTS)) {
+            self.check_match_and_consume_token(&Token::LeftParen)?;
+            let graph_pattern = self.parse_graph_pattern()?;
+            self.check_match_and_consume_token(&Token::RightParen)?;
+            return Ok(Expr::Exists(Box::new(graph_pattern)));
+        }
+
         if let Ok(expr) = self.parse_literal_value() {
             return Ok(expr);
         }
@@ -527,22 +980,62 @@ impl Parser {
     fn get_binding_power(&mut self) -> BindingPower {
         match BinaryOperator::from_token(&self.peek_token()) {
             Some(op) => op.get_binding_power(),
+            _ if self.peek_token() == Token::Keyword(Keyword::IS) => BindingPower::Compare,
+            _ if self.peek_token() == Token::Keyword(Keyword::IN) => BindingPower::Compare,
+            _ if self.peek_next_n_token(2)
+                == [Token::Keyword(Keyword::NOT), Token::Keyword(Keyword::IN)] =>
+            {
+                BindingPower::Compare
+            }
             _ => BindingPower::Lowest,
         }
     }
 
     fn parse_infix(&mut self, expr: Expr, lbp: BindingPower) -> Result<Expr, ParserError> {
+        if self.match_and_consume_token(&Token::Keyword(Keyword::IS)) {
+            let negated = self.match_and_consume_token(&Token::Keyword(Keyword::NOT));
+            self.check_match_and_consume_token(&Token::Keyword(Keyword::NULL))?;
+            return Ok(Expr::IsNull {
+                expr: Box::new(expr),
+                negated,
+            });
+        }
+        if self.match_and_consume_keywords(&[Keyword::NOT, Keyword::IN]) {
+            return Ok(UnaryOp {
+                op: UnaryOperator::Not,
+                expr: Box::new(self.parse_in_list(expr)?),
+            });
+        }
+        if self.match_and_consume_token(&Token::Keyword(Keyword::IN)) {
+            return self.parse_in_list(expr);
+        }
         if let Some(op) = BinaryOperator::from_token(&self.peek_token()) {
             self.consume_token();
+            // `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`
+            let rbp = match op {
+                BinaryOperator::Power => BindingPower::MultDiv,
+                _ => lbp,
+            };
             return Ok(Expr::BinaryOp {
                 op,
                 left: Box::new(expr),
-                right: Box::new(self.parse_expr_tdop(lbp)?),
+                right: Box::new(self.parse_expr_tdop(rbp)?),
             });
         }
         Err(self.parser_error("not impl".to_string()))
     }
 
+    /// the `(v1, v2, ...)` after `expr IN`.
+    fn parse_in_list(&mut self, expr: Expr) -> Result<Expr, ParserError> {
+        self.check_match_and_consume_token(&Token::LeftParen)?;
+        let list = self.parse_separated(&Token::Comma, |parser| parser.parse_expr())?;
+        self.check_match_and_consume_token(&Token::RightParen)?;
+        Ok(Expr::InList {
+            expr: Box::new(expr),
+            list,
+        })
+    }
+
     fn parse_separated<T, F>(
         &mut self,
         separator: &Token,
@@ -562,21 +1055,14 @@ impl Parser {
     }
 
     fn match_and_consume_keywords(&mut self, keywords: &[Keyword]) -> bool {
-        let tokens: Vec<Token> = keywords
-            .to_owned()
-            .into_iter()
-            .map(|kw| Token::Keyword(kw))
-            .collect();
+        let tokens: Vec<Token> = keywords.iter().cloned().map(Token::Keyword).collect();
         self.match_and_consume_tokens(&tokens)
     }
 
     fn match_and_consume_tokens(&mut self, tokens: &[Token]) -> bool {
         let n = tokens.len();
-        let peek_tokens = self.peek_next_n_token(n);
-        for i in 0..n {
-            if peek_tokens[i] != tokens[i] {
-                return false;
-            }
+        if self.peek_next_n_token(n) != tokens {
+            return false;
         }
         self.consume_next_n_token(n);
         true
@@ -599,7 +1085,8 @@ impl Parser {
     }
 
     fn peek_next_n_token(&self, n: usize) -> Vec<Token> {
-        self.tokens[self.index..self.index + n].to_vec()
+        let end = self.tokens.len().min(self.index + n);
+        self.tokens[self.index..end].to_vec()
     }
 
     fn peek_token(&self) -> Token {
@@ -628,11 +1115,31 @@ impl Parser {
         T: Display,
         U: Display,
     {
-        self.parser_error(format!("Expect `{}` but found `{}`", expect, found))
+        let found = found.to_string();
+        let msg = format!("Expect `{}` but found `{}`", expect, found);
+        // `found` was either peeked or already consumed
+        let consumed = self.index > 0
+            && self.tokens[self.index - 1].to_string() == found
+            && self.tokens[self.index].to_string() != found;
+        if consumed {
+            self.error_at(self.index - 1, msg)
+        } else {
+            self.parser_error(msg)
+        }
     }
 
+    /// an error at the current token, by its line and column if known.
     fn parser_error(&self, msg: String) -> ParserError {
-        ParserError::ParserError(format!("{} at position {}", msg, self.index))
+        self.error_at(self.index, msg)
+    }
+
+    fn error_at(&self, index: usize, msg: String) -> ParserError {
+        match self.spans.get(index) {
+            Some(span) => {
+                ParserError::ParserError(format!("{} at line {}, col {}", msg, span.line, span.col))
+            }
+            None => ParserError::ParserError(format!("{} at position {}", msg, index)),
+        }
     }
 }
 
@@ -642,14 +1149,16 @@ pub enum BindingPower {
     Lowest = 0,
     /// a AND b OR c ...
     AndOr = 20,
+    /// NOT a. binds looser than comparisons, so `NOT a = b` is `NOT (a = b)`
+    Not = 25,
     /// a == b, a <= b ...
     Compare = 30,
     /// a + b, a - b ...
     PlusMinus = 40,
     /// a * b, a / b, a % b
     MultDiv = 50,
-    /// NOT a
-    Not = 60,
+    /// a ^ b
+    Power = 60,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -658,75 +1167,741 @@ pub enum ParserError {
     ParserError(String),
 }
 
+impl Display for ParserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserError::TokenizerError(msg) => write!(f, "TokenizerError: {}", msg),
+            ParserError::ParserError(msg) => write!(f, "ParserError: {}", msg),
+        }
+    }
+}
+
+impl From<TokenizeError> for ParserError {
+    fn from(e: TokenizeError) -> Self {
+        ParserError::TokenizerError(format!("{} at line {}, col {}", e.message, e.line, e.col))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn parse_one(sql: &str) -> Statement {
+        let mut stmts = Parser::parse_sql(sql).unwrap();
+        assert_eq!(stmts.len(), 1);
+        stmts.remove(0)
+    }
+
+    fn select_items(sql: &str) -> Vec<String> {
+        match parse_one(sql) {
+            Statement::Select { items, .. } => items.iter().map(|e| format!("{:?}", e)).collect(),
+            stmt => panic!("not a select: {:?}", stmt),
+        }
+    }
+
     #[test]
     fn test_simple_schema_crud() {
-        let stmts = Parser::parse_sql("CREATE VERTEX LABEL vertex_label").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("CREATE EDGE LABEL (edge_label, one2one)").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("CREATE PROPERTY KEY (property_key, mono)").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("DROP VERTEX LABEL vertex_label").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("DROP EDGE LABEL edge_label ").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("DROP PROPERTY KEY property_key ").unwrap();
-        println!("{:?}", stmts);
+        assert!(matches!(
+            parse_one("CREATE VERTEX LABEL vertex_label"),
+            Statement::CreateVertexLabel { name } if name == "vertex_label"
+        ));
+        assert!(matches!(
+            parse_one("CREATE EDGE LABEL (edge_label, one2one)"),
+            Statement::CreateEdgeLabel { name, multiplicity }
+                if name == "edge_label" && multiplicity == "one2one"
+        ));
+        assert!(matches!(
+            parse_one("CREATE PROPERTY KEY (property_key, mono)"),
+            Statement::CreatePropertyKey { name, cardinality, default_value: None }
+                if name == "property_key" && cardinality == "mono"
+        ));
+        assert!(matches!(
+            parse_one("CREATE PROPERTY KEY (status, single, default 'active')"),
+            Statement::CreatePropertyKey {
+                default_value: Some(Expr::Value(Value::String(value))),
+                ..
+            } if value == "active"
+        ));
+        assert!(matches!(
+            parse_one("DROP VERTEX LABEL vertex_label"),
+            Statement::DropVertexLabel { name } if name == "vertex_label"
+        ));
+        assert!(matches!(
+            parse_one("DROP EDGE LABEL edge_label "),
+            Statement::DropEdgeLabel { name } if name == "edge_label"
+        ));
+        assert!(matches!(
+            parse_one("DROP PROPERTY KEY property_key "),
+            Statement::DropPropertyKey { name } if name == "property_key"
+        ));
+        assert!(Parser::parse_sql("CREATE VERTEX vertex_label").is_err());
+    }
+
+    #[test]
+    fn test_parse_multiple_statements() {
+        let stmts = Parser::parse_sql(concat!(
+            "CREATE VERTEX LABEL person;\n",
+            "CREATE EDGE LABEL (knows, one2one);\n",
+            "SELECT a.id FROM (a:person);",
+        ))
+        .unwrap();
+        assert_eq!(stmts.len(), 3);
+        assert!(matches!(&stmts[0], Statement::CreateVertexLabel { name } if name == "person"));
+        assert!(matches!(&stmts[1], Statement::CreateEdgeLabel { name, .. } if name == "knows"));
+        assert!(matches!(&stmts[2], Statement::Select { .. }));
+        assert_eq!(
+            Parser::parse_sql("CREATE VERTEX LABEL a; CREATE VERTEX LABEL b")
+                .unwrap()
+                .len(),
+            2
+        );
+        let err = Parser::parse_sql("CREATE VERTEX LABEL a b").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ParserError: Expect `;` but found `b` at line 1, col 23"
+        );
+        assert!(Parser::parse_sql("CREATE VERTEX LABEL a;;").is_err());
+    }
+
+    #[test]
+    fn test_trace() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static TRACED: AtomicUsize = AtomicUsize::new(0);
+        let options = ParserOptions {
+            trace: Some(|tokens| {
+                TRACED.fetch_add(tokens.len(), Ordering::SeqCst);
+            }),
+            ..ParserOptions::default()
+        };
+        Parser::parse_sql_with_options("CREATE VERTEX LABEL person", options).unwrap();
+        // CREATE VERTEX LABEL person EOF
+        assert_eq!(TRACED.load(Ordering::SeqCst), 5);
+        Parser::parse_sql("CREATE VERTEX LABEL person").unwrap();
+        assert_eq!(TRACED.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_parse_prints_nothing() {
+        // parse in a child run of this test, whose stdout isn't captured
+        if std::env::var_os("ANGELINA_PARSE_CHILD").is_some() {
+            Parser::parse_sql("SELECT a.id FROM (a) WHERE a.age > 1").unwrap();
+            return;
+        }
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "parser::parser::test::test_parse_prints_nothing",
+                "--nocapture",
+            ])
+            .env("ANGELINA_PARSE_CHILD", "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("1 passed"), "{}", stdout);
+        assert!(!stdout.contains("SELECT"), "{}", stdout);
+    }
+
+    #[test]
+    fn test_parse_graph_statements() {
+        assert!(matches!(
+            parse_one("CREATE GRAPH g1"),
+            Statement::CreateGraph { name } if name == "g1"
+        ));
+        assert!(matches!(
+            parse_one("DROP GRAPH g1"),
+            Statement::DropGraph { name } if name == "g1"
+        ));
+        assert!(matches!(
+            parse_one("USE g1"),
+            Statement::UseGraph { name } if name == "g1"
+        ));
+        assert!(Parser::parse_sql("CREATE GRAPH").is_err());
+        assert!(Parser::parse_sql("USE 'g1'").is_err());
+    }
+
+    #[test]
+    fn test_parse_create_schema() {
+        match parse_one(concat!(
+            "CREATE SCHEMA { VERTEX LABELS (person, software); ",
+            "EDGE LABELS ((knows, one2one)); ",
+            "PROPERTY KEYS ((name, single), (age, single, DEFAULT 0)); }"
+        )) {
+            Statement::CreateSchema { statements } => assert_eq!(
+                statements
+                    .iter()
+                    .map(|s| format!("{:?}", s))
+                    .collect::<Vec<_>>(),
+                vec![
+                    "CreateVertexLabel { name: \"person\" }",
+                    "CreateVertexLabel { name: \"software\" }",
+                    "CreateEdgeLabel { name: \"knows\", multiplicity: \"one2one\" }",
+                    "CreatePropertyKey { name: \"name\", cardinality: \"single\", default_value: None }",
+                    "CreatePropertyKey { name: \"age\", cardinality: \"single\", default_value: Some(Value(Number(\"0\"))) }",
+                ]
+            ),
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        assert!(matches!(
+            parse_one("CREATE SCHEMA {}"),
+            Statement::CreateSchema { statements } if statements.is_empty()
+        ));
+        assert!(Parser::parse_sql("CREATE SCHEMA { VERTEX LABELS (person) ").is_err());
+        assert!(Parser::parse_sql("CREATE SCHEMA { VERTEX LABEL (person) }").is_err());
     }
 
     #[test]
     fn test_simple_insert_vertex() {
-        let stmts = Parser::parse_sql(
+        match parse_one(
             "INSERT VERTEX 'vertex_label' PROPERTIES (prop1, prop2) VALUES ('vertex_id'):('value1', 'value2')",
-        ).unwrap();
-        println!("{:?}", stmts);
+        ) {
+            Statement::InsertVertex {
+                label,
+                properties,
+                vertex_id,
+                values,
+            } => {
+                assert!(matches!(label, Expr::Value(Value::String(s)) if s == "vertex_label"));
+                assert_eq!(properties, vec!["prop1", "prop2"]);
+                assert!(matches!(vertex_id, Expr::Value(Value::String(s)) if s == "vertex_id"));
+                assert_eq!(values.len(), 2);
+            }
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_structural_accessors() {
+        let sql = "SELECT a.label, a.id, a.b.id, label(a) FROM (a)";
+        let lenient = ParserOptions {
+            strict_structural_accessors: false,
+            ..ParserOptions::default()
+        };
+        match &Parser::parse_sql_with_options(sql, lenient).unwrap()[0] {
+            Statement::Select { items, .. } => assert_eq!(
+                format!("{:?}", items),
+                concat!(
+                    "[CompoundIdentifier([\"a\", \"label\"]), ",
+                    "CompoundIdentifier([\"a\", \"id\"]), ",
+                    "CompoundIdentifier([\"a\", \"b\", \"id\"]), ",
+                    "Function { func_name: \"label\", arguments: [Identifier(\"a\")] }]"
+                )
+            ),
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        match &Parser::parse_sql_with_options(
+            "CREATE PROPERTY KEY (label, single, DEFAULT 'x')",
+            lenient,
+        )
+        .unwrap()[0]
+        {
+            Statement::CreatePropertyKey { name, .. } => assert_eq!(name, "label"),
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+
+        // the default keeps the structural meaning
+        assert_eq!(
+            select_items("SELECT a.label, a.id"),
+            vec!["LabelExpr(\"a\")", "IdExpr(\"a\")"]
+        );
+        assert!(Parser::parse_sql("CREATE PROPERTY KEY (label, single)").is_err());
+        assert!(Parser::parse_sql("SELECT a.b.id FROM (a)").is_err());
     }
 
     #[test]
     fn test_simple_insert_edge() {
-        let stmts = Parser::parse_sql(
+        match parse_one(
             "INSERT EDGE 'edge_label' PROPERTIES (prop1, prop2) VALUES ('vertex_id_1' -> 'vertex_id_2'):('value1', 'value2')"
-        ).unwrap();
-        println!("{:?}", stmts);
+        ) {
+            Statement::InsertEdge {
+                properties,
+                src_vertex_id,
+                dst_vertex_id,
+                values,
+                ..
+            } => {
+                assert_eq!(properties, vec!["prop1", "prop2"]);
+                assert!(matches!(src_vertex_id, Expr::Value(Value::String(s)) if s == "vertex_id_1"));
+                assert!(matches!(dst_vertex_id, Expr::Value(Value::String(s)) if s == "vertex_id_2"));
+                assert_eq!(values.len(), 2);
+            }
+            stmt => panic!("unexpected {:?}", stmt),
+        }
     }
 
     #[test]
     fn test_parse_expr() {
-        let stmts = Parser::parse_sql("SELECT 1 + 2 + 3").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("SELECT -1 + 2 * 3 - 1").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("SELECT a > 3").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("SELECT NOT a.b AND (b OR c)").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("SELECT 2 * (3 + 1)").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("SELECT func(arg1, arg2, func2(a, b+1, True))").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("SELECT func(a.b.c, 2), a.b.*").unwrap();
-        println!("{:?}", stmts);
+        assert_eq!(
+            select_items("SELECT 1 + 2 * 3"),
+            vec![
+                "BinaryOp { op: Plus, left: Value(Number(\"1\")), right: BinaryOp { op: Multiply, left: Value(Number(\"2\")), right: Value(Number(\"3\")) } }"
+            ]
+        );
+        assert_eq!(
+            select_items("SELECT -2 ^ 3 ^ 2 * 4"),
+            vec![
+                "UnaryOp { op: Minus, expr: BinaryOp { op: Multiply, left: BinaryOp { op: Power, left: Value(Number(\"2\")), right: BinaryOp { op: Power, left: Value(Number(\"3\")), right: Value(Number(\"2\")) } }, right: Value(Number(\"4\")) } }"
+            ]
+        );
+        assert_eq!(
+            select_items("SELECT a > 3"),
+            vec!["BinaryOp { op: Gt, left: Identifier(\"a\"), right: Value(Number(\"3\")) }"]
+        );
+        assert_eq!(
+            select_items("SELECT NOT a.b AND (b OR c)"),
+            vec![
+                "BinaryOp { op: And, left: UnaryOp { op: Not, expr: CompoundIdentifier([\"a\", \"b\"]) }, right: Nested(BinaryOp { op: Or, left: Identifier(\"b\"), right: Identifier(\"c\") }) }"
+            ]
+        );
+        assert_eq!(
+            select_items("SELECT !a.b AND (b OR c)"),
+            select_items("SELECT NOT a.b AND (b OR c)")
+        );
+        assert_eq!(
+            select_items("SELECT func(arg1, True)"),
+            vec![
+                "Function { func_name: \"func\", arguments: [Identifier(\"arg1\"), Value(Boolean(true))] }"
+            ]
+        );
+        assert_eq!(
+            select_items("SELECT a.b.c, a.b.*, a.label, a.id"),
+            vec![
+                "CompoundIdentifier([\"a\", \"b\", \"c\"])",
+                "CompoundWildcard([\"a\", \"b\"])",
+                "LabelExpr(\"a\")",
+                "IdExpr(\"a\")"
+            ]
+        );
+        assert_eq!(select_items("SELECT func(a, func2(b, c+1))").len(), 1);
+        assert_eq!(
+            select_items("SELECT a.label IN ('person', 'dog'), b NOT IN (1) OR c"),
+            vec![
+                "InList { expr: LabelExpr(\"a\"), list: [Value(String(\"person\")), Value(String(\"dog\"))] }",
+                "BinaryOp { op: Or, left: UnaryOp { op: Not, expr: InList { expr: Identifier(\"b\"), list: [Value(Number(\"1\"))] } }, right: Identifier(\"c\") }"
+            ]
+        );
+        assert!(Parser::parse_sql("SELECT a IN 1").is_err());
+        assert_eq!(
+            select_items("SELECT a.name LIKE 'Jo%' AND b"),
+            vec![
+                "BinaryOp { op: And, left: BinaryOp { op: Like, left: CompoundIdentifier([\"a\", \"name\"]), right: Value(String(\"Jo%\")) }, right: Identifier(\"b\") }"
+            ]
+        );
+        assert_eq!(
+            select_items("SELECT a.b IS NOT NULL AND c IS NULL"),
+            vec![
+                "BinaryOp { op: And, left: IsNull { expr: CompoundIdentifier([\"a\", \"b\"]), negated: true }, right: IsNull { expr: Identifier(\"c\"), negated: false } }"
+            ]
+        );
+        assert_eq!(
+            select_items("SELECT 0x1F, 0b101, 1_000 LIMIT 0x10"),
+            vec![
+                "Value(Number(\"31\"))",
+                "Value(Number(\"5\"))",
+                "Value(Number(\"1000\"))"
+            ]
+        );
     }
 
     #[test]
     fn test_parse_select() {
-        let stmts = Parser::parse_sql("SELECT * FROM (a) - [e] -> (b)").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("SELECT * FROM (b) <- [e] - (a)").unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql("SELECT * FROM (b) <- [e] - (a) - [e2] -> (c)").unwrap();
-        println!("{:?}", stmts);
-        let stmts =
-            Parser::parse_sql("SELECT * FROM (b) <- [e] - (a) <- [e2] - (c), (b) - [e3] -> (c)")
-                .unwrap();
-        println!("{:?}", stmts);
-        let stmts = Parser::parse_sql(
+        let triplets = |sql: &str| match parse_one(sql) {
+            Statement::Select { graph_pattern, .. } => graph_pattern
+                .triplets
+                .iter()
+                .map(|t| format!("{:?}-{:?}->{:?}", t.src, t.edge, t.dst))
+                .collect::<Vec<String>>(),
+            stmt => panic!("not a select: {:?}", stmt),
+        };
+        let triplet = |src: &str, edge: &str, dst: &str| {
+            format!(
+                "Identifier({:?})-Identifier({:?})->Identifier({:?})",
+                src, edge, dst
+            )
+        };
+        assert_eq!(
+            triplets("SELECT * FROM (a) - [e] -> (b)"),
+            vec![triplet("a", "e", "b")]
+        );
+        assert_eq!(
+            triplets("SELECT * FROM (b) <- [e] - (a)"),
+            vec![triplet("a", "e", "b")]
+        );
+        assert_eq!(
+            triplets("SELECT * -- every element\nFROM (a) /* edge */ - [e] -> (b)"),
+            vec![triplet("a", "e", "b")]
+        );
+        assert_eq!(
+            triplets("SELECT * FROM (b) <- [e] - (a) - [e2] -> (c)"),
+            vec![triplet("a", "e", "b"), triplet("a", "e2", "c")]
+        );
+        assert_eq!(
+            triplets("SELECT * FROM (b) <- [e] - (a) <- [e2] - (c), (b) - [e3] -> (c)"),
+            vec![
+                triplet("a", "e", "b"),
+                triplet("c", "e2", "a"),
+                triplet("b", "e3", "c")
+            ]
+        );
+        match parse_one(
             "SELECT * FROM (b) <- [e] - (a) <- [e2] - (c) WHERE a.label = 'person' AND e.label == 'knows' AND a.id > '1' AND b.prop2 < 4 ",
-        ).unwrap();
-        println!("{:?}", stmts);
+        ) {
+            Statement::Select {
+                condition: Some(Expr::BinaryOp { op, .. }),
+                ..
+            } => assert_eq!(op, BinaryOperator::And),
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_parse_variable_length_edges() {
+        let hops = |sql: &str| match parse_one(sql) {
+            Statement::Select { graph_pattern, .. } => graph_pattern
+                .triplets
+                .iter()
+                .map(|t| t.hops)
+                .collect::<Vec<_>>(),
+            stmt => panic!("not a select: {:?}", stmt),
+        };
+        assert_eq!(
+            hops("SELECT * FROM (a)-[e*1..3]->(b)<-[e2]-(c), (c)<-[e3 * 2]-(d)"),
+            vec![(1, 3), (1, 1), (2, 2)]
+        );
+        assert!(Parser::parse_sql("SELECT * FROM (a)-[e*0..2]->(b)").is_err());
+        assert!(Parser::parse_sql("SELECT * FROM (a)-[e*3..2]->(b)").is_err());
+        assert!(Parser::parse_sql("SELECT * FROM (a)-[e*]->(b)").is_err());
+        assert!(Parser::parse_sql("SELECT * FROM (a)-[e*1.5]->(b)").is_err());
+    }
+
+    #[test]
+    fn test_parse_edge_direction() {
+        for sql in &["SELECT * FROM (a)-[e]->(b)", "SELECT * FROM (b)<-[e]-(a)"] {
+            match parse_one(sql) {
+                Statement::Select { graph_pattern, .. } => {
+                    let triplet = &graph_pattern.triplets[0];
+                    assert_eq!(*triplet.src, Expr::Identifier("a".to_owned()));
+                    assert_eq!(*triplet.dst, Expr::Identifier("b".to_owned()));
+                }
+                stmt => panic!("not a select: {:?}", stmt),
+            }
+        }
+        match parse_one("SELECT * FROM (a)-[e]-(b)<-[e2]-(c)") {
+            Statement::Select { graph_pattern, .. } => {
+                let undirected = graph_pattern.triplets.iter().map(|t| t.undirected);
+                assert_eq!(undirected.collect::<Vec<_>>(), vec![true, false]);
+            }
+            stmt => panic!("not a select: {:?}", stmt),
+        }
+        let err = |sql: &str| Parser::parse_sql(sql).unwrap_err().to_string();
+        assert_eq!(
+            err("SELECT * FROM (a)-[e*1..2]-(b)"),
+            "ParserError: undirected edges can not have a variable length at line 1, col 28"
+        );
+        assert_eq!(
+            err("SELECT * FROM (a)-[e]x(b)"),
+            "ParserError: Expect `->` but found `x` at line 1, col 22"
+        );
+        assert!(err("SELECT * FROM (a)-[e](b)").starts_with("ParserError: Expect `->`"));
+        assert!(err("SELECT * FROM (b)<-[e](a)").starts_with("ParserError: Expect `-`"));
+        assert!(err("SELECT * FROM (b)<-[e]->(a)").starts_with("ParserError: Expect `-`"));
+    }
+
+    #[test]
+    fn test_parse_inline_labels() {
+        match parse_one("SELECT * FROM (a:person)-[e:knows*1..2]->(b), (b)<-[e2]-(c:person)") {
+            Statement::Select { graph_pattern, .. } => {
+                assert_eq!(graph_pattern.triplets[0].hops, (1, 2));
+                assert_eq!(
+                    graph_pattern.labels,
+                    vec![
+                        ("a".to_owned(), "person".to_owned()),
+                        ("e".to_owned(), "knows".to_owned()),
+                        ("c".to_owned(), "person".to_owned()),
+                    ]
+                );
+            }
+            stmt => panic!("not a select: {:?}", stmt),
+        }
+        assert!(Parser::parse_sql("SELECT * FROM (a:person)-[e]->(a:person)").is_ok());
+        assert!(Parser::parse_sql("SELECT * FROM (a:person)-[e]->(a:dog)").is_err());
+        assert!(Parser::parse_sql("SELECT * FROM (a:)").is_err());
+    }
+
+    #[test]
+    fn test_parse_anonymous_elements() {
+        match parse_one("SELECT * FROM (a)-[]->()<-[:knows*2]-(:person)") {
+            Statement::Select { graph_pattern, .. } => {
+                let names = graph_pattern
+                    .vertices
+                    .iter()
+                    .chain(graph_pattern.triplets.iter().map(|t| &*t.edge))
+                    .map(|expr| match expr {
+                        Expr::Identifier(name) => name.as_str(),
+                        expr => panic!("not an identifier: {:?}", expr),
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    names,
+                    vec!["a", "__anon_1", "__anon_3", "__anon_0", "__anon_2"]
+                );
+                assert_eq!(graph_pattern.triplets[1].hops, (2, 2));
+                assert_eq!(
+                    graph_pattern.labels,
+                    vec![
+                        ("__anon_2".to_owned(), "knows".to_owned()),
+                        ("__anon_3".to_owned(), "person".to_owned()),
+                    ]
+                );
+            }
+            stmt => panic!("not a select: {:?}", stmt),
+        }
+        assert!(Parser::parse_sql("SELECT * FROM (1)").is_err());
+        // the generated names can't be given
+        assert!(Parser::parse_sql("SELECT b.id FROM (__anon_0)-[]->(b)").is_err());
+        assert!(Parser::parse_sql("SELECT a.id FROM (a)-[__anon_1]->()").is_err());
+    }
+
+    #[test]
+    fn test_parse_exists() {
+        match parse_one("SELECT a.id FROM (a) WHERE NOT EXISTS((a)-[:knows]->()) AND a.age > 1") {
+            Statement::Select {
+                condition: Some(Expr::BinaryOp { left, .. }),
+                ..
+            } => match *left {
+                Expr::UnaryOp {
+                    op: UnaryOperator::Not,
+                    expr,
+                } => match *expr {
+                    Expr::Exists(pattern) => {
+                        assert_eq!(pattern.triplets.len(), 1);
+                        assert_eq!(pattern.labels[0].1, "knows");
+                    }
+                    expr => panic!("not an exists: {:?}", expr),
+                },
+                expr => panic!("not a negation: {:?}", expr),
+            },
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        assert!(Parser::parse_sql("SELECT a.id FROM (a) WHERE EXISTS (a)").is_err());
+    }
+
+    #[test]
+    fn test_error_position() {
+        let err = Parser::parse_sql("SELECT a.id\nFROM (a)\nWHERE a.age > )").unwrap_err();
+        assert_eq!(
+            err,
+            ParserError::ParserError("syntax error at line 3, col 15".to_owned())
+        );
+        let err = Parser::parse_sql("SELECT a.id\n  FROM (a) WHER a.age").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ParserError: Expect `;` but found `WHER` at line 2, col 12"
+        );
+        let err = Parser::parse_expression("1 +\n  'a").unwrap_err();
+        assert!(err.to_string().ends_with("at line 2, col 3"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_order_by() {
+        match parse_one("SELECT a.id FROM (a) ORDER BY a.name DESC NULLS LAST") {
+            Statement::Select { order_by, .. } => {
+                assert_eq!(order_by.len(), 1);
+                assert!(!order_by[0].asc);
+                assert!(!order_by[0].nulls_first());
+            }
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        match parse_one("SELECT a.id FROM (a) ORDER BY a.label, a.age DESC, a.name ASC") {
+            Statement::Select { order_by, .. } => assert_eq!(
+                order_by.iter().map(|key| key.asc).collect::<Vec<_>>(),
+                vec![true, false, true]
+            ),
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_parse_group_by_limit() {
+        match parse_one(
+            "SELECT a.label, count(*) FROM (a) GROUP BY a.label ORDER BY count(*) DESC LIMIT 5",
+        ) {
+            Statement::Select {
+                group_by,
+                order_by,
+                limit: Some(5),
+                ..
+            } => {
+                assert_eq!(format!("{:?}", group_by), "[LabelExpr(\"a\")]");
+                assert_eq!(order_by.len(), 1);
+            }
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        assert!(Parser::parse_sql("SELECT a.id FROM (a) LIMIT -1").is_err());
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        match parse_one("SELECT a.prop1 AS name, count(*) AS total, a.id FROM (a)") {
+            Statement::Select { items, aliases, .. } => {
+                assert_eq!(items.len(), 3);
+                assert_eq!(
+                    aliases,
+                    vec![Some("name".to_string()), Some("total".to_string()), None]
+                );
+            }
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        assert!(Parser::parse_sql("SELECT a.id AS FROM (a)").is_err());
+        assert!(Parser::parse_sql("SELECT a.id AS 'x' FROM (a)").is_err());
+        assert!(Parser::parse_sql("SELECT * AS x FROM (a)").is_err());
+    }
+
+    #[test]
+    fn test_parse_limit_offset() {
+        match parse_one("SELECT a.id FROM (a) WHERE a.age > 1 LIMIT 10 OFFSET 20") {
+            Statement::Select {
+                condition: Some(_),
+                limit: Some(10),
+                offset: Some(20),
+                ..
+            } => {}
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        match parse_one("SELECT a.id FROM (a) LIMIT 10") {
+            Statement::Select {
+                limit: Some(10),
+                offset: None,
+                ..
+            } => {}
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        assert!(Parser::parse_sql("SELECT a.id FROM (a) OFFSET 2").is_err());
+        assert!(Parser::parse_sql("SELECT a.id FROM (a) LIMIT 1 OFFSET").is_err());
+    }
+
+    #[test]
+    fn test_parse_delete() {
+        match parse_one("DELETE (a), [e] FROM (a) - [e] -> (b) WHERE b.id = 'v1'") {
+            Statement::Delete {
+                elements,
+                graph_pattern,
+                condition: Some(_),
+            } => {
+                assert_eq!(
+                    format!("{:?}", elements),
+                    "[Identifier(\"a\"), Identifier(\"e\")]"
+                );
+                assert_eq!(graph_pattern.triplets.len(), 1);
+            }
+            stmt => panic!("unexpected {:?}", stmt),
+        }
+        assert!(matches!(
+            parse_one("DELETE a FROM (a)"),
+            Statement::Delete {
+                condition: None,
+                ..
+            }
+        ));
+        assert!(Parser::parse_sql("DELETE (a FROM (a)").is_err());
+    }
+
+    /// `expr` without the parentheses written around its operands.
+    fn strip_nested(expr: Expr) -> Expr {
+        match expr {
+            Expr::Nested(expr) => strip_nested(*expr),
+            Expr::UnaryOp { op, expr } => UnaryOp {
+                op,
+                expr: Box::new(strip_nested(*expr)),
+            },
+            Expr::BinaryOp { op, left, right } => Expr::BinaryOp {
+                op,
+                left: Box::new(strip_nested(*left)),
+                right: Box::new(strip_nested(*right)),
+            },
+            Expr::InList { expr, list } => Expr::InList {
+                expr: Box::new(strip_nested(*expr)),
+                list: list.into_iter().map(strip_nested).collect(),
+            },
+            Expr::IsNull { expr, negated } => Expr::IsNull {
+                expr: Box::new(strip_nested(*expr)),
+                negated,
+            },
+            expr => expr,
+        }
+    }
+
+    #[test]
+    fn test_display_expr() {
+        for (expr, displayed) in vec![
+            ("(a + b) * c", "(a + b) * c"),
+            ("a + (b * c)", "a + b * c"),
+            ("a - (b - c)", "a - (b - c)"),
+            ("(a - b) - c", "a - b - c"),
+            ("(2 ^ 3) ^ 2", "(2 ^ 3) ^ 2"),
+            ("2 ^ (3 ^ 2)", "2 ^ 3 ^ 2"),
+            ("-(a + b)", "-(a + b)"),
+            ("(-a) * b", "(-a) * b"),
+            ("a * (-b) * c", "a * (-b) * c"),
+            ("-(-a)", "-(-a)"),
+            ("NOT (a = b) AND (c OR d)", "NOT a = b AND (c OR d)"),
+            ("(NOT a) = b", "(NOT a) = b"),
+            ("(a = b) IS NOT NULL", "a = b IS NOT NULL"),
+            ("a = (b IS NULL)", "a = (b IS NULL)"),
+            (
+                "a.label NOT IN ('x', 'it\\'s')",
+                "NOT a.label IN ('x', 'it\\'s')",
+            ),
+            (
+                "count(*) > 1.5 OR id(a) != a.b.c",
+                "count(*) > 1.5 OR id(a) != a.b.c",
+            ),
+            (
+                "a.id == TRUE OR a.x LIKE NULL",
+                "a.id = TRUE OR a.x LIKE NULL",
+            ),
+        ] {
+            let expr = strip_nested(Parser::parse_expression(expr).unwrap());
+            assert_eq!(expr.to_string(), displayed);
+            let reparsed = strip_nested(Parser::parse_expression(&expr.to_string()).unwrap());
+            assert_eq!(reparsed, expr, "{}", displayed);
+        }
+    }
+
+    #[test]
+    fn test_display_statement() {
+        for sql in vec![
+            "SHOW VERTEX LABEL",
+            "SHOW CREATE EDGE LABEL knows",
+            "CREATE VERTEX LABEL person",
+            "CREATE EDGE LABEL (knows, many2manysimple)",
+            "CREATE PROPERTY KEY (age, single, DEFAULT 0)",
+            "CREATE SCHEMA { VERTEX LABELS (person, dog); PROPERTY KEYS ((name, single)); \
+             VERTEX LABELS (cat) }",
+            "CREATE SCHEMA { }",
+            "DROP PROPERTY KEY age",
+            "INSERT VERTEX person PROPERTIES (name, age) VALUES ('v1'):('it\\'s', -1)",
+            "INSERT EDGE knows PROPERTIES (since) VALUES ('v1' -> 'v2'):(2020)",
+            "INSERT EDGE knows BETWEEN ALL (a WHERE a.age > 1) AND (b) \
+             PROPERTIES (since) VALUES (2020)",
+            "DELETE a, e FROM (a)-[e]->(b) WHERE b.id = 'v1'",
+            "SELECT *",
+            "SELECT a.name AS name, count(*) FROM (a:person)-[e:knows*1..3]->(b), (c) \
+             SAMPLE 10 WHERE (a.age + 1) * 2 > 3 GROUP BY a.name \
+             ORDER BY a.name DESC NULLS LAST, b.age LIMIT 5 OFFSET 1",
+            "SELECT b FROM (a)<-[:knows]-(), (a:person)-[e]-(b:person)<-[f*2]-(a) \
+             WHERE EXISTS((b)-[]->(:dog))",
+            "SELECT x FROM (a), ()-[]->(a), (x)-[]->(x)",
+            "EXPLAIN SELECT a FROM (a) WHERE a.id > 'v1'",
+        ] {
+            let stmt = parse_one(sql);
+            let displayed = stmt.to_string();
+            assert_eq!(parse_one(&displayed), stmt, "{}", displayed);
+        }
+        assert_eq!(
+            parse_one("SELECT a.name FROM (a) -[ ]-> ( b : person ) WHERE a.x = -(1 + 2)")
+                .to_string(),
+            "SELECT a.name FROM (a)-[]->(b:person) WHERE a.x = -(1 + 2)"
+        );
     }
 }
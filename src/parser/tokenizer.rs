@@ -6,7 +6,6 @@ use std::str::FromStr;
 use std::string::ToString;
 
 use crate::parser::keyword::Keyword;
-use crate::parser::parser::ParserError::TokenizerError;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Token {
@@ -55,7 +54,7 @@ pub enum Token {
     /// ;
     SemiColon,
     /// !
-    Question,
+    Bang,
     /// \
     Backslash,
     /// _
@@ -91,44 +90,44 @@ pub enum Token {
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Token::EOF => write!(f, "{}", "EOF"),
-            Token::Keyword(keyword) => write!(f, "{}", keyword.to_string()),
+            Token::EOF => write!(f, "EOF"),
+            Token::Keyword(keyword) => write!(f, "{}", keyword),
             Token::Identifier(ident) => write!(f, "{}", ident),
             Token::Number(n) => write!(f, "{}", n),
             Token::String(s) => write!(f, "{}", s),
-            Token::Whitespace(ws) => write!(f, "{}", ws.to_string()),
-            Token::Eq => write!(f, "{}", "="),
-            Token::DoubleEq => write!(f, "{}", "=="),
-            Token::Neq => write!(f, "{}", "!="),
-            Token::Lt => write!(f, "{}", "<"),
-            Token::Gt => write!(f, "{}", ">"),
-            Token::Lte => write!(f, "{}", "<="),
-            Token::Gte => write!(f, "{}", ">="),
-            Token::Plus => write!(f, "{}", "+"),
-            Token::Minus => write!(f, "{}", "-"),
-            Token::Star => write!(f, "{}", "*"),
-            Token::Slash => write!(f, "{}", "/"),
-            Token::Percent => write!(f, "{}", "%"),
-            Token::Comma => write!(f, "{}", ","),
-            Token::Dot => write!(f, "{}", "."),
-            Token::Colon => write!(f, "{}", ":"),
-            Token::SemiColon => write!(f, "{}", ";"),
-            Token::Question => write!(f, "{}", "?"),
-            Token::Backslash => write!(f, "{}", "\\"),
-            Token::UnderScore => write!(f, "{}", "_"),
-            Token::Ampersand => write!(f, "{}", "&"),
-            Token::Bar => write!(f, "{}", "|"),
-            Token::Caret => write!(f, "{}", "^"),
-            Token::Dollar => write!(f, "{}", "$"),
-            Token::Sharp => write!(f, "{}", "#"),
-            Token::LeftParen => write!(f, "{}", "("),
-            Token::RightParen => write!(f, "{}", ")"),
-            Token::LeftBracket => write!(f, "{}", "["),
-            Token::RightBracket => write!(f, "{}", "]"),
-            Token::LeftBrace => write!(f, "{}", "{"),
-            Token::RightBrace => write!(f, "{}", "}"),
-            Token::LeftArrow => write!(f, "{}", "<-"),
-            Token::RightArrow => write!(f, "{}", "->"),
+            Token::Whitespace(ws) => write!(f, "{}", ws),
+            Token::Eq => write!(f, "="),
+            Token::DoubleEq => write!(f, "=="),
+            Token::Neq => write!(f, "!="),
+            Token::Lt => write!(f, "<"),
+            Token::Gt => write!(f, ">"),
+            Token::Lte => write!(f, "<="),
+            Token::Gte => write!(f, ">="),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Star => write!(f, "*"),
+            Token::Slash => write!(f, "/"),
+            Token::Percent => write!(f, "%"),
+            Token::Comma => write!(f, ","),
+            Token::Dot => write!(f, "."),
+            Token::Colon => write!(f, ":"),
+            Token::SemiColon => write!(f, ";"),
+            Token::Bang => write!(f, "!"),
+            Token::Backslash => write!(f, "\\"),
+            Token::UnderScore => write!(f, "_"),
+            Token::Ampersand => write!(f, "&"),
+            Token::Bar => write!(f, "|"),
+            Token::Caret => write!(f, "^"),
+            Token::Dollar => write!(f, "$"),
+            Token::Sharp => write!(f, "#"),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::LeftBracket => write!(f, "["),
+            Token::RightBracket => write!(f, "]"),
+            Token::LeftBrace => write!(f, "{{"),
+            Token::RightBrace => write!(f, "}}"),
+            Token::LeftArrow => write!(f, "<-"),
+            Token::RightArrow => write!(f, "->"),
         }
     }
 }
@@ -138,6 +137,10 @@ pub enum Whitespace {
     Space,
     Tab,
     Newline,
+    /// `-- comment` up to the end of the line, without the newline
+    SingleLineComment(String),
+    /// `/* comment */`
+    MultiLineComment(String),
 }
 
 impl Display for Whitespace {
@@ -145,11 +148,20 @@ impl Display for Whitespace {
         match self {
             Whitespace::Space => write!(f, " "),
             Whitespace::Tab => write!(f, "\t"),
-            Whitespace::Newline => write!(f, "\n"),
+            Whitespace::Newline => writeln!(f),
+            Whitespace::SingleLineComment(comment) => write!(f, "--{}", comment),
+            Whitespace::MultiLineComment(comment) => write!(f, "/*{}*/", comment),
         }
     }
 }
 
+/// position of the first character of a token, both from 1.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Span {
+    pub line: u64,
+    pub col: u64,
+}
+
 pub struct Tokenizer {
     pub query: String,
     pub line: u64,
@@ -166,32 +178,70 @@ impl Tokenizer {
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<Token>, TokenizeError> {
-        let mut chars = self.query.chars().peekable();
+        let tokens = self.tokenize_with_spans()?;
+        Ok(tokens.into_iter().map(|(token, _)| token).collect())
+    }
+
+    /// the tokens with their positions, for errors to point at the token.
+    pub fn tokenize_with_spans(&mut self) -> Result<Vec<(Token, Span)>, TokenizeError> {
+        let query = self.query.clone();
+        let mut chars = query.chars().peekable();
 
         let mut tokens = vec![];
 
-        while let Some(token) = self.next_token(&mut chars)? {
+        loop {
+            let span = Span {
+                line: self.line,
+                col: self.col,
+            };
+            // escaped quotes of a string take more columns than its value
+            let quote = chars.peek().copied().unwrap_or('\'');
+            let token = match self.next_token(&mut chars)? {
+                Some(token) => token,
+                None => break,
+            };
             match &token {
                 Token::Whitespace(Whitespace::Newline) => {
                     self.line += 1;
                     self.col = 1;
                 }
                 Token::Whitespace(Whitespace::Tab) => self.col += 4,
-                Token::Keyword(s) => self.col += s.to_string().len() as u64,
-                Token::Identifier(s) => self.col += s.len() as u64,
-                Token::Number(s) => self.col += s.len() as u64,
-                Token::String(s) => self.col += s.len() as u64 + 2,
+                Token::Whitespace(comment) => self.advance(&comment.to_string()),
+                Token::Keyword(s) => self.advance(&s.to_string()),
+                Token::Identifier(s) | Token::Number(s) => self.advance(s),
+                Token::String(s) => {
+                    let escaped = s.replace(quote, &format!("\\{}", quote));
+                    self.advance(&format!("{}{}{}", quote, escaped, quote))
+                }
                 Token::DoubleEq | Token::Lte | Token::Gte | Token::Neq => self.col += 2,
                 _ => self.col += 1,
             }
-            tokens.push(token);
+            tokens.push((token, span));
         }
 
-        tokens.push(Token::EOF);
+        tokens.push((
+            Token::EOF,
+            Span {
+                line: self.line,
+                col: self.col,
+            },
+        ));
 
         Ok(tokens)
     }
 
+    /// move the position past the source `text` of a token, which may span lines.
+    fn advance(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match text.rfind('\n') {
+            Some(last) => {
+                self.line += text.matches('\n').count() as u64;
+                self.col = text[last + 1..].chars().count() as u64 + 1;
+            }
+            None => self.col += text.chars().count() as u64,
+        }
+    }
+
     fn error<T>(&self, message: &str) -> Result<T, TokenizeError> {
         Err(TokenizeError {
             message: message.to_string(),
@@ -224,11 +274,9 @@ impl Tokenizer {
                     Token::Eq,
                     vec![('=', Token::DoubleEq)],
                 ),
-                '!' => Self::consume_token_and_match_peek(
-                    chars,
-                    Token::Question,
-                    vec![('=', Token::Neq)],
-                ),
+                '!' => {
+                    Self::consume_token_and_match_peek(chars, Token::Bang, vec![('=', Token::Neq)])
+                }
                 '<' => Self::consume_token_and_match_peek(
                     chars,
                     Token::Lt,
@@ -238,59 +286,164 @@ impl Tokenizer {
                     Self::consume_token_and_match_peek(chars, Token::Gt, vec![('=', Token::Gte)])
                 }
                 '+' => Self::consume_token(chars, Token::Plus),
-                '-' => Self::consume_token_and_match_peek(
-                    chars,
-                    Token::Minus,
-                    vec![('>', Token::RightArrow)],
-                ),
+                '-' => {
+                    chars.next();
+                    match chars.peek() {
+                        Some('>') => Self::consume_token(chars, Token::RightArrow),
+                        // only adjacent dashes, `- -b` is arithmetic
+                        Some('-') => {
+                            chars.next();
+                            let comment = Self::consume_while(chars, |c| c != '\n' && c != '\r');
+                            Ok(Some(Token::Whitespace(Whitespace::SingleLineComment(
+                                comment,
+                            ))))
+                        }
+                        _ => Ok(Some(Token::Minus)),
+                    }
+                }
                 '*' => Self::consume_token(chars, Token::Star),
-                '/' => Self::consume_token(chars, Token::Slash),
+                '/' => {
+                    chars.next();
+                    match chars.peek() {
+                        Some('*') => {
+                            chars.next();
+                            self.tokenize_block_comment(chars)
+                        }
+                        _ => Ok(Some(Token::Slash)),
+                    }
+                }
                 '%' => Self::consume_token(chars, Token::Percent),
                 ',' => Self::consume_token(chars, Token::Comma),
                 '.' => Self::consume_token(chars, Token::Dot),
                 ':' => Self::consume_token(chars, Token::Colon),
                 ';' => Self::consume_token(chars, Token::SemiColon),
                 '\\' => Self::consume_token(chars, Token::Backslash),
-                '_' => Self::consume_token(chars, Token::UnderScore),
                 '&' => Self::consume_token(chars, Token::Ampersand),
                 '|' => Self::consume_token(chars, Token::Bar),
                 '^' => Self::consume_token(chars, Token::Caret),
                 '$' => Self::consume_token(chars, Token::Dollar),
                 '#' => Self::consume_token(chars, Token::Sharp),
                 c if Self::is_identifier_start(c) => self.tokenize_identifier_or_keyword(chars),
-                '0'..='9' => {
-                    let s = Self::consume_while(chars, |x| match x {
-                        '0'..='9' | '.' => true,
-                        _ => false,
-                    });
-                    Ok(Some(Token::Number(s)))
-                }
+                '0'..='9' => self.tokenize_number(chars),
                 _ => self.error("unexpected token!"),
             },
             None => Ok(None),
         }
     }
 
+    /// a decimal number `digits[.digits][e[+-]digits]`, or a `0x` hex or `0b` binary
+    /// integer. digits can be separated by single `_`s, e.g. `1_000`. a second decimal point
+    /// is an error, `1..3` is a range and not a number. the token keeps the literal as
+    /// written, see `decimal_number`.
+    fn tokenize_number(&self, chars: &mut Peekable<Chars>) -> Result<Option<Token>, TokenizeError> {
+        let is_digit = |c: char| c.is_ascii_digit() || c == '_';
+        if chars.peek() == Some(&'0') {
+            let radix = match chars.clone().nth(1) {
+                Some('x') | Some('X') => Some(16),
+                Some('b') | Some('B') => Some(2),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                let mut s: String = chars.take(2).collect();
+                let digits = Self::consume_while(chars, |c| c.is_ascii_alphanumeric() || c == '_');
+                s.push_str(&digits);
+                if !Self::is_digits(&digits, radix) {
+                    return self.error(&format!("malformed number {}", s));
+                }
+                if u64::from_str_radix(&digits.replace('_', ""), radix).is_err() {
+                    return self.error(&format!("number {} is out of range", s));
+                }
+                return Ok(Some(Token::Number(s)));
+            }
+        }
+
+        let mut s = Self::consume_while(chars, is_digit);
+        let mut valid = Self::is_digits(&s, 10);
+        let next_is_digit = |chars: &Peekable<Chars>, n: usize| {
+            chars.clone().nth(n).is_some_and(|c| c.is_ascii_digit())
+        };
+        if chars.peek() == Some(&'.') && chars.clone().nth(1) != Some('.') {
+            s.push(chars.next().unwrap());
+            let fraction = Self::consume_while(chars, is_digit);
+            valid &= fraction.is_empty() || Self::is_digits(&fraction, 10);
+            s.push_str(&fraction);
+        }
+        if let Some('e') | Some('E') = chars.peek() {
+            let sign = matches!(chars.clone().nth(1), Some('+') | Some('-'));
+            if next_is_digit(chars, if sign { 2 } else { 1 }) {
+                s.push(chars.next().unwrap());
+                if sign {
+                    s.push(chars.next().unwrap());
+                }
+                let exponent = Self::consume_while(chars, is_digit);
+                valid &= Self::is_digits(&exponent, 10);
+                s.push_str(&exponent);
+            }
+        }
+        if !valid {
+            return self.error(&format!("malformed number {}", s));
+        }
+        if chars.peek() == Some(&'.') && next_is_digit(chars, 1) {
+            return self.error(&format!("malformed number {}.", s));
+        }
+        // `1abc` is neither a number nor an identifier, rather than two adjacent tokens
+        if chars.peek().is_some_and(|c| Self::is_identifier_char(*c)) {
+            let rest = Self::consume_while(chars, Self::is_identifier_char);
+            return self.error(&format!(
+                "malformed number {}{}, identifiers can not start with a digit",
+                s, rest
+            ));
+        }
+        Ok(Some(Token::Number(s)))
+    }
+
+    /// whether `digits` are digits of `radix` separated by single `_`s.
+    fn is_digits(digits: &str, radix: u32) -> bool {
+        !digits.is_empty()
+            && !digits.starts_with('_')
+            && !digits.ends_with('_')
+            && !digits.contains("__")
+            && digits.chars().all(|c| c == '_' || c.is_digit(radix))
+    }
+
     fn is_identifier_start(c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z')
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_identifier_char(c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || (c >= '0' && c <= '9') || c == '_'
+        c.is_ascii_alphanumeric() || c == '_'
     }
 
     fn tokenize_identifier_or_keyword(
         &self,
         chars: &mut Peekable<Chars>,
     ) -> Result<Option<Token>, TokenizeError> {
-        let s = Self::consume_while(chars, |x| Self::is_identifier_char(x));
+        let s = Self::consume_while(chars, Self::is_identifier_char);
 
         match Keyword::from_str(&s.to_uppercase()) {
             Ok(kw) => Ok(Some(Token::Keyword(kw))),
-            Err(e) => Ok(Some(Token::Identifier(s))),
+            Err(_) => Ok(Some(Token::Identifier(s))),
         }
     }
 
+    /// the rest of a `/* ... */` comment after its `/*`. comments do not nest.
+    fn tokenize_block_comment(
+        &self,
+        chars: &mut Peekable<Chars>,
+    ) -> Result<Option<Token>, TokenizeError> {
+        let mut comment = String::new();
+        while let Some(c) = chars.next() {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                return Ok(Some(Token::Whitespace(Whitespace::MultiLineComment(
+                    comment,
+                ))));
+            }
+            comment.push(c);
+        }
+        self.error("unterminated block comment")
+    }
+
     fn tokenize_quoted_string(
         &self,
         chars: &mut Peekable<Chars>,
@@ -354,6 +507,21 @@ impl Tokenizer {
     }
 }
 
+/// the decimal form of a number token, without its `_` separators.
+pub fn decimal_number(number: &str) -> String {
+    let number = number.replace('_', "");
+    let radix = match number.get(..2) {
+        Some("0x") | Some("0X") => 16,
+        Some("0b") | Some("0B") => 2,
+        _ => return number,
+    };
+    match u64::from_str_radix(&number[2..], radix) {
+        Ok(n) => n.to_string(),
+        Err(_) => number,
+    }
+}
+
+#[derive(Debug)]
 pub struct TokenizeError {
     pub message: String,
     pub line: u64,
@@ -367,7 +535,7 @@ mod test {
     #[test]
     fn test_select() {
         let sql = "SELECT * FROM label1 Where a = 123 and b != '456'";
-        let mut tokenizer = Tokenizer::new(&sql);
+        let mut tokenizer = Tokenizer::new(sql);
         let tokens = tokenizer.tokenize().unwrap_or_default();
 
         assert_eq!(
@@ -401,10 +569,183 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_range() {
+        let mut tokenizer = Tokenizer::new("1..3 1.5");
+        assert_eq!(
+            tokenizer.tokenize().unwrap_or_default(),
+            vec![
+                Token::Number("1".to_owned()),
+                Token::Dot,
+                Token::Dot,
+                Token::Number("3".to_owned()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Number("1.5".to_owned()),
+                Token::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bang() {
+        let tokenize = |sql: &str| {
+            Tokenizer::new(sql)
+                .tokenize()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|token| !matches!(token, Token::Whitespace(_)))
+                .collect::<Vec<_>>()
+        };
+        let ident = |s: &str| Token::Identifier(s.to_owned());
+        assert_eq!(
+            tokenize("a != b"),
+            vec![ident("a"), Token::Neq, ident("b"), Token::EOF]
+        );
+        assert_eq!(tokenize("!a"), vec![Token::Bang, ident("a"), Token::EOF]);
+        assert_eq!(
+            tokenize("! =a"),
+            vec![Token::Bang, Token::Eq, ident("a"), Token::EOF]
+        );
+    }
+
+    #[test]
+    fn test_underscore_identifier() {
+        let mut tokenizer = Tokenizer::new("_foo_1 __anon_0._x");
+        assert_eq!(
+            tokenizer.tokenize().unwrap_or_default(),
+            vec![
+                Token::Identifier("_foo_1".to_owned()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Identifier("__anon_0".to_owned()),
+                Token::Dot,
+                Token::Identifier("_x".to_owned()),
+                Token::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let tokenize = |sql: &str| Tokenizer::new(sql).tokenize().unwrap_or_default();
+        let ident = |s: &str| Token::Identifier(s.to_owned());
+        assert_eq!(
+            tokenize("a -- one\nb"),
+            vec![
+                ident("a"),
+                Token::Whitespace(Whitespace::Space),
+                Token::Whitespace(Whitespace::SingleLineComment(" one".to_owned())),
+                Token::Whitespace(Whitespace::Newline),
+                ident("b"),
+                Token::EOF,
+            ]
+        );
+        assert_eq!(
+            tokenize("a/* x\n * y */b"),
+            vec![
+                ident("a"),
+                Token::Whitespace(Whitespace::MultiLineComment(" x\n * y ".to_owned())),
+                ident("b"),
+                Token::EOF,
+            ]
+        );
+        assert_eq!(
+            tokenize("a - -b"),
+            vec![
+                ident("a"),
+                Token::Whitespace(Whitespace::Space),
+                Token::Minus,
+                Token::Whitespace(Whitespace::Space),
+                Token::Minus,
+                ident("b"),
+                Token::EOF,
+            ]
+        );
+        assert_eq!(
+            tokenize("a/b"),
+            vec![ident("a"), Token::Slash, ident("b"), Token::EOF]
+        );
+        assert_eq!(
+            tokenize("2^10"),
+            vec![
+                Token::Number("2".to_owned()),
+                Token::Caret,
+                Token::Number("10".to_owned()),
+                Token::EOF
+            ]
+        );
+
+        // positions after a comment spanning lines
+        let mut tokenizer = Tokenizer::new("a /* x\ny */ `");
+        let err = tokenizer.tokenize().unwrap_err();
+        assert_eq!((2, 6), (err.line, err.col));
+
+        let err = Tokenizer::new("a /* b").tokenize().unwrap_err();
+        assert_eq!(err.message, "unterminated block comment");
+        assert_eq!((1, 3), (err.line, err.col));
+    }
+
+    #[test]
+    fn test_number() {
+        let number = |sql: &str| Tokenizer::new(sql).tokenize().unwrap_or_default();
+        for n in &["3.14", "1e10", "2.5e-3", "7E+2", "10"] {
+            assert_eq!(number(n), vec![Token::Number(n.to_string()), Token::EOF]);
+        }
+        // a number directly followed by an identifier
+        for (sql, n) in &[("1abc", "1abc"), ("a = 3.14px", "3.14px"), ("1e", "1e")] {
+            let err = Tokenizer::new(sql).tokenize().unwrap_err();
+            assert_eq!(
+                err.message,
+                format!(
+                    "malformed number {}, identifiers can not start with a digit",
+                    n
+                )
+            );
+        }
+        assert_eq!(
+            number("1 abc"),
+            vec![
+                Token::Number("1".to_owned()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Identifier("abc".to_owned()),
+                Token::EOF
+            ]
+        );
+        // the literal as written, see decimal_number
+        for (n, decimal) in &[
+            ("0x1F", "31"),
+            ("0XfF", "255"),
+            ("0b1010", "10"),
+            ("1_000", "1000"),
+            ("1_000.000_1e1_0", "1000.0001e10"),
+            ("0xffff_ffff_ffff_ffff", "18446744073709551615"),
+        ] {
+            assert_eq!(number(n), vec![Token::Number(n.to_string()), Token::EOF]);
+            assert_eq!(decimal_number(n), decimal.to_owned());
+        }
+        for n in &[
+            "0x", "0xG1", "0b12", "0x_1", "1__0", "1_", "1_.5", "1._5", "2e1_",
+        ] {
+            let err = Tokenizer::new(n).tokenize().unwrap_err();
+            assert!(err.message.starts_with("malformed number"), "{}", n);
+        }
+        let err = Tokenizer::new("0x1_0000_0000_0000_0000")
+            .tokenize()
+            .unwrap_err();
+        assert_eq!(
+            err.message,
+            "number 0x1_0000_0000_0000_0000 is out of range"
+        );
+
+        let err = Tokenizer::new("a = 1.2.3").tokenize().unwrap_err();
+        assert_eq!(err.message, "malformed number 1.2.");
+        assert_eq!((1, 5), (err.line, err.col));
+        assert!(Tokenizer::new("1e5.3").tokenize().is_err());
+    }
+
     #[test]
     fn test_function() {
         let sql = "SELECT func(prop1) FROM label1";
-        let mut tokenizer = Tokenizer::new(&sql);
+        let mut tokenizer = Tokenizer::new(sql);
         let tokens = tokenizer.tokenize().unwrap_or_default();
 
         assert_eq!(
@@ -428,7 +769,7 @@ mod test {
     #[test]
     fn test_string() {
         let sql = "SELECT 'test_string'";
-        let mut tokenizer = Tokenizer::new(&sql);
+        let mut tokenizer = Tokenizer::new(sql);
         let tokens = tokenizer.tokenize().unwrap_or_default();
 
         assert_eq!(
@@ -445,7 +786,7 @@ mod test {
     #[test]
     fn test_string2() {
         let sql = "SELECT \"test_string\"";
-        let mut tokenizer = Tokenizer::new(&sql);
+        let mut tokenizer = Tokenizer::new(sql);
         let tokens = tokenizer.tokenize().unwrap_or_default();
 
         assert_eq!(
@@ -462,7 +803,7 @@ mod test {
     #[test]
     fn test_string3() {
         let sql = "SELECT 'test\\'_\\'string'";
-        let mut tokenizer = Tokenizer::new(&sql);
+        let mut tokenizer = Tokenizer::new(sql);
         let tokens = tokenizer.tokenize().unwrap_or_default();
 
         assert_eq!(
@@ -476,7 +817,7 @@ mod test {
         );
 
         let sql = "SELECT \"test\\\"_\\\"string\"";
-        let mut tokenizer = Tokenizer::new(&sql);
+        let mut tokenizer = Tokenizer::new(sql);
         let tokens = tokenizer.tokenize().unwrap_or_default();
 
         assert_eq!(
@@ -489,4 +830,86 @@ mod test {
             tokens
         );
     }
+
+    #[test]
+    fn test_multibyte_string_position() {
+        let mut tokenizer = Tokenizer::new("'héllo 世界' 'it\\'s' x");
+        let spans: Vec<_> = tokenizer
+            .tokenize_with_spans()
+            .unwrap()
+            .into_iter()
+            .filter(|(token, _)| !matches!(token, Token::Whitespace(_)))
+            .map(|(token, span)| (token, span.col))
+            .collect();
+        assert_eq!(
+            vec![
+                (Token::String("héllo 世界".to_owned()), 1),
+                (Token::String("it's".to_owned()), 12),
+                (Token::Identifier("x".to_owned()), 20),
+                (Token::EOF, 21),
+            ],
+            spans
+        );
+
+        let mut tokenizer = Tokenizer::new("/* 注释\n注释 */ '多\n行' `");
+        let err = tokenizer.tokenize().unwrap_err();
+        assert_eq!((3, 4), (err.line, err.col));
+    }
+
+    #[test]
+    fn test_mixed_newlines() {
+        // `\n\r` is two newlines, `\r\n` one
+        let sql = "a\nb\r\nc\rd\n\re\r\r-- x\rf";
+        let tokens = Tokenizer::new(sql).tokenize_with_spans().unwrap();
+        let identifiers: Vec<_> = tokens
+            .iter()
+            .filter_map(|(token, span)| match token {
+                Token::Identifier(s) => Some((s.as_str(), span.line, span.col)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("a", 1, 1),
+                ("b", 2, 1),
+                ("c", 3, 1),
+                ("d", 4, 1),
+                ("e", 6, 1),
+                ("f", 9, 1)
+            ],
+            identifiers
+        );
+        let newlines = tokens
+            .iter()
+            .filter(|(token, _)| *token == Token::Whitespace(Whitespace::Newline))
+            .count();
+        assert_eq!(8, newlines);
+    }
+
+    #[test]
+    fn test_newline_position() {
+        // `\r\n` and a lone `\r` are both a single newline
+        for sql in &["a\r\nb", "a\rb", "a\nb"] {
+            let mut tokenizer = Tokenizer::new(sql);
+            let tokens = tokenizer.tokenize().unwrap_or_default();
+            assert_eq!(
+                vec![
+                    Token::Identifier("a".to_owned()),
+                    Token::Whitespace(Whitespace::Newline),
+                    Token::Identifier("b".to_owned()),
+                    Token::EOF,
+                ],
+                tokens
+            );
+            assert_eq!((2, 2), (tokenizer.line, tokenizer.col));
+        }
+
+        // the position of the token after the newline, reported by its error
+        for sql in &["a\r\n`", "a\r`", "a\r\n\r`"] {
+            let mut tokenizer = Tokenizer::new(sql);
+            let err = tokenizer.tokenize().unwrap_err();
+            let line = sql.matches('\r').count() as u64 + 1;
+            assert_eq!((line, 1), (err.line, err.col));
+        }
+    }
 }
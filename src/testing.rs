@@ -0,0 +1,64 @@
+use crate::database::{AngelinaError, Database};
+#[cfg(test)]
+use crate::execution::executor::QueryExecutor;
+use crate::execution::output::QueryOutput;
+use crate::handlers::sled_engine::SledEngine;
+
+/// a temporary database for end-to-end tests, returning materialized rows.
+pub struct TestDatabase {
+    pub db: Database,
+}
+
+impl TestDatabase {
+    pub fn new() -> Self {
+        TestDatabase {
+            db: Database::open_tmp(),
+        }
+    }
+
+    /// a database over `engine`, e.g. to watch what queries read from it.
+    pub fn with_engine(engine: SledEngine) -> Self {
+        TestDatabase {
+            db: Database::from_engine(engine).unwrap(),
+        }
+    }
+
+    /// rows of the last statement in `sql`. panics if any statement fails.
+    pub fn run(&mut self, sql: &str) -> Vec<Vec<String>> {
+        self.query(sql).1
+    }
+
+    /// columns and rows of the last statement in `sql`. panics if any statement or any of
+    /// the rows fails.
+    pub fn query(&mut self, sql: &str) -> (Vec<String>, Vec<Vec<String>>) {
+        let output = self.db.execute(sql);
+        match output
+            .and_then(|output| Ok((output.columns, output.items.collect::<Result<_, _>>()?)))
+        {
+            Ok(rows) => rows,
+            Err(e) => panic!("`{}` failed: {}", sql, e),
+        }
+    }
+
+    /// the executor of the active graph, e.g. to run a hand-built operator.
+    #[cfg(test)]
+    pub(crate) fn executor(&self) -> &QueryExecutor {
+        self.db.executor()
+    }
+
+    /// error of `sql` or of its rows. panics if it succeeds.
+    pub fn run_err(&mut self, sql: &str) -> AngelinaError {
+        match self.db.execute(sql).map(QueryOutput::into_rows) {
+            Ok(Ok(rows)) => panic!("`{}` should fail but returned {:?}", sql, rows),
+            Ok(Err(e)) => e.into(),
+            Err(e) => e,
+        }
+    }
+}
+
+/// build the expected rows of `TestDatabase::run` from string slices.
+pub fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+    rows.iter()
+        .map(|row| row.iter().map(|s| s.to_string()).collect())
+        .collect()
+}
@@ -0,0 +1,86 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_scripted_session() {
+    let path = env::temp_dir().join(format!("angelina_repl_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    let mut repl = Command::new(env!("CARGO_BIN_EXE_angelina"))
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    repl.stdin
+        .take()
+        .unwrap()
+        .write_all(
+            concat!(
+                "CREATE VERTEX LABEL person; CREATE PROPERTY KEY (name, single)\n",
+                "\n",
+                "INSERT VERTEX person PROPERTIES (name) VALUES ('v1'):('tom')\n",
+                "SELEC a.id FROM (a)\n",
+                "CREATE EDGE LABEL (knows, bogus)\n",
+                "CREATE PROPERTY KEY (age, weird)\n",
+                "SELECT a.id, a.name FROM (a)\n",
+                ".exit\n",
+                "SHOW VERTEX LABEL\n",
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    let output = repl.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains(concat!(
+            "+------+--------+\n",
+            "| a.id | a.name |\n",
+            "+------+--------+\n",
+            "| v1   | tom    |\n",
+            "+------+--------+\n",
+            "(1 row)\n",
+        )),
+        "{}",
+        stdout
+    );
+    // nothing runs after .exit
+    assert!(!stdout.contains("| person |\n"), "{}", stdout);
+    assert_eq!(stdout.matches("angelina> ").count(), 8);
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let errors = stderr.lines().collect::<Vec<_>>();
+    assert_eq!(errors.len(), 3, "{}", stderr);
+    assert!(errors[0].contains("SELEC"), "{}", stderr);
+    assert!(errors[1].contains("bogus"), "{}", stderr);
+    assert!(errors[2].contains("weird"), "{}", stderr);
+    fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn test_closed_output() {
+    let path = env::temp_dir().join(format!("angelina_repl_pipe_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    let mut repl = Command::new(env!("CARGO_BIN_EXE_angelina"))
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // like `angelina db | head -0`
+    drop(repl.stdout.take());
+    let _ = repl
+        .stdin
+        .take()
+        .unwrap()
+        .write_all("SHOW VERTEX LABEL\n".repeat(100).as_bytes());
+    let output = repl.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+    fs::remove_dir_all(&path).unwrap();
+}
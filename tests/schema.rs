@@ -0,0 +1,29 @@
+use angelina::database::Database;
+use angelina::datamodel::base::{Cardinality, EdgeMultiplicity};
+use angelina::datamodel::property::PropertyValue;
+
+#[test]
+fn test_read_schema() {
+    let mut db = Database::open_tmp();
+    db.execute("CREATE VERTEX LABEL person").unwrap();
+    db.execute("CREATE EDGE LABEL (knows, one2many)").unwrap();
+    db.execute("CREATE PROPERTY KEY (tags, set, DEFAULT 'none')")
+        .unwrap();
+
+    let person = db.vertex_label("person").unwrap().unwrap();
+    assert_eq!(person.name(), "person");
+
+    let knows = db.edge_label("knows").unwrap().unwrap();
+    assert_eq!(knows.multiplicity(), EdgeMultiplicity::One2Many);
+    // labels of both kinds take their ids from the same sequence
+    assert_ne!(knows.id().0, person.id().0);
+
+    let tags = db.property_key("tags").unwrap().unwrap();
+    assert_eq!(tags.cardinality(), Cardinality::Set);
+    assert_eq!(
+        tags.default_value(),
+        Some(&PropertyValue::String("none".to_string()))
+    );
+
+    assert!(db.edge_label("person").unwrap().is_none());
+}